@@ -0,0 +1,167 @@
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::path::Path;
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
+
+use dashmap::DashMap;
+use maxminddb::{geoip2, Reader};
+
+use crate::config::{Config, RateLimit};
+
+/// Loaded GeoIP database.
+static GEOIP: OnceLock<Reader<Vec<u8>>> = OnceLock::new();
+
+/// Rate limit buckets per network.
+static RATE_LIMITS: OnceLock<DashMap<IpAddr, RateLimitBuckets>> = OnceLock::new();
+
+/// Time after which idle rate limit buckets are removed.
+const RATE_LIMIT_IDLE: Duration = Duration::from_secs(300);
+
+/// Rate limited actions.
+#[derive(Debug, Copy, Clone)]
+pub enum RateLimited {
+    Connection,
+    StatusPing,
+}
+
+/// Load GeoIP database.
+///
+/// Must be called once at startup if GeoIP blocking is enabled.
+pub fn load_geoip(path: &Path) -> Result<(), String> {
+    let reader = Reader::open_readfile(path).map_err(|err| err.to_string())?;
+    let database_type = &reader.metadata.database_type;
+    if !database_type.contains("Country") {
+        return Err(format!(
+            "database type is {database_type}, expected GeoLite2-Country or GeoIP2-Country"
+        ));
+    }
+
+    let _ = GEOIP.set(reader);
+    Ok(())
+}
+
+/// Look up ISO country code for IP, `None` if unknown or GeoIP is not loaded.
+pub fn country(ip: IpAddr) -> Option<String> {
+    let country: geoip2::Country = GEOIP.get()?.lookup(ip).ok()?;
+    country.country?.iso_code.map(|c| c.to_string())
+}
+
+/// Check whether the given IP is denied access, by access lists or GeoIP.
+///
+/// Returns whether it's denied, and the country code if looked up.
+pub fn check(config: &Config, ip: IpAddr) -> (bool, Option<String>) {
+    if config.access.is_denied(&ip) {
+        return (true, None);
+    }
+
+    let geoip = &config.access.geoip;
+    if !geoip.enabled {
+        return (false, None);
+    }
+    let country = country(ip);
+    (geoip.is_blocked(country.as_deref()), country)
+}
+
+/// Check rate limit for an action by the given IP, consuming a token.
+///
+/// Returns `true` if allowed, `false` if the rate limit is exceeded.
+pub fn rate_limit(config: &RateLimit, ip: IpAddr, action: RateLimited) -> bool {
+    if !config.enabled {
+        return true;
+    }
+
+    let per_minute = match action {
+        RateLimited::Connection => config.max_connections_per_minute,
+        RateLimited::StatusPing => config.max_status_pings_per_minute,
+    };
+    let capacity = config.burst.max(1) as f64;
+
+    let limits = RATE_LIMITS.get_or_init(DashMap::new);
+    let mut buckets = limits
+        .entry(rate_limit_network(ip))
+        .or_insert_with(|| RateLimitBuckets::new(capacity));
+    let bucket = match action {
+        RateLimited::Connection => &mut buckets.connections,
+        RateLimited::StatusPing => &mut buckets.status_pings,
+    };
+    bucket.take(per_minute as f64 / 60.0, capacity)
+}
+
+/// Remove idle rate limit buckets.
+pub fn rate_limit_cleanup() {
+    if let Some(limits) = RATE_LIMITS.get() {
+        limits.retain(|_, buckets| buckets.last_used().elapsed() < RATE_LIMIT_IDLE);
+    }
+}
+
+/// Get network to rate limit the given IP by, its IPv4 /24 or IPv6 /48 network.
+fn rate_limit_network(ip: IpAddr) -> IpAddr {
+    match ip {
+        IpAddr::V4(ip) => {
+            let [a, b, c, _] = ip.octets();
+            IpAddr::V4(Ipv4Addr::new(a, b, c, 0))
+        }
+        IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+            Some(ip) => rate_limit_network(IpAddr::V4(ip)),
+            None => {
+                let [a, b, c, ..] = ip.segments();
+                IpAddr::V6(Ipv6Addr::new(a, b, c, 0, 0, 0, 0, 0))
+            }
+        },
+    }
+}
+
+/// Rate limit buckets for a network.
+#[derive(Debug)]
+struct RateLimitBuckets {
+    connections: TokenBucket,
+    status_pings: TokenBucket,
+}
+
+impl RateLimitBuckets {
+    fn new(capacity: f64) -> Self {
+        Self {
+            connections: TokenBucket::new(capacity),
+            status_pings: TokenBucket::new(capacity),
+        }
+    }
+
+    /// Time any bucket was last used.
+    fn last_used(&self) -> Instant {
+        self.connections.last.max(self.status_pings.last)
+    }
+}
+
+/// Token bucket, refilled over time at a fixed rate.
+#[derive(Debug)]
+struct TokenBucket {
+    /// Available tokens.
+    tokens: f64,
+
+    /// Time tokens were last updated.
+    last: Instant,
+}
+
+impl TokenBucket {
+    /// Construct full bucket.
+    fn new(capacity: f64) -> Self {
+        Self {
+            tokens: capacity,
+            last: Instant::now(),
+        }
+    }
+
+    /// Refill bucket at rate in tokens per second, and try to take a token.
+    fn take(&mut self, rate: f64, capacity: f64) -> bool {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.last).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * rate).min(capacity);
+        self.last = now;
+
+        if self.tokens < 1.0 {
+            return false;
+        }
+        self.tokens -= 1.0;
+        true
+    }
+}
@@ -0,0 +1,57 @@
+use std::net::IpAddr;
+use std::path::PathBuf;
+
+use clap::ArgMatches;
+
+use crate::config::{self, Server as ConfigServer};
+use crate::mc::ban;
+use crate::util::error::{quit_error, quit_error_msg, ErrorHintsBuilder};
+
+/// Invoke ban command.
+pub fn invoke_ban(matches: &ArgMatches) {
+    let (path, ip) = (ban_file(matches), ip(matches));
+    let reason = matches.get_one::<String>("reason").unwrap();
+
+    if let Err(err) = ban::ban(&path, ip, reason) {
+        quit_error(
+            anyhow!(err.to_string()).context(format!("Failed to ban IP in {}", path.display())),
+            ErrorHintsBuilder::default().build().unwrap(),
+        );
+    }
+
+    eprintln!("Banned IP {ip}");
+}
+
+/// Invoke unban command.
+pub fn invoke_unban(matches: &ArgMatches) {
+    let (path, ip) = (ban_file(matches), ip(matches));
+
+    match ban::unban(&path, ip) {
+        Ok(0) => eprintln!("IP {ip} is not banned"),
+        Ok(_) => eprintln!("Unbanned IP {ip}"),
+        Err(err) => quit_error(
+            anyhow!(err.to_string()).context(format!("Failed to unban IP in {}", path.display())),
+            ErrorHintsBuilder::default().build().unwrap(),
+        ),
+    }
+}
+
+/// Get IP argument.
+fn ip(matches: &ArgMatches) -> IpAddr {
+    *matches.get_one::<IpAddr>("ip").unwrap()
+}
+
+/// Get path of banned IPs file in server directory from config.
+fn ban_file(matches: &ArgMatches) -> PathBuf {
+    let config = config::load(matches);
+    match ConfigServer::server_directory(&config) {
+        Some(dir) if dir.is_dir() => dir.join(ban::FILE),
+        _ => quit_error_msg(
+            "Server directory does not exist, can't manage banned IPs",
+            ErrorHintsBuilder::default()
+                .add_info("change 'server.directory' in the config file".into())
+                .build()
+                .unwrap(),
+        ),
+    }
+}
@@ -0,0 +1,349 @@
+use std::fs;
+use std::path::PathBuf;
+
+use clap::ArgMatches;
+use toml_edit::{value, DocumentMut, Item, Table};
+use version_compare::Cmp;
+
+use crate::config::CONFIG_VERSION;
+use crate::util::error::{quit_error, quit_error_msg, ErrorHintsBuilder};
+
+/// Oldest config version that can be migrated.
+const OLDEST_VERSION: &str = "0.2.0";
+
+/// Config migration from one lazymc version to the next.
+trait Migration {
+    /// Version this migrates from.
+    fn from(&self) -> &'static str;
+
+    /// Version this migrates to.
+    fn to(&self) -> &'static str;
+
+    /// Apply migration to config document.
+    fn migrate(&self, doc: &mut DocumentMut);
+}
+
+/// Migration backed by a function.
+struct FnMigration {
+    from: &'static str,
+    to: &'static str,
+    migrate: fn(&mut DocumentMut),
+}
+
+impl Migration for FnMigration {
+    fn from(&self) -> &'static str {
+        self.from
+    }
+
+    fn to(&self) -> &'static str {
+        self.to
+    }
+
+    fn migrate(&self, doc: &mut DocumentMut) {
+        (self.migrate)(doc)
+    }
+}
+
+/// All migrations, in order.
+///
+/// The last migration is to the current `CONFIG_VERSION`.
+fn migrations() -> Vec<Box<dyn Migration>> {
+    let migrations: [(_, _, fn(&mut DocumentMut)); 8] = [
+        ("0.2.0", "0.2.1", migrate_0_2_0_to_0_2_1),
+        ("0.2.1", "0.2.2", migrate_0_2_1_to_0_2_2),
+        ("0.2.2", "0.2.3", migrate_0_2_2_to_0_2_3),
+        ("0.2.3", "0.2.4", migrate_0_2_3_to_0_2_4),
+        ("0.2.4", "0.2.5", migrate_0_2_4_to_0_2_5),
+        ("0.2.5", "0.2.6", migrate_0_2_5_to_0_2_6),
+        ("0.2.6", "0.2.7", migrate_0_2_6_to_0_2_7),
+        ("0.2.7", CONFIG_VERSION, migrate_0_2_7_to_0_2_8),
+    ];
+    migrations
+        .into_iter()
+        .map(|(from, to, migrate)| {
+            Box::new(FnMigration { from, to, migrate }) as Box<dyn Migration>
+        })
+        .collect()
+}
+
+/// Banned IPs are now blocked by default, keep them unblocked as before.
+fn migrate_0_2_0_to_0_2_1(doc: &mut DocumentMut) {
+    set_default(doc, "server", "block_banned_ips", false);
+}
+
+/// Server favicon is now shown, no config changes.
+fn migrate_0_2_1_to_0_2_2(_doc: &mut DocumentMut) {}
+
+/// Added PROXY header support, disabled by default, no config changes.
+fn migrate_0_2_2_to_0_2_3(_doc: &mut DocumentMut) {}
+
+/// Server directory is now relative to the config file, no config changes.
+fn migrate_0_2_3_to_0_2_4(_doc: &mut DocumentMut) {}
+
+/// Added server probing and Forge support, disabled by default, no config changes.
+fn migrate_0_2_4_to_0_2_5(_doc: &mut DocumentMut) {}
+
+/// Server whitelist now prevents unknown users from waking the server, keep it disabled as before.
+fn migrate_0_2_5_to_0_2_6(doc: &mut DocumentMut) {
+    set_default(doc, "server", "wake_whitelist", false);
+}
+
+/// Default Minecraft version update only, no config changes.
+fn migrate_0_2_6_to_0_2_7(_doc: &mut DocumentMut) {}
+
+/// Sleeping servers are now frozen by default on Unix, keep stopping them as before.
+fn migrate_0_2_7_to_0_2_8(doc: &mut DocumentMut) {
+    set_default(doc, "server", "freeze_process", false);
+}
+
+/// Set value in table if not set, to keep behavior of an older default.
+fn set_default(doc: &mut DocumentMut, table: &str, key: &str, default: bool) {
+    let table = table_mut(doc, table);
+    if !table.contains_key(key) {
+        table.insert(key, value(default));
+    }
+}
+
+/// Get mutable table, created if it does not exist.
+fn table_mut<'a>(doc: &'a mut DocumentMut, name: &str) -> &'a mut Table {
+    let item = doc.entry(name).or_insert_with(|| Item::Table(Table::new()));
+    if !item.is_table() {
+        *item = Item::Table(Table::new());
+    }
+    item.as_table_mut().unwrap()
+}
+
+/// Invoke config migrate command.
+pub fn invoke(matches: &ArgMatches) {
+    // Get config path, attempt to canonicalize
+    let mut path = PathBuf::from(matches.get_one::<String>("config").unwrap());
+    if let Ok(p) = path.canonicalize() {
+        path = p;
+    }
+
+    // Read config document, comments and formatting are preserved
+    let data = match fs::read_to_string(&path) {
+        Ok(data) => data,
+        Err(err) => quit_error(
+            anyhow!(err).context("Failed to read config file"),
+            ErrorHintsBuilder::default().build().unwrap(),
+        ),
+    };
+    let mut doc: DocumentMut = match data.parse() {
+        Ok(doc) => doc,
+        Err(err) => quit_error(
+            anyhow!(err).context("Failed to parse config, only TOML configs can be migrated"),
+            ErrorHintsBuilder::default().build().unwrap(),
+        ),
+    };
+
+    // Config version must be known to select migrations, don't guess
+    let version = doc
+        .get("config")
+        .and_then(|config| config.get("version"))
+        .and_then(|version| version.as_str())
+        .map(String::from)
+        .or_else(|| matches.get_one::<String>("from").cloned());
+    let Some(version) = version else {
+        quit_error_msg(
+            "Config has no config.version, can't determine migrations to apply",
+            ErrorHintsBuilder::default()
+                .add_info(format!(
+                    "pass the lazymc version the config is for with --from, such as --from {OLDEST_VERSION}"
+                ))
+                .build()
+                .unwrap(),
+        );
+    };
+    if version_compare::compare(&version, OLDEST_VERSION).is_err() {
+        quit_error_msg(
+            format!("Config version '{version}' is invalid, can't migrate"),
+            ErrorHintsBuilder::default().build().unwrap(),
+        );
+    }
+    if is_too_old(&version) {
+        quit_error_msg(
+            format!("Config version '{version}' is older than {OLDEST_VERSION}, can't migrate"),
+            ErrorHintsBuilder::default()
+                .add_info(format!(
+                    "update the config to {OLDEST_VERSION} manually, then migrate it with --from {OLDEST_VERSION}"
+                ))
+                .build()
+                .unwrap(),
+        );
+    }
+
+    // Apply all migrations from the config version
+    let version = migrate(&mut doc, version);
+
+    // Report changes and write config
+    let migrated = doc.to_string();
+    if migrated == data {
+        eprintln!("Config is up to date");
+        return;
+    }
+    print_diff(&data, &migrated);
+    if matches.get_flag("dry-run") {
+        eprintln!("Dry run, config not saved");
+        return;
+    }
+    if let Err(err) = fs::write(&path, migrated) {
+        quit_error(
+            anyhow!(err).context("Failed to write migrated config"),
+            ErrorHintsBuilder::default().build().unwrap(),
+        );
+    }
+
+    eprintln!("Config migrated to {version} at: {}", path.display());
+}
+
+/// Whether the config version is older than the oldest version that can be migrated.
+fn is_too_old(version: &str) -> bool {
+    version_compare::compare_to(version, OLDEST_VERSION, Cmp::Lt).unwrap_or(false)
+}
+
+/// Apply all migrations from the given config version, returns the migrated version.
+fn migrate(doc: &mut DocumentMut, mut version: String) -> String {
+    for migration in migrations() {
+        if version_compare::compare_to(&version, migration.from(), Cmp::Gt).unwrap_or(true) {
+            continue;
+        }
+        eprintln!(
+            "Migrating config from {} to {}",
+            migration.from(),
+            migration.to()
+        );
+        migration.migrate(doc);
+        version = migration.to().into();
+        table_mut(doc, "config").insert("version", value(version.as_str()));
+    }
+    version
+}
+
+/// Print line based diff between old and new text.
+fn print_diff(old: &str, new: &str) {
+    let old: Vec<&str> = old.lines().collect();
+    let new: Vec<&str> = new.lines().collect();
+
+    // Longest common subsequence lengths of all suffixes
+    let mut lcs = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lcs[i][j] = if old[i] == new[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    // Walk both texts, only print changed lines
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && old[i] == new[j] {
+            i += 1;
+            j += 1;
+        } else if j < new.len() && (i == old.len() || lcs[i][j + 1] >= lcs[i + 1][j]) {
+            println!("+ {}", new[j]);
+            j += 1;
+        } else {
+            println!("- {}", old[i]);
+            i += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn doc(toml: &str) -> DocumentMut {
+        toml.parse().unwrap()
+    }
+
+    /// Value of boolean key in table, `None` if not set.
+    fn get_bool(doc: &DocumentMut, table: &str, key: &str) -> Option<bool> {
+        doc.get(table)?.get(key)?.as_bool()
+    }
+
+    #[test]
+    fn migrations_chain_to_config_version() {
+        let migrations = migrations();
+        assert_eq!(migrations.first().unwrap().from(), OLDEST_VERSION);
+        assert_eq!(migrations.last().unwrap().to(), CONFIG_VERSION);
+        for pair in migrations.windows(2) {
+            assert_eq!(pair[0].to(), pair[1].from());
+        }
+    }
+
+    #[test]
+    fn migrate_0_2_0_to_0_2_1_unblocks_banned_ips() {
+        let mut config = doc("[server]\ncommand = \"java -jar server.jar\"");
+        migrate_0_2_0_to_0_2_1(&mut config);
+        assert_eq!(get_bool(&config, "server", "block_banned_ips"), Some(false));
+
+        let mut config = doc("[server]\nblock_banned_ips = true");
+        migrate_0_2_0_to_0_2_1(&mut config);
+        assert_eq!(get_bool(&config, "server", "block_banned_ips"), Some(true));
+    }
+
+    #[test]
+    fn migrate_0_2_5_to_0_2_6_disables_wake_whitelist() {
+        let mut config = doc("");
+        migrate_0_2_5_to_0_2_6(&mut config);
+        assert_eq!(get_bool(&config, "server", "wake_whitelist"), Some(false));
+
+        let mut config = doc("[server]\nwake_whitelist = true");
+        migrate_0_2_5_to_0_2_6(&mut config);
+        assert_eq!(get_bool(&config, "server", "wake_whitelist"), Some(true));
+    }
+
+    #[test]
+    fn migrate_0_2_7_to_0_2_8_disables_freeze_process() {
+        let mut config = doc("[server]\ncommand = \"java -jar server.jar\"");
+        migrate_0_2_7_to_0_2_8(&mut config);
+        assert_eq!(get_bool(&config, "server", "freeze_process"), Some(false));
+
+        let mut config = doc("[server]\nfreeze_process = true");
+        migrate_0_2_7_to_0_2_8(&mut config);
+        assert_eq!(get_bool(&config, "server", "freeze_process"), Some(true));
+    }
+
+    #[test]
+    fn migrate_no_config_changes() {
+        let source = "[server]\ncommand = \"java -jar server.jar\"\n";
+        let steps: [fn(&mut DocumentMut); 5] = [
+            migrate_0_2_1_to_0_2_2,
+            migrate_0_2_2_to_0_2_3,
+            migrate_0_2_3_to_0_2_4,
+            migrate_0_2_4_to_0_2_5,
+            migrate_0_2_6_to_0_2_7,
+        ];
+        for step in steps {
+            let mut config = doc(source);
+            step(&mut config);
+            assert_eq!(config.to_string(), source);
+        }
+    }
+
+    #[test]
+    fn reject_too_old_version() {
+        assert!(is_too_old("0.1.0"));
+        assert!(!is_too_old(OLDEST_VERSION));
+        assert!(!is_too_old(CONFIG_VERSION));
+    }
+
+    #[test]
+    fn migrate_from_version() {
+        let mut config = doc("[config]\nversion = \"0.2.6\"");
+        assert_eq!(migrate(&mut config, "0.2.6".into()), CONFIG_VERSION);
+        assert_eq!(config["config"]["version"].as_str(), Some(CONFIG_VERSION));
+        assert_eq!(get_bool(&config, "server", "freeze_process"), Some(false));
+        assert_eq!(get_bool(&config, "server", "wake_whitelist"), None);
+
+        // Up to date config is left as is
+        let mut config = doc("[config]\nversion = \"0.2.11\"");
+        assert_eq!(migrate(&mut config, "0.2.11".into()), "0.2.11");
+        assert_eq!(get_bool(&config, "server", "freeze_process"), None);
+    }
+}
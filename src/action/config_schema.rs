@@ -0,0 +1,12 @@
+use crate::config::Config;
+
+/// Invoke config schema command.
+///
+/// Prints JSON Schema for the configuration to stdout.
+pub fn invoke() {
+    let schema = schemars::schema_for!(Config);
+    println!(
+        "{}",
+        serde_json::to_string_pretty(&schema).expect("failed to serialize config schema")
+    );
+}
@@ -22,7 +22,7 @@ pub fn invoke(matches: &ArgMatches) {
     }
 
     // Try to load config
-    let _config = match Config::load_from_file(path) {
+    let _config = match Config::load(path) {
         Ok(config) => config,
         Err(err) => {
             quit_error(
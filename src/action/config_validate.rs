@@ -0,0 +1,47 @@
+use std::path::PathBuf;
+
+use clap::ArgMatches;
+
+use crate::config::Config;
+use crate::util::error::{exit, print_error_msg, quit_error, quit_error_msg, ErrorHintsBuilder};
+
+/// Invoke config validate command.
+pub fn invoke(matches: &ArgMatches) {
+    // Get config path, attempt to canonicalize
+    let mut path = PathBuf::from(matches.get_one::<String>("config").unwrap());
+    if let Ok(p) = path.canonicalize() {
+        path = p;
+    }
+
+    // Ensure it exists
+    if !path.is_file() {
+        quit_error_msg(
+            format!("Config file does not exist at: {}", path.to_str().unwrap()),
+            ErrorHintsBuilder::default().build().unwrap(),
+        );
+    }
+
+    // Try to load config
+    let config = match Config::load(path) {
+        Ok(config) => config,
+        Err(err) => {
+            quit_error(
+                anyhow::anyhow!(err).context("Failed to load and parse config"),
+                ErrorHintsBuilder::default().build().unwrap(),
+            );
+        }
+    };
+
+    // Validate config, report all violations
+    let errors = config.validate();
+    if !errors.is_empty() {
+        for err in &errors {
+            print_error_msg(err.to_string());
+        }
+        eprintln!();
+        eprintln!("Config is invalid, found {} problem(s)", errors.len());
+        exit(1);
+    }
+
+    eprintln!("Config is valid!");
+}
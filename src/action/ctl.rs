@@ -0,0 +1,167 @@
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use clap::ArgMatches;
+use serde_json::Value;
+
+use crate::action::reload;
+use crate::config;
+use crate::os;
+use crate::util::error::{quit_error, quit_error_msg, ErrorHintsBuilder};
+
+/// Interval to poll server state at while waiting for it to change.
+const WAIT_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Invoke ctl command.
+pub fn invoke(matches: &ArgMatches) {
+    // IPC is done through Unix socket, only available on Unix
+    if !cfg!(unix) {
+        quit_error_msg(
+            "Controlling running lazymc instance is only supported on Unix",
+            ErrorHintsBuilder::default().build().unwrap(),
+        );
+    }
+
+    let command = matches.get_one::<String>("command").unwrap();
+
+    // Get socket path from arguments or config, fall back to PID file
+    let socket = match matches.get_one::<String>("socket") {
+        Some(socket) => PathBuf::from(socket),
+        None => {
+            let config = config::load(matches);
+            match config.advanced.ipc_socket.as_ref() {
+                Some(path) => config.resolve_path(path),
+                None => return invoke_pid(matches, command),
+            }
+        }
+    };
+
+    #[cfg(unix)]
+    match send(&socket, command) {
+        Ok(response) => println!("{}", response.trim_end()),
+        Err(err) => quit_error(
+            anyhow!(err).context(format!("Failed to send command over {}", socket.display())),
+            ErrorHintsBuilder::default().build().unwrap(),
+        ),
+    }
+}
+
+/// Get IPC socket path from arguments or config.
+///
+/// Quits with an error if no IPC socket is configured.
+pub fn socket(matches: &ArgMatches, action: &str) -> PathBuf {
+    // IPC is done through Unix socket, only available on Unix
+    if !cfg!(unix) {
+        quit_error_msg(
+            format!("{action} running lazymc instance is only supported on Unix"),
+            ErrorHintsBuilder::default().build().unwrap(),
+        );
+    }
+
+    match matches.get_one::<String>("socket") {
+        Some(socket) => PathBuf::from(socket),
+        None => {
+            let config = config::load(matches);
+            match config.advanced.ipc_socket.as_ref() {
+                Some(path) => config.resolve_path(path),
+                None => quit_error_msg(
+                    format!("{action} running lazymc instance requires an IPC socket"),
+                    ErrorHintsBuilder::default()
+                        .add_info("set 'advanced.ipc_socket' in the config file".into())
+                        .build()
+                        .unwrap(),
+                ),
+            }
+        }
+    }
+}
+
+/// Send command over IPC socket, return parsed JSON response.
+///
+/// Quits with an error on failure.
+pub fn request(socket: &Path, command: &str) -> Value {
+    #[cfg(unix)]
+    let response = send(socket, command)
+        .map_err(anyhow::Error::from)
+        .and_then(|response| Ok(serde_json::from_str::<Value>(&response)?));
+    #[cfg(not(unix))]
+    let response: anyhow::Result<Value> = Err(anyhow!("IPC is only supported on Unix"));
+
+    response.unwrap_or_else(|err| {
+        quit_error(
+            err.context(format!("Failed to send command over {}", socket.display())),
+            ErrorHintsBuilder::default().build().unwrap(),
+        )
+    })
+}
+
+/// Wait for server of running lazymc instance to reach the given state, printing progress dots.
+///
+/// Returns `false` if the state was not reached within the timeout.
+pub fn wait_for_state(socket: &Path, state: &str, timeout: Duration) -> bool {
+    let deadline = Instant::now() + timeout;
+    eprint!("Waiting for server to be {state}");
+
+    loop {
+        if request(socket, "status")["state"].as_str() == Some(state) {
+            eprintln!(" done");
+            return true;
+        }
+        if Instant::now() >= deadline {
+            eprintln!(" timed out");
+            return false;
+        }
+
+        eprint!(".");
+        let _ = std::io::stderr().flush();
+        thread::sleep(WAIT_POLL_INTERVAL);
+    }
+}
+
+/// Invoke ctl command through PID of running lazymc instance, if no IPC socket is configured.
+///
+/// Only supports a limited set of commands.
+fn invoke_pid(matches: &ArgMatches, command: &str) {
+    let pid = reload::find_pid(matches);
+
+    match command {
+        "status" => {
+            println!(
+                "{}",
+                serde_json::json!({ "pid": pid, "running": os::is_running(pid) })
+            );
+        }
+        "reload" => {
+            if !os::reload(pid) {
+                quit_error_msg(
+                    format!("Failed to signal lazymc process {pid} to reload config"),
+                    ErrorHintsBuilder::default().build().unwrap(),
+                );
+            }
+            println!("{}", serde_json::json!({ "ok": true }));
+        }
+        _ => quit_error_msg(
+            format!("Command '{command}' requires an IPC socket"),
+            ErrorHintsBuilder::default()
+                .add_info("set 'advanced.ipc_socket' in the config file".into())
+                .build()
+                .unwrap(),
+        ),
+    }
+}
+
+/// Send command over IPC socket, return response line.
+#[cfg(unix)]
+pub fn send(socket: &Path, command: &str) -> std::io::Result<String> {
+    use std::io::{BufRead, BufReader};
+    use std::os::unix::net::UnixStream;
+
+    let mut stream = UnixStream::connect(socket)?;
+    writeln!(stream, "{}", serde_json::json!({ "cmd": command }))?;
+
+    let mut response = String::new();
+    BufReader::new(stream).read_line(&mut response)?;
+    Ok(response)
+}
@@ -0,0 +1,27 @@
+use clap::ArgMatches;
+
+use crate::config;
+use crate::util::error::{quit_error, ErrorHintsBuilder};
+
+/// Invoke dump config command.
+///
+/// Loads the effective configuration from file or environment variables and prints it to stdout
+/// in the given format.
+pub fn invoke(matches: &ArgMatches, format: &str) {
+    let config = config::load(matches);
+
+    let data = match format {
+        "json" => serde_json::to_string_pretty(&config).map_err(|err| anyhow!(err)),
+        _ => toml::to_string(&config).map_err(|err| anyhow!(err)),
+    };
+
+    match data {
+        Ok(data) => println!("{}", data),
+        Err(err) => {
+            quit_error(
+                err.context("Failed to serialize config"),
+                ErrorHintsBuilder::default().build().unwrap(),
+            );
+        }
+    }
+}
@@ -1,3 +1,14 @@
+pub mod ban;
 pub mod config_generate;
+pub mod config_migrate;
+pub mod config_schema;
 pub mod config_test;
+pub mod config_validate;
+pub mod ctl;
+pub mod dump_config;
+pub mod reload;
+pub mod sleep;
 pub mod start;
+pub mod stats;
+pub mod status;
+pub mod wake;
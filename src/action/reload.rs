@@ -0,0 +1,61 @@
+use clap::ArgMatches;
+
+use crate::config;
+use crate::os;
+use crate::pid_file;
+use crate::util::error::{quit_error_msg, ErrorHintsBuilder};
+
+/// Invoke reload command.
+pub fn invoke(matches: &ArgMatches) {
+    // Reloading is done through SIGHUP, only available on Unix
+    if !cfg!(unix) {
+        quit_error_msg(
+            "Reloading config of running lazymc instance is only supported on Unix",
+            ErrorHintsBuilder::default().build().unwrap(),
+        );
+    }
+
+    let pid = match matches.get_one::<u32>("pid") {
+        Some(pid) => *pid,
+        None => find_pid(matches),
+    };
+
+    // Signal running instance to reload
+    if !os::reload(pid) {
+        quit_error_msg(
+            format!("Failed to signal lazymc process {pid} to reload config"),
+            ErrorHintsBuilder::default().build().unwrap(),
+        );
+    }
+
+    eprintln!("Signalled lazymc process {pid} to reload config");
+}
+
+/// Find PID of running lazymc instance through configured PID file.
+///
+/// Quits with an error message on failure.
+pub fn find_pid(matches: &ArgMatches) -> u32 {
+    let config = config::load(matches);
+    let path = match config.pid_file() {
+        Some(path) => path,
+        None => quit_error_msg(
+            "No PID given and no PID file configured",
+            ErrorHintsBuilder::default()
+                .add_info("use '--pid PID'".into())
+                .add_info("or set 'advanced.pid_file' in the config file".into())
+                .build()
+                .unwrap(),
+        ),
+    };
+
+    match pid_file::read(&path) {
+        Some(pid) => pid,
+        None => quit_error_msg(
+            format!(
+                "Failed to read PID file at {}, is lazymc running?",
+                path.display()
+            ),
+            ErrorHintsBuilder::default().build().unwrap(),
+        ),
+    }
+}
@@ -0,0 +1,35 @@
+use std::process;
+use std::time::Duration;
+
+use clap::ArgMatches;
+
+use crate::action::ctl;
+use crate::util::error::{quit_error_msg, ErrorHintsBuilder};
+
+/// Invoke sleep command.
+pub fn invoke(matches: &ArgMatches) {
+    let socket = ctl::socket(matches, "Sleeping");
+
+    // Force kill server, or stop it gracefully
+    if matches.get_flag("force") {
+        if ctl::request(&socket, "kill")["ok"].as_bool() != Some(true) {
+            quit_error_msg(
+                "Failed to force kill server, it may not be running",
+                ErrorHintsBuilder::default().build().unwrap(),
+            );
+        }
+        eprintln!("Force killed server");
+    } else if ctl::request(&socket, "sleep")["ok"].as_bool() == Some(true) {
+        eprintln!("Putting server to sleep");
+    } else {
+        eprintln!("Server is not online");
+    }
+
+    // Wait for server to go to sleep
+    if matches.get_flag("wait") {
+        let timeout = Duration::from_secs(*matches.get_one::<u64>("timeout").unwrap());
+        if !ctl::wait_for_state(&socket, "sleeping", timeout) {
+            process::exit(1);
+        }
+    }
+}
@@ -3,8 +3,11 @@ use std::sync::Arc;
 
 use clap::ArgMatches;
 
-use crate::config::{self, Config, Server as ConfigServer};
+use crate::access;
+use crate::config::{self, Config, ConfigError, LiveConfig, Server as ConfigServer};
+use crate::logger;
 use crate::mc::server_properties;
+use crate::pid_file::PidFile;
 use crate::proto;
 use crate::service;
 
@@ -13,21 +16,84 @@ use crate::service;
 const RCON_PASSWORD_LENGTH: usize = 32;
 
 /// Start lazymc.
+///
+/// Starts a server for each loaded config. Process wide settings, such as logging and the PID
+/// file, must be the same in all configs and are taken from the first one.
 pub fn invoke(matches: &ArgMatches) -> Result<(), ()> {
-    // Load config
-    #[allow(unused_mut)]
-    let mut config = config::load(matches);
+    // Load configs
+    let mut configs = config::load_all(matches);
+    let first = &configs[0];
 
-    // Prepare RCON if enabled
-    #[cfg(feature = "rcon")]
-    prepare_rcon(&mut config);
+    // Enable configured log outputs
+    logger::configure(&first.advanced.log, |path| first.resolve_path(path));
+
+    // Load GeoIP database if enabled
+    if let Some(config) = configs.iter().find(|config| config.access.geoip.enabled) {
+        load_geoip(config);
+    }
+
+    for config in configs.iter_mut() {
+        // Don't start if lazymc would proxy to itself
+        check_addresses(config);
+
+        // Prepare RCON if enabled
+        #[cfg(feature = "rcon")]
+        prepare_rcon(config);
 
-    // Rewrite server server.properties file
-    rewrite_server_properties(&config);
+        // Rewrite server server.properties file, and those of virtual hosts
+        rewrite_server_properties(config);
+        for vhost in &config.vhosts {
+            rewrite_server_properties(&config.vhost(vhost));
+        }
+    }
+
+    // Write PID file, removed when lazymc quits
+    let _pid_file = configs[0].pid_file().and_then(|path| {
+        PidFile::create(path.clone())
+            .map_err(|err| {
+                error!(target: "lazymc", "Failed to write PID file at {}: {}", path.display(), err);
+            })
+            .ok()
+    });
 
     // Start server service
-    let config = Arc::new(config);
-    service::server::service(config)
+    let configs = configs
+        .into_iter()
+        .map(|config| Arc::new(LiveConfig::new(config)))
+        .collect();
+    service::server::service(configs)
+}
+
+/// Ensure configured addresses don't point to lazymc itself, quits on error.
+fn check_addresses(config: &Config) {
+    use crate::util::error::{quit_error_msg, ErrorHintsBuilder};
+
+    if let Some(err) = config.validate().into_iter().find(ConfigError::is_fatal) {
+        quit_error_msg(
+            err.to_string(),
+            ErrorHintsBuilder::default()
+                .config(true)
+                .config_test(true)
+                .build()
+                .unwrap(),
+        );
+    }
+}
+
+/// Load GeoIP database, quits on error.
+fn load_geoip(config: &Config) {
+    use crate::util::error::{quit_error_msg, ErrorHintsBuilder};
+
+    let path = config.resolve_path(&config.access.geoip.database);
+    if let Err(err) = access::load_geoip(&path) {
+        quit_error_msg(
+            format!("Failed to load GeoIP database from {}: {}", path.display(), err),
+            ErrorHintsBuilder::default()
+                .add_info("change 'access.geoip.database' to a GeoLite2-Country or GeoIP2-Country .mmdb file".into())
+                .build()
+                .unwrap(),
+        );
+    }
 }
 
 /// Prepare RCON.
@@ -113,7 +179,9 @@ fn generate_random_password() -> String {
 }
 
 /// Rewrite server server.properties file with correct internal IP and port.
-fn rewrite_server_properties(config: &Config) {
+///
+/// Also writes configured server.properties overrides.
+pub fn rewrite_server_properties(config: &Config) {
     // Rewrite must be enabled
     if !config.advanced.rewrite_server_properties {
         return;
@@ -128,9 +196,16 @@ fn rewrite_server_properties(config: &Config) {
         }
     };
 
+    // Start with configured overrides, required values below take precedence
+    let mut changes: HashMap<&str, String> = config
+        .advanced
+        .server_properties_overrides
+        .iter()
+        .map(|(key, value)| (key.as_str(), value.clone()))
+        .collect();
+
     // Build list of changes
-    #[allow(unused_mut)]
-    let mut changes = HashMap::from([
+    changes.extend([
         ("server-ip", config.server.address.ip().to_string()),
         ("server-port", config.server.address.port().to_string()),
         ("enable-status", "true".into()),
@@ -163,3 +238,41 @@ fn rewrite_server_properties(config: &Config) {
     // Rewrite file
     server_properties::rewrite_dir(dir, changes)
 }
+
+#[cfg(all(test, feature = "rcon"))]
+mod tests {
+    use std::fs;
+
+    use super::*;
+
+    #[test]
+    fn rewrite_randomized_rcon_password() {
+        let dir = std::env::temp_dir().join(format!("lazymc-test-rcon-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let file = dir.join(server_properties::FILE);
+        fs::write(&file, "rcon.password=old\r\nmotd=A Minecraft Server").unwrap();
+
+        // Server directory defaults to config directory
+        let mut config: Config =
+            toml::from_str("[server]\ncommand = \"java -jar server.jar\"").unwrap();
+        config.path = Some(dir.join("lazymc.toml"));
+        config.rcon.enabled = true;
+        config.rcon.randomize_password = true;
+        config.advanced.rewrite_server_properties = true;
+        prepare_rcon(&mut config);
+        rewrite_server_properties(&config);
+
+        assert_eq!(config.rcon.password.len(), RCON_PASSWORD_LENGTH);
+        assert_eq!(
+            server_properties::read_property(&file, "rcon.password"),
+            Some(config.rcon.password.clone())
+        );
+        assert_eq!(
+            server_properties::read_property(&file, "motd").as_deref(),
+            Some("A Minecraft Server")
+        );
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
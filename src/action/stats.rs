@@ -0,0 +1,48 @@
+use clap::ArgMatches;
+
+use crate::config;
+use crate::history;
+use crate::motd;
+use crate::util::error::{quit_error, quit_error_msg, ErrorHintsBuilder};
+
+/// Invoke stats command.
+pub fn invoke(matches: &ArgMatches) {
+    let config = config::load(matches);
+    let path = config.resolve_path(&config.stats.database);
+    if !path.is_file() {
+        quit_error_msg(
+            format!("Stats database does not exist at {}", path.display()),
+            ErrorHintsBuilder::default()
+                .add_info("set 'stats.enabled = true' in the config file to record stats".into())
+                .build()
+                .unwrap(),
+        );
+    }
+
+    let summary = match history::summary(&path) {
+        Ok(summary) => summary,
+        Err(err) => quit_error(
+            anyhow!(err).context(format!("Failed to read stats from {}", path.display())),
+            ErrorHintsBuilder::default().build().unwrap(),
+        ),
+    };
+
+    println!("Sessions:         {}", summary.sessions);
+    println!(
+        "Average session:  {}",
+        summary
+            .average_session
+            .map(motd::format_duration)
+            .as_deref()
+            .unwrap_or("-")
+    );
+    println!("Wakes:            {}", summary.wakes);
+    println!(
+        "Wakes per day:    {}",
+        summary
+            .wakes_per_day
+            .map(|wakes| format!("{wakes:.1}"))
+            .as_deref()
+            .unwrap_or("-")
+    );
+}
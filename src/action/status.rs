@@ -0,0 +1,57 @@
+use std::process;
+use std::time::Duration;
+
+use chrono::{Local, TimeZone};
+use clap::ArgMatches;
+use serde_json::Value;
+
+use crate::action::ctl;
+use crate::motd;
+
+/// Exit code if server is online.
+const EXIT_ONLINE: i32 = 0;
+
+/// Exit code if server is sleeping or stopping.
+const EXIT_SLEEPING: i32 = 1;
+
+/// Exit code if server is starting or resuming.
+const EXIT_STARTING: i32 = 2;
+
+/// Invoke status command.
+pub fn invoke(matches: &ArgMatches) {
+    let socket = ctl::socket(matches, "Showing status of");
+    let status = ctl::request(&socket, "status");
+    process::exit(print(&status));
+}
+
+/// Print human readable status, returns exit code for state.
+fn print(status: &Value) -> i32 {
+    let state = status["state"].as_str().unwrap_or("unknown");
+    let uptime = status["uptime"]
+        .as_u64()
+        .map(|secs| motd::format_duration(Duration::from_secs(secs)));
+    let players = match status["players_max"].as_u64() {
+        Some(max) if max > 0 => format!("{}/{}", status["players"].as_u64().unwrap_or(0), max),
+        _ => status["players"].as_u64().unwrap_or(0).to_string(),
+    };
+
+    println!("State:      {state}");
+    println!("Uptime:     {}", uptime.as_deref().unwrap_or("-"));
+    println!("Players:    {players}");
+    println!("Last wake:  {}", format_time(&status["last_wake"]));
+    println!("Last sleep: {}", format_time(&status["last_sleep"]));
+
+    match state {
+        "online" => EXIT_ONLINE,
+        "starting" | "resuming" => EXIT_STARTING,
+        _ => EXIT_SLEEPING,
+    }
+}
+
+/// Format Unix timestamp as local time.
+fn format_time(time: &Value) -> String {
+    time.as_i64()
+        .and_then(|secs| Local.timestamp_opt(secs, 0).single())
+        .map(|time| time.format("%Y-%m-%d %H:%M:%S").to_string())
+        .unwrap_or_else(|| "never".into())
+}
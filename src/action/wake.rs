@@ -0,0 +1,25 @@
+use std::process;
+use std::time::Duration;
+
+use clap::ArgMatches;
+
+use crate::action::ctl;
+
+/// Invoke wake command.
+pub fn invoke(matches: &ArgMatches) {
+    let socket = ctl::socket(matches, "Waking");
+
+    if ctl::request(&socket, "wake")["ok"].as_bool() == Some(true) {
+        eprintln!("Waking server");
+    } else {
+        eprintln!("Server is not sleeping");
+    }
+
+    // Wait for server to come online
+    if matches.get_flag("wait") {
+        let timeout = Duration::from_secs(*matches.get_one::<u64>("timeout").unwrap());
+        if !ctl::wait_for_state(&socket, "online", timeout) {
+            process::exit(1);
+        }
+    }
+}
@@ -0,0 +1,250 @@
+use std::sync::OnceLock;
+use std::time::Duration;
+
+use bytes::BytesMut;
+use rand::Rng;
+use rsa::pkcs8::EncodePublicKey;
+use rsa::{Pkcs1v15Encrypt, RsaPrivateKey, RsaPublicKey};
+use serde::{Deserialize, Serialize};
+use sha1::{Digest, Sha1};
+use tokio::io::{AsyncRead, AsyncWrite};
+use uuid::Uuid;
+
+use crate::config::{Auth, Config, Method};
+use crate::proto::client::{Client, ClientInfo};
+use crate::proto::packet::{self, RawPacket};
+use crate::proto::packets;
+use crate::stream::ClientStream;
+use crate::types;
+
+/// Server key pair for encryption requests, generated once.
+static KEY: OnceLock<(RsaPrivateKey, Vec<u8>)> = OnceLock::new();
+
+/// Authenticated player profile, as returned by the session server.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Profile {
+    /// Player UUID, without dashes.
+    pub id: String,
+
+    /// Player username.
+    pub name: String,
+
+    /// Profile properties, such as skin textures.
+    #[serde(default)]
+    pub properties: Vec<ProfileProperty>,
+}
+
+/// Player profile property.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ProfileProperty {
+    /// Property name.
+    pub name: String,
+
+    /// Property value.
+    pub value: String,
+
+    /// Property signature.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signature: Option<String>,
+}
+
+/// Whether lazymc must authenticate players itself.
+///
+/// This is the case when lazymc handles the login, because the lobby method is used or player info
+/// is forwarded to a server in offline mode.
+pub fn must_authenticate(config: &Config) -> bool {
+    config.public.online_mode
+        && (config.join.forwarding.is_enabled() || config.join.methods.contains(&Method::Lobby))
+}
+
+/// Authenticate client with the session server.
+///
+/// Must be called right after receiving login start. Encryption is enabled on the client stream
+/// once the client sent the shared secret, buffered data is decrypted.
+///
+/// Updates client info with the authenticated profile.
+pub async fn authenticate(
+    config: &Config,
+    client: &Client,
+    client_info: &mut ClientInfo,
+    inbound: &mut ClientStream,
+    buf: &mut BytesMut,
+) -> Result<(), ()> {
+    let username = client_info.username.clone().ok_or(())?;
+    let protocol = client_info.protocol().unwrap_or_default();
+
+    // Request and decrypt shared secret, enable encryption
+    let (mut reader, mut writer) = inbound.split();
+    let secret = request_encryption(client, protocol, buf, &mut reader, &mut writer).await?;
+    inbound.encrypt(&secret, buf)?;
+
+    let profile = has_joined(&config.auth, &username, &secret).await?;
+    debug!(target: "lazymc::auth", "Authenticated player '{}' ({})", profile.name, profile.id);
+
+    client_info.username = Some(profile.name.clone());
+    client_info.uuid = Uuid::try_parse(&profile.id).ok();
+    if client_info.login_uuid.is_some() && client_info.login_uuid != client_info.uuid {
+        debug!(target: "lazymc::auth", "Player '{}' sent a different UUID in login start, using authenticated UUID", profile.name);
+    }
+    client_info.properties = profile.properties;
+
+    Ok(())
+}
+
+/// Send encryption request and wait for the response.
+///
+/// Returns the decrypted shared secret.
+async fn request_encryption(
+    client: &Client,
+    protocol: u32,
+    buf: &mut BytesMut,
+    reader: &mut (impl AsyncRead + Unpin),
+    writer: &mut (impl AsyncWrite + Unpin),
+) -> Result<Vec<u8>, ()> {
+    let (key, public_key) = key();
+    let verify_token: [u8; 4] = rand::thread_rng().gen();
+
+    // Server ID is empty since 1.7
+    let mut data = types::encode_string("")?;
+    data.extend(types::encode_var_int(public_key.len() as i32)?);
+    data.extend_from_slice(public_key);
+    data.extend(types::encode_var_int(verify_token.len() as i32)?);
+    data.extend_from_slice(&verify_token);
+
+    // 1.20.5+: should authenticate
+    if protocol >= 766 {
+        data.push(1);
+    }
+
+    packet::write_raw_packet(
+        RawPacket::new(packets::login::CLIENT_ENCRYPTION_REQUEST, data),
+        client,
+        writer,
+    )
+    .await?;
+
+    let (packet, _raw) = packet::read_packet(client, buf, reader).await?.ok_or(())?;
+    if packet.id != packets::login::SERVER_ENCRYPTION_RESPONSE {
+        debug!(target: "lazymc::auth", "Expected encryption response from client, got packet 0x{:02X}", packet.id);
+        return Err(());
+    }
+
+    // Read shared secret and verify token
+    let data = packet.data.as_slice();
+    let (secret, mut data) = read_byte_array(data)?;
+
+    // 1.19 to 1.19.2: verify token may be replaced by signature, which is unsupported
+    if (759..=760).contains(&protocol) {
+        let (has_verify_token, rest) = data.split_first().ok_or(())?;
+        if *has_verify_token == 0 {
+            warn!(target: "lazymc::auth", "Client sent signed encryption response, which is not supported");
+            return Err(());
+        }
+        data = rest;
+    }
+    let (token, _) = read_byte_array(data)?;
+
+    let secret = key.decrypt(Pkcs1v15Encrypt, secret).map_err(|_| ())?;
+    let token = key.decrypt(Pkcs1v15Encrypt, token).map_err(|_| ())?;
+    if token != verify_token || secret.len() != 16 {
+        debug!(target: "lazymc::auth", "Client sent invalid encryption response");
+        return Err(());
+    }
+
+    Ok(secret)
+}
+
+/// Ask session server whether player joined with the given shared secret.
+async fn has_joined(auth: &Auth, username: &str, secret: &[u8]) -> Result<Profile, ()> {
+    let (_, public_key) = key();
+    let url = format!(
+        "{}/session/minecraft/hasJoined",
+        auth.session_server.trim_end_matches('/')
+    );
+
+    let client = reqwest::Client::builder()
+        .danger_accept_invalid_certs(!auth.tls_verify)
+        .timeout(Duration::from_secs(auth.timeout_secs as u64))
+        .build()
+        .map_err(|err| {
+            error!(target: "lazymc::auth", "Failed to set up session server client: {}", err);
+        })?;
+    let response = client
+        .get(url)
+        .query(&[
+            ("username", username),
+            ("serverId", &server_hash(secret, public_key)),
+        ])
+        .send()
+        .await
+        .map_err(|err| {
+            warn!(target: "lazymc::auth", "Failed to reach session server: {}", err);
+        })?;
+
+    // Session server responds with no content if player did not join
+    if response.status() != reqwest::StatusCode::OK {
+        info!(target: "lazymc::auth", "Player '{}' failed to authenticate", username);
+        return Err(());
+    }
+
+    response.json().await.map_err(|err| {
+        warn!(target: "lazymc::auth", "Failed to parse session server response: {}", err);
+    })
+}
+
+/// Get server key pair, generates it on first use.
+///
+/// Returns the private key and DER encoded public key.
+fn key() -> &'static (RsaPrivateKey, Vec<u8>) {
+    KEY.get_or_init(|| {
+        let key = RsaPrivateKey::new(&mut rand::thread_rng(), 1024)
+            .expect("failed to generate server key pair");
+        let public_key = RsaPublicKey::from(&key)
+            .to_public_key_der()
+            .expect("failed to encode server public key")
+            .as_bytes()
+            .to_vec();
+        (key, public_key)
+    })
+}
+
+/// Minecraft server hash, a signed hexadecimal SHA-1 digest.
+fn server_hash(secret: &[u8], public_key: &[u8]) -> String {
+    let mut hash: [u8; 20] = Sha1::new()
+        .chain_update(secret)
+        .chain_update(public_key)
+        .finalize()
+        .into();
+
+    // Negative numbers are shown as two's complement with a minus sign
+    let negative = hash[0] & 0x80 != 0;
+    if negative {
+        let mut carry = true;
+        for byte in hash.iter_mut().rev() {
+            *byte = !*byte;
+            if carry {
+                (*byte, carry) = byte.overflowing_add(1);
+            }
+        }
+    }
+
+    let hex: String = hash.iter().map(|b| format!("{b:02x}")).collect();
+    let hex = hex.trim_start_matches('0');
+    if negative {
+        format!("-{hex}")
+    } else {
+        hex.into()
+    }
+}
+
+/// Read var-int length prefixed byte array.
+///
+/// Returns the array and remaining data.
+fn read_byte_array(data: &[u8]) -> Result<(&[u8], &[u8]), ()> {
+    let (read, len) = types::read_var_int(data)?;
+    let data = &data[read..];
+    if data.len() < len as usize {
+        return Err(());
+    }
+    Ok(data.split_at(len as usize))
+}
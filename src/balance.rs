@@ -0,0 +1,103 @@
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicU32, AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use dashmap::DashMap;
+use rand::Rng;
+
+use crate::config::{BackendServer, BalanceStrategy, Config};
+
+/// Backend balancer of a server.
+#[derive(Default)]
+pub struct Balancer {
+    /// Round-robin position, used to pick the next backend.
+    round_robin: AtomicUsize,
+
+    /// Number of connections currently proxied to each backend.
+    connections: DashMap<SocketAddr, Arc<AtomicU32>>,
+}
+
+impl Balancer {
+    /// Connection count for given backend address.
+    fn connections(&self, addr: SocketAddr) -> Arc<AtomicU32> {
+        self.connections.entry(addr).or_default().clone()
+    }
+
+    /// Select the server address to proxy a new player connection to.
+    ///
+    /// Balances over configured backends, uses the server address if there are none.
+    pub fn select(&self, config: &Config) -> (SocketAddr, BackendGuard) {
+        let backends: Vec<&BackendServer> = config
+            .server
+            .backends
+            .iter()
+            .filter(|backend| backend.weight > 0)
+            .collect();
+        if backends.is_empty() {
+            return (config.server.address, BackendGuard::none());
+        }
+
+        let total_weight: u32 = backends.iter().map(|backend| backend.weight).sum();
+        let backend = match config.server.balance {
+            BalanceStrategy::RoundRobin => {
+                let pos = self.round_robin.fetch_add(1, Ordering::Relaxed) % total_weight as usize;
+                by_weight(&backends, pos as u32)
+            }
+            BalanceStrategy::Random => {
+                by_weight(&backends, rand::thread_rng().gen_range(0..total_weight))
+            }
+            BalanceStrategy::LeastConnections => backends
+                .iter()
+                .min_by(|a, b| {
+                    let a_load = self.connections(a.address).load(Ordering::Relaxed) as f64
+                        / a.weight as f64;
+                    let b_load = self.connections(b.address).load(Ordering::Relaxed) as f64
+                        / b.weight as f64;
+                    a_load.total_cmp(&b_load)
+                })
+                .copied()
+                .unwrap(),
+        };
+
+        trace!(target: "lazymc", "Selected backend {} for connection", backend.address);
+
+        (
+            backend.address,
+            BackendGuard::new(self.connections(backend.address)),
+        )
+    }
+}
+
+/// Guard for a connection to a backend, tracks backend connection count.
+pub struct BackendGuard(Option<Arc<AtomicU32>>);
+
+impl BackendGuard {
+    /// Guard that does not track any backend.
+    pub fn none() -> Self {
+        Self(None)
+    }
+
+    fn new(count: Arc<AtomicU32>) -> Self {
+        count.fetch_add(1, Ordering::Relaxed);
+        Self(Some(count))
+    }
+}
+
+impl Drop for BackendGuard {
+    fn drop(&mut self) {
+        if let Some(count) = &self.0 {
+            count.fetch_sub(1, Ordering::Relaxed);
+        }
+    }
+}
+
+/// Select backend at the given position in the total weight range.
+fn by_weight<'a>(backends: &[&'a BackendServer], mut pos: u32) -> &'a BackendServer {
+    for backend in backends {
+        if pos < backend.weight {
+            return backend;
+        }
+        pos -= backend.weight;
+    }
+    backends[backends.len() - 1]
+}
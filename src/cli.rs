@@ -1,4 +1,4 @@
-use clap::{Arg, Command};
+use clap::{Arg, ArgAction, Command};
 
 /// The clap app for CLI argument parsing.
 pub fn app() -> Command {
@@ -22,7 +22,108 @@ pub fn app() -> Command {
                         .alias("gen")
                         .about("Generate config"),
                 )
-                .subcommand(Command::new("test").about("Test config")),
+                .subcommand(Command::new("test").about("Test config"))
+                .subcommand(
+                    Command::new("validate")
+                        .about("Validate config, report all semantic problems"),
+                )
+                .subcommand(
+                    Command::new("migrate")
+                        .about("Migrate config to current version, prints changes")
+                        .arg(
+                            Arg::new("dry-run")
+                                .long("dry-run")
+                                .action(ArgAction::SetTrue)
+                                .help("Print changes without saving config"),
+                        )
+                        .arg(
+                            Arg::new("from")
+                                .long("from")
+                                .value_name("VERSION")
+                                .help("lazymc version the config is for, if it has no config.version")
+                                .num_args(1),
+                        ),
+                )
+                .subcommand(
+                    Command::new("schema").about("Print JSON Schema for config, for editor support"),
+                ),
+        )
+        .subcommand(
+            Command::new("reload")
+                .long_flag("reload")
+                .about("Reload config of running lazymc instance")
+                .arg(
+                    Arg::new("pid")
+                        .long("pid")
+                        .value_name("PID")
+                        .value_parser(value_parser!(u32))
+                        .help("PID of running lazymc process, defaults to advanced.pid_file from config")
+                        .num_args(1),
+                ),
+        )
+        .subcommand(
+            Command::new("status")
+                .about("Show state of running lazymc instance, exits 0 if online, 1 if sleeping, 2 if starting")
+                .arg(arg_socket()),
+        )
+        .subcommand(
+            Command::new("wake")
+                .about("Wake server of running lazymc instance")
+                .arg(arg_wait())
+                .arg(arg_timeout())
+                .arg(arg_socket()),
+        )
+        .subcommand(
+            Command::new("sleep")
+                .about("Put server of running lazymc instance to sleep")
+                .arg(
+                    Arg::new("force")
+                        .long("force")
+                        .action(ArgAction::SetTrue)
+                        .help("Force kill server immediately instead of stopping it gracefully"),
+                )
+                .arg(arg_wait())
+                .arg(arg_timeout())
+                .arg(arg_socket()),
+        )
+        .subcommand(
+            Command::new("ban")
+                .about("Ban IP in server banned-ips.json, applied without restart")
+                .arg(arg_ip())
+                .arg(
+                    Arg::new("reason")
+                        .long("reason")
+                        .value_name("REASON")
+                        .default_value("Banned by an operator.")
+                        .help("Ban reason shown to the player")
+                        .num_args(1),
+                ),
+        )
+        .subcommand(
+            Command::new("unban")
+                .about("Unban IP in server banned-ips.json, applied without restart")
+                .arg(arg_ip()),
+        )
+        .subcommand(
+            Command::new("stats").about("Show summary of recorded player sessions and server wakes"),
+        )
+        .subcommand(
+            Command::new("ctl")
+                .about("Control running lazymc instance over IPC socket")
+                .arg(
+                    Arg::new("command")
+                        .value_name("COMMAND")
+                        .required(true)
+                        .value_parser(["status", "wake", "sleep", "kill", "reload"])
+                        .help("Command to send"),
+                )
+                .arg(
+                    Arg::new("socket")
+                        .long("socket")
+                        .value_name("PATH")
+                        .help("IPC socket path, defaults to advanced.ipc_socket from config")
+                        .num_args(1),
+                ),
         )
         .arg(
             Arg::new("config")
@@ -35,4 +136,67 @@ pub fn app() -> Command {
                 .help("Use config file")
                 .num_args(1),
         )
+        .arg(
+            Arg::new("config-dir")
+                .long("config-dir")
+                .value_name("DIR")
+                .help("Start a server for each .toml config file in directory, such as lazymc.d")
+                .num_args(1),
+        )
+        .arg(
+            Arg::new("log-format")
+                .long("log-format")
+                .global(true)
+                .value_name("FORMAT")
+                .value_parser(["human", "json"])
+                .default_value("human")
+                .help("Log output format")
+                .num_args(1),
+        )
+        .arg(
+            Arg::new("dump-config")
+                .long("dump-config")
+                .value_name("FORMAT")
+                .value_parser(["toml", "json"])
+                .num_args(0..=1)
+                .default_missing_value("toml")
+                .help("Print effective config to stdout and exit"),
+        )
+}
+
+/// Argument to wait for server state change.
+fn arg_wait() -> Arg {
+    Arg::new("wait")
+        .long("wait")
+        .action(ArgAction::SetTrue)
+        .help("Wait until the server reached the target state")
+}
+
+/// Argument for maximum time to wait for server state change.
+fn arg_timeout() -> Arg {
+    Arg::new("timeout")
+        .long("timeout")
+        .value_name("SECONDS")
+        .value_parser(value_parser!(u64))
+        .default_value("300")
+        .help("Exit with error if target state is not reached in time when waiting")
+        .num_args(1)
+}
+
+/// Argument for IPC socket path.
+fn arg_socket() -> Arg {
+    Arg::new("socket")
+        .long("socket")
+        .value_name("PATH")
+        .help("IPC socket path, defaults to advanced.ipc_socket from config")
+        .num_args(1)
+}
+
+/// Argument for IP address.
+fn arg_ip() -> Arg {
+    Arg::new("ip")
+        .value_name("IP")
+        .required(true)
+        .value_parser(value_parser!(std::net::IpAddr))
+        .help("IP address")
 }
@@ -1,22 +1,135 @@
+use std::collections::HashMap;
 use std::env;
 use std::fs;
 use std::io;
-use std::net::SocketAddr;
-use std::path::PathBuf;
+use std::net::{IpAddr, SocketAddr, ToSocketAddrs};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::Duration;
 
+use chrono::{DateTime, TimeZone};
 use clap::ArgMatches;
-use serde::Deserialize;
+use ipnet::IpNet;
+use regex::Regex;
+use schemars::JsonSchema;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use thiserror::Error;
+use uuid::Uuid;
 use version_compare::Cmp;
 
+use crate::auth;
+use crate::mc::favicon;
+use crate::net;
 use crate::proto;
+use crate::stats::ServerStats;
 use crate::util::error::{quit_error, quit_error_msg, ErrorHintsBuilder};
-use crate::util::serde::to_socket_addrs;
+use crate::util::serde::{
+    from_uuid, protocol_or_auto, string_or_vec, to_ipnets, to_socket_addrs, to_uuid,
+};
 
 /// Default configuration file location.
 pub const CONFIG_FILE: &str = "lazymc.toml";
 
+/// Default prefix of environment variables to load configuration from.
+const ENV_PREFIX: &str = "LAZYMC_";
+
 /// Configuration version user should be using, or warning will be shown.
-const CONFIG_VERSION: &str = "0.2.8";
+pub const CONFIG_VERSION: &str = "0.2.8";
+
+/// Minimum interval to poll a starting server at, to never poll in a busy loop.
+const MIN_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Default ACME directory, Let's Encrypt production.
+const ACME_DIRECTORY_URL: &str = "https://acme-v02.api.letsencrypt.org/directory";
+
+/// Load configs to start lazymc with, based on CLI arguments.
+///
+/// Loads each config in the config directory if given, the single config otherwise. Quits with
+/// an error message on failure.
+pub fn load_all(matches: &ArgMatches) -> Vec<Config> {
+    let Some(dir) = matches.get_one::<String>("config-dir") else {
+        return vec![load(matches)];
+    };
+
+    let configs = match Config::load_directory(PathBuf::from(dir)) {
+        Ok(configs) => configs,
+        Err(err) => {
+            quit_error(
+                anyhow::anyhow!(err).context("Failed to load config directory"),
+                ErrorHintsBuilder::default()
+                    .config(true)
+                    .config_test(true)
+                    .build()
+                    .unwrap(),
+            );
+        }
+    };
+    if configs.is_empty() {
+        quit_error_msg(
+            format!("No config files found in config directory {}", dir),
+            ErrorHintsBuilder::default()
+                .add_info("add a '.toml' config file for each server to the directory".into())
+                .build()
+                .unwrap(),
+        );
+    }
+    if let Some(key) = process_wide_conflict(&configs) {
+        quit_error_msg(
+            format!("{key} differs between config files in config directory {dir}"),
+            ErrorHintsBuilder::default()
+                .add_info(format!(
+                    "{key} is shared by all servers, set it to the same value in each config file"
+                ))
+                .build()
+                .unwrap(),
+        );
+    }
+    configs
+}
+
+/// Find process wide setting that differs between configs.
+///
+/// Returns the key of the first conflicting setting, `None` if all configs agree.
+fn process_wide_conflict(configs: &[Config]) -> Option<&'static str> {
+    // Only one GeoIP database is loaded
+    let mut geoip = configs
+        .iter()
+        .filter(|config| config.access.geoip.enabled)
+        .map(|config| config.resolve_path(&config.access.geoip.database));
+    if let Some(first) = geoip.next() {
+        if geoip.any(|path| path != first) {
+            return Some("access.geoip.database");
+        }
+    }
+
+    // Notifications go to the single systemd service
+    if configs
+        .iter()
+        .any(|config| config.advanced.systemd_notify() != configs[0].advanced.systemd_notify())
+    {
+        return Some("advanced.systemd_notify");
+    }
+
+    // Logger is configured once, with file paths relative to each config
+    let log = |config: &Config| {
+        let mut log = config.advanced.log.clone();
+        log.file = log.file.map(|path| config.resolve_path(&path));
+        log
+    };
+    if configs.iter().any(|config| log(config) != log(&configs[0])) {
+        return Some("advanced.log");
+    }
+
+    // Only one PID file is written
+    if configs
+        .iter()
+        .any(|config| config.pid_file() != configs[0].pid_file())
+    {
+        return Some("advanced.pid_file");
+    }
+
+    None
+}
 
 /// Load config from file or environment variables, based on CLI arguments.
 ///
@@ -31,7 +144,7 @@ pub fn load(matches: &ArgMatches) -> Config {
     // Check if configuration file exists
     if path.is_file() {
         // Load from file
-        match Config::load_from_file(path) {
+        match Config::load(path) {
             Ok(config) => config,
             Err(err) => {
                 quit_error(
@@ -47,71 +160,279 @@ pub fn load(matches: &ArgMatches) -> Config {
     } else {
         // Load from environment variables with defaults
         info!(target: "lazymc::config", "Config file not found at {}, using environment variables and defaults", path.display());
-        Config::load_from_env()
+        Config::load_from_env(&EnvReader::default())
+    }
+}
+
+/// Check whether the given config path is a YAML file, based on its extension.
+fn is_yaml(path: &Path) -> bool {
+    matches!(
+        path.extension().and_then(|ext| ext.to_str()),
+        Some("yaml" | "yml")
+    )
+}
+
+/// Load TOML file as table, with files listed in its `includes` array merged in.
+///
+/// Included paths are relative to the including file. Values in the including file take
+/// precedence, arrays are appended. `chain` holds the files being loaded to detect circular
+/// includes.
+fn load_toml_includes(path: &Path, chain: &mut Vec<PathBuf>) -> Result<toml::Table, io::Error> {
+    let path = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+    if chain.contains(&path) {
+        return Err(io::Error::other(format!(
+            "circular config include of {}",
+            path.display()
+        )));
+    }
+
+    let data = fs::read_to_string(&path)?;
+    let mut table: toml::Table = toml::from_str(&data).map_err(io::Error::other)?;
+    let includes = match table.remove("includes") {
+        None => vec![],
+        Some(toml::Value::Array(includes)) => includes,
+        Some(_) => {
+            return Err(io::Error::other(
+                "config includes must be an array of paths",
+            ))
+        }
+    };
+
+    chain.push(path.clone());
+    let dir = path.parent().unwrap_or_else(|| Path::new("."));
+    for include in includes {
+        let include = include
+            .as_str()
+            .ok_or_else(|| io::Error::other("config includes must be an array of paths"))?;
+        let included = load_toml_includes(&dir.join(include), chain).map_err(|err| {
+            io::Error::new(err.kind(), format!("failed to include {include}: {err}"))
+        })?;
+        merge_toml(&mut table, included);
     }
+    chain.pop();
+
+    Ok(table)
 }
 
-/// Get environment variable as string with optional default, processing escape sequences
-fn get_env_string(key: &str, default: Option<&str>) -> Option<String> {
-    let value = env::var(key).ok().or_else(|| default.map(|s| s.to_string()))?;
-    Some(process_escape_sequences(&value))
+/// Deep merge included TOML table into base table.
+///
+/// Base values take precedence, tables are merged and arrays are appended.
+fn merge_toml(base: &mut toml::Table, included: toml::Table) {
+    for (key, value) in included {
+        match (base.get_mut(&key), value) {
+            (None, value) => {
+                base.insert(key, value);
+            }
+            (Some(toml::Value::Table(base)), toml::Value::Table(included)) => {
+                merge_toml(base, included)
+            }
+            (Some(toml::Value::Array(base)), toml::Value::Array(included)) => base.extend(included),
+            (Some(_), _) => {}
+        }
+    }
 }
 
 /// Process common escape sequences in strings
+///
+/// Supports `\n`, `\r`, `\t`, `\\` and `\uXXXX` with exactly 4 hex digits. Escaped code points
+/// that are not valid characters are replaced with a `\u{XXXX}` placeholder. Unknown escape
+/// sequences are kept as-is.
 fn process_escape_sequences(input: &str) -> String {
-    input
-        .replace("\\n", "\n")
-        .replace("\\r", "\r")
-        .replace("\\t", "\t")
-        .replace("\\\\", "\\")
-}
-
-/// Get environment variable as socket address with default
-fn get_env_socket_addr(key: &str, default: &str) -> SocketAddr {
-    env::var(key)
-        .ok()
-        .and_then(|s| s.parse().ok())
-        .unwrap_or_else(|| default.parse().unwrap())
-}
-
-/// Get environment variable as u32 with default
-fn get_env_u32(key: &str, default: u32) -> u32 {
-    env::var(key)
-        .ok()
-        .and_then(|s| s.parse().ok())
-        .unwrap_or(default)
-}
-
-/// Get environment variable as u16 with default
-fn get_env_u16(key: &str, default: u16) -> u16 {
-    env::var(key)
-        .ok()
-        .and_then(|s| s.parse().ok())
-        .unwrap_or(default)
-}
-
-/// Get environment variable as bool with default
-fn get_env_bool(key: &str, default: bool) -> bool {
-    env::var(key)
-        .ok()
-        .map(|s| match s.to_lowercase().as_str() {
-            "true" | "1" | "yes" | "on" => true,
-            "false" | "0" | "no" | "off" => false,
-            _ => default,
-        })
-        .unwrap_or(default)
+    let mut output = String::with_capacity(input.len());
+    let mut chars = input.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            output.push(c);
+            continue;
+        }
+
+        let rest = chars.as_str();
+        match rest.chars().next() {
+            Some('n') => output.push('\n'),
+            Some('r') => output.push('\r'),
+            Some('t') => output.push('\t'),
+            Some('\\') => output.push('\\'),
+            Some('u') => {
+                let hex = rest
+                    .get(1..5)
+                    .filter(|hex| hex.chars().all(|c| c.is_ascii_hexdigit()));
+                let Some(hex) = hex else {
+                    output.push(c);
+                    continue;
+                };
+                match u32::from_str_radix(hex, 16).ok().and_then(char::from_u32) {
+                    Some(c) => output.push(c),
+                    None => output.push_str(&format!("\\u{{{hex}}}")),
+                }
+                chars = rest[5..].chars();
+                continue;
+            }
+            _ => {
+                output.push(c);
+                continue;
+            }
+        }
+        chars.next();
+    }
+    output
+}
+
+/// Environment variable reader.
+///
+/// Prepends the configured prefix to all keys, `LAZYMC_` by default.
+#[derive(Debug, Clone)]
+pub struct EnvReader {
+    prefix: String,
+}
+
+impl Default for EnvReader {
+    fn default() -> Self {
+        Self::new(None)
+    }
 }
 
-/// Get environment variable as vector of strings
-fn get_env_vec_string(key: &str, default: Vec<&str>) -> Vec<String> {
-    env::var(key)
-        .ok()
-        .map(|s| s.split(',').map(|s| s.trim().to_string()).collect())
-        .unwrap_or_else(|| default.into_iter().map(|s| s.to_string()).collect())
+impl EnvReader {
+    /// Create reader for given prefix, uses the default prefix if `None`.
+    pub fn new(prefix: Option<&str>) -> Self {
+        Self {
+            prefix: prefix.unwrap_or(ENV_PREFIX).to_string(),
+        }
+    }
+
+    /// Full environment variable name for given key.
+    pub fn key(&self, key: &str) -> String {
+        format!("{}{}", self.prefix, key)
+    }
+
+    /// Get environment variable.
+    fn var(&self, key: &str) -> Result<String, env::VarError> {
+        env::var(self.key(key))
+    }
+
+    /// Get required environment variable, error message naming it if missing.
+    fn required(&self, key: &str) -> Result<String, String> {
+        self.var(key)
+            .map_err(|_| format!("Missing required environment variable: {}", self.key(key)))
+    }
+
+    /// Get environment variable as string with optional default, processing escape sequences
+    fn string(&self, key: &str, default: Option<&str>) -> Option<String> {
+        let value = self
+            .var(key)
+            .ok()
+            .or_else(|| default.map(|s| s.to_string()))?;
+        Some(process_escape_sequences(&value))
+    }
+
+    /// Get environment variable as socket address with default
+    ///
+    /// Hostnames are resolved once when reading, the first resolved address is used. Falls back
+    /// to the default with a warning if the address can't be resolved.
+    fn socket_addr(&self, key: &str, default: &str) -> SocketAddr {
+        let default = default.parse().unwrap();
+        let Ok(value) = self.var(key) else {
+            return default;
+        };
+        if let Ok(addr) = value.parse() {
+            return addr;
+        }
+
+        match value.to_socket_addrs().map(|mut addrs| addrs.next()) {
+            Ok(Some(addr)) => {
+                debug!(target: "lazymc::config", "Resolved {} address {} to {}", self.key(key), value, addr);
+                addr
+            }
+            Ok(None) => {
+                warn!(target: "lazymc::config", "Failed to resolve {} address {}, using {}: no addresses found", self.key(key), value, default);
+                default
+            }
+            Err(err) => {
+                warn!(target: "lazymc::config", "Failed to resolve {} address {}, using {}: {}", self.key(key), value, default, err);
+                default
+            }
+        }
+    }
+
+    /// Get environment variable as u32 with default
+    fn u32(&self, key: &str, default: u32) -> u32 {
+        self.var(key)
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .unwrap_or(default)
+    }
+
+    /// Get environment variable as u64 with default
+    fn u64(&self, key: &str, default: u64) -> u64 {
+        self.var(key)
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .unwrap_or(default)
+    }
+
+    /// Get environment variable as f64 with default
+    fn f64(&self, key: &str, default: f64) -> f64 {
+        self.var(key)
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .unwrap_or(default)
+    }
+
+    /// Get environment variable as u16 with default
+    fn u16(&self, key: &str, default: u16) -> u16 {
+        self.var(key)
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .unwrap_or(default)
+    }
+
+    /// Get environment variable as bool with default
+    fn bool(&self, key: &str, default: bool) -> bool {
+        self.var(key)
+            .ok()
+            .map(|s| match s.to_lowercase().as_str() {
+                "true" | "1" | "yes" | "on" => true,
+                "false" | "0" | "no" | "off" => false,
+                _ => default,
+            })
+            .unwrap_or(default)
+    }
+
+    /// Get environment variable as vector of strings
+    fn vec_string(&self, key: &str, default: Vec<&str>) -> Vec<String> {
+        self.var(key)
+            .ok()
+            .map(|s| s.split(',').map(|s| s.trim().to_string()).collect())
+            .unwrap_or_else(|| default.into_iter().map(|s| s.to_string()).collect())
+    }
+
+    /// Get environment variable as list of strings, separated by semicolons.
+    ///
+    /// For values commonly containing commas, such as commands and messages.
+    fn vec_semicolon(&self, key: &str) -> Vec<String> {
+        self.var(key)
+            .map(|s| {
+                s.split(';')
+                    .map(|s| s.trim().to_string())
+                    .filter(|s| !s.is_empty())
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Get environment variable as list of networks, comma separated.
+    ///
+    /// Plain IP addresses are accepted as single address networks. Invalid entries are skipped.
+    fn vec_ipnet(&self, key: &str) -> Vec<IpNet> {
+        self.vec_string(key, vec![])
+            .iter()
+            .filter(|s| !s.is_empty())
+            .filter_map(|s| net::parse_ipnet(s).ok())
+            .collect()
+    }
 }
 
 /// Configuration.
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize, JsonSchema, Serialize)]
 pub struct Config {
     /// Configuration path if known.
     ///
@@ -142,10 +463,62 @@ pub struct Config {
     #[serde(default)]
     pub lockout: Lockout,
 
+    /// Access configuration.
+    #[serde(default)]
+    pub access: Access,
+
+    /// Authentication configuration.
+    #[serde(default)]
+    pub auth: Auth,
+
     /// RCON configuration.
     #[serde(default)]
     pub rcon: Rcon,
 
+    /// Metrics configuration.
+    #[serde(default)]
+    pub metrics: Metrics,
+
+    /// Management API configuration.
+    #[serde(default)]
+    pub api: Api,
+
+    /// Query protocol configuration.
+    #[serde(default)]
+    pub query: Query,
+
+    /// Notification configuration.
+    #[serde(default)]
+    pub notify: Notify,
+
+    /// Hooks configuration.
+    #[serde(default)]
+    pub hooks: Hooks,
+
+    /// Stats configuration.
+    #[serde(default)]
+    pub stats: Stats,
+
+    /// TLS configuration.
+    #[serde(default)]
+    pub tls: Tls,
+
+    /// Chat filter configuration.
+    #[serde(default)]
+    pub chat_filter: ChatFilter,
+
+    /// Command filter configuration.
+    #[serde(default)]
+    pub command_filter: CommandFilter,
+
+    /// Virtual hosts, routed by handshake hostname.
+    #[serde(default, rename = "vhost")]
+    pub vhosts: Vec<VirtualHost>,
+
+    /// Dedicated servers for specific players.
+    #[serde(default, rename = "player_route")]
+    pub player_routes: Vec<PlayerRoute>,
+
     /// Advanced configuration.
     #[serde(default)]
     pub advanced: Advanced,
@@ -156,17 +529,85 @@ pub struct Config {
 }
 
 impl Config {
-    /// Load configuration from file.
+    /// Load configuration from TOML file.
+    ///
+    /// Files listed in `includes` are merged in, see `load_toml_includes`.
     pub fn load_from_file(path: PathBuf) -> Result<Self, io::Error> {
+        let table = load_toml_includes(&path, &mut vec![])?;
+        let mut config: Config = toml::Value::Table(table)
+            .try_into()
+            .map_err(io::Error::other)?;
+        config.check_version();
+        config.path.replace(path);
+        config.check_favicons();
+        config.check_motd();
+
+        Ok(config)
+    }
+
+    /// Load configuration from YAML file.
+    pub fn load_from_yaml(path: PathBuf) -> Result<Self, io::Error> {
         let data = fs::read_to_string(&path)?;
-        let mut config: Config = toml::from_str(&data).map_err(io::Error::other)?;
+        let mut config: Config = serde_yaml::from_str(&data).map_err(io::Error::other)?;
+        config.check_version();
+        config.path.replace(path);
+        config.check_favicons();
+        config.check_motd();
+
+        Ok(config)
+    }
+
+    /// Load configuration from file path.
+    ///
+    /// Loads as YAML if the file has a `.yaml` or `.yml` extension, as TOML otherwise.
+    ///
+    /// If the file sets an environment variable prefix, configuration is loaded from environment
+    /// variables with that prefix instead.
+    pub fn load(path: PathBuf) -> Result<Self, io::Error> {
+        let config = if is_yaml(&path) {
+            Self::load_from_yaml(path)?
+        } else {
+            Self::load_from_file(path)?
+        };
+
+        if let Some(prefix) = config.config.env_prefix.as_deref() {
+            info!(target: "lazymc::config", "Loading configuration from environment variables with prefix {}", prefix);
+            let mut env_config = Self::load_from_env(&EnvReader::new(Some(prefix)));
+            env_config.path = config.path;
+            return Ok(env_config);
+        }
+
+        Ok(config)
+    }
+
+    /// Load all `*.toml` configuration files from a directory, in sorted order.
+    ///
+    /// Each file is an independent server configuration.
+    pub fn load_directory(dir: PathBuf) -> Result<Vec<Self>, io::Error> {
+        let mut paths: Vec<PathBuf> = fs::read_dir(&dir)?
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| path.is_file() && path.extension().is_some_and(|ext| ext == "toml"))
+            .collect();
+        paths.sort();
+
+        paths
+            .into_iter()
+            .map(|path| {
+                let path = path.canonicalize().unwrap_or(path);
+                Self::load(path.clone()).map_err(|err| {
+                    io::Error::new(err.kind(), format!("{}: {}", path.display(), err))
+                })
+            })
+            .collect()
+    }
 
-        // Show warning if config version is problematic
-        match &config.config.version {
+    /// Show warning if config version is problematic.
+    fn check_version(&self) {
+        match &self.config.version {
             None => warn!(target: "lazymc::config", "Config version unknown, it may be outdated"),
             Some(version) => match version_compare::compare_to(version, CONFIG_VERSION, Cmp::Ge) {
                 Ok(false) => {
-                    warn!(target: "lazymc::config", "Config is for older lazymc version, you may need to update it")
+                    warn!(target: "lazymc::config", "Config is for older lazymc version, you may need to update it with: lazymc config migrate")
                 }
                 Err(_) => {
                     warn!(target: "lazymc::config", "Config version is invalid, you may need to update it")
@@ -174,573 +615,3450 @@ impl Config {
                 Ok(true) => {}
             },
         }
-        config.path.replace(path);
+    }
 
-        Ok(config)
+    /// Show warning if configured favicons are problematic.
+    fn check_favicons(&self) {
+        let favicons = [
+            &self.motd.favicon_sleeping,
+            &self.motd.favicon_starting,
+            &self.motd.favicon_stopping,
+        ];
+        for path in favicons.into_iter().flatten() {
+            let path = self.resolve_path(path);
+            let result = fs::read(&path)
+                .map_err(|err| err.to_string())
+                .and_then(|data| favicon::validate_favicon(&data));
+            if let Err(err) = result {
+                warn!(target: "lazymc::config", "Favicon at {} may not be shown: {}", path.display(), err);
+            }
+        }
     }
 
-    /// Convenience method to load from file path.
-    pub fn load(path: PathBuf) -> Result<Self, io::Error> {
-        Self::load_from_file(path)
+    /// Show warning if configured MOTD is problematic.
+    fn check_motd(&self) {
+        for err in self.motd.json_errors() {
+            warn!(target: "lazymc::config", "MOTD is not valid JSON, it will be shown as text: {}", err);
+        }
+    }
+
+    /// Get PID file path if configured.
+    ///
+    /// Relative paths are resolved in `XDG_RUNTIME_DIR` on Linux if set, or relative to the
+    /// configuration directory otherwise.
+    pub fn pid_file(&self) -> Option<PathBuf> {
+        let path = self.advanced.pid_file.as_ref()?;
+
+        #[cfg(target_os = "linux")]
+        if path.is_relative() {
+            if let Some(dir) = env::var_os("XDG_RUNTIME_DIR") {
+                return Some(PathBuf::from(dir).join(path));
+            }
+        }
+
+        Some(self.resolve_path(path))
+    }
+
+    /// Resolve path relative to configuration directory if known.
+    pub fn resolve_path(&self, path: &Path) -> PathBuf {
+        match self.path.as_ref().and_then(|p| p.parent()) {
+            Some(config_dir) => config_dir.join(path),
+            None => path.to_path_buf(),
+        }
     }
 
     /// Load configuration from environment variables with defaults.
-    pub fn load_from_env() -> Self {
+    pub fn load_from_env(env: &EnvReader) -> Self {
         // Validate required environment variables
-        let server_command = env::var("LAZYMC_SERVER_COMMAND")
-            .unwrap_or_else(|_| {
-                quit_error_msg(
-                    "Missing required environment variable: LAZYMC_SERVER_COMMAND".to_string(),
-                    ErrorHintsBuilder::default()
-                        .build()
-                        .unwrap(),
-                );
-            });
+        let server_command = env.required("SERVER_COMMAND").unwrap_or_else(|err| {
+            quit_error_msg(err, ErrorHintsBuilder::default().build().unwrap());
+        });
 
-        Self {
+        let config = Self {
             path: None,
-            public: Public::from_env(),
-            server: Server::from_env(server_command),
-            time: Time::from_env(),
-            motd: Motd::from_env(),
-            join: Join::from_env(),
-            lockout: Lockout::from_env(),
-            rcon: Rcon::from_env(),
-            advanced: Advanced::from_env(),
-            config: ConfigConfig::from_env(),
+            public: Public::from_env(env),
+            server: Server::from_env(env, server_command),
+            time: Time::from_env(env),
+            motd: Motd::from_env(env),
+            join: Join::from_env(env),
+            lockout: Lockout::from_env(env),
+            access: Access::from_env(env),
+            auth: Auth::from_env(env),
+            rcon: Rcon::from_env(env),
+            metrics: Metrics::from_env(env),
+            api: Api::from_env(env),
+            query: Query::from_env(env),
+            notify: Notify::from_env(env),
+            hooks: Hooks::from_env(env),
+            stats: Stats::from_env(env),
+            tls: Tls::from_env(env),
+            chat_filter: ChatFilter::from_env(env),
+            command_filter: CommandFilter::from_env(env),
+            vhosts: Vec::new(),
+            player_routes: Vec::new(),
+            advanced: Advanced::from_env(env),
+            config: ConfigConfig::from_env(env),
+        };
+        config.check_favicons();
+        config.check_motd();
+
+        config
+    }
+
+    /// Reload configuration from the same source it was loaded from.
+    ///
+    /// Reloads from file if the configuration path is known, or from environment variables
+    /// otherwise.
+    pub fn reload(&self) -> Result<Self, io::Error> {
+        match &self.path {
+            Some(path) => Self::load(path.clone()),
+            None => Ok(Self::load_from_env(&EnvReader::new(
+                self.config.env_prefix.as_deref(),
+            ))),
         }
     }
-}
 
-/// Public configuration.
-#[derive(Debug, Deserialize)]
-#[serde(default)]
-pub struct Public {
-    /// Public address.
-    #[serde(deserialize_with = "to_socket_addrs")]
-    pub address: SocketAddr,
+    /// Compare configurations, list fields changed from `old` to `new`.
+    pub fn diff(old: &Config, new: &Config) -> ConfigDiff {
+        let mut diff = ConfigDiff::default();
+        if old == new {
+            return diff;
+        }
 
-    /// Minecraft protocol version name hint.
-    pub version: String,
+        // Compare serialized form to find changed fields in nested sections
+        match (serde_json::to_value(old), serde_json::to_value(new)) {
+            (Ok(old), Ok(new)) => diff_values(String::new(), &old, &new, &mut diff.changed),
+            _ => diff.changed.push(String::new()),
+        }
+        if old.path != new.path {
+            diff.changed.push("path".into());
+        }
 
-    /// Minecraft protocol version hint.
-    pub protocol: u32,
-}
+        diff
+    }
 
-impl Public {
-    fn from_env() -> Self {
-        Self {
-            address: get_env_socket_addr("LAZYMC_PUBLIC_ADDRESS", "0.0.0.0:25565"),
-            version: get_env_string("LAZYMC_PUBLIC_VERSION", Some(proto::PROTO_DEFAULT_VERSION))
-                .unwrap_or_else(|| proto::PROTO_DEFAULT_VERSION.to_string()),
-            protocol: get_env_u32("LAZYMC_PUBLIC_PROTOCOL", proto::PROTO_DEFAULT_PROTOCOL),
+    /// Build configuration for the given virtual host.
+    ///
+    /// Uses this configuration as base, with the virtual host overrides applied.
+    pub fn vhost(&self, vhost: &VirtualHost) -> Self {
+        let mut config = self.clone();
+        config.vhosts.clear();
+        config.server.address = vhost.server_address;
+        if let Some(command) = &vhost.server_command {
+            config.server.command = command.clone();
+        }
+
+        // Don't rewrite properties of the server if it shares its directory
+        match &vhost.server_directory {
+            Some(dir) => config.server.directory = Some(dir.clone()),
+            None => config.advanced.rewrite_server_properties = false,
+        }
+        if let Some(motd) = &vhost.motd {
+            config.motd = motd.clone();
         }
+        if let Some(join) = &vhost.join {
+            config.join = join.clone();
+        }
+        config.stats.instance = Some(match &self.stats.instance {
+            Some(instance) => format!("{instance}/{}", vhost.hostname),
+            None => vhost.hostname.clone(),
+        });
+        config
     }
-}
 
-impl Default for Public {
-    fn default() -> Self {
-        Self {
-            address: "0.0.0.0:25565".parse().unwrap(),
-            version: proto::PROTO_DEFAULT_VERSION.to_string(),
-            protocol: proto::PROTO_DEFAULT_PROTOCOL,
+    /// Whether player login must be known before proxying to the server.
+    ///
+    /// True if player info is forwarded, players are routed, player hooks are used or player
+    /// sessions are recorded. lazymc then handles every connection up to login start, including
+    /// status requests, instead of proxying it to the online server right away.
+    pub fn needs_login_to_proxy(&self) -> bool {
+        self.join.forwarding.is_enabled()
+            || !self.player_routes.is_empty()
+            || self.hooks.has_player_hooks()
+            || self.stats.enabled
+    }
+
+    /// Find dedicated server address for the given player, if any.
+    ///
+    /// Players are only routed if lazymc authenticates them, the username and UUID are whatever
+    /// the client claims otherwise.
+    pub fn player_route(&self, username: Option<&str>, uuid: Option<Uuid>) -> Option<SocketAddr> {
+        if !auth::must_authenticate(self) {
+            return None;
         }
+        self.player_routes
+            .iter()
+            .find(|route| route.matches(username, uuid))
+            .map(|route| route.server_address)
     }
-}
 
-/// Server configuration.
-#[derive(Debug, Deserialize)]
-pub struct Server {
-    /// Server directory.
+    /// Validate configuration semantics.
     ///
-    /// Private because you should use `Server::server_directory()` instead.
-    #[serde(default = "option_pathbuf_dot")]
-    directory: Option<PathBuf>,
+    /// Returns a list of all violations found, which is empty if the configuration is valid.
+    pub fn validate(&self) -> Vec<ConfigError> {
+        let mut errors = Vec::new();
 
-    /// Start command.
-    pub command: String,
+        if is_public_address(self.public.address, self.server.address) {
+            errors.push(ConfigError::SameServerPublicAddress);
+        }
 
-    /// Server address.
-    #[serde(
-        deserialize_with = "to_socket_addrs",
-        default = "server_address_default"
-    )]
-    pub address: SocketAddr,
+        if self.server.start_timeout > 0 && self.join.hold.timeout >= self.server.start_timeout {
+            errors.push(ConfigError::HoldTimeoutExceedsStartTimeout);
+        }
 
-    /// Freeze the server process instead of restarting it when no players online, making it start up faster.
-    /// Only works on Unix (Linux or MacOS)
-    #[serde(default = "bool_true")]
-    pub freeze_process: bool,
+        if self.join.methods.is_empty() {
+            errors.push(ConfigError::NoJoinMethods);
+        }
 
-    /// Immediately wake server when starting lazymc.
-    #[serde(default)]
-    pub wake_on_start: bool,
+        for vhost in &self.vhosts {
+            if is_public_address(self.public.address, vhost.server_address) {
+                errors.push(ConfigError::SameVhostPublicAddress(vhost.hostname.clone()));
+            }
+            if vhost.server_directory.is_some() {
+                match Server::server_directory(&self.vhost(vhost)) {
+                    Some(dir) if !dir.is_dir() => errors.push(
+                        ConfigError::MissingVhostServerDirectory(vhost.hostname.clone(), dir),
+                    ),
+                    _ => {}
+                }
+            }
+        }
 
-    /// Immediately wake server after crash.
-    #[serde(default)]
-    pub wake_on_crash: bool,
+        if self.rcon.enabled
+            && !self.rcon.randomize_password
+            && self.rcon.password.trim().is_empty()
+        {
+            errors.push(ConfigError::EmptyRconPassword);
+        }
 
-    /// Probe required server details when starting lazymc, wakes server on start.
-    #[serde(default)]
-    pub probe_on_start: bool,
+        if self.join.methods.contains(&Method::Forward)
+            && is_public_address(self.public.address, self.join.forward.address)
+        {
+            errors.push(ConfigError::SameForwardPublicAddress);
+        }
 
-    /// Whether this server runs forge.
-    #[serde(default)]
-    pub forge: bool,
+        match Server::server_directory(self) {
+            Some(dir) if !dir.is_dir() => {
+                errors.push(ConfigError::MissingServerDirectory(dir));
+            }
+            _ => {}
+        }
 
-    /// Server starting timeout. Force kill server process if it takes longer.
-    #[serde(default = "u32_300")]
-    pub start_timeout: u32,
+        for err in self.motd.json_errors() {
+            errors.push(ConfigError::InvalidMotdJson(err));
+        }
+        for (key, err) in self.join.kick.json_errors() {
+            errors.push(ConfigError::InvalidJson(key, err));
+        }
 
-    /// Server stopping timeout. Force kill server process if it takes longer.
-    #[serde(default = "u32_150")]
-    pub stop_timeout: u32,
+        for (key, regex) in [
+            ("server.log_filter_regex", &self.server.log_filter_regex),
+            ("server.ready_log_pattern", &self.server.ready_log_pattern),
+        ] {
+            if let Some(Err(err)) = regex.as_deref().map(Regex::new) {
+                errors.push(ConfigError::InvalidRegex(key, err.to_string()));
+            }
+        }
+
+        if self.tls.enabled
+            && !self.tls.acme.enabled
+            && (self.tls.cert.as_os_str().is_empty() || self.tls.key.as_os_str().is_empty())
+        {
+            errors.push(ConfigError::MissingTlsCertificate);
+        }
+
+        if self.tls.enabled && self.tls.acme.enabled && self.tls.acme.domain.trim().is_empty() {
+            errors.push(ConfigError::MissingAcmeDomain);
+        }
+
+        if self.tls.enabled && self.tls.acme.enabled && !self.tls.acme.accept_terms {
+            errors.push(ConfigError::AcmeTermsNotAccepted);
+        }
+
+        if let ForwardingMode::Velocity { secret } = &self.join.forwarding {
+            if secret.is_empty() {
+                errors.push(ConfigError::EmptyForwardingSecret);
+            }
+        }
+
+        if !self.player_routes.is_empty() && !auth::must_authenticate(self) {
+            errors.push(ConfigError::UnverifiedPlayerRoutes);
+        }
+
+        for (key, err) in self.time.schedule.cron_errors() {
+            errors.push(ConfigError::InvalidCron(key, err));
+        }
+        if let Some(schedule) = &self.lockout.schedule {
+            for (key, err) in schedule.cron_errors() {
+                errors.push(ConfigError::InvalidCron(key, err));
+            }
+        }
+        if let Some(Err(err)) = self
+            .time
+            .wake_cron
+            .as_deref()
+            .map(str::parse::<cron::Schedule>)
+        {
+            errors.push(ConfigError::InvalidCron("time.wake_cron", err.to_string()));
+        }
+
+        if self.advanced.poll.initial_interval_ms == 0 || self.advanced.poll.max_interval_ms == 0 {
+            errors.push(ConfigError::ZeroPollInterval);
+        }
+
+        errors
+    }
+}
+
+/// Whether connecting to `addr` would connect to lazymc itself, listening on `public`.
+///
+/// A public address on all interfaces also matches loopback addresses on the same port.
+fn is_public_address(public: SocketAddr, addr: SocketAddr) -> bool {
+    addr == public
+        || (addr.port() == public.port()
+            && public.ip().is_unspecified()
+            && (addr.ip().is_unspecified() || addr.ip().is_loopback()))
+}
+
+/// Semantic configuration error.
+#[derive(Debug, Error)]
+pub enum ConfigError {
+    /// Server and public address are the same.
+    #[error("server.address and public.address must differ; server.address should be the Minecraft server's internal port")]
+    SameServerPublicAddress,
+
+    /// Hold timeout is not below start timeout.
+    #[error("join.hold.timeout must be lower than server.start_timeout")]
+    HoldTimeoutExceedsStartTimeout,
+
+    /// No join method configured.
+    #[error("join.methods must contain at least one method")]
+    NoJoinMethods,
+
+    /// Virtual host server and public address are the same.
+    #[error("server_address of vhost '{0}' and public.address must differ")]
+    SameVhostPublicAddress(String),
+
+    /// RCON password is empty without randomization.
+    #[error("rcon.password can't be empty when rcon.enabled is true and rcon.randomize_password is false")]
+    EmptyRconPassword,
+
+    /// Forward and public address are the same.
+    #[error("join.forward.address and public.address must differ")]
+    SameForwardPublicAddress,
+
+    /// Velocity forwarding secret is empty.
+    #[error("join.forwarding velocity secret can't be empty")]
+    EmptyForwardingSecret,
+
+    /// TLS certificate or key is not set.
+    #[error("tls.cert and tls.key must be set when tls.enabled is true")]
+    MissingTlsCertificate,
+
+    /// ACME domain is not set.
+    #[error("tls.acme.domain must be set when tls.acme.enabled is true")]
+    MissingAcmeDomain,
+
+    /// ACME terms of service are not accepted.
+    #[error(
+        "tls.acme.accept_terms must be set to agree to the terms of service of the ACME server"
+    )]
+    AcmeTermsNotAccepted,
+
+    /// Server directory does not exist.
+    #[error("server.directory does not exist: {}", .0.display())]
+    MissingServerDirectory(PathBuf),
+
+    /// Virtual host server directory does not exist.
+    #[error("server_directory of vhost '{0}' does not exist: {}", .1.display())]
+    MissingVhostServerDirectory(String, PathBuf),
+
+    /// MOTD is not valid JSON while JSON format is used.
+    #[error("motd is not valid JSON: {0}")]
+    InvalidMotdJson(String),
+
+    /// Message is not valid JSON.
+    #[error("{0} is not valid JSON: {1}")]
+    InvalidJson(&'static str, String),
+
+    /// Regular expression is invalid.
+    #[error("{0} is not a valid regular expression: {1}")]
+    InvalidRegex(&'static str, String),
+
+    /// Cron expression is invalid.
+    #[error("{0} is not a valid cron expression: {1}")]
+    InvalidCron(&'static str, String),
+
+    /// Poll interval is zero.
+    #[error("advanced.poll.initial_interval_ms and max_interval_ms must be greater than 0")]
+    ZeroPollInterval,
+
+    /// Player routes are configured, but player identity is not verified.
+    #[error("player_route is ignored unless lazymc authenticates players, which requires public.online_mode with join.forwarding or the lobby join method")]
+    UnverifiedPlayerRoutes,
+}
+
+impl ConfigError {
+    /// Whether lazymc can't run with this error, because it would proxy connections to itself.
+    pub fn is_fatal(&self) -> bool {
+        matches!(
+            self,
+            Self::SameServerPublicAddress
+                | Self::SameVhostPublicAddress(_)
+                | Self::SameForwardPublicAddress
+        )
+    }
+}
+
+/// Fields changed between two configurations.
+///
+/// Fields are listed by their dotted path, such as `server.command`. An empty path means the
+/// whole configuration changed.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct ConfigDiff {
+    /// Paths of changed fields.
+    pub changed: Vec<String>,
+}
+
+impl ConfigDiff {
+    /// Whether nothing changed.
+    pub fn is_empty(&self) -> bool {
+        self.changed.is_empty()
+    }
+
+    /// Whether the field at the given path, or any field below it, changed.
+    pub fn contains(&self, path: &str) -> bool {
+        self.changed
+            .iter()
+            .any(|changed| changed.is_empty() || is_within(changed, path))
+    }
+
+    /// Top level sections that changed, in order.
+    pub fn sections(&self) -> Vec<&str> {
+        let mut sections: Vec<&str> = self
+            .changed
+            .iter()
+            .filter_map(|path| path.split('.').next())
+            .collect();
+        sections.dedup();
+        sections
+    }
+}
+
+/// Whether `changed` path equals `path`, or is a field below it.
+fn is_within(changed: &str, path: &str) -> bool {
+    changed
+        .strip_prefix(path)
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with('.'))
+}
+
+/// Collect paths of values that differ between `old` and `new` into `changed`.
+fn diff_values(
+    path: String,
+    old: &serde_json::Value,
+    new: &serde_json::Value,
+    changed: &mut Vec<String>,
+) {
+    use serde_json::Value;
+
+    if old == new {
+        return;
+    }
+
+    match (old, new) {
+        (Value::Object(old), Value::Object(new)) => {
+            let keys = old
+                .keys()
+                .chain(new.keys().filter(|key| !old.contains_key(*key)));
+            for key in keys {
+                let child = if path.is_empty() {
+                    key.clone()
+                } else {
+                    format!("{path}.{key}")
+                };
+                diff_values(
+                    child,
+                    old.get(key).unwrap_or(&Value::Null),
+                    new.get(key).unwrap_or(&Value::Null),
+                    changed,
+                );
+            }
+        }
+        _ => changed.push(path),
+    }
+}
+
+/// Live configuration.
+///
+/// Holds the currently active configuration, shared between all subsystems. It may be swapped
+/// at runtime when the configuration is reloaded.
+#[derive(Debug)]
+pub struct LiveConfig {
+    /// Currently active configuration.
+    active: RwLock<Arc<Config>>,
+
+    /// Server configuration to apply once the server process has stopped.
+    pending_server: Mutex<Option<Server>>,
+}
+
+impl LiveConfig {
+    /// Construct live configuration with the given initial configuration.
+    pub fn new(config: Config) -> Self {
+        Self {
+            active: RwLock::new(Arc::new(config)),
+            pending_server: Mutex::new(None),
+        }
+    }
+
+    /// Get the currently active configuration.
+    pub fn get(&self) -> Arc<Config> {
+        self.active.read().unwrap().clone()
+    }
+
+    /// Atomically replace the active configuration.
+    ///
+    /// If `defer_server` is set, the currently active server configuration is kept until
+    /// `apply_pending` is called, because it can't be changed while the server is running.
+    pub fn replace(&self, mut config: Config, defer_server: bool) {
+        let mut active = self.active.write().unwrap();
+
+        if defer_server {
+            let server = std::mem::replace(&mut config.server, active.server.clone());
+            self.pending_server.lock().unwrap().replace(server);
+        } else {
+            self.pending_server.lock().unwrap().take();
+        }
+
+        *active = Arc::new(config);
+    }
+
+    /// Apply pending server configuration, if there is any.
+    ///
+    /// Must only be called when the server process is not running.
+    ///
+    /// Returns `true` if pending configuration was applied.
+    pub fn apply_pending(&self) -> bool {
+        let server = match self.pending_server.lock().unwrap().take() {
+            Some(server) => server,
+            None => return false,
+        };
+
+        let mut active = self.active.write().unwrap();
+        let mut config = Config::clone(&active);
+        config.server = server;
+        *active = Arc::new(config);
+
+        true
+    }
+}
+
+/// Public configuration.
+#[derive(Debug, Clone, PartialEq, Deserialize, JsonSchema, Serialize)]
+#[serde(default)]
+pub struct Public {
+    /// Public address.
+    ///
+    /// IPv6 addresses must be bracketed, `[::]:25565` listens on IPv4 and IPv6 where supported.
+    #[schemars(with = "String")]
+    #[serde(deserialize_with = "to_socket_addrs")]
+    pub address: SocketAddr,
+
+    /// Minecraft protocol version name hint.
+    pub version: String,
+
+    /// Minecraft protocol version hint.
+    ///
+    /// `0` or `"auto"` to use the protocol version probed from the server.
+    #[serde(deserialize_with = "protocol_or_auto")]
+    pub protocol: u32,
+
+    /// Maximum number of players hint.
+    ///
+    /// Used if server.properties does not exist and the server status is not known yet.
+    pub max_players: u32,
+
+    /// Authenticate players with Mojang when lazymc handles their login.
+    pub online_mode: bool,
+
+    /// Respond to Bedrock Edition status pings.
+    pub bedrock: bool,
+
+    /// Public address for Bedrock Edition status pings.
+    #[schemars(with = "String")]
+    #[serde(deserialize_with = "to_socket_addrs")]
+    pub bedrock_address: SocketAddr,
+}
+
+impl Public {
+    fn from_env(env: &EnvReader) -> Self {
+        Self {
+            address: env.socket_addr("PUBLIC_ADDRESS", "0.0.0.0:25565"),
+            version: env
+                .string("PUBLIC_VERSION", Some(proto::PROTO_DEFAULT_VERSION))
+                .unwrap_or_else(|| proto::PROTO_DEFAULT_VERSION.to_string()),
+            protocol: match env.string("PUBLIC_PROTOCOL", None) {
+                Some(protocol) if protocol.eq_ignore_ascii_case("auto") => 0,
+                _ => env.u32("PUBLIC_PROTOCOL", proto::PROTO_DEFAULT_PROTOCOL),
+            },
+            max_players: env.u32("PUBLIC_MAX_PLAYERS", 20),
+            online_mode: env.bool("PUBLIC_ONLINE_MODE", true),
+            bedrock: env.bool("PUBLIC_BEDROCK", false),
+            bedrock_address: env.socket_addr("PUBLIC_BEDROCK_ADDRESS", "0.0.0.0:19132"),
+        }
+    }
+}
+
+impl Default for Public {
+    fn default() -> Self {
+        Self {
+            address: "0.0.0.0:25565".parse().unwrap(),
+            version: proto::PROTO_DEFAULT_VERSION.to_string(),
+            protocol: proto::PROTO_DEFAULT_PROTOCOL,
+            max_players: 20,
+            online_mode: true,
+            bedrock: false,
+            bedrock_address: "0.0.0.0:19132".parse().unwrap(),
+        }
+    }
+}
+
+/// Server configuration.
+#[derive(Debug, Clone, PartialEq, Deserialize, JsonSchema, Serialize)]
+pub struct Server {
+    /// Server directory.
+    ///
+    /// Private because you should use `Server::server_directory()` instead.
+    #[serde(default = "option_pathbuf_dot")]
+    directory: Option<PathBuf>,
+
+    /// Start command.
+    pub command: String,
+
+    /// Server address.
+    #[schemars(with = "String")]
+    #[serde(
+        deserialize_with = "to_socket_addrs",
+        default = "server_address_default"
+    )]
+    pub address: SocketAddr,
+
+    /// Freeze the server process instead of restarting it when no players online, making it start up faster.
+    /// Only works on Unix (Linux or MacOS)
+    #[serde(default = "bool_true")]
+    pub freeze_process: bool,
+
+    /// What to do if freezing the server process fails.
+    #[serde(default)]
+    pub freeze_fallback: FreezeFailAction,
+
+    /// Seconds to wait after the last player left before freezing the server process.
+    #[serde(default = "u32_30")]
+    pub freeze_grace_secs: u32,
+
+    /// Immediately wake server when starting lazymc.
+    #[serde(default)]
+    pub wake_on_start: bool,
+
+    /// Immediately wake server after crash.
+    #[serde(default)]
+    pub wake_on_crash: bool,
+
+    /// Maximum number of consecutive crash restarts before giving up, unlimited if not set.
+    #[serde(default)]
+    pub max_crash_restarts: Option<u32>,
+
+    /// Base delay in seconds between crash restarts, doubled on each consecutive attempt.
+    #[serde(default = "u32_5")]
+    pub restart_backoff_base_secs: u32,
+
+    /// Don't consider server online if its port is held by a process other than the started one.
+    #[serde(default = "bool_true")]
+    pub orphan_port_check: bool,
+
+    /// Probe required server details when starting lazymc, wakes server on start.
+    #[serde(default)]
+    pub probe_on_start: bool,
+
+    /// Whether this server runs forge.
+    ///
+    /// Same as the FML2 Forge variant, if no variant is set.
+    #[serde(default)]
+    pub forge: bool,
+
+    /// Forge handshake variant this server uses.
+    #[serde(default)]
+    pub forge_version: ForgeVariant,
+
+    /// Server starting timeout. Force kill server process if it takes longer.
+    #[serde(default = "u32_300")]
+    pub start_timeout: u32,
+
+    /// Frozen server resuming timeout. Force kill server process if it takes longer.
+    #[serde(default = "u32_30")]
+    pub resume_timeout: u32,
+
+    /// What to do once the server is force killed for reaching the start timeout.
+    #[serde(default)]
+    pub start_timeout_action: TimeoutAction,
+
+    /// Server stopping timeout. Force kill server process if it takes longer.
+    #[serde(default = "u32_150")]
+    pub stop_timeout: u32,
+
+    /// Command to gracefully stop the server with, sent to stdin or over RCON.
+    #[serde(default)]
+    pub stop_command: Option<String>,
+
+    /// Send stop command over RCON rather than stdin. Defaults to whether RCON is enabled.
+    #[serde(default)]
+    pub stop_via_rcon: Option<bool>,
+
+    /// Command to check server health with while online, exit code 0 is healthy.
+    #[serde(default)]
+    pub health_check_command: Option<String>,
+
+    /// Interval in seconds to run the health check command at.
+    #[serde(default = "u32_10")]
+    pub health_check_interval_secs: u32,
+
+    /// Number of consecutive failed health checks before restarting the server.
+    #[serde(default = "u32_3")]
+    pub health_check_failures_before_restart: u32,
+
+    /// Log server process stdout lines.
+    #[serde(default = "bool_true")]
+    pub log_stdout: bool,
+
+    /// Log server process stderr lines.
+    #[serde(default = "bool_true")]
+    pub log_stderr: bool,
+
+    /// Prefix for logged server output lines.
+    #[serde(default = "log_prefix_default")]
+    pub log_prefix: String,
+
+    /// Don't log server output lines matching this pattern.
+    #[serde(default)]
+    pub log_filter_regex: Option<String>,
+
+    /// Consider server online once a stdout line matches this pattern.
+    #[serde(default = "ready_log_pattern_default")]
+    pub ready_log_pattern: Option<String>,
 
     /// To wake server, user must be in server whitelist if enabled on server.
     #[serde(default = "bool_true")]
     pub wake_whitelist: bool,
 
-    /// Block banned IPs as listed in banned-ips.json in server directory.
-    #[serde(default = "bool_true")]
-    pub block_banned_ips: bool,
+    /// Block banned IPs as listed in banned-ips.json in server directory.
+    #[serde(default = "bool_true")]
+    pub block_banned_ips: bool,
+
+    /// Drop connections from banned IPs.
+    #[serde(default)]
+    pub drop_banned_ips: bool,
+
+    /// Add HAProxy v2 header to proxied connections.
+    #[serde(default)]
+    pub send_proxy_v2: bool,
+
+    /// HAProxy PROXY protocol version to use for headers.
+    #[serde(default)]
+    pub proxy_protocol_version: ProxyVersion,
+
+    /// Probe whether server accepts proxy headers, overrides `send_proxy_v2` once probed.
+    #[serde(default)]
+    pub probe_proxy_v2: bool,
+
+    /// Backend servers to balance player connections over when online.
+    #[serde(default)]
+    pub backends: Vec<BackendServer>,
+
+    /// Load balancing strategy for backend servers.
+    #[serde(default)]
+    pub balance: BalanceStrategy,
+
+    /// Environment variables to pass to the server process, along with a minimal safe set.
+    ///
+    /// The full environment is passed if empty.
+    #[serde(default)]
+    pub env_passthrough: Vec<String>,
+
+    /// Additional environment variables to set for the server process.
+    #[serde(default)]
+    pub env_set: HashMap<String, String>,
+}
+
+impl Server {
+    fn from_env(env: &EnvReader, command: String) -> Self {
+        let directory = env.string("SERVER_DIRECTORY", Some(".")).map(PathBuf::from);
+
+        Self {
+            directory,
+            command,
+            address: env.socket_addr("SERVER_ADDRESS", "127.0.0.1:25566"),
+            freeze_process: env.bool("SERVER_FREEZE_PROCESS", true),
+            freeze_fallback: env
+                .string("SERVER_FREEZE_FALLBACK", None)
+                .and_then(|s| s.parse().ok())
+                .unwrap_or_default(),
+            freeze_grace_secs: env.u32("SERVER_FREEZE_GRACE_SECS", 30),
+            wake_on_start: env.bool("SERVER_WAKE_ON_START", false),
+            wake_on_crash: env.bool("SERVER_WAKE_ON_CRASH", false),
+            max_crash_restarts: env
+                .var("SERVER_MAX_CRASH_RESTARTS")
+                .ok()
+                .and_then(|s| s.parse().ok()),
+            restart_backoff_base_secs: env.u32("SERVER_RESTART_BACKOFF_BASE_SECS", 5),
+            orphan_port_check: env.bool("SERVER_ORPHAN_PORT_CHECK", true),
+            probe_on_start: env.bool("SERVER_PROBE_ON_START", false),
+            forge: env.bool("SERVER_FORGE", false),
+            forge_version: env
+                .string("SERVER_FORGE_VERSION", None)
+                .and_then(|s| s.parse().ok())
+                .unwrap_or_default(),
+            start_timeout: env.u32("SERVER_START_TIMEOUT", 300),
+            resume_timeout: env.u32("SERVER_RESUME_TIMEOUT", 30),
+            start_timeout_action: env
+                .string("SERVER_START_TIMEOUT_ACTION", None)
+                .and_then(|s| s.parse().ok())
+                .unwrap_or_default(),
+            stop_timeout: env.u32("SERVER_STOP_TIMEOUT", 150),
+            stop_command: env.string("SERVER_STOP_COMMAND", None),
+            stop_via_rcon: env
+                .var("SERVER_STOP_VIA_RCON")
+                .ok()
+                .map(|_| env.bool("SERVER_STOP_VIA_RCON", false)),
+            health_check_command: env.string("SERVER_HEALTH_CHECK_COMMAND", None),
+            health_check_interval_secs: env.u32("SERVER_HEALTH_CHECK_INTERVAL_SECS", 10),
+            health_check_failures_before_restart: env
+                .u32("SERVER_HEALTH_CHECK_FAILURES_BEFORE_RESTART", 3),
+            log_stdout: env.bool("SERVER_LOG_STDOUT", true),
+            log_stderr: env.bool("SERVER_LOG_STDERR", true),
+            log_prefix: env.string("SERVER_LOG_PREFIX", Some("server")).unwrap(),
+            log_filter_regex: env.string("SERVER_LOG_FILTER_REGEX", None),
+            ready_log_pattern: env.string(
+                "SERVER_READY_LOG_PATTERN",
+                ready_log_pattern_default().as_deref(),
+            ),
+            wake_whitelist: env.bool("SERVER_WAKE_WHITELIST", true),
+            block_banned_ips: env.bool("SERVER_BLOCK_BANNED_IPS", true),
+            drop_banned_ips: env.bool("SERVER_DROP_BANNED_IPS", false),
+            send_proxy_v2: env.bool("SERVER_SEND_PROXY_V2", false),
+            proxy_protocol_version: env
+                .string("SERVER_PROXY_PROTOCOL_VERSION", None)
+                .and_then(|s| s.parse().ok())
+                .unwrap_or_default(),
+            probe_proxy_v2: env.bool("SERVER_PROBE_PROXY_V2", false),
+            backends: env
+                .vec_string("SERVER_BACKENDS", vec![])
+                .iter()
+                .filter_map(|s| s.parse().ok())
+                .map(|address| BackendServer { address, weight: 1 })
+                .collect(),
+            balance: env
+                .string("SERVER_BALANCE", None)
+                .and_then(|s| s.parse().ok())
+                .unwrap_or_default(),
+            env_passthrough: env.vec_string("SERVER_ENV_PASSTHROUGH", vec![]),
+            env_set: env
+                .vec_string("SERVER_ENV_SET", vec![])
+                .iter()
+                .filter_map(|s| s.split_once('='))
+                .map(|(key, value)| (key.trim().to_string(), value.trim().to_string()))
+                .collect(),
+        }
+    }
+
+    /// Parsed log filter pattern, `None` if not configured or invalid.
+    pub fn log_filter(&self) -> Option<Regex> {
+        self.log_filter_regex
+            .as_deref()
+            .and_then(|r| Regex::new(r).ok())
+    }
+
+    /// Parsed ready log pattern, `None` if not configured, empty or invalid.
+    pub fn ready_pattern(&self) -> Option<Regex> {
+        self.ready_log_pattern
+            .as_deref()
+            .filter(|r| !r.is_empty())
+            .and_then(|r| Regex::new(r).ok())
+    }
+
+    /// Configured Forge variant, taking the legacy Forge flag into account.
+    pub fn forge_variant(&self) -> ForgeVariant {
+        match self.forge_version {
+            ForgeVariant::None if self.forge => ForgeVariant::Fml2,
+            variant => variant,
+        }
+    }
+
+    /// Get server command arguments.
+    ///
+    /// Applies the configured Java path and flags if the command starts with `java`. Returns `None`
+    /// if the command is invalid.
+    pub fn command_args(config: &Config) -> Option<Vec<String>> {
+        let mut args = shlex::split(&config.server.command)?;
+        let program = args.first()?;
+        if Path::new(program).file_stem().and_then(|s| s.to_str()) != Some("java") {
+            return Some(args);
+        }
+
+        if let Some(java_path) = &config.advanced.java_path {
+            args[0] = java_path.to_string_lossy().into_owned();
+        }
+        args.splice(1..1, config.advanced.java_flags.iter().cloned());
+        Some(args)
+    }
+
+    /// Whether to send the stop command over RCON rather than stdin.
+    pub fn stop_via_rcon(config: &Config) -> bool {
+        config.server.stop_via_rcon.unwrap_or(config.rcon.enabled)
+    }
+
+    /// Whether to send the stop command to the server process stdin.
+    pub fn stop_via_stdin(config: &Config) -> bool {
+        config.server.stop_command.is_some() && !Self::stop_via_rcon(config)
+    }
+
+    /// Get delay before the given crash restart attempt, starting at 0.
+    ///
+    /// Exponential backoff, capped at the start timeout if set.
+    pub fn restart_backoff(&self, attempt: u32) -> Duration {
+        let secs =
+            (self.restart_backoff_base_secs as u64).saturating_mul(2u64.saturating_pow(attempt));
+        match self.start_timeout {
+            0 => Duration::from_secs(secs),
+            timeout => Duration::from_secs(secs.min(timeout as u64)),
+        }
+    }
+
+    /// Get the server directory.
+    ///
+    /// This does not check whether it exists.
+    pub fn server_directory(config: &Config) -> Option<PathBuf> {
+        // Get directory, relative to config directory if known
+        match config.path.as_ref().and_then(|p| p.parent()) {
+            Some(config_dir) => Some(config_dir.join(config.server.directory.as_ref()?)),
+            None => config.server.directory.clone(),
+        }
+    }
+}
+
+/// Time configuration.
+#[derive(Debug, Clone, PartialEq, Deserialize, JsonSchema, Serialize)]
+#[serde(default)]
+pub struct Time {
+    /// Sleep after number of seconds.
+    pub sleep_after: u32,
+
+    /// Sleep after number of seconds based on the peak number of players while online.
+    ///
+    /// The rule with the highest matching `min_players` wins, `sleep_after` is used if none match.
+    pub sleep_after_by_players: Vec<SleepAfterRule>,
+
+    /// Minimum time in seconds to stay online when server is started.
+    #[serde(default, alias = "minimum_online_time")]
+    pub min_online_time: u32,
+
+    /// Minimum number of online players to keep the server awake.
+    ///
+    /// Server sleeps once fewer players are online for `sleep_after` seconds.
+    pub min_players_to_stay_awake: u32,
+
+    /// Cron expression of times to wake the server at before players arrive.
+    ///
+    /// Server stays online for `min_online_time`, then sleeps after `sleep_after` if nobody joined.
+    pub wake_cron: Option<String>,
+
+    /// Scheduled sleep and wake times.
+    pub schedule: Schedule,
+}
+
+impl Time {
+    fn from_env(env: &EnvReader) -> Self {
+        Self {
+            sleep_after: env.u32("TIME_SLEEP_AFTER", 60),
+            sleep_after_by_players: env
+                .vec_string("TIME_SLEEP_AFTER_BY_PLAYERS", vec![])
+                .iter()
+                .filter_map(|s| s.split_once('='))
+                .filter_map(|(players, secs)| {
+                    Some(SleepAfterRule {
+                        min_players: players.trim().parse().ok()?,
+                        sleep_after_secs: secs.trim().parse().ok()?,
+                    })
+                })
+                .collect(),
+            min_online_time: env.u32("TIME_MIN_ONLINE_TIME", 60),
+            min_players_to_stay_awake: env.u32("TIME_MIN_PLAYERS_TO_STAY_AWAKE", 0),
+            wake_cron: env.string("TIME_WAKE_CRON", None),
+            schedule: Schedule::from_env(env),
+        }
+    }
+
+    /// Parsed wake schedule, `None` if not configured or invalid.
+    ///
+    /// Uses `wake_cron`, or the wake time of the schedule if not set.
+    pub fn wake_schedule(&self) -> Option<cron::Schedule> {
+        self.wake_cron
+            .as_deref()
+            .or(self.schedule.wake_cron.as_deref())
+            .and_then(|c| c.parse().ok())
+    }
+
+    /// Number of idle seconds to sleep after, given the peak number of players while online.
+    pub fn sleep_after_for(&self, peak_players: u32) -> u32 {
+        self.sleep_after_by_players
+            .iter()
+            .filter(|rule| rule.min_players <= peak_players)
+            .max_by_key(|rule| rule.min_players)
+            .map(|rule| rule.sleep_after_secs)
+            .unwrap_or(self.sleep_after)
+    }
+
+    /// Number of online players required to keep the server awake, at least one.
+    pub fn min_players(&self) -> u32 {
+        self.min_players_to_stay_awake.max(1)
+    }
+}
+
+impl Default for Time {
+    fn default() -> Self {
+        Self {
+            sleep_after: 60,
+            sleep_after_by_players: vec![],
+            min_online_time: 60,
+            min_players_to_stay_awake: 0,
+            wake_cron: None,
+            schedule: Default::default(),
+        }
+    }
+}
+
+/// Rule to sleep after a number of seconds, if enough players were online.
+#[derive(Debug, Clone, PartialEq, Deserialize, JsonSchema, Serialize)]
+pub struct SleepAfterRule {
+    /// Minimum peak number of players for this rule to match.
+    pub min_players: u32,
+
+    /// Sleep after number of seconds.
+    pub sleep_after_secs: u32,
+}
+
+/// Schedule configuration.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, JsonSchema, Serialize)]
+#[serde(default)]
+pub struct Schedule {
+    /// Cron expression of times to put the server to sleep at, once no players are online.
+    pub sleep_cron: Option<String>,
+
+    /// Cron expression of times to wake the server at.
+    pub wake_cron: Option<String>,
+}
+
+impl Schedule {
+    fn from_env(env: &EnvReader) -> Self {
+        Self {
+            sleep_cron: env.string("TIME_SCHEDULE_SLEEP_CRON", None),
+            wake_cron: env.string("TIME_SCHEDULE_WAKE_CRON", None),
+        }
+    }
+
+    /// Parsed sleep schedule, `None` if not configured or invalid.
+    pub fn sleep(&self) -> Option<cron::Schedule> {
+        self.sleep_cron.as_deref().and_then(|c| c.parse().ok())
+    }
+
+    /// Get errors for configured cron expressions that fail to parse.
+    fn cron_errors(&self) -> Vec<(&'static str, String)> {
+        [
+            ("time.schedule.sleep_cron", &self.sleep_cron),
+            ("time.schedule.wake_cron", &self.wake_cron),
+        ]
+        .into_iter()
+        .filter_map(|(key, cron)| {
+            let err = cron.as_deref()?.parse::<cron::Schedule>().err()?;
+            Some((key, err.to_string()))
+        })
+        .collect()
+    }
+}
+
+/// MOTD configuration.
+#[derive(Debug, Clone, PartialEq, Deserialize, JsonSchema, Serialize)]
+#[serde(default)]
+pub struct Motd {
+    /// MOTD messages when server is sleeping, cycled through on status pings.
+    #[serde(alias = "sleeping", deserialize_with = "string_or_vec")]
+    #[schemars(with = "Vec<String>")]
+    pub sleeping_messages: Vec<String>,
+
+    /// Number of status pings to show each sleeping MOTD message for before advancing.
+    pub cycle_interval: u32,
+
+    /// MOTD when server is starting.
+    pub starting: String,
+
+    /// MOTD when server is stopping.
+    pub stopping: String,
+
+    /// MOTD when frozen server is resuming.
+    pub resuming: String,
+
+    /// MOTD when server stopped restarting after crashing repeatedly.
+    pub crash_loop: String,
+
+    /// MOTD when server was force killed for taking longer than the start timeout.
+    pub start_timeout: String,
+
+    /// Use MOTD from Minecraft server once known.
+    pub from_server: bool,
+
+    /// Format of MOTD strings.
+    pub format: MotdFormat,
+
+    /// Favicon when server is sleeping.
+    pub favicon_sleeping: Option<PathBuf>,
+
+    /// Favicon when server is starting.
+    pub favicon_starting: Option<PathBuf>,
+
+    /// Favicon when server is stopping.
+    pub favicon_stopping: Option<PathBuf>,
+
+    /// Players to list in status while server is sleeping, cosmetic only.
+    #[serde(rename = "fake_player", alias = "fake_players")]
+    pub fake_players: Vec<FakePlayer>,
+}
+
+impl Motd {
+    fn from_env(env: &EnvReader) -> Self {
+        Self {
+            sleeping_messages: match env.var("MOTD_SLEEPING_MESSAGES") {
+                Ok(_) => env
+                    .vec_semicolon("MOTD_SLEEPING_MESSAGES")
+                    .iter()
+                    .map(|s| process_escape_sequences(s))
+                    .collect(),
+                Err(_) => vec![env
+                    .string(
+                        "MOTD_SLEEPING",
+                        Some("☠ Server is sleeping\n§2☻ Join to start it up"),
+                    )
+                    .unwrap()],
+            },
+            cycle_interval: env.u32("MOTD_CYCLE_INTERVAL", 1),
+            starting: env
+                .string(
+                    "MOTD_STARTING",
+                    Some("§2☻ Server is starting...\n§7⌛ Please wait..."),
+                )
+                .unwrap(),
+            stopping: env
+                .string(
+                    "MOTD_STOPPING",
+                    Some("☠ Server going to sleep...\n⌛ Please wait..."),
+                )
+                .unwrap(),
+            resuming: env
+                .string(
+                    "MOTD_RESUMING",
+                    Some("§2☻ Server is waking up...\n§7⌛ Almost there..."),
+                )
+                .unwrap(),
+            crash_loop: env
+                .string(
+                    "MOTD_CRASH_LOOP",
+                    Some("§c☠ Server crashed repeatedly\n§7Join to try starting it again"),
+                )
+                .unwrap(),
+            start_timeout: env
+                .string(
+                    "MOTD_START_TIMEOUT",
+                    Some("§c☠ Server took too long to start\n§7Join to try starting it again"),
+                )
+                .unwrap(),
+            from_server: env.bool("MOTD_FROM_SERVER", false),
+            format: env
+                .string("MOTD_FORMAT", None)
+                .and_then(|s| s.parse().ok())
+                .unwrap_or_default(),
+            favicon_sleeping: env.string("MOTD_FAVICON_SLEEPING", None).map(PathBuf::from),
+            favicon_starting: env.string("MOTD_FAVICON_STARTING", None).map(PathBuf::from),
+            favicon_stopping: env.string("MOTD_FAVICON_STOPPING", None).map(PathBuf::from),
+            fake_players: env
+                .var("MOTD_FAKE_PLAYERS")
+                .map(|list| FakePlayer::parse_list(&list))
+                .unwrap_or_default(),
+        }
+    }
+
+    /// Get all configured MOTD strings.
+    fn messages(&self) -> impl Iterator<Item = &String> {
+        self.sleeping_messages.iter().chain([
+            &self.starting,
+            &self.stopping,
+            &self.resuming,
+            &self.crash_loop,
+            &self.start_timeout,
+        ])
+    }
+
+    /// Get JSON parse errors of configured MOTD strings, if JSON format is used.
+    pub fn json_errors(&self) -> Vec<String> {
+        if self.format != MotdFormat::Json {
+            return vec![];
+        }
+
+        self.messages()
+            .filter_map(|msg| serde_json::from_str::<serde_json::Value>(msg).err())
+            .map(|err| err.to_string())
+            .collect()
+    }
+}
+
+impl Default for Motd {
+    fn default() -> Self {
+        Self {
+            sleeping_messages: vec!["☠ Server is sleeping\n§2☻ Join to start it up".into()],
+            cycle_interval: 1,
+            starting: "§2☻ Server is starting...\n§7⌛ Please wait...".into(),
+            stopping: "☠ Server going to sleep...\n⌛ Please wait...".into(),
+            resuming: "§2☻ Server is waking up...\n§7⌛ Almost there...".into(),
+            crash_loop: "§c☠ Server crashed repeatedly\n§7Join to try starting it again".into(),
+            start_timeout: "§c☠ Server took too long to start\n§7Join to try starting it again"
+                .into(),
+            from_server: false,
+            format: MotdFormat::default(),
+            favicon_sleeping: None,
+            favicon_starting: None,
+            favicon_stopping: None,
+            fake_players: Vec::new(),
+        }
+    }
+}
+
+/// Fake player shown in status player list.
+#[derive(Debug, Clone, PartialEq, Deserialize, JsonSchema, Serialize)]
+pub struct FakePlayer {
+    /// Player name.
+    pub name: String,
+
+    /// Player UUID.
+    #[schemars(with = "String")]
+    #[serde(deserialize_with = "to_uuid", serialize_with = "from_uuid")]
+    pub uuid: Uuid,
+}
+
+impl FakePlayer {
+    /// Parse fake players from `name:uuid` pairs separated by semicolons.
+    ///
+    /// Invalid pairs are skipped with a warning.
+    fn parse_list(list: &str) -> Vec<Self> {
+        list.split(';')
+            .map(str::trim)
+            .filter(|pair| !pair.is_empty())
+            .filter_map(|pair| {
+                let player = pair.split_once(':').and_then(|(name, uuid)| {
+                    Some(Self {
+                        name: name.trim().to_string(),
+                        uuid: Uuid::parse_str(uuid.trim()).ok()?,
+                    })
+                });
+                if player.is_none() {
+                    warn!(target: "lazymc::config", "Ignoring invalid fake player '{}', expected name:uuid", pair);
+                }
+                player
+            })
+            .collect()
+    }
+}
+
+/// MOTD formats.
+#[derive(Debug, Deserialize, JsonSchema, Serialize, Copy, Clone, Eq, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum MotdFormat {
+    /// Legacy text with `§` formatting codes.
+    #[default]
+    Legacy,
+
+    /// Minecraft JSON text component.
+    Json,
+}
+
+impl std::str::FromStr for MotdFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "legacy" => Ok(MotdFormat::Legacy),
+            "json" => Ok(MotdFormat::Json),
+            _ => Err(format!("Unknown MOTD format: {}", s)),
+        }
+    }
+}
+
+/// Join method types.
+#[derive(Debug, Deserialize, JsonSchema, Serialize, Copy, Clone, Eq, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Method {
+    /// Kick client with message.
+    Kick,
+
+    /// Hold client connection until server is ready.
+    Hold,
+
+    /// Hold client connection in numbered queue until server is ready.
+    Queue,
+
+    /// Forward connection to another host.
+    Forward,
+
+    /// Redirect client to another server address.
+    Redirect,
+
+    /// Keep client in temporary fake lobby until server is ready.
+    Lobby,
+}
+
+impl std::str::FromStr for Method {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "kick" => Ok(Method::Kick),
+            "hold" => Ok(Method::Hold),
+            "queue" => Ok(Method::Queue),
+            "forward" => Ok(Method::Forward),
+            "redirect" => Ok(Method::Redirect),
+            "lobby" => Ok(Method::Lobby),
+            _ => Err(format!("Unknown join method: {}", s)),
+        }
+    }
+}
+
+/// Player info forwarding modes.
+#[derive(Debug, Default, Deserialize, JsonSchema, Serialize, Clone, Eq, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ForwardingMode {
+    /// Do not forward player info.
+    #[default]
+    None,
+
+    /// Legacy BungeeCord IP forwarding, through the handshake host field.
+    #[serde(alias = "bungeecord")]
+    Legacy,
+
+    /// Velocity modern forwarding, signed with the given secret.
+    Velocity {
+        /// Forwarding secret shared with the server.
+        secret: String,
+    },
+}
+
+impl ForwardingMode {
+    fn from_env(env: &EnvReader) -> Self {
+        match env
+            .string("JOIN_FORWARDING", None)
+            .map(|s| s.to_lowercase())
+            .as_deref()
+        {
+            Some("legacy") | Some("bungeecord") => ForwardingMode::Legacy,
+            Some("velocity") => ForwardingMode::Velocity {
+                secret: env.string("JOIN_FORWARDING_SECRET", Some("")).unwrap(),
+            },
+            _ => ForwardingMode::None,
+        }
+    }
+
+    /// Whether player info is forwarded.
+    pub fn is_enabled(&self) -> bool {
+        *self != ForwardingMode::None
+    }
+}
+
+/// Join configuration.
+#[derive(Debug, Clone, PartialEq, Deserialize, JsonSchema, Serialize)]
+#[serde(default)]
+pub struct Join {
+    /// Join methods.
+    pub methods: Vec<Method>,
+
+    /// Join kick configuration.
+    #[serde(default)]
+    pub kick: JoinKick,
+
+    /// Join hold configuration.
+    #[serde(default)]
+    pub hold: JoinHold,
+
+    /// Join queue configuration.
+    #[serde(default)]
+    pub queue: JoinQueue,
+
+    /// Join forward configuration.
+    #[serde(default)]
+    pub forward: JoinForward,
+
+    /// Join redirect configuration.
+    #[serde(default)]
+    pub redirect: JoinRedirect,
+
+    /// Join lobby configuration.
+    #[serde(default)]
+    pub lobby: JoinLobby,
+
+    /// Player info forwarding mode for proxied connections.
+    #[serde(default)]
+    pub forwarding: ForwardingMode,
+}
+
+impl Join {
+    fn from_env(env: &EnvReader) -> Self {
+        let methods_str = env.vec_string("JOIN_METHODS", vec!["hold", "kick"]);
+        let methods = methods_str
+            .into_iter()
+            .filter_map(|s| s.parse().ok())
+            .collect();
+
+        Self {
+            methods,
+            kick: JoinKick::from_env(env),
+            hold: JoinHold::from_env(env),
+            queue: JoinQueue::from_env(env),
+            forward: JoinForward::from_env(env),
+            redirect: JoinRedirect::from_env(env),
+            lobby: JoinLobby::from_env(env),
+            forwarding: ForwardingMode::from_env(env),
+        }
+    }
+}
+
+impl Default for Join {
+    fn default() -> Self {
+        Self {
+            methods: vec![Method::Hold, Method::Kick],
+            kick: Default::default(),
+            hold: Default::default(),
+            queue: Default::default(),
+            forward: Default::default(),
+            redirect: Default::default(),
+            lobby: Default::default(),
+            forwarding: Default::default(),
+        }
+    }
+}
+
+/// Join kick configuration.
+#[derive(Debug, Clone, PartialEq, Deserialize, JsonSchema, Serialize)]
+#[serde(default)]
+pub struct JoinKick {
+    /// Kick message when server is starting.
+    pub starting: String,
+
+    /// Kick message when server is stopping.
+    pub stopping: String,
+
+    /// Kick message for held clients when server took longer than the start timeout.
+    pub start_timeout: String,
+
+    /// Kick message as raw JSON chat component when server is starting, replaces `starting`.
+    pub starting_json: Option<String>,
+
+    /// Kick message as raw JSON chat component when server is stopping, replaces `stopping`.
+    pub stopping_json: Option<String>,
+}
+
+impl JoinKick {
+    fn from_env(env: &EnvReader) -> Self {
+        Self {
+            starting: env.string("JOIN_KICK_STARTING", 
+                Some("Server is starting... §c♥§r\n\nThis may take some time.\n\nPlease try to reconnect in a minute."))
+                .unwrap(),
+            stopping: env.string("JOIN_KICK_STOPPING", 
+                Some("Server is going to sleep... §7☠§r\n\nPlease try to reconnect in a minute to wake it again."))
+                .unwrap(),
+            start_timeout: env.string("JOIN_KICK_START_TIMEOUT",
+                Some("Server took too long to start §c☠§r\n\nPlease try to reconnect in a minute."))
+                .unwrap(),
+            starting_json: env.string("JOIN_KICK_STARTING_JSON", None),
+            stopping_json: env.string("JOIN_KICK_STOPPING_JSON", None),
+        }
+    }
+
+    /// Get JSON parse errors of configured JSON kick messages.
+    pub fn json_errors(&self) -> Vec<(&'static str, String)> {
+        [
+            ("join.kick.starting_json", &self.starting_json),
+            ("join.kick.stopping_json", &self.stopping_json),
+        ]
+        .into_iter()
+        .filter_map(|(key, msg)| {
+            let err = serde_json::from_str::<serde_json::Value>(msg.as_deref()?).err()?;
+            Some((key, err.to_string()))
+        })
+        .collect()
+    }
+}
+
+impl Default for JoinKick {
+    fn default() -> Self {
+        Self {
+            starting: "Server is starting... §c♥§r\n\nThis may take some time.\n\nPlease try to reconnect in a minute.".into(),
+            stopping: "Server is going to sleep... §7☠§r\n\nPlease try to reconnect in a minute to wake it again.".into(),
+            start_timeout: "Server took too long to start §c☠§r\n\nPlease try to reconnect in a minute.".into(),
+            starting_json: None,
+            stopping_json: None,
+        }
+    }
+}
+
+/// Join hold configuration.
+#[derive(Debug, Clone, PartialEq, Deserialize, JsonSchema, Serialize)]
+#[serde(default)]
+pub struct JoinHold {
+    /// Hold client for number of seconds on connect while server starts.
+    pub timeout: u32,
+
+    /// Interval in milliseconds to check whether the server is ready for held clients.
+    pub poll_interval_ms: u32,
+
+    /// Interval in milliseconds to send keep-alive packets to held clients, `0` to disable.
+    pub keepalive_packet_interval_ms: u32,
+}
+
+impl JoinHold {
+    fn from_env(env: &EnvReader) -> Self {
+        Self {
+            timeout: env.u32("JOIN_HOLD_TIMEOUT", 25),
+            poll_interval_ms: env.u32("JOIN_HOLD_POLL_INTERVAL_MS", 500),
+            keepalive_packet_interval_ms: env.u32("JOIN_HOLD_KEEPALIVE_PACKET_INTERVAL_MS", 0),
+        }
+    }
+}
+
+impl Default for JoinHold {
+    fn default() -> Self {
+        Self {
+            timeout: 25,
+            poll_interval_ms: 500,
+            keepalive_packet_interval_ms: 0,
+        }
+    }
+}
+
+/// Join queue configuration.
+#[derive(Debug, Clone, PartialEq, Deserialize, JsonSchema, Serialize)]
+#[serde(default)]
+pub struct JoinQueue {
+    /// Maximum number of queued clients, others fall through to the next method.
+    pub max_size: usize,
+
+    /// Message shown to client when it times out in the queue.
+    ///
+    /// Supports `{pos}` and `{total}` placeholders.
+    pub position_message: String,
+
+    /// Queue client for number of seconds on connect while server starts.
+    pub timeout: u32,
+}
+
+impl JoinQueue {
+    fn from_env(env: &EnvReader) -> Self {
+        Self {
+            max_size: env.u32("JOIN_QUEUE_MAX_SIZE", 20) as usize,
+            position_message: env.string("JOIN_QUEUE_POSITION_MESSAGE",
+                Some("Server is starting... §c♥§r\n\nYou were §e#{pos}§r of {total} in the queue.\n\nPlease try to reconnect in a minute."))
+                .unwrap(),
+            timeout: env.u32("JOIN_QUEUE_TIMEOUT", 25),
+        }
+    }
+}
+
+impl Default for JoinQueue {
+    fn default() -> Self {
+        Self {
+            max_size: 20,
+            position_message: "Server is starting... §c♥§r\n\nYou were §e#{pos}§r of {total} in the queue.\n\nPlease try to reconnect in a minute.".into(),
+            timeout: 25,
+        }
+    }
+}
+
+/// Join forward configuration.
+#[derive(Debug, Clone, PartialEq, Deserialize, JsonSchema, Serialize)]
+#[serde(default)]
+pub struct JoinForward {
+    /// IP and port to forward to.
+    #[schemars(with = "String")]
+    #[serde(deserialize_with = "to_socket_addrs")]
+    pub address: SocketAddr,
+
+    /// Add HAProxy v2 header to proxied connections.
+    #[serde(default)]
+    pub send_proxy_v2: bool,
+
+    /// HAProxy PROXY protocol version to use for headers.
+    #[serde(default)]
+    pub proxy_protocol_version: ProxyVersion,
+}
+
+impl JoinForward {
+    fn from_env(env: &EnvReader) -> Self {
+        Self {
+            address: env.socket_addr("JOIN_FORWARD_ADDRESS", "127.0.0.1:25565"),
+            send_proxy_v2: env.bool("JOIN_FORWARD_SEND_PROXY_V2", false),
+            proxy_protocol_version: env
+                .string("JOIN_FORWARD_PROXY_PROTOCOL_VERSION", None)
+                .and_then(|s| s.parse().ok())
+                .unwrap_or_default(),
+        }
+    }
+}
+
+impl Default for JoinForward {
+    fn default() -> Self {
+        Self {
+            address: "127.0.0.1:25565".parse().unwrap(),
+            send_proxy_v2: false,
+            proxy_protocol_version: ProxyVersion::default(),
+        }
+    }
+}
+
+/// Join redirect configuration.
+#[derive(Debug, Clone, PartialEq, Deserialize, JsonSchema, Serialize)]
+#[serde(default)]
+pub struct JoinRedirect {
+    /// IP and port to redirect to.
+    #[schemars(with = "String")]
+    #[serde(deserialize_with = "to_socket_addrs")]
+    pub address: SocketAddr,
+}
+
+impl JoinRedirect {
+    fn from_env(env: &EnvReader) -> Self {
+        Self {
+            address: env.socket_addr("JOIN_REDIRECT_ADDRESS", "127.0.0.1:25565"),
+        }
+    }
+}
+
+impl Default for JoinRedirect {
+    fn default() -> Self {
+        Self {
+            address: "127.0.0.1:25565".parse().unwrap(),
+        }
+    }
+}
+
+/// Backend server configuration.
+#[derive(Debug, Clone, PartialEq, Deserialize, JsonSchema, Serialize)]
+pub struct BackendServer {
+    /// IP and port of backend server.
+    #[schemars(with = "String")]
+    #[serde(deserialize_with = "to_socket_addrs")]
+    pub address: SocketAddr,
+
+    /// Relative weight of backend server.
+    #[serde(default = "u32_1")]
+    pub weight: u32,
+}
+
+/// Load balancing strategies.
+#[derive(Debug, Default, Deserialize, JsonSchema, Serialize, Copy, Clone, Eq, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum BalanceStrategy {
+    /// Pick backends in turn, by weight.
+    #[default]
+    RoundRobin,
+
+    /// Pick backend with least connections relative to its weight.
+    LeastConnections,
+
+    /// Pick random backend, by weight.
+    Random,
+}
+
+impl std::str::FromStr for BalanceStrategy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "round_robin" => Ok(BalanceStrategy::RoundRobin),
+            "least_connections" => Ok(BalanceStrategy::LeastConnections),
+            "random" => Ok(BalanceStrategy::Random),
+            _ => Err(format!("Unknown balance strategy: {}", s)),
+        }
+    }
+}
+
+/// Forge handshake variants.
+#[derive(Debug, Default, Deserialize, JsonSchema, Serialize, Copy, Clone, Eq, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ForgeVariant {
+    /// Not a Forge server.
+    #[default]
+    None,
+
+    /// Legacy FML handshake, used by Forge 1.12 and older.
+    Fml1,
+
+    /// FML2 login handshake, used by Forge 1.13 and newer and NeoForge.
+    Fml2,
+
+    /// Detect from server status.
+    Auto,
+}
+
+impl std::str::FromStr for ForgeVariant {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "none" => Ok(ForgeVariant::None),
+            "fml1" => Ok(ForgeVariant::Fml1),
+            "fml2" => Ok(ForgeVariant::Fml2),
+            "auto" => Ok(ForgeVariant::Auto),
+            _ => Err(format!("Unknown Forge variant: {}", s)),
+        }
+    }
+}
+
+/// Actions if freezing the server process fails.
+#[derive(Debug, Default, Deserialize, JsonSchema, Serialize, Copy, Clone, Eq, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum FreezeFailAction {
+    /// Log error and keep server running, retry freezing once idle again.
+    Error,
+
+    /// Stop server instead, and stop it from now on.
+    #[default]
+    FallbackToStop,
+}
+
+impl std::str::FromStr for FreezeFailAction {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().replace('-', "_").as_str() {
+            "error" => Ok(FreezeFailAction::Error),
+            "fallback_to_stop" | "stop" => Ok(FreezeFailAction::FallbackToStop),
+            _ => Err(format!("Unknown freeze fallback: {}", s)),
+        }
+    }
+}
+
+/// Actions once the server is force killed for reaching the start timeout.
+#[derive(Debug, Default, Deserialize, JsonSchema, Serialize, Copy, Clone, Eq, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum TimeoutAction {
+    /// Kill server, restarts on crash if enabled.
+    #[default]
+    Kill,
+
+    /// Kill server and try to start it once more, sleep if that times out as well.
+    KillAndRestart,
+
+    /// Kill server and sleep until woken again.
+    KillAndSleep,
+}
+
+impl std::str::FromStr for TimeoutAction {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().replace('-', "_").as_str() {
+            "kill" => Ok(TimeoutAction::Kill),
+            "kill_and_restart" => Ok(TimeoutAction::KillAndRestart),
+            "kill_and_sleep" => Ok(TimeoutAction::KillAndSleep),
+            _ => Err(format!("Unknown start timeout action: {}", s)),
+        }
+    }
+}
+
+/// HAProxy PROXY protocol versions.
+#[derive(Debug, Default, Deserialize, JsonSchema, Serialize, Copy, Clone, Eq, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ProxyVersion {
+    /// Version 1, text format.
+    V1,
+
+    /// Version 2, binary format.
+    #[default]
+    V2,
+}
+
+impl std::str::FromStr for ProxyVersion {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "v1" | "1" => Ok(ProxyVersion::V1),
+            "v2" | "2" => Ok(ProxyVersion::V2),
+            _ => Err(format!("Unknown PROXY protocol version: {}", s)),
+        }
+    }
+}
+
+/// Join lobby configuration.
+#[derive(Debug, Clone, PartialEq, Deserialize, JsonSchema, Serialize)]
+#[serde(default)]
+pub struct JoinLobby {
+    /// Hold client in lobby for number of seconds on connect while server starts.
+    pub timeout: u32,
+
+    /// Message banner in lobby shown to client.
+    pub message: String,
+
+    /// Sound effect to play when server is ready.
+    pub ready_sound: Option<String>,
+
+    /// Show boss bar with server startup progress.
+    pub show_bossbar: bool,
+
+    /// Title of startup progress boss bar.
+    pub bossbar_title: String,
+
+    /// Tab list header shown in lobby.
+    pub tab_header: Option<String>,
+
+    /// Tab list footer shown in lobby.
+    pub tab_footer: Option<String>,
+
+    /// Show action bar with startup countdown.
+    pub show_actionbar: bool,
+
+    /// Startup countdown action bar message, supports `{remaining}` placeholder.
+    pub actionbar_message: String,
+
+    /// Resource pack URL to send to client in lobby.
+    pub resource_pack_url: Option<String>,
+
+    /// Hex encoded SHA-1 hash of resource pack.
+    pub resource_pack_hash: Option<String>,
+
+    /// Disconnect client if it declines the resource pack.
+    pub resource_pack_required: bool,
+
+    /// Disconnect client with following message if it declines a required resource pack.
+    pub resource_pack_declined_message: String,
+}
+
+impl JoinLobby {
+    fn from_env(env: &EnvReader) -> Self {
+        Self {
+            timeout: env.u32("JOIN_LOBBY_TIMEOUT", 10 * 60),
+            message: env
+                .string(
+                    "JOIN_LOBBY_MESSAGE",
+                    Some("§2Server is starting\n§7⌛ Please wait..."),
+                )
+                .unwrap(),
+            ready_sound: env.string("JOIN_LOBBY_READY_SOUND", Some("block.note_block.chime")),
+            show_bossbar: env.bool("JOIN_LOBBY_SHOW_BOSSBAR", true),
+            bossbar_title: env
+                .string("JOIN_LOBBY_BOSSBAR_TITLE", Some("§2Server is starting..."))
+                .unwrap(),
+            tab_header: env.string("JOIN_LOBBY_TAB_HEADER", None),
+            tab_footer: env.string("JOIN_LOBBY_TAB_FOOTER", None),
+            show_actionbar: env.bool("JOIN_LOBBY_SHOW_ACTIONBAR", false),
+            actionbar_message: env
+                .string(
+                    "JOIN_LOBBY_ACTIONBAR_MESSAGE",
+                    Some("§7⌛ About {remaining} seconds remaining..."),
+                )
+                .unwrap(),
+            resource_pack_url: env.string("JOIN_LOBBY_RESOURCE_PACK_URL", None),
+            resource_pack_hash: env.string("JOIN_LOBBY_RESOURCE_PACK_HASH", None),
+            resource_pack_required: env.bool("JOIN_LOBBY_RESOURCE_PACK_REQUIRED", false),
+            resource_pack_declined_message: env
+                .string(
+                    "JOIN_LOBBY_RESOURCE_PACK_DECLINED_MESSAGE",
+                    Some("§cThis server requires a resource pack\n\n§7Accept it to join."),
+                )
+                .unwrap(),
+        }
+    }
+
+    /// Whether a custom tab list header or footer is configured.
+    pub fn has_tab_header_footer(&self) -> bool {
+        self.tab_header.is_some() || self.tab_footer.is_some()
+    }
+}
+
+impl Default for JoinLobby {
+    fn default() -> Self {
+        Self {
+            timeout: 10 * 60,
+            message: "§2Server is starting\n§7⌛ Please wait...".into(),
+            ready_sound: Some("block.note_block.chime".into()),
+            show_bossbar: true,
+            bossbar_title: "§2Server is starting...".into(),
+            tab_header: None,
+            tab_footer: None,
+            show_actionbar: false,
+            actionbar_message: "§7⌛ About {remaining} seconds remaining...".into(),
+            resource_pack_url: None,
+            resource_pack_hash: None,
+            resource_pack_required: false,
+            resource_pack_declined_message:
+                "§cThis server requires a resource pack\n\n§7Accept it to join.".into(),
+        }
+    }
+}
+
+/// Lockout configuration.
+#[derive(Debug, Clone, PartialEq, Deserialize, JsonSchema, Serialize)]
+#[serde(default)]
+pub struct Lockout {
+    /// Enable to prevent everybody from connecting through lazymc. Instantly kicks player.
+    pub enabled: bool,
+
+    /// Kick players with following message.
+    pub message: String,
+
+    /// Schedule to enable and disable lockout at.
+    pub schedule: Option<LockoutSchedule>,
+
+    /// Networks allowed to bypass lockout.
+    #[schemars(with = "Vec<String>")]
+    #[serde(deserialize_with = "to_ipnets")]
+    pub allowlist_ips: Vec<IpNet>,
+
+    /// Usernames allowed to bypass lockout, case-insensitive.
+    pub allowlist_usernames: Vec<String>,
+}
+
+impl Lockout {
+    fn from_env(env: &EnvReader) -> Self {
+        Self {
+            enabled: env.bool("LOCKOUT_ENABLED", false),
+            message: env
+                .string(
+                    "LOCKOUT_MESSAGE",
+                    Some("Server is closed §7☠§r\n\nPlease come back another time."),
+                )
+                .unwrap(),
+            schedule: LockoutSchedule::from_env(env),
+            allowlist_ips: env.vec_ipnet("LOCKOUT_ALLOWLIST_IPS"),
+            allowlist_usernames: env.vec_string("LOCKOUT_ALLOWLIST_USERNAMES", vec![]),
+        }
+    }
+
+    /// Check whether the given IP bypasses lockout.
+    pub fn allows_ip(&self, ip: &IpAddr) -> bool {
+        // Match IPv4-mapped IPv6 addresses as IPv4
+        let ip = match ip {
+            IpAddr::V6(ip) => ip
+                .to_ipv4_mapped()
+                .map(IpAddr::V4)
+                .unwrap_or(IpAddr::V6(ip)),
+            ip => *ip,
+        };
+        self.allowlist_ips.iter().any(|net| net.contains(&ip))
+    }
+
+    /// Check whether the given IP or username bypasses lockout.
+    ///
+    /// The username is only verified if the player is authenticated, check again after
+    /// authenticating.
+    pub fn allows(&self, ip: &IpAddr, username: Option<&str>) -> bool {
+        self.allows_ip(ip)
+            || username.is_some_and(|username| {
+                self.allowlist_usernames
+                    .iter()
+                    .any(|allowed| allowed.eq_ignore_ascii_case(username))
+            })
+    }
+}
+
+impl Default for Lockout {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            message: "Server is closed §7☠§r\n\nPlease come back another time.".into(),
+            schedule: None,
+            allowlist_ips: vec![],
+            allowlist_usernames: vec![],
+        }
+    }
+}
+
+/// Lockout schedule configuration.
+#[derive(Debug, Clone, PartialEq, Deserialize, JsonSchema, Serialize)]
+pub struct LockoutSchedule {
+    /// Cron expression of times to enable lockout at.
+    pub enabled_cron: String,
+
+    /// Cron expression of times to revert lockout to the configured state at.
+    pub disabled_cron: String,
+}
+
+impl LockoutSchedule {
+    /// Lockout schedule from environment, `None` unless both expressions are set.
+    fn from_env(env: &EnvReader) -> Option<Self> {
+        Some(Self {
+            enabled_cron: env.string("LOCKOUT_SCHEDULE_ENABLED_CRON", None)?,
+            disabled_cron: env.string("LOCKOUT_SCHEDULE_DISABLED_CRON", None)?,
+        })
+    }
+
+    /// Parsed schedule to enable lockout at, `None` if invalid.
+    pub fn enabled(&self) -> Option<cron::Schedule> {
+        self.enabled_cron.parse().ok()
+    }
+
+    /// Parsed schedule to disable lockout at, `None` if invalid.
+    pub fn disabled(&self) -> Option<cron::Schedule> {
+        self.disabled_cron.parse().ok()
+    }
+
+    /// Whether lockout is enabled by the schedule at the given time.
+    ///
+    /// Enabled if the last time to enable lockout at is more recent than the last time to
+    /// disable it. `None` if an expression is invalid.
+    pub fn is_enabled_at<Tz: TimeZone>(&self, at: &DateTime<Tz>) -> Option<bool> {
+        let enabled = self.enabled()?.after(at).next_back();
+        let disabled = self.disabled()?.after(at).next_back();
+        Some(match (enabled, disabled) {
+            (Some(enabled), Some(disabled)) => enabled > disabled,
+            (enabled, _) => enabled.is_some(),
+        })
+    }
+
+    /// Get errors for configured cron expressions that fail to parse.
+    fn cron_errors(&self) -> Vec<(&'static str, String)> {
+        [
+            ("lockout.schedule.enabled_cron", &self.enabled_cron),
+            ("lockout.schedule.disabled_cron", &self.disabled_cron),
+        ]
+        .into_iter()
+        .filter_map(|(key, cron)| {
+            let err = cron.parse::<cron::Schedule>().err()?;
+            Some((key, err.to_string()))
+        })
+        .collect()
+    }
+}
+
+/// Access configuration.
+#[derive(Debug, Clone, PartialEq, Deserialize, JsonSchema, Serialize)]
+#[serde(default)]
+pub struct Access {
+    /// Networks allowed to connect. Everybody is allowed if empty.
+    #[schemars(with = "Vec<String>")]
+    #[serde(deserialize_with = "to_ipnets")]
+    pub allowlist: Vec<IpNet>,
+
+    /// Networks denied to connect, unless allowlisted.
+    #[schemars(with = "Vec<String>")]
+    #[serde(deserialize_with = "to_ipnets")]
+    pub denylist: Vec<IpNet>,
+
+    /// Action to take for denied clients.
+    pub denylist_action: DenyAction,
+
+    /// Kick denied clients with following message.
+    pub denylist_message: String,
+
+    /// GeoIP country blocking configuration.
+    pub geoip: AccessGeoip,
+
+    /// Per network rate limiting configuration.
+    pub rate_limit: RateLimit,
+}
+
+impl Access {
+    fn from_env(env: &EnvReader) -> Self {
+        Self {
+            allowlist: env.vec_ipnet("ACCESS_ALLOWLIST"),
+            denylist: env.vec_ipnet("ACCESS_DENYLIST"),
+            denylist_action: env
+                .string("ACCESS_DENYLIST_ACTION", None)
+                .and_then(|s| s.parse().ok())
+                .unwrap_or_default(),
+            denylist_message: env
+                .string(
+                    "ACCESS_DENYLIST_MESSAGE",
+                    Some("You are not allowed to connect to this server."),
+                )
+                .unwrap(),
+            geoip: AccessGeoip::from_env(env),
+            rate_limit: RateLimit::from_env(env),
+        }
+    }
+
+    /// Check whether the given IP is denied access.
+    ///
+    /// Allowlisted IPs are never denied. If an allowlist is configured, all other IPs are denied.
+    pub fn is_denied(&self, ip: &IpAddr) -> bool {
+        // Match IPv4-mapped IPv6 addresses as IPv4
+        let ip = match ip {
+            IpAddr::V6(ip) => ip
+                .to_ipv4_mapped()
+                .map(IpAddr::V4)
+                .unwrap_or(IpAddr::V6(ip)),
+            ip => *ip,
+        };
+        if self.allowlist.iter().any(|net| net.contains(&ip)) {
+            return false;
+        }
+        !self.allowlist.is_empty() || self.denylist.iter().any(|net| net.contains(&ip))
+    }
+}
+
+impl Default for Access {
+    fn default() -> Self {
+        Self {
+            allowlist: vec![],
+            denylist: vec![],
+            denylist_action: DenyAction::default(),
+            denylist_message: "You are not allowed to connect to this server.".into(),
+            geoip: Default::default(),
+            rate_limit: Default::default(),
+        }
+    }
+}
+
+/// Rate limiting configuration.
+///
+/// Limits are applied per IPv4 /24 or IPv6 /48 network.
+#[derive(Debug, Clone, PartialEq, Deserialize, JsonSchema, Serialize)]
+#[serde(default)]
+pub struct RateLimit {
+    /// Enable rate limiting.
+    pub enabled: bool,
+
+    /// Maximum number of connections per minute.
+    pub max_connections_per_minute: u32,
+
+    /// Maximum number of status pings per minute.
+    pub max_status_pings_per_minute: u32,
+
+    /// Number of connections or status pings allowed in a burst above the rate.
+    pub burst: u32,
+}
+
+impl RateLimit {
+    fn from_env(env: &EnvReader) -> Self {
+        Self {
+            enabled: env.bool("ACCESS_RATE_LIMIT_ENABLED", false),
+            max_connections_per_minute: env.u32("ACCESS_RATE_LIMIT_MAX_CONNECTIONS_PER_MINUTE", 60),
+            max_status_pings_per_minute: env
+                .u32("ACCESS_RATE_LIMIT_MAX_STATUS_PINGS_PER_MINUTE", 30),
+            burst: env.u32("ACCESS_RATE_LIMIT_BURST", 10),
+        }
+    }
+}
+
+impl Default for RateLimit {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            max_connections_per_minute: 60,
+            max_status_pings_per_minute: 30,
+            burst: 10,
+        }
+    }
+}
+
+/// GeoIP country blocking configuration.
+#[derive(Debug, Clone, PartialEq, Deserialize, JsonSchema, Serialize)]
+#[serde(default)]
+pub struct AccessGeoip {
+    /// Enable GeoIP country blocking.
+    pub enabled: bool,
+
+    /// Path to MaxMind GeoLite2-Country or GeoIP2-Country database.
+    pub database: PathBuf,
+
+    /// ISO 3166-1 alpha-2 country codes to deny.
+    pub blocked_countries: Vec<String>,
+
+    /// Allow clients with unknown country.
+    pub allow_unknown: bool,
+}
+
+impl AccessGeoip {
+    fn from_env(env: &EnvReader) -> Self {
+        Self {
+            enabled: env.bool("ACCESS_GEOIP_ENABLED", false),
+            database: env
+                .string("ACCESS_GEOIP_DATABASE", Some("GeoLite2-Country.mmdb"))
+                .map(PathBuf::from)
+                .unwrap(),
+            blocked_countries: env
+                .vec_string("ACCESS_GEOIP_BLOCKED_COUNTRIES", vec![])
+                .into_iter()
+                .filter(|s| !s.is_empty())
+                .collect(),
+            allow_unknown: env.bool("ACCESS_GEOIP_ALLOW_UNKNOWN", true),
+        }
+    }
+
+    /// Check whether the given country is blocked, `None` if unknown.
+    pub fn is_blocked(&self, country: Option<&str>) -> bool {
+        match country {
+            Some(country) => self
+                .blocked_countries
+                .iter()
+                .any(|c| c.eq_ignore_ascii_case(country)),
+            None => !self.allow_unknown,
+        }
+    }
+}
+
+impl Default for AccessGeoip {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            database: "GeoLite2-Country.mmdb".into(),
+            blocked_countries: vec![],
+            allow_unknown: true,
+        }
+    }
+}
+
+/// Actions for denied clients.
+#[derive(Debug, Default, Deserialize, JsonSchema, Serialize, Copy, Clone, Eq, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum DenyAction {
+    /// Kick client with message on login.
+    #[default]
+    Kick,
+
+    /// Drop connection.
+    Drop,
+
+    /// Forward connection to the forward join method address.
+    Forward,
+}
+
+impl std::str::FromStr for DenyAction {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "kick" => Ok(DenyAction::Kick),
+            "drop" => Ok(DenyAction::Drop),
+            "forward" => Ok(DenyAction::Forward),
+            _ => Err(format!("Unknown deny action: {}", s)),
+        }
+    }
+}
+
+/// Authentication configuration, used when lazymc authenticates players itself.
+#[derive(Debug, Clone, PartialEq, Deserialize, JsonSchema, Serialize)]
+#[serde(default)]
+pub struct Auth {
+    /// Session server base URL to verify player logins with.
+    pub session_server: String,
+
+    /// Session server request timeout in seconds.
+    pub timeout_secs: u32,
+
+    /// Verify session server TLS certificate.
+    pub tls_verify: bool,
+}
+
+impl Auth {
+    fn from_env(env: &EnvReader) -> Self {
+        Self {
+            session_server: env
+                .string(
+                    "AUTH_SESSION_SERVER",
+                    Some("https://sessionserver.mojang.com"),
+                )
+                .unwrap(),
+            timeout_secs: env.u32("AUTH_TIMEOUT_SECS", 5),
+            tls_verify: env.bool("AUTH_TLS_VERIFY", true),
+        }
+    }
+}
+
+impl Default for Auth {
+    fn default() -> Self {
+        Self {
+            session_server: "https://sessionserver.mojang.com".into(),
+            timeout_secs: 5,
+            tls_verify: true,
+        }
+    }
+}
+
+/// RCON configuration.
+#[derive(Debug, Clone, PartialEq, Deserialize, JsonSchema, Serialize)]
+#[serde(default)]
+pub struct Rcon {
+    /// Enable sleeping server through RCON.
+    pub enabled: bool,
+
+    /// Server RCON port.
+    pub port: u16,
+
+    /// Server RCON password.
+    pub password: String,
+
+    /// Randomize server RCON password on each start.
+    pub randomize_password: bool,
+
+    /// Add HAProxy v2 header to RCON connections.
+    pub send_proxy_v2: bool,
+
+    /// Maximum number of idle RCON connections to keep open for reuse.
+    pub pool_size: u32,
+
+    /// Timeout in milliseconds for connecting to RCON.
+    pub connection_timeout_ms: u32,
+
+    /// Commands to run through RCON after the server started.
+    pub on_start_commands: Vec<String>,
+
+    /// Commands to run through RCON before the server is stopped.
+    pub on_stop_commands: Vec<String>,
+
+    /// Connect to RCON over TLS.
+    pub tls: bool,
+
+    /// CA certificate to verify the RCON TLS certificate with, web PKI roots are used if not set.
+    pub tls_ca_cert: Option<PathBuf>,
+
+    /// Name to verify the RCON TLS certificate for, the server IP is used if not set.
+    pub tls_server_name: Option<String>,
+}
+
+impl Rcon {
+    fn from_env(env: &EnvReader) -> Self {
+        Self {
+            enabled: env.bool("RCON_ENABLED", cfg!(windows)),
+            port: env.u16("RCON_PORT", 25575),
+            password: env.string("RCON_PASSWORD", Some("")).unwrap(),
+            randomize_password: env.bool("RCON_RANDOMIZE_PASSWORD", true),
+            send_proxy_v2: env.bool("RCON_SEND_PROXY_V2", false),
+            pool_size: env.u32("RCON_POOL_SIZE", 2),
+            connection_timeout_ms: env.u32("RCON_CONNECTION_TIMEOUT_MS", 3000),
+            on_start_commands: env.vec_semicolon("RCON_ON_START_COMMANDS"),
+            on_stop_commands: env.vec_semicolon("RCON_ON_STOP_COMMANDS"),
+            tls: env.bool("RCON_TLS", false),
+            tls_ca_cert: env.string("RCON_TLS_CA_CERT", None).map(PathBuf::from),
+            tls_server_name: env.string("RCON_TLS_SERVER_NAME", None),
+        }
+    }
+}
+
+impl Default for Rcon {
+    fn default() -> Self {
+        Self {
+            enabled: cfg!(windows),
+            port: 25575,
+            password: "".into(),
+            randomize_password: true,
+            send_proxy_v2: false,
+            pool_size: 2,
+            connection_timeout_ms: 3000,
+            on_start_commands: vec![],
+            on_stop_commands: vec![],
+            tls: false,
+            tls_ca_cert: None,
+            tls_server_name: None,
+        }
+    }
+}
+
+/// Metrics configuration.
+#[derive(Debug, Clone, PartialEq, Deserialize, JsonSchema, Serialize)]
+#[serde(default)]
+pub struct Metrics {
+    /// Enable Prometheus metrics endpoint.
+    pub enabled: bool,
+
+    /// Address to serve metrics on.
+    #[schemars(with = "String")]
+    #[serde(deserialize_with = "to_socket_addrs")]
+    pub address: SocketAddr,
+
+    /// HTTP path to serve metrics at.
+    pub path: String,
+}
+
+impl Metrics {
+    fn from_env(env: &EnvReader) -> Self {
+        Self {
+            enabled: env.bool("METRICS_ENABLED", false),
+            address: env.socket_addr("METRICS_ADDRESS", "0.0.0.0:9090"),
+            path: env.string("METRICS_PATH", Some("/metrics")).unwrap(),
+        }
+    }
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            address: "0.0.0.0:9090".parse().unwrap(),
+            path: "/metrics".into(),
+        }
+    }
+}
+
+/// Management API configuration.
+#[derive(Debug, Clone, PartialEq, Deserialize, JsonSchema, Serialize)]
+#[serde(default)]
+pub struct Api {
+    /// Enable HTTP management API.
+    pub enabled: bool,
+
+    /// Address to serve API on.
+    #[schemars(with = "String")]
+    #[serde(deserialize_with = "to_socket_addrs")]
+    pub address: SocketAddr,
+
+    /// Bearer token required to use the API.
+    pub token: Option<String>,
+}
+
+impl Api {
+    fn from_env(env: &EnvReader) -> Self {
+        Self {
+            enabled: env.bool("API_ENABLED", false),
+            address: env.socket_addr("API_ADDRESS", "127.0.0.1:8080"),
+            token: env.string("API_TOKEN", None),
+        }
+    }
+}
+
+impl Default for Api {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            address: "127.0.0.1:8080".parse().unwrap(),
+            token: None,
+        }
+    }
+}
+
+/// Query protocol configuration.
+#[derive(Debug, Clone, PartialEq, Deserialize, JsonSchema, Serialize)]
+#[serde(default)]
+pub struct Query {
+    /// Respond to Minecraft Query protocol requests.
+    pub enabled: bool,
+
+    /// UDP port to respond to queries on, on the public address.
+    pub port: u16,
+}
+
+impl Query {
+    fn from_env(env: &EnvReader) -> Self {
+        Self {
+            enabled: env.bool("QUERY_ENABLED", false),
+            port: env.u16("QUERY_PORT", 25565),
+        }
+    }
+}
+
+impl Default for Query {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            port: 25565,
+        }
+    }
+}
+
+/// Notification configuration.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, JsonSchema, Serialize)]
+#[serde(default)]
+pub struct Notify {
+    /// Webhooks to call on server events.
+    pub webhooks: Vec<Webhook>,
+
+    /// Discord webhook to post server events to.
+    pub discord: Option<NotifyDiscord>,
+}
+
+impl Notify {
+    fn from_env(env: &EnvReader) -> Self {
+        Self {
+            webhooks: Webhook::from_env(env).into_iter().collect(),
+            discord: NotifyDiscord::from_env(env),
+        }
+    }
+}
+
+/// Webhook configuration.
+#[derive(Debug, Clone, PartialEq, Deserialize, JsonSchema, Serialize)]
+#[serde(default)]
+pub struct Webhook {
+    /// URL to call.
+    pub url: String,
 
-    /// Drop connections from banned IPs.
-    #[serde(default)]
-    pub drop_banned_ips: bool,
+    /// Events to call webhook on.
+    pub events: Vec<ServerEvent>,
 
-    /// Add HAProxy v2 header to proxied connections.
-    #[serde(default)]
-    pub send_proxy_v2: bool,
+    /// HTTP method to use.
+    pub method: String,
+
+    /// Request body template.
+    ///
+    /// Supports `{state}`, `{players}` and `{timestamp}` placeholders. A JSON body with these
+    /// fields is sent if not set.
+    pub body_template: Option<String>,
 }
 
-impl Server {
-    fn from_env(command: String) -> Self {
-        let directory = get_env_string("LAZYMC_SERVER_DIRECTORY", Some("."))
-            .map(PathBuf::from);
+impl Webhook {
+    /// Webhook from environment, `None` if no URL is set.
+    fn from_env(env: &EnvReader) -> Option<Self> {
+        Some(Self {
+            url: env.string("NOTIFY_WEBHOOK_URL", None)?,
+            events: env
+                .vec_string(
+                    "NOTIFY_WEBHOOK_EVENTS",
+                    vec!["sleeping", "starting", "online", "stopping", "crash_loop"],
+                )
+                .iter()
+                .filter_map(|s| s.parse().ok())
+                .collect(),
+            method: env.string("NOTIFY_WEBHOOK_METHOD", Some("POST")).unwrap(),
+            body_template: env.string("NOTIFY_WEBHOOK_BODY_TEMPLATE", None),
+        })
+    }
+}
 
+impl Default for Webhook {
+    fn default() -> Self {
         Self {
-            directory,
-            command,
-            address: get_env_socket_addr("LAZYMC_SERVER_ADDRESS", "127.0.0.1:25566"),
-            freeze_process: get_env_bool("LAZYMC_SERVER_FREEZE_PROCESS", true),
-            wake_on_start: get_env_bool("LAZYMC_SERVER_WAKE_ON_START", false),
-            wake_on_crash: get_env_bool("LAZYMC_SERVER_WAKE_ON_CRASH", false),
-            probe_on_start: get_env_bool("LAZYMC_SERVER_PROBE_ON_START", false),
-            forge: get_env_bool("LAZYMC_SERVER_FORGE", false),
-            start_timeout: get_env_u32("LAZYMC_SERVER_START_TIMEOUT", 300),
-            stop_timeout: get_env_u32("LAZYMC_SERVER_STOP_TIMEOUT", 150),
-            wake_whitelist: get_env_bool("LAZYMC_SERVER_WAKE_WHITELIST", true),
-            block_banned_ips: get_env_bool("LAZYMC_SERVER_BLOCK_BANNED_IPS", true),
-            drop_banned_ips: get_env_bool("LAZYMC_SERVER_DROP_BANNED_IPS", false),
-            send_proxy_v2: get_env_bool("LAZYMC_SERVER_SEND_PROXY_V2", false),
+            url: String::new(),
+            events: vec![
+                ServerEvent::Sleeping,
+                ServerEvent::Starting,
+                ServerEvent::Online,
+                ServerEvent::Stopping,
+                ServerEvent::CrashLoop,
+            ],
+            method: "POST".into(),
+            body_template: None,
         }
     }
+}
 
-    /// Get the server directory.
+/// Discord notification configuration.
+#[derive(Debug, Clone, PartialEq, Deserialize, JsonSchema, Serialize)]
+#[serde(default)]
+pub struct NotifyDiscord {
+    /// Discord webhook URL.
+    pub webhook_url: String,
+
+    /// Events to post.
+    pub events: Vec<ServerEvent>,
+
+    /// Override webhook username.
+    pub username: Option<String>,
+
+    /// Override webhook avatar.
+    pub avatar_url: Option<String>,
+}
+
+impl NotifyDiscord {
+    /// Discord notification from environment, `None` if no webhook URL is set.
+    fn from_env(env: &EnvReader) -> Option<Self> {
+        Some(Self {
+            webhook_url: env.string("NOTIFY_DISCORD_WEBHOOK_URL", None)?,
+            events: env
+                .vec_string(
+                    "NOTIFY_DISCORD_EVENTS",
+                    vec!["sleeping", "starting", "online", "crash_loop"],
+                )
+                .iter()
+                .filter_map(|s| s.parse().ok())
+                .collect(),
+            username: env.string("NOTIFY_DISCORD_USERNAME", None),
+            avatar_url: env.string("NOTIFY_DISCORD_AVATAR_URL", None),
+        })
+    }
+}
+
+impl Default for NotifyDiscord {
+    fn default() -> Self {
+        Self {
+            webhook_url: String::new(),
+            events: vec![
+                ServerEvent::Sleeping,
+                ServerEvent::Starting,
+                ServerEvent::Online,
+                ServerEvent::CrashLoop,
+            ],
+            username: None,
+            avatar_url: None,
+        }
+    }
+}
+
+/// Server event types.
+#[derive(Debug, Deserialize, JsonSchema, Serialize, Copy, Clone, Eq, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ServerEvent {
+    /// Server is now sleeping.
+    Sleeping,
+
+    /// Server is starting.
+    Starting,
+
+    /// Server is now online.
+    Online,
+
+    /// Server is stopping.
+    Stopping,
+
+    /// Server stopped restarting after crashing repeatedly.
+    CrashLoop,
+}
+
+impl ServerEvent {
+    /// Event name.
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Sleeping => "sleeping",
+            Self::Starting => "starting",
+            Self::Online => "online",
+            Self::Stopping => "stopping",
+            Self::CrashLoop => "crash_loop",
+        }
+    }
+}
+
+impl std::str::FromStr for ServerEvent {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "sleeping" => Ok(ServerEvent::Sleeping),
+            "starting" => Ok(ServerEvent::Starting),
+            "online" => Ok(ServerEvent::Online),
+            "stopping" => Ok(ServerEvent::Stopping),
+            "crash_loop" | "crashloop" => Ok(ServerEvent::CrashLoop),
+            _ => Err(format!("Unknown server event: {}", s)),
+        }
+    }
+}
+
+/// Hooks configuration.
+#[derive(Debug, Clone, PartialEq, Deserialize, JsonSchema, Serialize)]
+#[serde(default)]
+pub struct Hooks {
+    /// Shell command to run before starting the server, start is aborted if it fails.
+    pub pre_start: Option<String>,
+
+    /// Shell command to run once the server is online.
+    pub post_start: Option<String>,
+
+    /// Shell command to run before stopping the server.
+    pub pre_stop: Option<String>,
+
+    /// Shell command to run once the server is sleeping.
+    pub post_stop: Option<String>,
+
+    /// Shell command to run when a player joins through lazymc, does not block.
+    pub on_player_join: Option<String>,
+
+    /// Shell command to run when a player leaves, does not block.
+    pub on_player_leave: Option<String>,
+
+    /// Hook timeout in seconds.
+    pub hook_timeout_secs: u32,
+}
+
+impl Hooks {
+    fn from_env(env: &EnvReader) -> Self {
+        Self {
+            pre_start: env.string("HOOKS_PRE_START", None),
+            post_start: env.string("HOOKS_POST_START", None),
+            pre_stop: env.string("HOOKS_PRE_STOP", None),
+            post_stop: env.string("HOOKS_POST_STOP", None),
+            on_player_join: env.string("HOOKS_ON_PLAYER_JOIN", None),
+            on_player_leave: env.string("HOOKS_ON_PLAYER_LEAVE", None),
+            hook_timeout_secs: env.u32("HOOKS_HOOK_TIMEOUT_SECS", 30),
+        }
+    }
+
+    /// Whether player join or leave hooks are configured.
+    pub fn has_player_hooks(&self) -> bool {
+        [&self.on_player_join, &self.on_player_leave]
+            .iter()
+            .any(|hook| hook.as_deref().is_some_and(|cmd| !cmd.trim().is_empty()))
+    }
+}
+
+impl Default for Hooks {
+    fn default() -> Self {
+        Self {
+            pre_start: None,
+            post_start: None,
+            pre_stop: None,
+            post_stop: None,
+            on_player_join: None,
+            on_player_leave: None,
+            hook_timeout_secs: 30,
+        }
+    }
+}
+
+/// Stats configuration.
+#[derive(Debug, Clone, PartialEq, Deserialize, JsonSchema, Serialize)]
+#[serde(default)]
+pub struct Stats {
+    /// Whether to record player sessions and server events.
+    pub enabled: bool,
+
+    /// SQLite database file, relative to the configuration directory.
+    pub database: PathBuf,
+
+    /// Instance name recorded with sessions and events, to tell apart servers sharing a database.
     ///
-    /// This does not check whether it exists.
-    pub fn server_directory(config: &Config) -> Option<PathBuf> {
-        // Get directory, relative to config directory if known
-        match config.path.as_ref().and_then(|p| p.parent()) {
-            Some(config_dir) => Some(config_dir.join(config.server.directory.as_ref()?)),
-            None => config.server.directory.clone(),
+    /// Virtual hosts append their hostname.
+    pub instance: Option<String>,
+}
+
+impl Stats {
+    fn from_env(env: &EnvReader) -> Self {
+        Self {
+            enabled: env.bool("STATS_ENABLED", false),
+            database: env
+                .string("STATS_DATABASE", Some("lazymc-stats.db"))
+                .map(PathBuf::from)
+                .unwrap(),
+            instance: env.string("STATS_INSTANCE", None),
         }
     }
 }
 
-/// Time configuration.
-#[derive(Debug, Deserialize)]
+impl Default for Stats {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            database: PathBuf::from("lazymc-stats.db"),
+            instance: None,
+        }
+    }
+}
+
+/// TLS configuration.
+#[derive(Debug, Clone, PartialEq, Deserialize, JsonSchema, Serialize)]
 #[serde(default)]
-pub struct Time {
-    /// Sleep after number of seconds.
-    pub sleep_after: u32,
+pub struct Tls {
+    /// Whether clients connect through TLS.
+    pub enabled: bool,
 
-    /// Minimum time in seconds to stay online when server is started.
-    #[serde(default, alias = "minimum_online_time")]
-    pub min_online_time: u32,
+    /// PEM certificate chain file, relative to the configuration directory.
+    pub cert: PathBuf,
+
+    /// PEM private key file, relative to the configuration directory.
+    pub key: PathBuf,
+
+    /// PEM CA certificate file to require and verify client certificates with.
+    pub client_ca: Option<PathBuf>,
+
+    /// Automatic certificate provisioning, replaces cert and key.
+    #[serde(default)]
+    pub acme: TlsAcme,
 }
 
-impl Time {
-    fn from_env() -> Self {
+impl Tls {
+    fn from_env(env: &EnvReader) -> Self {
         Self {
-            sleep_after: get_env_u32("LAZYMC_TIME_SLEEP_AFTER", 60),
-            min_online_time: get_env_u32("LAZYMC_TIME_MIN_ONLINE_TIME", 60),
+            enabled: env.bool("TLS_ENABLED", false),
+            cert: env.string("TLS_CERT", Some("")).map(PathBuf::from).unwrap(),
+            key: env.string("TLS_KEY", Some("")).map(PathBuf::from).unwrap(),
+            client_ca: env.string("TLS_CLIENT_CA", None).map(PathBuf::from),
+            acme: TlsAcme::from_env(env),
         }
     }
 }
 
-impl Default for Time {
+impl Default for Tls {
     fn default() -> Self {
         Self {
-            sleep_after: 60,
-            min_online_time: 60,
+            enabled: false,
+            cert: PathBuf::new(),
+            key: PathBuf::new(),
+            client_ca: None,
+            acme: TlsAcme::default(),
         }
     }
 }
 
-/// MOTD configuration.
-#[derive(Debug, Deserialize)]
+/// ACME certificate provisioning configuration.
+#[derive(Debug, Clone, PartialEq, Deserialize, JsonSchema, Serialize)]
 #[serde(default)]
-pub struct Motd {
-    /// MOTD when server is sleeping.
-    pub sleeping: String,
+pub struct TlsAcme {
+    /// Whether to obtain and renew the TLS certificate through ACME.
+    pub enabled: bool,
 
-    /// MOTD when server is starting.
-    pub starting: String,
+    /// Domain to obtain certificate for.
+    pub domain: String,
 
-    /// MOTD when server is stopping.
-    pub stopping: String,
+    /// Contact email for the ACME account.
+    pub email: String,
 
-    /// Use MOTD from Minecraft server once known.
-    pub from_server: bool,
+    /// Whether the terms of service of the ACME server are agreed to, required to create an
+    /// account.
+    pub accept_terms: bool,
+
+    /// Directory to store account, certificate and key in, relative to the configuration directory.
+    pub cache_dir: PathBuf,
+
+    /// Address to serve HTTP-01 challenges on, must be reachable on port 80 for the domain.
+    #[schemars(with = "String")]
+    #[serde(deserialize_with = "to_socket_addrs")]
+    pub http_address: SocketAddr,
+
+    /// ACME directory URL.
+    pub directory_url: String,
 }
 
-impl Motd {
-    fn from_env() -> Self {
+impl TlsAcme {
+    fn from_env(env: &EnvReader) -> Self {
         Self {
-            sleeping: get_env_string("LAZYMC_MOTD_SLEEPING", 
-                Some("☠ Server is sleeping\n§2☻ Join to start it up"))
+            enabled: env.bool("TLS_ACME_ENABLED", false),
+            domain: env.string("TLS_ACME_DOMAIN", Some("")).unwrap(),
+            email: env.string("TLS_ACME_EMAIL", Some("")).unwrap(),
+            accept_terms: env.bool("TLS_ACME_ACCEPT_TERMS", false),
+            cache_dir: env
+                .string("TLS_ACME_CACHE_DIR", Some("acme"))
+                .map(PathBuf::from)
                 .unwrap(),
-            starting: get_env_string("LAZYMC_MOTD_STARTING", 
-                Some("§2☻ Server is starting...\n§7⌛ Please wait..."))
+            http_address: env.socket_addr("TLS_ACME_HTTP_ADDRESS", "0.0.0.0:80"),
+            directory_url: env
+                .string("TLS_ACME_DIRECTORY_URL", Some(ACME_DIRECTORY_URL))
                 .unwrap(),
-            stopping: get_env_string("LAZYMC_MOTD_STOPPING", 
-                Some("☠ Server going to sleep...\n⌛ Please wait..."))
+        }
+    }
+}
+
+impl Default for TlsAcme {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            domain: String::new(),
+            email: String::new(),
+            accept_terms: false,
+            cache_dir: PathBuf::from("acme"),
+            http_address: "0.0.0.0:80".parse().unwrap(),
+            directory_url: ACME_DIRECTORY_URL.into(),
+        }
+    }
+}
+
+/// Chat filter configuration.
+#[derive(Debug, Clone, PartialEq, Deserialize, JsonSchema, Serialize)]
+#[serde(default)]
+pub struct ChatFilter {
+    /// Whether to filter chat messages of players on the server.
+    pub enabled: bool,
+
+    /// Filter rules, applied in order.
+    pub rules: Vec<ChatRule>,
+
+    /// Message sent to players whose chat message is blocked, empty to send nothing.
+    pub blocked_message: String,
+}
+
+impl ChatFilter {
+    fn from_env(env: &EnvReader) -> Self {
+        Self {
+            enabled: env.bool("CHAT_FILTER_ENABLED", false),
+            rules: Vec::new(),
+            blocked_message: env
+                .string(
+                    "CHAT_FILTER_BLOCKED_MESSAGE",
+                    Some("§cYour message was blocked."),
+                )
                 .unwrap(),
-            from_server: get_env_bool("LAZYMC_MOTD_FROM_SERVER", false),
         }
     }
 }
 
-impl Default for Motd {
+impl Default for ChatFilter {
     fn default() -> Self {
         Self {
-            sleeping: "☠ Server is sleeping\n§2☻ Join to start it up".into(),
-            starting: "§2☻ Server is starting...\n§7⌛ Please wait...".into(),
-            stopping: "☠ Server going to sleep...\n⌛ Please wait...".into(),
-            from_server: false,
+            enabled: false,
+            rules: Vec::new(),
+            blocked_message: "§cYour message was blocked.".into(),
         }
     }
 }
 
-/// Join method types.
-#[derive(Debug, Deserialize, Copy, Clone, Eq, PartialEq)]
-#[serde(rename_all = "lowercase")]
-pub enum Method {
-    /// Kick client with message.
-    Kick,
+/// Chat filter rule.
+#[derive(Debug, Clone, PartialEq, Deserialize, JsonSchema, Serialize)]
+pub struct ChatRule {
+    /// Regular expression to match message text with.
+    #[schemars(with = "String")]
+    pub pattern: Pattern,
 
-    /// Hold client connection until server is ready.
-    Hold,
+    /// Action to take on matching messages.
+    pub action: FilterAction,
 
-    /// Forward connection to another host.
-    Forward,
+    /// Text to replace matches with, masks matches with `*` if not set.
+    #[serde(default)]
+    pub replacement: Option<String>,
+}
 
-    /// Keep client in temporary fake lobby until server is ready.
-    Lobby,
+/// Regular expression, compiled once when the configuration is loaded.
+#[derive(Debug, Clone)]
+pub struct Pattern(Regex);
+
+impl Pattern {
+    /// Compiled regular expression.
+    pub fn regex(&self) -> &Regex {
+        &self.0
+    }
 }
 
-impl std::str::FromStr for Method {
-    type Err = String;
+impl std::str::FromStr for Pattern {
+    type Err = regex::Error;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        match s.to_lowercase().as_str() {
-            "kick" => Ok(Method::Kick),
-            "hold" => Ok(Method::Hold),
-            "forward" => Ok(Method::Forward),
-            "lobby" => Ok(Method::Lobby),
-            _ => Err(format!("Unknown join method: {}", s)),
-        }
+        Regex::new(s).map(Self)
     }
 }
 
-/// Join configuration.
-#[derive(Debug, Deserialize)]
-#[serde(default)]
-pub struct Join {
-    /// Join methods.
-    pub methods: Vec<Method>,
+impl PartialEq for Pattern {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.as_str() == other.0.as_str()
+    }
+}
 
-    /// Join kick configuration.
-    #[serde(default)]
-    pub kick: JoinKick,
+impl<'de> Deserialize<'de> for Pattern {
+    fn deserialize<D>(d: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        String::deserialize(d)?
+            .parse()
+            .map_err(serde::de::Error::custom)
+    }
+}
 
-    /// Join hold configuration.
-    #[serde(default)]
-    pub hold: JoinHold,
+impl Serialize for Pattern {
+    fn serialize<S>(&self, s: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        s.serialize_str(self.0.as_str())
+    }
+}
 
-    /// Join forward configuration.
-    #[serde(default)]
-    pub forward: JoinForward,
+/// Chat filter rule action.
+#[derive(Debug, Copy, Clone, Deserialize, JsonSchema, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum FilterAction {
+    /// Don't send message to server, tell player it was blocked.
+    Block,
 
-    /// Join lobby configuration.
-    #[serde(default)]
-    pub lobby: JoinLobby,
+    /// Replace matches before sending message to server.
+    Replace,
 }
 
-impl Join {
-    fn from_env() -> Self {
-        let methods_str = get_env_vec_string("LAZYMC_JOIN_METHODS", vec!["hold", "kick"]);
-        let methods = methods_str.into_iter()
-            .filter_map(|s| s.parse().ok())
-            .collect();
+/// Command filter configuration.
+#[derive(Debug, Clone, PartialEq, Deserialize, JsonSchema, Serialize)]
+#[serde(default)]
+pub struct CommandFilter {
+    /// Whether to block commands of players on the server.
+    pub enabled: bool,
+
+    /// Commands to block, without leading `/`.
+    pub blocked_commands: Vec<String>,
+
+    /// Whether OPs in the server's ops.json may use blocked commands.
+    pub bypass_ops: bool,
+
+    /// Message sent to players whose command is blocked, empty to send nothing.
+    pub blocked_message: String,
+}
 
+impl CommandFilter {
+    fn from_env(env: &EnvReader) -> Self {
         Self {
-            methods,
-            kick: JoinKick::from_env(),
-            hold: JoinHold::from_env(),
-            forward: JoinForward::from_env(),
-            lobby: JoinLobby::from_env(),
+            enabled: env.bool("COMMAND_FILTER_ENABLED", false),
+            blocked_commands: env.vec_string("COMMAND_FILTER_BLOCKED_COMMANDS", vec![]),
+            bypass_ops: env.bool("COMMAND_FILTER_BYPASS_OPS", false),
+            blocked_message: env
+                .string(
+                    "COMMAND_FILTER_BLOCKED_MESSAGE",
+                    Some("§cThis command is blocked."),
+                )
+                .unwrap(),
         }
     }
+
+    /// Whether the given command line is blocked, by its first token.
+    ///
+    /// Namespaced commands such as `minecraft:stop` match their plain name as well.
+    pub fn is_blocked(&self, command: &str) -> bool {
+        let name = command
+            .trim_start_matches('/')
+            .split_whitespace()
+            .next()
+            .unwrap_or_default();
+        let plain = name.rsplit(':').next().unwrap_or(name);
+        self.blocked_commands.iter().any(|blocked| {
+            let blocked = blocked.trim_start_matches('/');
+            blocked.eq_ignore_ascii_case(name) || blocked.eq_ignore_ascii_case(plain)
+        })
+    }
 }
 
-impl Default for Join {
+impl Default for CommandFilter {
     fn default() -> Self {
         Self {
-            methods: vec![Method::Hold, Method::Kick],
-            kick: Default::default(),
-            hold: Default::default(),
-            forward: Default::default(),
-            lobby: Default::default(),
+            enabled: false,
+            blocked_commands: vec![],
+            bypass_ops: false,
+            blocked_message: "§cThis command is blocked.".into(),
         }
     }
 }
 
-/// Join kick configuration.
-#[derive(Debug, Deserialize)]
-#[serde(default)]
-pub struct JoinKick {
-    /// Kick message when server is starting.
-    pub starting: String,
+/// Advanced configuration.
+#[derive(Debug, Clone, PartialEq, Deserialize, JsonSchema, Serialize)]
+#[serde(default)]
+pub struct Advanced {
+    /// Rewrite server.properties.
+    pub rewrite_server_properties: bool,
+
+    /// Log configuration.
+    #[serde(default)]
+    pub log: Log,
+
+    /// Resource limits for the server process, Unix only.
+    #[serde(default)]
+    pub resource_limits: ResourceLimits,
+
+    /// TCP settings for client and server connections.
+    #[serde(default)]
+    pub tcp: TcpSettings,
+
+    /// Server status polling while the server is starting.
+    #[serde(default)]
+    pub poll: Poll,
+
+    /// Forward proxied connections with `splice(2)` in the kernel, Linux only.
+    pub splice_forwarding: bool,
+
+    /// Unix socket path to accept IPC commands on.
+    pub ipc_socket: Option<PathBuf>,
+
+    /// File to write lazymc PID to.
+    pub pid_file: Option<PathBuf>,
+
+    /// Maximum number of concurrent connections.
+    pub max_connections: Option<u32>,
+
+    /// Maximum number of concurrent connections per IP.
+    pub max_connections_per_ip: Option<u32>,
+
+    /// Kick clients exceeding connection limits with following message.
+    pub connection_limit_message: String,
+
+    /// Send systemd service notifications.
+    ///
+    /// Enabled automatically if `NOTIFY_SOCKET` is set and not configured.
+    pub systemd_notify: Option<bool>,
+
+    /// Cache status responses for this many milliseconds, `0` to disable.
+    pub status_cache_ms: u64,
+
+    /// Additional server.properties values to write before each start.
+    #[serde(rename = "server_properties")]
+    pub server_properties_overrides: HashMap<String, String>,
+
+    /// Java binary to use if the server command starts with `java`.
+    pub java_path: Option<PathBuf>,
+
+    /// Flags to add after the Java binary if the server command starts with `java`.
+    pub java_flags: Vec<String>,
+
+    /// User to run the server process as, Unix only.
+    pub server_user: Option<String>,
+
+    /// Group to run the server process as, Unix only.
+    ///
+    /// Defaults to the primary group of the server user.
+    pub server_group: Option<String>,
+
+    /// Network interface to restrict the public listener to, Linux only.
+    pub bind_interface: Option<String>,
+
+    /// Shared libraries to load packet hooks from, relative to the configuration directory.
+    pub packet_hooks: Vec<PathBuf>,
 
-    /// Kick message when server is stopping.
-    pub stopping: String,
+    /// Close proxied connections if no data is received in one direction for this many seconds.
+    pub proxy_read_timeout_secs: Option<u32>,
+
+    /// Close proxied connections if writing data in one direction stalls for this many seconds.
+    pub proxy_write_timeout_secs: Option<u32>,
 }
 
-impl JoinKick {
-    fn from_env() -> Self {
+impl Advanced {
+    fn from_env(env: &EnvReader) -> Self {
         Self {
-            starting: get_env_string("LAZYMC_JOIN_KICK_STARTING", 
-                Some("Server is starting... §c♥§r\n\nThis may take some time.\n\nPlease try to reconnect in a minute."))
-                .unwrap(),
-            stopping: get_env_string("LAZYMC_JOIN_KICK_STOPPING", 
-                Some("Server is going to sleep... §7☠§r\n\nPlease try to reconnect in a minute to wake it again."))
+            rewrite_server_properties: env.bool("ADVANCED_REWRITE_SERVER_PROPERTIES", true),
+            log: Log::from_env(env),
+            resource_limits: ResourceLimits::from_env(env),
+            tcp: TcpSettings::from_env(env),
+            poll: Poll::from_env(env),
+            splice_forwarding: env.bool("ADVANCED_SPLICE_FORWARDING", false),
+            ipc_socket: env.string("ADVANCED_IPC_SOCKET", None).map(PathBuf::from),
+            pid_file: env.string("ADVANCED_PID_FILE", None).map(PathBuf::from),
+            max_connections: env
+                .var("ADVANCED_MAX_CONNECTIONS")
+                .ok()
+                .and_then(|s| s.parse().ok()),
+            max_connections_per_ip: env
+                .var("ADVANCED_MAX_CONNECTIONS_PER_IP")
+                .ok()
+                .and_then(|s| s.parse().ok()),
+            connection_limit_message: env
+                .string(
+                    "ADVANCED_CONNECTION_LIMIT_MESSAGE",
+                    Some("Too many connections §c✖§r\n\nPlease try to reconnect in a minute."),
+                )
                 .unwrap(),
+            systemd_notify: env
+                .var("ADVANCED_SYSTEMD_NOTIFY")
+                .ok()
+                .map(|_| env.bool("ADVANCED_SYSTEMD_NOTIFY", false)),
+            status_cache_ms: env.u64("ADVANCED_STATUS_CACHE_MS", 0),
+            server_properties_overrides: env
+                .vec_string("ADVANCED_SERVER_PROPERTIES", vec![])
+                .iter()
+                .filter_map(|s| s.split_once('='))
+                .map(|(key, value)| (key.trim().to_string(), value.trim().to_string()))
+                .collect(),
+            java_path: env.string("ADVANCED_JAVA_PATH", None).map(PathBuf::from),
+            java_flags: env
+                .string("ADVANCED_JAVA_FLAGS", None)
+                .and_then(|flags| shlex::split(&flags))
+                .unwrap_or_default(),
+            server_user: env.string("ADVANCED_SERVER_USER", None),
+            server_group: env.string("ADVANCED_SERVER_GROUP", None),
+            bind_interface: env.string("ADVANCED_BIND_INTERFACE", None),
+            packet_hooks: env
+                .vec_string("ADVANCED_PACKET_HOOKS", vec![])
+                .into_iter()
+                .map(PathBuf::from)
+                .collect(),
+            proxy_read_timeout_secs: env
+                .var("ADVANCED_PROXY_READ_TIMEOUT_SECS")
+                .ok()
+                .and_then(|s| s.parse().ok()),
+            proxy_write_timeout_secs: env
+                .var("ADVANCED_PROXY_WRITE_TIMEOUT_SECS")
+                .ok()
+                .and_then(|s| s.parse().ok()),
         }
     }
-}
 
-impl Default for JoinKick {
-    fn default() -> Self {
-        Self {
-            starting: "Server is starting... §c♥§r\n\nThis may take some time.\n\nPlease try to reconnect in a minute.".into(),
-            stopping: "Server is going to sleep... §7☠§r\n\nPlease try to reconnect in a minute to wake it again.".into(),
-        }
+    /// Timeout for reading data of proxied connections, if any.
+    pub fn proxy_read_timeout(&self) -> Option<Duration> {
+        self.proxy_read_timeout_secs
+            .map(|secs| Duration::from_secs(secs as u64))
     }
-}
 
-/// Join hold configuration.
-#[derive(Debug, Deserialize)]
-#[serde(default)]
-pub struct JoinHold {
-    /// Hold client for number of seconds on connect while server starts.
-    pub timeout: u32,
-}
+    /// Timeout for writing data of proxied connections, if any.
+    pub fn proxy_write_timeout(&self) -> Option<Duration> {
+        self.proxy_write_timeout_secs
+            .map(|secs| Duration::from_secs(secs as u64))
+    }
 
-impl JoinHold {
-    fn from_env() -> Self {
-        Self {
-            timeout: get_env_u32("LAZYMC_JOIN_HOLD_TIMEOUT", 25),
-        }
+    /// Whether a new connection from the given IP exceeds connection limits of the server.
+    pub fn is_connection_limited(&self, stats: &ServerStats, ip: &IpAddr) -> bool {
+        let total = self
+            .max_connections
+            .map(|max| stats.active_connections() >= max as u64)
+            .unwrap_or(false);
+        let per_ip = self
+            .max_connections_per_ip
+            .map(|max| stats.ip_connections(ip) >= max)
+            .unwrap_or(false);
+        total || per_ip
+    }
+
+    /// Whether to send systemd service notifications.
+    pub fn systemd_notify(&self) -> bool {
+        self.systemd_notify
+            .unwrap_or_else(|| env::var_os("NOTIFY_SOCKET").is_some())
     }
 }
 
-impl Default for JoinHold {
+impl Default for Advanced {
     fn default() -> Self {
-        Self { timeout: 25 }
+        Self {
+            rewrite_server_properties: true,
+            log: Default::default(),
+            resource_limits: Default::default(),
+            tcp: Default::default(),
+            poll: Default::default(),
+            splice_forwarding: false,
+            ipc_socket: None,
+            pid_file: None,
+            max_connections: None,
+            max_connections_per_ip: None,
+            connection_limit_message:
+                "Too many connections §c✖§r\n\nPlease try to reconnect in a minute.".into(),
+            systemd_notify: None,
+            status_cache_ms: 0,
+            server_properties_overrides: HashMap::new(),
+            java_path: None,
+            java_flags: vec![],
+            server_user: None,
+            server_group: None,
+            bind_interface: None,
+            packet_hooks: vec![],
+            proxy_read_timeout_secs: None,
+            proxy_write_timeout_secs: None,
+        }
     }
 }
 
-/// Join forward configuration.
-#[derive(Debug, Deserialize)]
+/// Log configuration.
+#[derive(Debug, Clone, PartialEq, Deserialize, JsonSchema, Serialize)]
 #[serde(default)]
-pub struct JoinForward {
-    /// IP and port to forward to.
-    #[serde(deserialize_with = "to_socket_addrs")]
-    pub address: SocketAddr,
+pub struct Log {
+    /// Log to file at given path.
+    pub file: Option<PathBuf>,
 
-    /// Add HAProxy v2 header to proxied connections.
-    #[serde(default)]
-    pub send_proxy_v2: bool,
+    /// Rotate log file when it exceeds this size in megabytes.
+    pub max_size_mb: u64,
+
+    /// Maximum number of rotated log files to keep.
+    pub max_files: usize,
+
+    /// Log to console.
+    pub console: bool,
 }
 
-impl JoinForward {
-    fn from_env() -> Self {
+impl Log {
+    fn from_env(env: &EnvReader) -> Self {
         Self {
-            address: get_env_socket_addr("LAZYMC_JOIN_FORWARD_ADDRESS", "127.0.0.1:25565"),
-            send_proxy_v2: get_env_bool("LAZYMC_JOIN_FORWARD_SEND_PROXY_V2", false),
+            file: env.string("ADVANCED_LOG_FILE", None).map(PathBuf::from),
+            max_size_mb: env.u32("ADVANCED_LOG_MAX_SIZE_MB", 10) as u64,
+            max_files: env.u32("ADVANCED_LOG_MAX_FILES", 5) as usize,
+            console: env.bool("ADVANCED_LOG_CONSOLE", true),
         }
     }
 }
 
-impl Default for JoinForward {
+impl Default for Log {
     fn default() -> Self {
         Self {
-            address: "127.0.0.1:25565".parse().unwrap(),
-            send_proxy_v2: false,
+            file: None,
+            max_size_mb: 10,
+            max_files: 5,
+            console: true,
         }
     }
 }
 
-/// Join lobby configuration.
-#[derive(Debug, Deserialize)]
+/// Resource limits configuration for the server process.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, JsonSchema, Serialize)]
 #[serde(default)]
-pub struct JoinLobby {
-    /// Hold client in lobby for number of seconds on connect while server starts.
-    pub timeout: u32,
+pub struct ResourceLimits {
+    /// Maximum number of open file descriptors.
+    pub max_open_files: Option<u64>,
 
-    /// Message banner in lobby shown to client.
-    pub message: String,
+    /// Maximum size of virtual memory in bytes.
+    pub max_virtual_memory_bytes: Option<u64>,
 
-    /// Sound effect to play when server is ready.
-    pub ready_sound: Option<String>,
+    /// Maximum number of processes for the server user.
+    pub max_processes: Option<u32>,
 }
 
-impl JoinLobby {
-    fn from_env() -> Self {
+impl ResourceLimits {
+    fn from_env(env: &EnvReader) -> Self {
         Self {
-            timeout: get_env_u32("LAZYMC_JOIN_LOBBY_TIMEOUT", 10 * 60),
-            message: get_env_string("LAZYMC_JOIN_LOBBY_MESSAGE", 
-                Some("§2Server is starting\n§7⌛ Please wait..."))
-                .unwrap(),
-            ready_sound: get_env_string("LAZYMC_JOIN_LOBBY_READY_SOUND", 
-                Some("block.note_block.chime")),
+            max_open_files: env
+                .var("ADVANCED_RESOURCE_LIMITS_MAX_OPEN_FILES")
+                .ok()
+                .and_then(|s| s.parse().ok()),
+            max_virtual_memory_bytes: env
+                .var("ADVANCED_RESOURCE_LIMITS_MAX_VIRTUAL_MEMORY_BYTES")
+                .ok()
+                .and_then(|s| s.parse().ok()),
+            max_processes: env
+                .var("ADVANCED_RESOURCE_LIMITS_MAX_PROCESSES")
+                .ok()
+                .and_then(|s| s.parse().ok()),
         }
     }
 }
 
-impl Default for JoinLobby {
-    fn default() -> Self {
+/// TCP settings for proxied connections.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, JsonSchema, Serialize)]
+#[serde(default)]
+pub struct TcpSettings {
+    /// Send TCP keepalive probes after connection is idle for this many seconds.
+    pub keepalive_secs: Option<u32>,
+
+    /// Disable Nagle's algorithm.
+    pub nodelay: bool,
+
+    /// Socket send buffer size in bytes.
+    pub send_buffer_size: Option<u32>,
+
+    /// Socket receive buffer size in bytes.
+    pub recv_buffer_size: Option<u32>,
+}
+
+impl TcpSettings {
+    fn from_env(env: &EnvReader) -> Self {
         Self {
-            timeout: 10 * 60,
-            message: "§2Server is starting\n§7⌛ Please wait...".into(),
-            ready_sound: Some("block.note_block.chime".into()),
+            keepalive_secs: env
+                .var("ADVANCED_TCP_KEEPALIVE_SECS")
+                .ok()
+                .and_then(|s| s.parse().ok()),
+            nodelay: env.bool("ADVANCED_TCP_NODELAY", false),
+            send_buffer_size: env
+                .var("ADVANCED_TCP_SEND_BUFFER_SIZE")
+                .ok()
+                .and_then(|s| s.parse().ok()),
+            recv_buffer_size: env
+                .var("ADVANCED_TCP_RECV_BUFFER_SIZE")
+                .ok()
+                .and_then(|s| s.parse().ok()),
         }
     }
 }
 
-/// Lockout configuration.
-#[derive(Debug, Deserialize)]
+/// Status polling configuration for a starting server.
+#[derive(Debug, Clone, PartialEq, Deserialize, JsonSchema, Serialize)]
 #[serde(default)]
-pub struct Lockout {
-    /// Enable to prevent everybody from connecting through lazymc. Instantly kicks player.
-    pub enabled: bool,
+pub struct Poll {
+    /// Interval in milliseconds to start polling at.
+    pub initial_interval_ms: u64,
 
-    /// Kick players with following message.
-    pub message: String,
+    /// Maximum interval in milliseconds to back off to.
+    pub max_interval_ms: u64,
+
+    /// Factor to multiply the interval with after each failed attempt.
+    pub backoff_multiplier: f64,
 }
 
-impl Lockout {
-    fn from_env() -> Self {
+impl Poll {
+    fn from_env(env: &EnvReader) -> Self {
         Self {
-            enabled: get_env_bool("LAZYMC_LOCKOUT_ENABLED", false),
-            message: get_env_string("LAZYMC_LOCKOUT_MESSAGE", 
-                Some("Server is closed §7☠§r\n\nPlease come back another time."))
-                .unwrap(),
+            initial_interval_ms: env.u64("ADVANCED_POLL_INITIAL_INTERVAL_MS", 500),
+            max_interval_ms: env.u64("ADVANCED_POLL_MAX_INTERVAL_MS", 5000),
+            backoff_multiplier: env.f64("ADVANCED_POLL_BACKOFF_MULTIPLIER", 1.5),
         }
     }
+
+    /// Interval for the first poll, at least the minimum poll interval.
+    pub fn initial_interval(&self) -> Duration {
+        Duration::from_millis(self.initial_interval_ms).max(MIN_POLL_INTERVAL)
+    }
+
+    /// Interval for the next poll after a failed attempt, capped at the maximum interval.
+    ///
+    /// Never less than the minimum poll interval.
+    pub fn next_interval(&self, interval: Duration) -> Duration {
+        let max = Duration::from_millis(self.max_interval_ms);
+        Duration::try_from_secs_f64(interval.as_secs_f64() * self.backoff_multiplier.max(1.0))
+            .unwrap_or(max)
+            .min(max)
+            .max(MIN_POLL_INTERVAL)
+    }
 }
 
-impl Default for Lockout {
+impl Default for Poll {
     fn default() -> Self {
         Self {
-            enabled: false,
-            message: "Server is closed §7☠§r\n\nPlease come back another time.".into(),
+            initial_interval_ms: 500,
+            max_interval_ms: 5000,
+            backoff_multiplier: 1.5,
         }
     }
 }
 
-/// RCON configuration.
-#[derive(Debug, Deserialize)]
-#[serde(default)]
-pub struct Rcon {
-    /// Enable sleeping server through RCON.
-    pub enabled: bool,
+/// Virtual host configuration.
+#[derive(Debug, Clone, PartialEq, Deserialize, JsonSchema, Serialize)]
+pub struct VirtualHost {
+    /// Hostname clients connect with.
+    pub hostname: String,
 
-    /// Server RCON port.
-    pub port: u16,
+    /// Server address for this virtual host.
+    #[schemars(with = "String")]
+    #[serde(deserialize_with = "to_socket_addrs")]
+    pub server_address: SocketAddr,
 
-    /// Server RCON password.
-    pub password: String,
+    /// Server start command, uses server command if not set.
+    #[serde(default)]
+    pub server_command: Option<String>,
 
-    /// Randomize server RCON password on each start.
-    pub randomize_password: bool,
+    /// Server directory, relative to the configuration directory.
+    ///
+    /// Uses the server directory if not set, server.properties is not rewritten then.
+    #[serde(default)]
+    pub server_directory: Option<PathBuf>,
 
-    /// Add HAProxy v2 header to RCON connections.
-    pub send_proxy_v2: bool,
-}
+    /// MOTD override.
+    #[serde(default)]
+    pub motd: Option<Motd>,
 
-impl Rcon {
-    fn from_env() -> Self {
-        Self {
-            enabled: get_env_bool("LAZYMC_RCON_ENABLED", cfg!(windows)),
-            port: get_env_u16("LAZYMC_RCON_PORT", 25575),
-            password: get_env_string("LAZYMC_RCON_PASSWORD", Some("")).unwrap(),
-            randomize_password: get_env_bool("LAZYMC_RCON_RANDOMIZE_PASSWORD", true),
-            send_proxy_v2: get_env_bool("LAZYMC_RCON_SEND_PROXY_V2", false),
-        }
-    }
+    /// Join override.
+    #[serde(default)]
+    pub join: Option<Join>,
 }
 
-impl Default for Rcon {
-    fn default() -> Self {
-        Self {
-            enabled: cfg!(windows),
-            port: 25575,
-            password: "".into(),
-            randomize_password: true,
-            send_proxy_v2: false,
-        }
-    }
-}
+/// Player route configuration.
+#[derive(Debug, Clone, PartialEq, Deserialize, JsonSchema, Serialize)]
+pub struct PlayerRoute {
+    /// Player UUID to route.
+    #[serde(default)]
+    pub uuid: Option<String>,
 
-/// Advanced configuration.
-#[derive(Debug, Deserialize)]
-#[serde(default)]
-pub struct Advanced {
-    /// Rewrite server.properties.
-    pub rewrite_server_properties: bool,
-}
+    /// Player username to route.
+    #[serde(default)]
+    pub username: Option<String>,
 
-impl Advanced {
-    fn from_env() -> Self {
-        Self {
-            rewrite_server_properties: get_env_bool("LAZYMC_ADVANCED_REWRITE_SERVER_PROPERTIES", true),
-        }
-    }
+    /// Server address to route player to.
+    #[schemars(with = "String")]
+    #[serde(deserialize_with = "to_socket_addrs")]
+    pub server_address: SocketAddr,
 }
 
-impl Default for Advanced {
-    fn default() -> Self {
-        Self {
-            rewrite_server_properties: true,
-        }
+impl PlayerRoute {
+    /// Whether this route matches the given player.
+    fn matches(&self, username: Option<&str>, uuid: Option<Uuid>) -> bool {
+        let uuid_matches = match (&self.uuid, uuid) {
+            (Some(route), Some(uuid)) => Uuid::parse_str(route).map_or(false, |r| r == uuid),
+            _ => false,
+        };
+        let username_matches = match (&self.username, username) {
+            (Some(route), Some(username)) => route.eq_ignore_ascii_case(username),
+            _ => false,
+        };
+        uuid_matches || username_matches
     }
 }
 
 /// Config configuration.
-#[derive(Debug, Deserialize, Default)]
+#[derive(Debug, Clone, PartialEq, Deserialize, JsonSchema, Serialize, Default)]
 #[serde(default)]
 pub struct ConfigConfig {
     /// Configuration for lazymc version.
     pub version: Option<String>,
+
+    /// Prefix of environment variables to load configuration from, instead of `LAZYMC_`.
+    pub env_prefix: Option<String>,
 }
 
 impl ConfigConfig {
-    fn from_env() -> Self {
+    fn from_env(env: &EnvReader) -> Self {
         Self {
-            version: get_env_string("LAZYMC_CONFIG_VERSION", None),
+            version: env.string("CONFIG_VERSION", None),
+            env_prefix: Some(env.prefix.clone()).filter(|prefix| prefix != ENV_PREFIX),
         }
     }
 }
@@ -749,6 +4067,14 @@ fn option_pathbuf_dot() -> Option<PathBuf> {
     Some(".".into())
 }
 
+fn log_prefix_default() -> String {
+    "server".into()
+}
+
+fn ready_log_pattern_default() -> Option<String> {
+    Some(r"Done \(.*\)! For help".into())
+}
+
 fn server_address_default() -> SocketAddr {
     "127.0.0.1:25566".parse().unwrap()
 }
@@ -761,6 +4087,581 @@ fn u32_150() -> u32 {
     150
 }
 
+fn u32_30() -> u32 {
+    30
+}
+
+fn u32_1() -> u32 {
+    1
+}
+
+fn u32_3() -> u32 {
+    3
+}
+
+fn u32_5() -> u32 {
+    5
+}
+
+fn u32_10() -> u32 {
+    10
+}
+
 fn bool_true() -> bool {
     true
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> Config {
+        toml::from_str("[server]\ncommand = \"java -jar server.jar\"").unwrap()
+    }
+
+    /// Environment variables set for the lifetime of this guard, removed on drop.
+    ///
+    /// Each test uses its own prefix so tests running in parallel don't interfere.
+    struct Vars(Vec<String>);
+
+    impl Drop for Vars {
+        fn drop(&mut self) {
+            for key in &self.0 {
+                env::remove_var(key);
+            }
+        }
+    }
+
+    /// Set environment variables with the given prefix, get reader for them.
+    fn vars(prefix: &str, vars: &[(&str, &str)]) -> (EnvReader, Vars) {
+        let env = EnvReader::new(Some(prefix));
+        let keys = vars
+            .iter()
+            .map(|(key, value)| {
+                let key = env.key(key);
+                env::set_var(&key, value);
+                key
+            })
+            .collect();
+        (env, Vars(keys))
+    }
+
+    #[test]
+    fn escape_sequences() {
+        assert_eq!(
+            process_escape_sequences("a\\nb\\tc\\r\\\\n"),
+            "a\nb\tc\r\\n"
+        );
+        assert_eq!(process_escape_sequences("\\u3053\\u3093\\u00e9!"), "こんé!");
+        assert_eq!(process_escape_sequences("\\uD800"), "\\u{D800}");
+        assert_eq!(process_escape_sequences("\\u12"), "\\u12");
+        assert_eq!(process_escape_sequences("\\u12g4 \\x"), "\\u12g4 \\x");
+        assert_eq!(process_escape_sequences("trailing \\"), "trailing \\");
+    }
+
+    #[test]
+    fn env_bool_variants() {
+        let variants = [
+            "true", "1", "yes", "on", "TRUE", "Yes", "false", "0", "no", "off", "maybe",
+        ];
+        let pairs: Vec<(String, &str)> = variants
+            .iter()
+            .enumerate()
+            .map(|(i, value)| (format!("B{i}"), *value))
+            .collect();
+        let pairs: Vec<(&str, &str)> = pairs.iter().map(|(k, v)| (k.as_str(), *v)).collect();
+        let (env, _vars) = vars("LAZYMC_TEST_BOOL_", &pairs);
+
+        let parsed: Vec<bool> = (0..variants.len())
+            .map(|i| env.bool(&format!("B{i}"), false))
+            .collect();
+        assert_eq!(
+            parsed,
+            [true, true, true, true, true, true, false, false, false, false, false]
+        );
+
+        // Unknown values and missing variables fall back to default
+        assert!(env.bool("B10", true));
+        assert!(env.bool("MISSING", true));
+        assert!(!env.bool("B9", false));
+    }
+
+    #[test]
+    fn env_socket_addr_fallback() {
+        let (env, _vars) = vars(
+            "LAZYMC_TEST_ADDR_",
+            &[
+                ("PUBLIC_ADDRESS", "not an address"),
+                ("PUBLIC_BEDROCK_ADDRESS", "0.0.0.0:19133"),
+                ("SERVER_ADDRESS", "127.0.0.1"),
+                ("METRICS_ADDRESS", "[::1]:9100"),
+                ("API_ADDRESS", "localhost:8081"),
+            ],
+        );
+
+        let public = Public::from_env(&env);
+        assert_eq!(public.address, "0.0.0.0:25565".parse().unwrap());
+        assert_eq!(public.bedrock_address, "0.0.0.0:19133".parse().unwrap());
+
+        let server = Server::from_env(&env, "java".into());
+        assert_eq!(server.address, "127.0.0.1:25566".parse().unwrap());
+
+        let metrics = Metrics::from_env(&env);
+        assert_eq!(metrics.address, "[::1]:9100".parse().unwrap());
+        assert_eq!(metrics.path, "/metrics");
+
+        // Hostnames are resolved
+        let api = Api::from_env(&env);
+        assert!(api.address.ip().is_loopback());
+        assert_eq!(api.address.port(), 8081);
+    }
+
+    #[test]
+    fn env_join_methods_filter_invalid() {
+        let (env, _vars) = vars(
+            "LAZYMC_TEST_JOIN_",
+            &[
+                ("JOIN_METHODS", "lobby, bogus,kick,"),
+                ("JOIN_HOLD_TIMEOUT", "15"),
+                ("JOIN_QUEUE_MAX_SIZE", "invalid"),
+                ("JOIN_FORWARDING", "Velocity"),
+                ("JOIN_FORWARDING_SECRET", "s3cret"),
+            ],
+        );
+
+        let join = Join::from_env(&env);
+        assert_eq!(join.methods, vec![Method::Lobby, Method::Kick]);
+        assert_eq!(join.hold.timeout, 15);
+        assert_eq!(join.queue.max_size, 20);
+        assert_eq!(
+            join.forwarding,
+            ForwardingMode::Velocity {
+                secret: "s3cret".into()
+            }
+        );
+
+        // Defaults if not set
+        let (env, _vars) = vars("LAZYMC_TEST_JOIN_DEFAULT_", &[]);
+        let join = Join::from_env(&env);
+        assert_eq!(join.methods, vec![Method::Hold, Method::Kick]);
+        assert_eq!(join.forwarding, ForwardingMode::None);
+        assert_eq!(join.lobby.timeout, 10 * 60);
+    }
+
+    #[test]
+    fn env_server() {
+        let (env, _vars) = vars(
+            "LAZYMC_TEST_SERVER_",
+            &[
+                ("SERVER_DIRECTORY", "/srv/minecraft"),
+                ("SERVER_START_TIMEOUT", "-5"),
+                ("SERVER_STOP_TIMEOUT", "60"),
+                ("SERVER_MAX_CRASH_RESTARTS", "3"),
+                ("SERVER_STOP_VIA_RCON", "yes"),
+                ("SERVER_BACKENDS", "127.0.0.1:25567, invalid"),
+                ("SERVER_ENV_SET", "EULA = true, invalid"),
+            ],
+        );
+
+        let server = Server::from_env(&env, "java -jar server.jar".into());
+        assert_eq!(server.command, "java -jar server.jar");
+        assert_eq!(server.directory, Some(PathBuf::from("/srv/minecraft")));
+        assert_eq!(server.start_timeout, 300);
+        assert_eq!(server.stop_timeout, 60);
+        assert_eq!(server.max_crash_restarts, Some(3));
+        assert_eq!(server.stop_via_rcon, Some(true));
+        assert_eq!(server.backends.len(), 1);
+        assert_eq!(server.env_set.get("EULA").map(String::as_str), Some("true"));
+        assert_eq!(server.env_set.len(), 1);
+        assert!(server.freeze_process);
+        assert_eq!(server.log_prefix, "server");
+    }
+
+    #[test]
+    fn env_missing_server_command() {
+        let (env, _vars) = vars("LAZYMC_TEST_MISSING_", &[]);
+        assert_eq!(
+            env.required("SERVER_COMMAND"),
+            Err("Missing required environment variable: LAZYMC_TEST_MISSING_SERVER_COMMAND".into())
+        );
+
+        let (env, _vars) = vars("LAZYMC_TEST_REQUIRED_", &[("SERVER_COMMAND", "java")]);
+        assert_eq!(env.required("SERVER_COMMAND"), Ok("java".into()));
+    }
+
+    #[test]
+    fn env_sections() {
+        let (env, _vars) = vars(
+            "LAZYMC_TEST_SECTIONS_",
+            &[
+                ("TIME_SLEEP_AFTER_BY_PLAYERS", "5=600, invalid, 1=x"),
+                ("LOCKOUT_SCHEDULE_ENABLED_CRON", "0 0 22 * * *"),
+                ("ACCESS_ALLOWLIST", "10.0.0.0/8, 192.168.1.2, invalid"),
+                ("RCON_ON_START_COMMANDS", "say hi, all; ; gamerule x"),
+                ("RCON_PORT", "70000"),
+                ("NOTIFY_WEBHOOK_URL", "https://example.com/hook"),
+                ("NOTIFY_WEBHOOK_EVENTS", "online, bogus"),
+                ("COMMAND_FILTER_BLOCKED_COMMANDS", "stop,op"),
+                ("ADVANCED_POLL_BACKOFF_MULTIPLIER", "2.5"),
+                ("ADVANCED_LOG_MAX_FILES", "2"),
+            ],
+        );
+
+        let time = Time::from_env(&env);
+        assert_eq!(time.sleep_after, 60);
+        assert_eq!(time.sleep_after_by_players.len(), 1);
+        assert_eq!(time.sleep_after_by_players[0].min_players, 5);
+        assert_eq!(time.sleep_after_by_players[0].sleep_after_secs, 600);
+        assert_eq!(time.schedule.sleep_cron, None);
+
+        // Lockout schedule requires both crons
+        assert!(Lockout::from_env(&env).schedule.is_none());
+
+        let access = Access::from_env(&env);
+        assert_eq!(access.allowlist.len(), 2);
+        assert!(!access.rate_limit.enabled);
+        assert!(!access.geoip.enabled);
+
+        let rcon = Rcon::from_env(&env);
+        assert_eq!(rcon.on_start_commands, vec!["say hi, all", "gamerule x"]);
+        assert_eq!(rcon.port, 25575);
+
+        let notify = Notify::from_env(&env);
+        assert_eq!(notify.webhooks.len(), 1);
+        assert_eq!(notify.webhooks[0].events, vec![ServerEvent::Online]);
+        assert_eq!(notify.webhooks[0].method, "POST");
+        assert!(notify.discord.is_none());
+
+        let filter = CommandFilter::from_env(&env);
+        assert_eq!(filter.blocked_commands, vec!["stop", "op"]);
+        assert!(!ChatFilter::from_env(&env).enabled);
+
+        let advanced = Advanced::from_env(&env);
+        assert_eq!(advanced.poll.backoff_multiplier, 2.5);
+        assert_eq!(advanced.poll.initial_interval_ms, 500);
+        assert_eq!(advanced.log.max_files, 2);
+        assert!(advanced.log.console);
+        assert!(advanced.rewrite_server_properties);
+        assert!(advanced.proxy_read_timeout_secs.is_none());
+    }
+
+    #[test]
+    fn fake_player_uuid() {
+        let players = "[[motd.fake_player]]\nname = \"Notch\"\nuuid = ";
+        let config: Config = toml::from_str(&format!(
+            "{players}\"069a79f444e94726a5befca90e38aaf5\"\n[server]\ncommand = \"java\""
+        ))
+        .unwrap();
+        assert_eq!(
+            config.motd.fake_players[0].uuid.to_string(),
+            "069a79f4-44e9-4726-a5be-fca90e38aaf5"
+        );
+        assert!(toml::from_str::<Config>(&format!(
+            "{players}\"not-a-uuid\"\n[server]\ncommand = \"java\""
+        ))
+        .is_err());
+    }
+
+    #[test]
+    fn env_sleeping_messages() {
+        let (env, _vars) = vars(
+            "LAZYMC_TEST_SLEEPING_MESSAGES_",
+            &[(
+                "MOTD_SLEEPING_MESSAGES",
+                "Sleeping, join to wake\\nPlease wait; Zzz, {players_online} online",
+            )],
+        );
+
+        assert_eq!(
+            Motd::from_env(&env).sleeping_messages,
+            vec![
+                "Sleeping, join to wake\nPlease wait",
+                "Zzz, {players_online} online"
+            ]
+        );
+    }
+
+    #[test]
+    fn env_fake_players() {
+        let (env, _vars) = vars(
+            "LAZYMC_TEST_FAKE_PLAYERS_",
+            &[(
+                "MOTD_FAKE_PLAYERS",
+                "Notch:069a79f4-44e9-4726-a5be-fca90e38aaf5; jeb_ : 853c80ef3c3749fdaa49938b674adae6;invalid;Bad:1234",
+            )],
+        );
+
+        let players = Motd::from_env(&env).fake_players;
+        assert_eq!(players.len(), 2);
+        assert_eq!(players[0].name, "Notch");
+        assert_eq!(players[1].name, "jeb_");
+        assert_eq!(
+            players[1].uuid,
+            Uuid::parse_str("853c80ef-3c37-49fd-aa49-938b674adae6").unwrap()
+        );
+    }
+
+    #[test]
+    fn env_load_all_sections() {
+        let (env, _vars) = vars(
+            "LAZYMC_TEST_LOAD_",
+            &[
+                ("SERVER_COMMAND", "java -jar server.jar"),
+                ("MOTD_STARTING", "Booting\\nPlease wait"),
+                ("TLS_ACME_ENABLED", "on"),
+                ("STATS_ENABLED", "1"),
+                ("HOOKS_PRE_START", "echo start"),
+            ],
+        );
+
+        let config = Config::load_from_env(&env);
+        assert_eq!(config.server.command, "java -jar server.jar");
+        assert_eq!(config.motd.starting, "Booting\nPlease wait");
+        assert!(config.tls.acme.enabled);
+        assert!(!config.tls.enabled);
+        assert!(config.stats.enabled);
+        assert_eq!(config.hooks.pre_start.as_deref(), Some("echo start"));
+        assert_eq!(config.hooks.hook_timeout_secs, 30);
+        assert!(!config.api.enabled);
+        assert!(!config.query.enabled);
+        assert_eq!(config.auth.timeout_secs, 5);
+        assert_eq!(
+            config.config.env_prefix.as_deref(),
+            Some("LAZYMC_TEST_LOAD_")
+        );
+        assert!(config.vhosts.is_empty());
+    }
+
+    #[test]
+    fn validate_same_public_address() {
+        let mut config = config();
+        assert!(!config.validate().iter().any(ConfigError::is_fatal));
+
+        config.public.address = "0.0.0.0:25565".parse().unwrap();
+        config.server.address = "0.0.0.0:25565".parse().unwrap();
+        assert!(config
+            .validate()
+            .iter()
+            .any(|err| matches!(err, ConfigError::SameServerPublicAddress)));
+
+        config.server.address = "127.0.0.1:25565".parse().unwrap();
+        assert!(config.validate().iter().any(ConfigError::is_fatal));
+
+        config.server.address = "10.0.0.2:25565".parse().unwrap();
+        config.join.methods = vec![Method::Forward];
+        config.join.forward.address = "[::1]:25565".parse().unwrap();
+        config.public.address = "[::]:25565".parse().unwrap();
+        let errors = config.validate();
+        assert!(!errors
+            .iter()
+            .any(|err| matches!(err, ConfigError::SameServerPublicAddress)));
+        assert!(errors
+            .iter()
+            .any(|err| matches!(err, ConfigError::SameForwardPublicAddress)));
+    }
+
+    #[test]
+    fn chat_filter_patterns_compiled_on_load() {
+        let rules =
+            "[server]\ncommand = \"java\"\n[[chat_filter.rules]]\naction = \"block\"\npattern = ";
+        let config: Config = toml::from_str(&format!("{rules}'(?i)discord\\.gg/'")).unwrap();
+        assert!(config.chat_filter.rules[0]
+            .pattern
+            .regex()
+            .is_match("DISCORD.gg/abc"));
+        assert!(toml::from_str::<Config>(&format!("{rules}'(unclosed'")).is_err());
+    }
+
+    #[test]
+    fn player_route_requires_authentication() {
+        let mut config = config();
+        config.player_routes = vec![PlayerRoute {
+            uuid: None,
+            username: Some("Notch".into()),
+            server_address: "127.0.0.1:25569".parse().unwrap(),
+        }];
+        assert_eq!(config.player_route(Some("notch"), None), None);
+        assert!(config
+            .validate()
+            .iter()
+            .any(|err| matches!(err, ConfigError::UnverifiedPlayerRoutes)));
+
+        config.join.forwarding = ForwardingMode::Legacy;
+        assert_eq!(
+            config.player_route(Some("notch"), None),
+            Some("127.0.0.1:25569".parse().unwrap())
+        );
+        assert_eq!(config.player_route(Some("Herobrine"), None), None);
+        assert!(!config
+            .validate()
+            .iter()
+            .any(|err| matches!(err, ConfigError::UnverifiedPlayerRoutes)));
+    }
+
+    #[test]
+    fn process_wide_conflicts() {
+        let mut other = config();
+        assert_eq!(process_wide_conflict(&[config(), other.clone()]), None);
+
+        other.access.geoip.enabled = true;
+        assert_eq!(process_wide_conflict(&[config(), other.clone()]), None);
+
+        let mut first = other.clone();
+        first.access.geoip.database = "other.mmdb".into();
+        assert_eq!(
+            process_wide_conflict(&[first, other.clone()]),
+            Some("access.geoip.database")
+        );
+
+        other.advanced.systemd_notify = Some(!config().advanced.systemd_notify());
+        assert_eq!(
+            process_wide_conflict(&[config(), other]),
+            Some("advanced.systemd_notify")
+        );
+
+        let mut other = config();
+        other.advanced.log.console = false;
+        assert_eq!(
+            process_wide_conflict(&[config(), other]),
+            Some("advanced.log")
+        );
+
+        let mut other = config();
+        other.advanced.pid_file = Some("other.pid".into());
+        assert_eq!(
+            process_wide_conflict(&[config(), other]),
+            Some("advanced.pid_file")
+        );
+    }
+
+    #[test]
+    fn vhost_server_directory() {
+        let config: Config = toml::from_str(
+            "[server]\ncommand = \"java -jar server.jar\"\n\
+             [[vhost]]\nhostname = \"a.example.com\"\nserver_address = \"127.0.0.1:25567\"\nserver_directory = \"a\"\n\
+             [[vhost]]\nhostname = \"b.example.com\"\nserver_address = \"127.0.0.1:25568\"",
+        )
+        .unwrap();
+
+        let a = config.vhost(&config.vhosts[0]);
+        assert_eq!(Server::server_directory(&a), Some(PathBuf::from("a")));
+        assert!(a.advanced.rewrite_server_properties);
+        assert_eq!(a.stats.instance.as_deref(), Some("a.example.com"));
+
+        let b = config.vhost(&config.vhosts[1]);
+        assert_eq!(
+            Server::server_directory(&b),
+            Server::server_directory(&config)
+        );
+        assert!(!b.advanced.rewrite_server_properties);
+    }
+
+    #[test]
+    fn ip_lists_accept_plain_ips() {
+        let config: Config = toml::from_str(
+            "[server]\ncommand = \"java -jar server.jar\"\n\
+             [lockout]\nallowlist_ips = [\"192.168.1.10\", \"10.0.0.0/8\"]\n\
+             [access]\nallowlist = [\"::1\"]\ndenylist = [\"203.0.113.7\"]",
+        )
+        .unwrap();
+        assert_eq!(
+            config.lockout.allowlist_ips,
+            vec![
+                "192.168.1.10/32".parse::<IpNet>().unwrap(),
+                "10.0.0.0/8".parse().unwrap()
+            ]
+        );
+        assert_eq!(config.access.allowlist, vec!["::1/128".parse().unwrap()]);
+        assert_eq!(
+            config.access.denylist,
+            vec!["203.0.113.7/32".parse().unwrap()]
+        );
+
+        assert!(toml::from_str::<Config>(
+            "[server]\ncommand = \"java -jar server.jar\"\n[access]\ndenylist = [\"nope\"]"
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn lockout_allows() {
+        let lockout = Lockout {
+            allowlist_ips: vec!["10.0.0.0/8".parse().unwrap(), "::1/128".parse().unwrap()],
+            allowlist_usernames: vec!["Notch".into()],
+            ..Default::default()
+        };
+
+        assert!(lockout.allows_ip(&"10.1.2.3".parse().unwrap()));
+        assert!(lockout.allows_ip(&"::ffff:10.1.2.3".parse().unwrap()));
+        assert!(lockout.allows_ip(&"::1".parse().unwrap()));
+        assert!(!lockout.allows_ip(&"192.168.1.2".parse().unwrap()));
+
+        let ip = "192.168.1.2".parse().unwrap();
+        assert!(lockout.allows(&ip, Some("notch")));
+        assert!(!lockout.allows(&ip, Some("Herobrine")));
+        assert!(!lockout.allows(&ip, None));
+        assert!(!Lockout::default().allows(&"10.1.2.3".parse().unwrap(), Some("Notch")));
+    }
+
+    #[test]
+    fn lockout_schedule_enabled_at() {
+        use chrono::Utc;
+
+        let schedule = LockoutSchedule {
+            enabled_cron: "0 0 8 * * * *".into(),
+            disabled_cron: "0 0 20 * * * *".into(),
+        };
+        let at = |hour| Utc.with_ymd_and_hms(2024, 1, 1, hour, 30, 0).unwrap();
+        assert_eq!(schedule.is_enabled_at(&at(12)), Some(true));
+        assert_eq!(schedule.is_enabled_at(&at(22)), Some(false));
+        assert_eq!(schedule.is_enabled_at(&at(7)), Some(false));
+
+        let invalid = LockoutSchedule {
+            enabled_cron: "invalid".into(),
+            ..schedule
+        };
+        assert_eq!(invalid.is_enabled_at(&at(12)), None);
+    }
+
+    #[test]
+    fn poll_interval_never_zero() {
+        let mut config = config();
+        config.advanced.poll = Poll {
+            initial_interval_ms: 0,
+            max_interval_ms: 0,
+            backoff_multiplier: 1.0,
+        };
+        assert!(config
+            .validate()
+            .iter()
+            .any(|err| matches!(err, ConfigError::ZeroPollInterval)));
+
+        let poll = &config.advanced.poll;
+        assert_eq!(poll.initial_interval(), MIN_POLL_INTERVAL);
+        assert_eq!(
+            poll.next_interval(poll.initial_interval()),
+            MIN_POLL_INTERVAL
+        );
+    }
+
+    #[test]
+    fn diff_changed_fields() {
+        let old = config();
+        assert!(Config::diff(&old, &old.clone()).is_empty());
+
+        let mut new = old.clone();
+        new.motd.starting = "Booting".into();
+        let diff = Config::diff(&old, &new);
+        assert_eq!(diff.changed, vec!["motd.starting".to_string()]);
+        assert_eq!(diff.sections(), vec!["motd"]);
+        assert!(!diff.contains("server"));
+
+        new.server.command = "java -jar other.jar".into();
+        let diff = Config::diff(&old, &new);
+        assert!(diff.contains("server.command"));
+        assert!(!diff.contains("server.commands"));
+        let mut sections = diff.sections();
+        sections.sort();
+        assert_eq!(sections, vec!["motd", "server"]);
+    }
+}
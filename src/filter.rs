@@ -0,0 +1,186 @@
+use regex::Captures;
+
+use crate::config::{ChatFilter, Config, FilterAction, Server};
+use crate::mc::whitelist;
+use crate::proto::packet::RawPacket;
+use crate::proto::packets::play::chat::{self, Kind};
+
+/// Verdict on packet sent by player.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Verdict {
+    /// Relay packet unchanged.
+    Pass,
+
+    /// Relay packet with the given payload instead.
+    Replace(Vec<u8>),
+
+    /// Do not relay packet, send the given feedback message to the player if set.
+    Block(Option<String>),
+}
+
+/// Outcome of filtering chat message text.
+#[derive(Debug, PartialEq, Eq)]
+enum Filtered {
+    /// Message is allowed unchanged.
+    Allow,
+
+    /// Message is blocked.
+    Block,
+
+    /// Message is allowed with replaced text.
+    Replace(String),
+}
+
+/// Whether packets of players must be inspected by filters.
+pub fn is_enabled(config: &Config) -> bool {
+    config.chat_filter.enabled || config.command_filter.enabled
+}
+
+/// Filter packet sent by player in play state.
+pub fn serverbound(
+    config: &Config,
+    protocol: Option<u32>,
+    username: Option<&str>,
+    packet: &RawPacket,
+) -> Verdict {
+    let Some(kind) = protocol.and_then(|protocol| chat::kind(protocol, packet.id)) else {
+        return Verdict::Pass;
+    };
+    let Ok(text) = chat::text(packet) else {
+        return Verdict::Pass;
+    };
+
+    // Commands are sent as chat messages before Minecraft 1.19
+    match kind {
+        Kind::Command => filter_command(config, username, &text),
+        Kind::Message if text.starts_with('/') => filter_command(config, username, &text),
+        Kind::Message => filter_message(config, packet, &text),
+    }
+}
+
+/// Filter command sent by player.
+fn filter_command(config: &Config, username: Option<&str>, command: &str) -> Verdict {
+    let filter = &config.command_filter;
+    if !filter.enabled || !filter.is_blocked(command) {
+        return Verdict::Pass;
+    }
+
+    // OPs may bypass, only checked for blocked commands
+    if filter.bypass_ops {
+        let is_op = username
+            .zip(Server::server_directory(config))
+            .is_some_and(|(username, dir)| whitelist::is_op(&dir, username));
+        if is_op {
+            return Verdict::Pass;
+        }
+    }
+
+    debug!(target: "lazymc", "Command filter blocked command of {}: {}", username.unwrap_or("unknown player"), command);
+    Verdict::Block(Some(filter.blocked_message.clone()).filter(|msg| !msg.is_empty()))
+}
+
+/// Filter chat message sent by player.
+fn filter_message(config: &Config, packet: &RawPacket, message: &str) -> Verdict {
+    let filter = &config.chat_filter;
+    if !filter.enabled {
+        return Verdict::Pass;
+    }
+
+    match filter_chat(filter, message) {
+        Filtered::Allow => Verdict::Pass,
+        Filtered::Block => {
+            debug!(target: "lazymc", "Chat filter blocked message: {}", message);
+            Verdict::Block(Some(filter.blocked_message.clone()).filter(|msg| !msg.is_empty()))
+        }
+        Filtered::Replace(text) => match chat::replace_text(packet, &text) {
+            Ok(data) => Verdict::Replace(data),
+            Err(_) => Verdict::Pass,
+        },
+    }
+}
+
+/// Filter chat message text through chat filter rules.
+///
+/// Rules are applied in order, blocking rules take effect on the text replaced so far.
+fn filter_chat(filter: &ChatFilter, message: &str) -> Filtered {
+    let mut text = message.to_string();
+    for rule in &filter.rules {
+        let regex = rule.pattern.regex();
+        match rule.action {
+            FilterAction::Block if regex.is_match(&text) => return Filtered::Block,
+            FilterAction::Block => {}
+            FilterAction::Replace => {
+                text = regex
+                    .replace_all(&text, |caps: &Captures| match &rule.replacement {
+                        Some(replacement) => {
+                            let mut expanded = String::new();
+                            caps.expand(replacement, &mut expanded);
+                            expanded
+                        }
+                        None => "*".repeat(caps[0].chars().count()),
+                    })
+                    .into_owned();
+            }
+        }
+    }
+
+    if text == message {
+        Filtered::Allow
+    } else {
+        Filtered::Replace(text)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{ChatRule, CommandFilter};
+
+    fn filter(rules: &[(&str, FilterAction, Option<&str>)]) -> ChatFilter {
+        ChatFilter {
+            enabled: true,
+            rules: rules
+                .iter()
+                .map(|(pattern, action, replacement)| ChatRule {
+                    pattern: pattern.parse().unwrap(),
+                    action: *action,
+                    replacement: replacement.map(Into::into),
+                })
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn filter_chat_block() {
+        let filter = filter(&[("(?i)discord\\.gg/", FilterAction::Block, None)]);
+        assert_eq!(filter_chat(&filter, "join discord.gg/abc"), Filtered::Block);
+        assert_eq!(filter_chat(&filter, "hello"), Filtered::Allow);
+    }
+
+    #[test]
+    fn filter_chat_replace() {
+        let filter = filter(&[
+            ("heck", FilterAction::Replace, None),
+            ("darn", FilterAction::Replace, Some("d*rn")),
+        ]);
+        assert_eq!(
+            filter_chat(&filter, "heck darn"),
+            Filtered::Replace("**** d*rn".into())
+        );
+    }
+
+    #[test]
+    fn command_filter_blocked() {
+        let filter = CommandFilter {
+            enabled: true,
+            blocked_commands: vec!["stop".into(), "/op".into()],
+            ..Default::default()
+        };
+        assert!(filter.is_blocked("/stop"));
+        assert!(filter.is_blocked("OP Notch"));
+        assert!(filter.is_blocked("minecraft:stop now"));
+        assert!(!filter.is_blocked("stopwatch"));
+        assert!(!filter.is_blocked("say stop"));
+    }
+}
@@ -10,15 +10,16 @@ use minecraft_protocol::encoder::Encoder;
 use minecraft_protocol::version::forge_v1_13::login::{Acknowledgement, LoginWrapper, ModList};
 use minecraft_protocol::version::v1_14_4::login::{LoginPluginRequest, LoginPluginResponse};
 use minecraft_protocol::version::PacketId;
+use tokio::io::AsyncWrite;
 #[cfg(feature = "lobby")]
 use tokio::io::AsyncWriteExt;
-use tokio::net::tcp::WriteHalf;
-#[cfg(feature = "lobby")]
-use tokio::net::TcpStream;
 #[cfg(feature = "lobby")]
 use tokio::time;
 
+use crate::config::{Config, ForgeVariant};
 use crate::forge;
+use crate::monitor;
+use crate::probe;
 use crate::proto::client::Client;
 #[cfg(feature = "lobby")]
 use crate::proto::client::ClientState;
@@ -26,12 +27,16 @@ use crate::proto::packet;
 use crate::proto::packet::RawPacket;
 #[cfg(feature = "lobby")]
 use crate::proto::packets;
-#[cfg(feature = "lobby")]
 use crate::server::Server;
+#[cfg(feature = "lobby")]
+use crate::stream::ClientStream;
 
 /// Forge status magic.
 pub const STATUS_MAGIC: &str = "\0FML2\0";
 
+/// Legacy Forge status magic, used by FML1.
+pub const LEGACY_STATUS_MAGIC: &str = "\0FML\0";
+
 /// Forge plugin wrapper login plugin request channel.
 pub const CHANNEL_LOGIN_WRAPPER: &str = "fml:loginwrapper";
 
@@ -42,10 +47,58 @@ pub const CHANNEL_HANDSHAKE: &str = "fml:handshake";
 #[cfg(feature = "lobby")]
 const CLIENT_DRAIN_FORGE_TIMEOUT: Duration = Duration::from_secs(5);
 
+/// Get the handshake server address magic for the given Forge variant.
+pub fn status_magic(variant: ForgeVariant) -> &'static str {
+    match variant {
+        ForgeVariant::Fml1 => LEGACY_STATUS_MAGIC,
+        ForgeVariant::Fml2 => STATUS_MAGIC,
+        ForgeVariant::None | ForgeVariant::Auto => "",
+    }
+}
+
+/// Detect and remember the Forge variant of the server, if set to automatic detection.
+///
+/// Does nothing if already detected.
+pub async fn detect(config: &Config, server: &Server) {
+    if config.server.forge_variant() != ForgeVariant::Auto
+        || server.forge_detected.read().await.is_some()
+    {
+        return;
+    }
+
+    match detect_variant(config, server).await {
+        Ok(variant) => {
+            info!(target: "lazymc::forge", "Detected Forge variant from server status: {:?}", variant);
+            server.forge_detected.write().await.replace(variant);
+        }
+        Err(_) => {
+            debug!(target: "lazymc::forge", "Failed to detect Forge variant from server status")
+        }
+    }
+}
+
+/// Detect the Forge variant from the server status.
+///
+/// Forge 1.13+ and NeoForge report `forgeData`, legacy Forge reports `modinfo`.
+async fn detect_variant(config: &Config, server: &Server) -> Result<ForgeVariant, ()> {
+    let addr = config.server.address;
+    let status =
+        monitor::fetch_status_json(config, server, addr, probe::send_proxy_v2(config, server))
+            .await?;
+
+    if status.get("forgeData").is_some() {
+        Ok(ForgeVariant::Fml2)
+    } else if status.get("modinfo").is_some() {
+        Ok(ForgeVariant::Fml1)
+    } else {
+        Ok(ForgeVariant::None)
+    }
+}
+
 /// Respond with Forge login wrapper packet.
 pub async fn respond_forge_login_packet(
     client: &Client,
-    writer: &mut WriteHalf<'_>,
+    writer: &mut (impl AsyncWrite + Unpin),
     message_id: i32,
     forge_channel: String,
     forge_packet: impl PacketId + Encoder,
@@ -83,7 +136,7 @@ pub async fn respond_forge_login_packet(
 pub async fn respond_login_plugin_request(
     client: &Client,
     packet: LoginPluginRequest,
-    writer: &mut WriteHalf<'_>,
+    writer: &mut (impl AsyncWrite + Unpin),
 ) -> Result<(), ()> {
     // Decode Forge login wrapper packet
     let (message_id, login_wrapper, packet) =
@@ -162,7 +215,7 @@ pub async fn decode_forge_login_packet(
 #[cfg(feature = "lobby")]
 pub async fn replay_login_payload(
     client: &Client,
-    inbound: &mut TcpStream,
+    inbound: &mut ClientStream,
     server: Arc<Server>,
     inbound_buf: &mut BytesMut,
 ) -> Result<(), ()> {
@@ -174,6 +227,7 @@ pub async fn replay_login_payload(
             error!(target: "lazymc::lobby", "Failed to send Forge join payload to lobby client, will likely cause issues: {}", err);
         })?;
     }
+    inbound.flush().await.map_err(|_| ())?;
 
     // Drain all responses
     let count = server.forge_payload.read().await.len();
@@ -188,7 +242,7 @@ pub async fn replay_login_payload(
 #[cfg(feature = "lobby")]
 async fn drain_forge_responses(
     client: &Client,
-    inbound: &mut TcpStream,
+    inbound: &mut ClientStream,
     buf: &mut BytesMut,
     mut count: usize,
 ) -> Result<(), ()> {
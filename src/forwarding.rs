@@ -0,0 +1,259 @@
+use std::net::IpAddr;
+
+use bytes::BytesMut;
+use hmac::{Hmac, Mac};
+use minecraft_protocol::decoder::Decoder;
+use minecraft_protocol::encoder::Encoder;
+use minecraft_protocol::version::v1_14_4::handshake::Handshake;
+use minecraft_protocol::version::v1_14_4::login::{LoginPluginRequest, LoginPluginResponse};
+use sha2::Sha256;
+use tokio::io::{AsyncWrite, AsyncWriteExt};
+use tokio::net::TcpStream;
+use uuid::Uuid;
+
+use crate::auth::ProfileProperty;
+use crate::config::{Config, ForwardingMode};
+use crate::net;
+use crate::proto::client::{Client, ClientInfo, ClientState};
+use crate::proto::packet::{self, RawPacket};
+use crate::proto::packets;
+use crate::types;
+
+/// Velocity player info login plugin request channel.
+pub const CHANNEL_VELOCITY: &str = "velocity:player_info";
+
+/// Velocity modern forwarding version, without player key.
+const VELOCITY_FORWARDING_VERSION: i32 = 1;
+
+/// Player info to forward to the server.
+#[derive(Debug, Clone)]
+pub struct Forwarding {
+    /// Forwarding mode.
+    mode: ForwardingMode,
+
+    /// Player IP address.
+    ip: IpAddr,
+
+    /// Player username.
+    username: String,
+
+    /// Player UUID.
+    uuid: Uuid,
+
+    /// Player profile properties.
+    properties: Vec<ProfileProperty>,
+}
+
+impl Forwarding {
+    /// Construct player info for forwarding.
+    ///
+    /// Returns `None` if forwarding is disabled or if the username is unknown.
+    pub fn new(config: &Config, ip: IpAddr, client_info: &ClientInfo) -> Option<Self> {
+        if !config.join.forwarding.is_enabled() {
+            return None;
+        }
+
+        Some(Self {
+            mode: config.join.forwarding.clone(),
+            ip,
+            username: client_info.username.clone()?,
+            uuid: client_info.player_uuid()?,
+            properties: client_info.properties.clone(),
+        })
+    }
+
+    /// Send queued login packets to the server, forwarding player info.
+    ///
+    /// Returns data received from the server that must be relayed to the client.
+    pub async fn forward(&self, outbound: &mut TcpStream, queue: &[u8]) -> Result<BytesMut, ()> {
+        match &self.mode {
+            ForwardingMode::None => {
+                outbound.write_all(queue).await.map_err(|_| ())?;
+                Ok(BytesMut::new())
+            }
+            ForwardingMode::Legacy => {
+                let queue = self.rewrite_handshake_queue(queue)?;
+                outbound.write_all(&queue).await.map_err(|_| ())?;
+                Ok(BytesMut::new())
+            }
+            ForwardingMode::Velocity { .. } => {
+                outbound.write_all(queue).await.map_err(|_| ())?;
+                self.velocity_login(outbound).await
+            }
+        }
+    }
+
+    /// Rewrite handshake to include player info, if using legacy forwarding.
+    ///
+    /// BungeeCord appends the player IP and UUID to the server address field, separated by null
+    /// bytes.
+    pub fn handshake(&self, mut handshake: Handshake) -> Handshake {
+        if self.mode != ForwardingMode::Legacy {
+            return handshake;
+        }
+
+        // Strip existing markers, such as Forge
+        let host = handshake.server_addr.split('\0').next().unwrap_or_default();
+        handshake.server_addr = format!(
+            "{host}\0{}\0{}",
+            net::canonical_ip(self.ip),
+            self.uuid.simple()
+        );
+        handshake
+    }
+
+    /// Rewrite the handshake packet at the start of the queue.
+    fn rewrite_handshake_queue(&self, queue: &[u8]) -> Result<Vec<u8>, ()> {
+        // Handshake is never compressed
+        let client = Client::dummy();
+
+        let (read, len) = types::read_var_int(queue)?;
+        let end = read + len as usize;
+        if queue.len() < end {
+            return Err(());
+        }
+
+        let packet = RawPacket::decode_with_len(&client, &queue[..end])?;
+        if packet.id != packets::handshake::SERVER_HANDSHAKE {
+            return Err(());
+        }
+        let handshake = Handshake::decode(&mut packet.data.as_slice()).map_err(|_| ())?;
+
+        trace!(target: "lazymc::forwarding", "Rewriting handshake for BungeeCord forwarding");
+
+        let mut data = Vec::new();
+        self.handshake(handshake)
+            .encode(&mut data)
+            .map_err(|_| ())?;
+        let mut rewritten =
+            RawPacket::new(packets::handshake::SERVER_HANDSHAKE, data).encode_with_len(&client)?;
+        rewritten.extend_from_slice(&queue[end..]);
+        Ok(rewritten)
+    }
+
+    /// Answer the Velocity player info request on a fresh server connection.
+    ///
+    /// The server sends the request right after login start. Any other packet is returned as-is
+    /// to relay it to the client.
+    async fn velocity_login(&self, outbound: &mut TcpStream) -> Result<BytesMut, ()> {
+        let (mut reader, mut writer) = outbound.split();
+        let mut buf = BytesMut::new();
+
+        // Temporary server client, in login state
+        let tmp_client = Client::dummy();
+        tmp_client.set_state(ClientState::Login);
+
+        let (packet, raw) = match packet::read_packet(&tmp_client, &mut buf, &mut reader).await? {
+            Some(packet) => packet,
+            None => return Ok(buf),
+        };
+
+        if packet.id == packets::login::CLIENT_LOGIN_PLUGIN_REQUEST {
+            let plugin_request =
+                LoginPluginRequest::decode(&mut packet.data.as_slice()).map_err(|_| ())?;
+            if plugin_request.channel == CHANNEL_VELOCITY {
+                self.respond_velocity(&tmp_client, plugin_request, &mut writer)
+                    .await?;
+                return Ok(buf);
+            }
+        }
+
+        debug!(target: "lazymc::forwarding", "Server did not request Velocity player info, is modern forwarding enabled on the server?");
+
+        let mut queue = BytesMut::from(raw.as_slice());
+        queue.extend(buf);
+        Ok(queue)
+    }
+
+    /// Respond to a Velocity player info login plugin request.
+    pub async fn respond_velocity(
+        &self,
+        client: &Client,
+        plugin_request: LoginPluginRequest,
+        writer: &mut (impl AsyncWrite + Unpin),
+    ) -> Result<(), ()> {
+        let secret = match &self.mode {
+            ForwardingMode::Velocity { secret } => secret,
+            _ => return Err(()),
+        };
+
+        trace!(target: "lazymc::forwarding", "Responding to Velocity player info request");
+
+        packet::write_packet(
+            LoginPluginResponse {
+                message_id: plugin_request.message_id,
+                successful: true,
+                data: velocity_player_info(
+                    secret,
+                    self.ip,
+                    self.uuid,
+                    &self.username,
+                    &self.properties,
+                )?,
+            },
+            client,
+            writer,
+        )
+        .await
+    }
+}
+
+/// Build signed Velocity modern forwarding player info.
+///
+/// Layout: HMAC-SHA256 signature, followed by the forwarding version, client address, UUID,
+/// username and player properties.
+/// See: <https://github.com/PaperMC/Velocity/blob/dev/3.0.0/proxy/src/main/java/com/velocitypowered/proxy/connection/backend/VelocityServerConnection.java>
+pub fn velocity_player_info(
+    secret: &str,
+    ip: IpAddr,
+    uuid: Uuid,
+    username: &str,
+    properties: &[ProfileProperty],
+) -> Result<Vec<u8>, ()> {
+    let mut data = types::encode_var_int(VELOCITY_FORWARDING_VERSION)?;
+    data.extend(types::encode_string(&net::canonical_ip(ip).to_string())?);
+    data.extend_from_slice(uuid.as_bytes());
+    data.extend(types::encode_string(username)?);
+
+    data.extend(types::encode_var_int(properties.len() as i32)?);
+    for property in properties {
+        data.extend(types::encode_string(&property.name)?);
+        data.extend(types::encode_string(&property.value)?);
+        match &property.signature {
+            Some(signature) => {
+                data.push(1);
+                data.extend(types::encode_string(signature)?);
+            }
+            None => data.push(0),
+        }
+    }
+
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).map_err(|_| ())?;
+    mac.update(&data);
+
+    let mut payload = mac.finalize().into_bytes().to_vec();
+    payload.append(&mut data);
+    Ok(payload)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::mc::uuid::offline_player_uuid;
+
+    #[test]
+    fn offline_ignores_login_uuid() {
+        let config: Config = toml::from_str(
+            "[public]\nonline_mode = false\n[server]\ncommand = \"java -jar server.jar\"\n[join]\nforwarding = \"legacy\"",
+        )
+        .unwrap();
+        let mut client_info = ClientInfo::empty();
+        client_info.username = Some("Notch".into());
+        client_info.login_uuid = Some(Uuid::from_u128(0x069a79f444e94726a5befca90e38aaf5));
+
+        let forwarding =
+            Forwarding::new(&config, "127.0.0.1".parse().unwrap(), &client_info).unwrap();
+        assert_eq!(forwarding.uuid, offline_player_uuid("Notch"));
+    }
+}
@@ -0,0 +1,276 @@
+use std::path::Path;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use rusqlite::{params, Connection, OpenFlags};
+
+use crate::config::Config;
+use crate::server::WakeEvent;
+
+/// Time to wait for a database locked by another process.
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Initial database schema, created if it does not exist.
+///
+/// Columns added later are added with [`MIGRATIONS`].
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS sessions (
+        username TEXT,
+        uuid TEXT,
+        joined_at INTEGER,
+        left_at INTEGER,
+        server_state TEXT
+    );
+    CREATE TABLE IF NOT EXISTS server_events (
+        state TEXT,
+        at INTEGER
+    );
+";
+
+/// Text columns added to the schema, as `(table, column)`, by schema version.
+///
+/// The database `user_version` is the number of migrations applied.
+const MIGRATIONS: &[&[(&str, &str)]] = &[
+    &[("server_events", "username"), ("server_events", "ip")],
+    &[("sessions", "instance"), ("server_events", "instance")],
+];
+
+/// Statistics summary from history database.
+#[derive(Debug)]
+pub struct Summary {
+    /// Total number of player sessions.
+    pub sessions: u64,
+
+    /// Average player session length.
+    pub average_session: Option<Duration>,
+
+    /// Number of times the server was woken.
+    pub wakes: u64,
+
+    /// Average number of wakes per day, since the first recorded event.
+    pub wakes_per_day: Option<f64>,
+}
+
+/// History database of a server, recording player sessions and server events.
+#[derive(Default)]
+pub struct History {
+    /// Opened database, set if stats are enabled.
+    database: OnceLock<Database>,
+}
+
+/// Opened history database.
+#[derive(Clone)]
+struct Database {
+    /// Database connection, shared by blocking tasks.
+    con: Arc<Mutex<Connection>>,
+
+    /// Instance name recorded with each row.
+    instance: Option<Arc<str>>,
+}
+
+impl History {
+    /// Open history database if stats are enabled.
+    ///
+    /// Does nothing if already opened. Logs an error and continues without history on failure.
+    pub fn open(&self, config: &Config) {
+        if !config.stats.enabled || self.database.get().is_some() {
+            return;
+        }
+
+        let path = config.resolve_path(&config.stats.database);
+        match open_database(&path) {
+            Ok(con) => {
+                debug!(target: "lazymc::stats", "Recording stats in {}", path.display());
+                let _ = self.database.set(Database {
+                    con: Arc::new(Mutex::new(con)),
+                    instance: config.stats.instance.as_deref().map(Arc::from),
+                });
+            }
+            Err(err) => {
+                error!(target: "lazymc::stats", "Failed to open stats database {}, not recording stats: {}", path.display(), err)
+            }
+        }
+    }
+
+    /// Record finished player session, does not block.
+    pub fn record_session(
+        &self,
+        username: String,
+        uuid: String,
+        joined_at: SystemTime,
+        left_at: SystemTime,
+        server_state: &'static str,
+    ) {
+        self.execute(move |con, instance| {
+            con.execute(
+                "INSERT INTO sessions (username, uuid, joined_at, left_at, server_state, instance) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                params![
+                    username,
+                    uuid,
+                    unix_secs(joined_at),
+                    unix_secs(left_at),
+                    server_state,
+                    instance
+                ],
+            )
+        });
+    }
+
+    /// Record server state transition, does not block.
+    pub fn record_event(&self, state: &'static str) {
+        let at = unix_secs(SystemTime::now());
+        self.execute(move |con, instance| {
+            con.execute(
+                "INSERT INTO server_events (state, at, instance) VALUES (?1, ?2, ?3)",
+                params![state, at, instance],
+            )
+        });
+    }
+
+    /// Record player that woke the server, does not block.
+    ///
+    /// Stored as separate `wake` event next to the `starting` state transition.
+    pub fn record_wake(&self, wake: &WakeEvent) {
+        let (username, ip, at) = (
+            wake.triggered_by_username.clone(),
+            wake.triggered_by_ip.ip().to_string(),
+            wake.triggered_at.timestamp(),
+        );
+        self.execute(move |con, instance| {
+            con.execute(
+                "INSERT INTO server_events (state, at, username, ip, instance) VALUES ('wake', ?1, ?2, ?3, ?4)",
+                params![at, username, ip, instance],
+            )
+        });
+    }
+
+    /// Run statement on history database in blocking task, if opened.
+    fn execute<F>(&self, f: F)
+    where
+        F: FnOnce(&Connection, Option<&str>) -> rusqlite::Result<usize> + Send + 'static,
+    {
+        let Some(database) = self.database.get().cloned() else {
+            return;
+        };
+
+        tokio::task::spawn_blocking(move || {
+            let con = database.con.lock().unwrap_or_else(|err| err.into_inner());
+            if let Err(err) = f(&con, database.instance.as_deref()) {
+                warn!(target: "lazymc::stats", "Failed to record stats: {}", err);
+            }
+        });
+    }
+}
+
+/// Open database at path for writing, create schema.
+fn open_database(path: &Path) -> rusqlite::Result<Connection> {
+    let con = Connection::open(path)?;
+    con.busy_timeout(BUSY_TIMEOUT)?;
+    con.execute_batch(SCHEMA)?;
+    migrate(&con)?;
+    Ok(con)
+}
+
+/// Apply schema migrations not applied yet.
+///
+/// Columns that already exist are skipped, databases created before migrations were tracked may
+/// have some of them.
+fn migrate(con: &Connection) -> rusqlite::Result<()> {
+    let version: u32 = con.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+    for (i, columns) in MIGRATIONS.iter().enumerate().skip(version as usize) {
+        let tx = con.unchecked_transaction()?;
+        for (table, column) in columns.iter() {
+            let exists: bool = tx.query_row(
+                "SELECT COUNT(*) > 0 FROM pragma_table_info(?1) WHERE name = ?2",
+                params![table, column],
+                |row| row.get(0),
+            )?;
+            if !exists {
+                tx.execute_batch(&format!("ALTER TABLE {table} ADD COLUMN {column} TEXT"))?;
+            }
+        }
+        tx.pragma_update(None, "user_version", i as u32 + 1)?;
+        tx.commit()?;
+    }
+    Ok(())
+}
+
+/// Query statistics summary from history database at path.
+pub fn summary(path: &Path) -> rusqlite::Result<Summary> {
+    let con = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+    con.busy_timeout(BUSY_TIMEOUT)?;
+
+    let (sessions, average_session): (u64, Option<f64>) = con.query_row(
+        "SELECT COUNT(*), AVG(left_at - joined_at) FROM sessions",
+        [],
+        |row| Ok((row.get(0)?, row.get(1)?)),
+    )?;
+    let wakes: u64 = con.query_row(
+        "SELECT COUNT(*) FROM server_events WHERE state IN ('starting', 'resuming')",
+        [],
+        |row| row.get(0),
+    )?;
+    let first_event: Option<i64> =
+        con.query_row("SELECT MIN(at) FROM server_events", [], |row| row.get(0))?;
+
+    // Count at least one day, to not extrapolate a short history
+    let wakes_per_day = first_event.map(|first| {
+        let days = (unix_secs(SystemTime::now()) - first) as f64 / 86400.0;
+        wakes as f64 / days.max(1.0)
+    });
+
+    Ok(Summary {
+        sessions,
+        average_session: average_session.map(|secs| Duration::from_secs(secs.max(0.0) as u64)),
+        wakes,
+        wakes_per_day,
+    })
+}
+
+/// Seconds since Unix epoch.
+fn unix_secs(time: SystemTime) -> i64 {
+    time.duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Column names of a table.
+    fn columns(con: &Connection, table: &str) -> Vec<String> {
+        let mut stmt = con
+            .prepare("SELECT name FROM pragma_table_info(?1)")
+            .unwrap();
+        let names = stmt.query_map([table], |row| row.get(0)).unwrap();
+        names.map(Result::unwrap).collect()
+    }
+
+    #[test]
+    fn migrate_existing_database() {
+        // Database with wake columns, created before migrations were tracked
+        let con = Connection::open_in_memory().unwrap();
+        con.execute_batch(SCHEMA).unwrap();
+        con.execute_batch(
+            "ALTER TABLE server_events ADD COLUMN username TEXT;
+             ALTER TABLE server_events ADD COLUMN ip TEXT;",
+        )
+        .unwrap();
+
+        migrate(&con).unwrap();
+        assert!(columns(&con, "sessions").contains(&"instance".into()));
+        assert_eq!(
+            columns(&con, "server_events"),
+            ["state", "at", "username", "ip", "instance"]
+        );
+        let version: u32 = con
+            .query_row("PRAGMA user_version", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(version as usize, MIGRATIONS.len());
+
+        // Migrating again is a no-op
+        migrate(&con).unwrap();
+        assert_eq!(columns(&con, "server_events").len(), 5);
+    }
+}
@@ -0,0 +1,263 @@
+use std::net::IpAddr;
+use std::process::Stdio;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+use tokio::process::Command;
+use tokio::time;
+
+use crate::config::{Config, Server as ConfigServer};
+use crate::proto::client::ClientInfo;
+use crate::server::Server;
+use crate::util::shell;
+
+/// Hook types.
+#[derive(Debug, Copy, Clone)]
+pub enum Hook {
+    PreStart,
+    PostStart,
+    PreStop,
+    PostStop,
+}
+
+impl Hook {
+    /// Hook name.
+    fn name(self) -> &'static str {
+        match self {
+            Self::PreStart => "pre_start",
+            Self::PostStart => "post_start",
+            Self::PreStop => "pre_stop",
+            Self::PostStop => "post_stop",
+        }
+    }
+
+    /// Configured command for this hook.
+    fn command(self, config: &Config) -> Option<&str> {
+        match self {
+            Self::PreStart => config.hooks.pre_start.as_deref(),
+            Self::PostStart => config.hooks.post_start.as_deref(),
+            Self::PreStop => config.hooks.pre_stop.as_deref(),
+            Self::PostStop => config.hooks.post_stop.as_deref(),
+        }
+    }
+}
+
+/// Run hook if configured, and wait for it to complete.
+///
+/// Returns `false` if the hook failed or timed out, `true` if it succeeded or isn't configured.
+pub async fn run(config: &Config, server: &Server, hook: Hook) -> bool {
+    match command(config, server, hook).await {
+        Some(cmd) => wait(hook, cmd, config.hooks.hook_timeout_secs).await,
+        None => true,
+    }
+}
+
+/// Run hook if configured, without waiting for it to complete.
+pub async fn spawn(config: &Config, server: &Server, hook: Hook) {
+    if let Some(cmd) = command(config, server, hook).await {
+        tokio::spawn(wait(hook, cmd, config.hooks.hook_timeout_secs));
+    }
+}
+
+/// Build command for hook with current server details, `None` if the hook isn't configured.
+async fn command(config: &Config, server: &Server, hook: Hook) -> Option<Command> {
+    let command = match hook.command(config) {
+        Some(command) if !command.trim().is_empty() => command,
+        _ => return None,
+    };
+
+    let players = server
+        .status()
+        .await
+        .as_ref()
+        .map(|s| s.players.online)
+        .unwrap_or(0);
+
+    let mut cmd = shell::command(command);
+    cmd.stdin(Stdio::null())
+        .kill_on_drop(true)
+        .env("LAZYMC_SERVER_STATE", server.state().name())
+        .env("LAZYMC_SERVER_ADDRESS", config.server.address.to_string())
+        .env("LAZYMC_PLAYER_COUNT", players.to_string());
+    if let Some(ref dir) = ConfigServer::server_directory(config) {
+        cmd.current_dir(dir);
+    }
+
+    debug!(target: "lazymc::hooks", "Running {} hook: {}", hook.name(), command);
+    Some(cmd)
+}
+
+/// Run hook command and wait for it to complete, returns `true` if it succeeded.
+async fn wait(hook: Hook, mut cmd: Command, timeout_secs: u32) -> bool {
+    let timeout = Duration::from_secs(timeout_secs as u64);
+    match time::timeout(timeout, cmd.status()).await {
+        Ok(Ok(status)) if status.success() => true,
+        Ok(Ok(status)) => {
+            warn!(target: "lazymc::hooks", "The {} hook failed ({})", hook.name(), status);
+            false
+        }
+        Ok(Err(err)) => {
+            error!(target: "lazymc::hooks", "Failed to run {} hook: {}", hook.name(), err);
+            false
+        }
+        Err(_) => {
+            warn!(target: "lazymc::hooks", "The {} hook timed out after {}s", hook.name(), timeout_secs);
+            false
+        }
+    }
+}
+
+/// Player logging in through lazymc, not joined the server yet.
+///
+/// Must be [joined](Self::join) once the server accepts the login.
+pub struct PendingPlayer {
+    config: Arc<Config>,
+    server: Arc<Server>,
+    player: Player,
+}
+
+impl PendingPlayer {
+    /// Construct pending player for player hooks and stats.
+    ///
+    /// Returns `None` if no player hooks are configured and stats are disabled.
+    pub fn new(
+        config: Arc<Config>,
+        server: Arc<Server>,
+        client_info: &ClientInfo,
+        ip: IpAddr,
+    ) -> Option<Self> {
+        if !config.hooks.has_player_hooks() && !config.stats.enabled {
+            return None;
+        }
+
+        let player = Player {
+            username: client_info.username.clone().unwrap_or_default(),
+            uuid: client_info
+                .player_uuid()
+                .map(|uuid| uuid.to_string())
+                .unwrap_or_default(),
+            ip,
+        };
+        Some(Self {
+            config,
+            server,
+            player,
+        })
+    }
+
+    /// Start player session, running the join hook.
+    pub fn join(self) -> PlayerSession {
+        let count = self.server.players.fetch_add(1, Ordering::Relaxed) + 1;
+        spawn_player(
+            &self.config,
+            "on_player_join",
+            &self.config.hooks.on_player_join,
+            &self.player,
+            count,
+        );
+
+        PlayerSession {
+            config: self.config,
+            server: self.server,
+            player: self.player,
+            joined_at: SystemTime::now(),
+        }
+    }
+}
+
+/// Player session for player join and leave hooks.
+///
+/// Started by [`PendingPlayer::join`], runs the leave hook and records the session when dropped.
+pub struct PlayerSession {
+    config: Arc<Config>,
+    server: Arc<Server>,
+    player: Player,
+    joined_at: SystemTime,
+}
+
+impl Drop for PlayerSession {
+    fn drop(&mut self) {
+        let count = self
+            .server
+            .players
+            .fetch_sub(1, Ordering::Relaxed)
+            .saturating_sub(1);
+        spawn_player(
+            &self.config,
+            "on_player_leave",
+            &self.config.hooks.on_player_leave,
+            &self.player,
+            count,
+        );
+
+        if self.config.stats.enabled {
+            self.server.history.record_session(
+                self.player.username.clone(),
+                self.player.uuid.clone(),
+                self.joined_at,
+                SystemTime::now(),
+                self.server.state().name(),
+            );
+        }
+    }
+}
+
+/// Player details for player hooks.
+struct Player {
+    username: String,
+    uuid: String,
+    ip: IpAddr,
+}
+
+/// Spawn player hook if configured, does not wait for it to complete.
+fn spawn_player(
+    config: &Config,
+    name: &str,
+    command: &Option<String>,
+    player: &Player,
+    count: u32,
+) {
+    let command = match command {
+        Some(command) if !command.trim().is_empty() => command,
+        _ => return,
+    };
+
+    // Only keep safe username characters in command, the raw username is in the environment
+    let safe_username: String = player
+        .username
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric() || *c == '_')
+        .collect();
+    let command = command
+        .replace("{username}", &safe_username)
+        .replace("{uuid}", &player.uuid)
+        .replace("{ip}", &player.ip.to_string());
+
+    let mut cmd = shell::command(&command);
+    cmd.stdin(Stdio::null())
+        .kill_on_drop(true)
+        .env("LAZYMC_PLAYER_USERNAME", &player.username)
+        .env("LAZYMC_PLAYER_UUID", &player.uuid)
+        .env("LAZYMC_PLAYER_IP", player.ip.to_string())
+        .env("LAZYMC_PLAYER_COUNT", count.to_string());
+    if let Some(ref dir) = ConfigServer::server_directory(config) {
+        cmd.current_dir(dir);
+    }
+
+    debug!(target: "lazymc::hooks", "Running {} hook: {}", name, command);
+    let timeout = Duration::from_secs(config.hooks.hook_timeout_secs as u64);
+    let name = name.to_string();
+    tokio::spawn(async move {
+        match time::timeout(timeout, cmd.status()).await {
+            Ok(Ok(status)) if status.success() => {}
+            Ok(Ok(status)) => {
+                warn!(target: "lazymc::hooks", "The {} hook failed ({})", name, status)
+            }
+            Ok(Err(err)) => {
+                error!(target: "lazymc::hooks", "Failed to run {} hook: {}", name, err)
+            }
+            Err(_) => warn!(target: "lazymc::hooks", "The {} hook timed out", name),
+        }
+    });
+}
@@ -1,28 +1,39 @@
 use std::sync::Arc;
 
 use bytes::BytesMut;
-use tokio::net::TcpStream;
 
 use crate::config::*;
+use crate::forwarding::Forwarding;
+use crate::proto::client::ClientInfo;
 use crate::proxy::ProxyHeader;
+use crate::server::Server;
 use crate::service;
+use crate::stream::ClientStream;
 
 use super::MethodResult;
 
 /// Forward the client.
 pub async fn occupy(
     config: Arc<Config>,
-    inbound: TcpStream,
+    server: &Server,
+    client_info: &ClientInfo,
+    inbound: ClientStream,
     inbound_history: &mut BytesMut,
 ) -> Result<MethodResult, ()> {
     trace!(target: "lazymc", "Using forward method to occupy joining client");
 
     debug!(target: "lazymc", "Forwarding client to {:?}!", config.join.forward.address);
 
+    let forwarding = Forwarding::new(&config, inbound.peer().ip(), client_info);
+
     service::server::route_proxy_address_queue(
         inbound,
-        ProxyHeader::Proxy.not_none(config.join.forward.send_proxy_v2),
+        ProxyHeader::Proxy(config.join.forward.proxy_protocol_version)
+            .not_none(config.join.forward.send_proxy_v2),
+        forwarding,
         config.join.forward.address,
+        config.clone(),
+        server,
         inbound_history.clone(),
     );
 
@@ -3,32 +3,72 @@ use std::sync::Arc;
 use std::time::Duration;
 
 use bytes::BytesMut;
-use tokio::net::TcpStream;
 use tokio::time;
 
 use crate::config::*;
+use crate::net;
+use crate::proto::action;
+use crate::proto::client::{Client, ClientInfo};
+use crate::proto::packet;
 use crate::server::{Server, State};
 use crate::service;
+use crate::stream::ClientStream;
 
+use super::keepalive::KeepAlive;
 use super::MethodResult;
 
+/// Login plugin channel to keep held clients alive with.
+const CHANNEL_KEEPALIVE: &str = "lazymc:keepalive";
+
 /// Hold the client.
 pub async fn occupy(
+    client: &Client,
     config: Arc<Config>,
     server: Arc<Server>,
-    inbound: TcpStream,
+    client_info: &ClientInfo,
+    mut inbound: ClientStream,
     inbound_history: &mut BytesMut,
 ) -> Result<MethodResult, ()> {
     trace!(target: "lazymc", "Using hold method to occupy joining client");
 
     // Server must be starting
-    if server.state() != State::Starting {
+    if !server.state().is_starting() {
         return Ok(MethodResult::Continue(inbound));
     }
 
     // Start holding, consume client
-    if hold(&config, &server).await? {
-        service::server::route_proxy_queue(inbound, config, inbound_history.clone());
+    if hold(
+        &config,
+        &server,
+        client,
+        client_info,
+        &mut inbound,
+        inbound_history,
+    )
+    .await?
+    {
+        // Kick with starting message if the server refuses the connection
+        let refused_message = Some(config.join.kick.starting.clone());
+        service::server::route_proxy_queue(
+            inbound,
+            config,
+            server,
+            client_info,
+            inbound_history.clone(),
+            refused_message,
+        );
+        return Ok(MethodResult::Consumed);
+    }
+
+    // Kick with start timeout message if the server was killed while holding
+    if server.is_start_timed_out() {
+        action::kick(
+            client,
+            &config.join.kick.start_timeout,
+            &mut inbound.split().1,
+        )
+        .await?;
+        net::close_tcp_stream(inbound).await.map_err(|_| ())?;
         return Ok(MethodResult::Consumed);
     }
 
@@ -39,20 +79,65 @@ pub async fn occupy(
 ///
 /// Returns holding status. `true` if client is held and it should be proxied, `false` it was held
 /// but it timed out.
-async fn hold<'a>(config: &Config, server: &Server) -> Result<bool, ()> {
+///
+/// Packets the client sends while held are added to the inbound history, keep-alive responses
+/// are dropped. Responses to outstanding keep-alive requests are awaited before relaying.
+async fn hold(
+    config: &Config,
+    server: &Server,
+    client: &Client,
+    client_info: &ClientInfo,
+    inbound: &mut ClientStream,
+    inbound_history: &mut BytesMut,
+) -> Result<bool, ()> {
     trace!(target: "lazymc", "Started holding client");
 
+    let poll_interval = Duration::from_millis(config.join.hold.poll_interval_ms.max(1) as u64);
+
+    let mut keepalive = KeepAlive::new(client_info, config.join.hold.keepalive_packet_interval_ms);
+
+    let (mut reader, mut writer) = inbound.split();
+    let mut buf = BytesMut::new();
+
     // A task to wait for suitable server state
     // Waits for started state, errors if stopping/stopped state is reached
     let task_wait = async {
         let mut state = server.state_receiver();
         loop {
-            // Wait for state change
-            state.changed().await.unwrap();
+            // Wait for state change, recheck state after poll interval
+            tokio::select! {
+                _ = time::timeout(poll_interval, state.changed()) => {}
+
+                // Send keep-alive at interval
+                _ = keepalive.tick() => {
+                    trace!(target: "lazymc", "Sending keep-alive to held client");
+                    if keepalive.send(client, &mut writer, CHANNEL_KEEPALIVE, vec![]).await.is_err() {
+                        debug!(target: "lazymc", "Failed to send keep-alive to held client, disconnecting");
+                        break false;
+                    }
+                    continue;
+                }
+
+                // Drop keep-alive responses, remember other client packets
+                result = packet::read_packet(client, &mut buf, &mut reader) => {
+                    match result {
+                        Ok(Some((packet, raw))) => {
+                            if !keepalive.take_response(&packet) {
+                                inbound_history.extend(raw);
+                            }
+                        }
+                        Ok(None) | Err(_) => {
+                            debug!(target: "lazymc", "Held client disconnected");
+                            break false;
+                        }
+                    }
+                    continue;
+                }
+            }
 
             match state.borrow().deref() {
                 // Still waiting on server start
-                State::Starting => {
+                State::Starting | State::Resuming => {
                     trace!(target: "lazymc", "Server not ready, holding client for longer");
                     continue;
                 }
@@ -69,7 +154,7 @@ async fn hold<'a>(config: &Config, server: &Server) -> Result<bool, ()> {
                 }
 
                 // Server stopped, this shouldn't happen, disconnect
-                State::Stopped => {
+                State::Stopped | State::CrashLoopBackoff => {
                     error!(target: "lazymc", "Server stopped for held client, disconnecting");
                     break false;
                 }
@@ -79,7 +164,19 @@ async fn hold<'a>(config: &Config, server: &Server) -> Result<bool, ()> {
 
     // Wait for server state with timeout
     let timeout = Duration::from_secs(config.join.hold.timeout as u64);
-    match time::timeout(timeout, task_wait).await {
+    let result = time::timeout(timeout, task_wait).await;
+
+    // Consume answers to keep-alive requests still in flight, the server must not receive them
+    if let Ok(true) = result {
+        keepalive
+            .drain(client, &mut buf, &mut reader, inbound_history)
+            .await?;
+    }
+
+    // Keep partially read packets to relay
+    inbound_history.extend(buf);
+
+    match result {
         // Relay client to proxy
         Ok(true) => {
             info!(target: "lazymc", "Server ready for held client, relaying to server");
@@ -0,0 +1,138 @@
+use std::collections::HashSet;
+use std::time::Duration;
+
+use bytes::BytesMut;
+use minecraft_protocol::version::v1_14_4::login::LoginPluginRequest;
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::time::{self, Instant, Interval};
+
+use crate::proto::client::{Client, ClientInfo};
+use crate::proto::packet::{self, RawPacket};
+use crate::proto::packets;
+use crate::types;
+
+/// Minimum protocol version supporting login plugin requests, Minecraft 1.13.
+const PROTOCOL_LOGIN_PLUGIN: u32 = 393;
+
+/// Maximum time to wait for responses to outstanding keep-alive requests.
+const DRAIN_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Keep-alive for clients waiting in login state.
+///
+/// Waiting clients are still logging in, so login plugin requests are sent in place of keep-alive
+/// packets. Clients respond that they don't understand them. Responses must not reach the server,
+/// it disconnects clients sending answers to queries it didn't make.
+pub struct KeepAlive {
+    /// Interval to send keep-alive requests at, `None` if disabled.
+    interval: Option<Interval>,
+
+    /// Message ID of the next request.
+    next_id: i32,
+
+    /// Message IDs of requests not answered yet.
+    pending: HashSet<i32>,
+}
+
+impl KeepAlive {
+    /// Construct keep-alive sending requests at the given interval in milliseconds.
+    ///
+    /// Disabled if the interval is 0, or if the client does not support login plugin requests.
+    pub fn new(client_info: &ClientInfo, interval_ms: u32) -> Self {
+        let interval = match interval_ms {
+            0 => None,
+            _ if !Self::is_supported(client_info) => {
+                debug!(target: "lazymc", "Client does not support login plugin requests, not sending keep-alive packets");
+                None
+            }
+            ms => {
+                let interval = Duration::from_millis(ms as u64);
+                Some(time::interval_at(Instant::now() + interval, interval))
+            }
+        };
+
+        Self {
+            interval,
+            next_id: 0,
+            pending: HashSet::new(),
+        }
+    }
+
+    /// Whether the client supports login plugin requests.
+    pub fn is_supported(client_info: &ClientInfo) -> bool {
+        client_info
+            .protocol()
+            .map_or(true, |p| p >= PROTOCOL_LOGIN_PLUGIN)
+    }
+
+    /// Wait until the next keep-alive request must be sent.
+    ///
+    /// Never completes if disabled.
+    pub async fn tick(&mut self) {
+        match self.interval.as_mut() {
+            Some(interval) => {
+                interval.tick().await;
+            }
+            None => std::future::pending().await,
+        }
+    }
+
+    /// Send login plugin request on the given channel, remember it to drop its response.
+    pub async fn send(
+        &mut self,
+        client: &Client,
+        writer: &mut (impl AsyncWrite + Unpin),
+        channel: &str,
+        data: Vec<u8>,
+    ) -> Result<(), ()> {
+        let message_id = self.next_id;
+        self.next_id = self.next_id.wrapping_add(1);
+        let request = LoginPluginRequest {
+            message_id,
+            channel: channel.into(),
+            data,
+        };
+        packet::write_packet(request, client, writer).await?;
+        self.pending.insert(message_id);
+        Ok(())
+    }
+
+    /// Check whether packet is a response to an outstanding request, consumes it if so.
+    pub fn take_response(&mut self, packet: &RawPacket) -> bool {
+        packet.id == packets::login::SERVER_LOGIN_PLUGIN_RESPONSE
+            && types::read_var_int(&packet.data).is_ok_and(|(_, id)| self.pending.remove(&id))
+    }
+
+    /// Read responses to all outstanding requests.
+    ///
+    /// Must be called before relaying the client to the server. Other packets are added to the
+    /// inbound history. Gives up after a timeout, errors if the client disconnects.
+    pub async fn drain(
+        &mut self,
+        client: &Client,
+        buf: &mut BytesMut,
+        reader: &mut (impl AsyncRead + Unpin),
+        inbound_history: &mut BytesMut,
+    ) -> Result<(), ()> {
+        let drain = async {
+            while !self.pending.is_empty() {
+                match packet::read_packet(client, buf, reader).await? {
+                    Some((packet, raw)) => {
+                        if !self.take_response(&packet) {
+                            inbound_history.extend(raw);
+                        }
+                    }
+                    None => return Err(()),
+                }
+            }
+            Ok(())
+        };
+
+        match time::timeout(DRAIN_TIMEOUT, drain).await {
+            Ok(result) => result,
+            Err(_) => {
+                debug!(target: "lazymc", "Client did not answer {} keep-alive request(s), relaying anyway", self.pending.len());
+                Ok(())
+            }
+        }
+    }
+}
@@ -1,30 +1,50 @@
-use tokio::net::TcpStream;
-
 use crate::config::*;
 use crate::net;
 use crate::proto::action;
-use crate::proto::client::Client;
+use crate::proto::client::{Client, ClientInfo};
 use crate::server::{self, Server};
+use crate::stream::ClientStream;
 
 use super::MethodResult;
 
+/// Minimum protocol version to send JSON kick messages to, Minecraft 1.20.
+const PROTOCOL_JSON_KICK: u32 = 763;
+
 /// Kick the client.
 pub async fn occupy(
     client: &Client,
+    client_info: &ClientInfo,
     config: &Config,
     server: &Server,
-    mut inbound: TcpStream,
+    mut inbound: ClientStream,
 ) -> Result<MethodResult, ()> {
     trace!(target: "lazymc", "Using kick method to occupy joining client");
 
-    // Select message and kick
-    let msg = match server.state() {
-        server::State::Starting | server::State::Stopped | server::State::Started => {
-            &config.join.kick.starting
-        }
-        server::State::Stopping => &config.join.kick.stopping,
+    // Select message
+    let kick = &config.join.kick;
+    let (msg, json) = match server.state() {
+        server::State::Starting
+        | server::State::Resuming
+        | server::State::Stopped
+        | server::State::CrashLoopBackoff
+        | server::State::Started => (&kick.starting, &kick.starting_json),
+        server::State::Stopping => (&kick.stopping, &kick.stopping_json),
     };
-    action::kick(client, msg, &mut inbound.split().1).await?;
+
+    // Kick with JSON message if valid and supported by client, fall back to plain message
+    let json = json
+        .as_deref()
+        .filter(|json| serde_json::from_str::<serde_json::Value>(json).is_ok())
+        .filter(|_| {
+            client_info
+                .protocol()
+                .map_or(true, |p| p >= PROTOCOL_JSON_KICK)
+        });
+    match json {
+        Some(json) => action::kick_json(client, json, &mut inbound.split().1).await?,
+        None => action::kick(client, msg, &mut inbound.split().1).await?,
+    }
+    server.stats.record_kick();
 
     // Gracefully close connection
     net::close_tcp_stream(inbound).await.map_err(|_| ())?;
@@ -1,12 +1,12 @@
 use std::sync::Arc;
 
 use bytes::BytesMut;
-use tokio::net::TcpStream;
 
 use crate::config::*;
 use crate::lobby;
 use crate::proto::client::{Client, ClientInfo};
 use crate::server::Server;
+use crate::stream::ClientStream;
 
 use super::MethodResult;
 
@@ -16,7 +16,7 @@ pub async fn occupy(
     client_info: ClientInfo,
     config: Arc<Config>,
     server: Arc<Server>,
-    inbound: TcpStream,
+    inbound: ClientStream,
     inbound_queue: BytesMut,
 ) -> Result<MethodResult, ()> {
     trace!(target: "lazymc", "Using lobby method to occupy joining client");
@@ -42,5 +42,5 @@ async fn must_still_probe(config: &Config, server: &Server) -> bool {
 
 /// Check whether we must have probed data.
 fn must_probe(config: &Config) -> bool {
-    config.server.forge
+    config.server.forge_variant() != ForgeVariant::None
 }
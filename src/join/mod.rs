@@ -1,18 +1,21 @@
 use std::sync::Arc;
 
 use bytes::BytesMut;
-use tokio::net::TcpStream;
 
 use crate::config::*;
 use crate::net;
 use crate::proto::client::{Client, ClientInfo, ClientState};
 use crate::server::Server;
+use crate::stream::ClientStream;
 
 pub mod forward;
 pub mod hold;
+mod keepalive;
 pub mod kick;
 #[cfg(feature = "lobby")]
 pub mod lobby;
+pub mod queue;
+pub mod redirect;
 
 /// A result returned by a join occupy method.
 pub enum MethodResult {
@@ -20,7 +23,7 @@ pub enum MethodResult {
     Consumed,
 
     /// Method is done, continue with the next.
-    Continue(TcpStream),
+    Continue(ClientStream),
 }
 
 /// Start occupying client.
@@ -28,10 +31,10 @@ pub enum MethodResult {
 /// This assumes the login start packet has just been received.
 pub async fn occupy(
     client: Client,
-    #[allow(unused_variables)] client_info: ClientInfo,
+    client_info: ClientInfo,
     config: Arc<Config>,
     server: Arc<Server>,
-    mut inbound: TcpStream,
+    mut inbound: ClientStream,
     mut inbound_history: BytesMut,
     #[allow(unused_variables)] login_queue: BytesMut,
 ) -> Result<(), ()> {
@@ -47,13 +50,28 @@ pub async fn occupy(
         // Invoke method, take result
         let result = match method {
             // Kick method, immediately kick client
-            Method::Kick => kick::occupy(&client, &config, &server, inbound).await?,
+            Method::Kick => kick::occupy(&client, &client_info, &config, &server, inbound).await?,
 
             // Hold method, hold client connection while server starts
             Method::Hold => {
                 hold::occupy(
+                    &client,
+                    config.clone(),
+                    server.clone(),
+                    &client_info,
+                    inbound,
+                    &mut inbound_history,
+                )
+                .await?
+            }
+
+            // Queue method, hold client in numbered queue while server starts
+            Method::Queue => {
+                queue::occupy(
+                    &client,
                     config.clone(),
                     server.clone(),
+                    &client_info,
                     inbound,
                     &mut inbound_history,
                 )
@@ -62,9 +80,19 @@ pub async fn occupy(
 
             // Forward method, forward client connection while server starts
             Method::Forward => {
-                forward::occupy(config.clone(), inbound, &mut inbound_history).await?
+                forward::occupy(
+                    config.clone(),
+                    &server,
+                    &client_info,
+                    inbound,
+                    &mut inbound_history,
+                )
+                .await?
             }
 
+            // Redirect method, point client to another server address
+            Method::Redirect => redirect::occupy(&client, &config, &server, inbound).await?,
+
             // Lobby method, keep client in lobby while server starts
             #[cfg(feature = "lobby")]
             Method::Lobby => {
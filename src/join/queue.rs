@@ -0,0 +1,274 @@
+use std::collections::VecDeque;
+use std::ops::Deref;
+use std::sync::Arc;
+use std::time::Duration;
+
+use bytes::BytesMut;
+use tokio::time;
+
+use crate::config::*;
+use crate::net;
+use crate::proto::action;
+use crate::proto::client::{Client, ClientInfo};
+use crate::proto::packet;
+use crate::server::{Server, State};
+use crate::service;
+use crate::stream::ClientStream;
+use crate::types;
+
+use super::keepalive::KeepAlive;
+use super::MethodResult;
+
+/// Interval to check queue position at.
+const POSITION_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Login plugin channel to send queue position updates on.
+///
+/// Data holds the 1-based position and total queue size as VarInts. Vanilla clients can't show
+/// it, but it keeps them alive and client mods may display it.
+const CHANNEL_POSITION: &str = "lazymc:queue_position";
+
+/// Queue of clients waiting for the server to start.
+///
+/// Clients are identified by a ticket, and are released in FIFO order.
+#[derive(Debug, Default)]
+pub struct QueueState {
+    /// Next ticket to hand out.
+    next_ticket: u64,
+
+    /// Tickets of queued clients, in order.
+    tickets: VecDeque<u64>,
+}
+
+impl QueueState {
+    /// Add client to the back of the queue.
+    ///
+    /// Returns `None` if the queue is full.
+    fn join(&mut self, max_size: usize) -> Option<u64> {
+        if self.tickets.len() >= max_size {
+            return None;
+        }
+
+        let ticket = self.next_ticket;
+        self.next_ticket = self.next_ticket.wrapping_add(1);
+        self.tickets.push_back(ticket);
+        Some(ticket)
+    }
+
+    /// Remove client from the queue.
+    fn leave(&mut self, ticket: u64) {
+        self.tickets.retain(|t| *t != ticket);
+    }
+
+    /// Get 1-based queue position of client, and total queue size.
+    fn position(&self, ticket: u64) -> Option<(usize, usize)> {
+        self.tickets
+            .iter()
+            .position(|t| *t == ticket)
+            .map(|pos| (pos + 1, self.tickets.len()))
+    }
+}
+
+/// Queue the client.
+pub async fn occupy(
+    client: &Client,
+    config: Arc<Config>,
+    server: Arc<Server>,
+    client_info: &ClientInfo,
+    mut inbound: ClientStream,
+    inbound_history: &mut BytesMut,
+) -> Result<MethodResult, ()> {
+    trace!(target: "lazymc", "Using queue method to occupy joining client");
+
+    // Server must be starting
+    if !server.state().is_starting() {
+        return Ok(MethodResult::Continue(inbound));
+    }
+
+    // Enter queue, continue with next method if full
+    let ticket = match server
+        .join_queue
+        .lock()
+        .await
+        .join(config.join.queue.max_size)
+    {
+        Some(ticket) => ticket,
+        None => {
+            debug!(target: "lazymc", "Join queue is full, trying next method");
+            return Ok(MethodResult::Continue(inbound));
+        }
+    };
+
+    let result = queue(
+        &config,
+        &server,
+        client,
+        client_info,
+        ticket,
+        &mut inbound,
+        inbound_history,
+    )
+    .await;
+    let position = {
+        let mut queue = server.join_queue.lock().await;
+        let position = queue.position(ticket);
+        queue.leave(ticket);
+        position
+    };
+
+    match result {
+        // Released from queue, relay to server
+        Ok(true) => {
+            service::server::route_proxy_queue(
+                inbound,
+                config,
+                server,
+                client_info,
+                inbound_history.clone(),
+                None,
+            );
+            Ok(MethodResult::Consumed)
+        }
+
+        // Timed out in queue, kick with last known position
+        Ok(false) => {
+            let (pos, total) = position.unwrap_or((1, 1));
+            let msg = position_message(&config.join.queue.position_message, pos, total);
+            action::kick(client, &msg, &mut inbound.split().1).await?;
+            net::close_tcp_stream(inbound).await.map_err(|_| ())?;
+            Ok(MethodResult::Consumed)
+        }
+
+        // Server stopping/stopped, continue with next method
+        Err(()) => Ok(MethodResult::Continue(inbound)),
+    }
+}
+
+/// Keep client in queue until it is released.
+///
+/// Sends position updates to the client as it moves up in the queue, and keep-alives as held
+/// clients get them. Packets the client sends while queued are added to the inbound history.
+///
+/// Returns `Ok(true)` if the client is at the front of the queue and the server is ready,
+/// `Ok(false)` if it timed out or disconnected, and `Err(())` if the server is stopping or
+/// stopped.
+async fn queue(
+    config: &Config,
+    server: &Server,
+    client: &Client,
+    client_info: &ClientInfo,
+    ticket: u64,
+    inbound: &mut ClientStream,
+    inbound_history: &mut BytesMut,
+) -> Result<bool, ()> {
+    trace!(target: "lazymc", "Started queueing client");
+
+    let mut keepalive = KeepAlive::new(client_info, config.join.hold.keepalive_packet_interval_ms);
+    let send_position = KeepAlive::is_supported(client_info);
+
+    let (mut reader, mut writer) = inbound.split();
+    let mut buf = BytesMut::new();
+
+    let task_wait = async {
+        let mut state = server.state_receiver();
+        let mut last_position = None;
+        loop {
+            // Wait for state change, or recheck position after interval
+            tokio::select! {
+                _ = time::timeout(POSITION_INTERVAL, state.changed()) => {}
+
+                // Report last known position at keep-alive interval
+                _ = keepalive.tick() => {
+                    let data = position_data(last_position.unwrap_or((1, 1)))?;
+                    keepalive.send(client, &mut writer, CHANNEL_POSITION, data).await?;
+                    continue;
+                }
+
+                // Drop keep-alive responses, remember other client packets
+                result = packet::read_packet(client, &mut buf, &mut reader) => {
+                    match result {
+                        Ok(Some((packet, raw))) => {
+                            if !keepalive.take_response(&packet) {
+                                inbound_history.extend(raw);
+                            }
+                        }
+                        Ok(None) | Err(_) => {
+                            debug!(target: "lazymc", "Queued client disconnected");
+                            break Ok(false);
+                        }
+                    }
+                    continue;
+                }
+            }
+
+            let current = *state.borrow().deref();
+            match current {
+                State::Starting | State::Resuming | State::Started => {}
+                State::Stopping | State::Stopped | State::CrashLoopBackoff => {
+                    warn!(target: "lazymc", "Server stopping for queued client, disconnecting");
+                    break Err(());
+                }
+            }
+
+            // Report position updates as clients in front leave the queue
+            let position = server.join_queue.lock().await.position(ticket);
+            if position != last_position {
+                if let Some((pos, total)) = position {
+                    debug!(target: "lazymc", "Queued client now at position {} of {}", pos, total);
+                    if send_position {
+                        let data = position_data((pos, total))?;
+                        keepalive
+                            .send(client, &mut writer, CHANNEL_POSITION, data)
+                            .await?;
+                    }
+                }
+                last_position = position;
+            }
+
+            // Release client once at the front and server is ready
+            if current == State::Started && matches!(position, Some((1, _))) {
+                break Ok(true);
+            }
+        }
+    };
+
+    // Wait for release with timeout
+    let timeout = Duration::from_secs(config.join.queue.timeout as u64);
+    let result = time::timeout(timeout, task_wait).await;
+
+    // Consume answers to position updates still in flight, the server must not receive them
+    if let Ok(Ok(true)) = result {
+        keepalive
+            .drain(client, &mut buf, &mut reader, inbound_history)
+            .await?;
+    }
+
+    // Keep partially read packets to relay
+    inbound_history.extend(buf);
+
+    match result {
+        Ok(Ok(true)) => {
+            info!(target: "lazymc", "Server ready for queued client, relaying to server");
+            Ok(true)
+        }
+        Ok(result) => result,
+        Err(_) => {
+            warn!(target: "lazymc", "Queued client reached timeout of {}s", config.join.queue.timeout);
+            Ok(false)
+        }
+    }
+}
+
+/// Encode queue position and total for a position update.
+fn position_data((pos, total): (usize, usize)) -> Result<Vec<u8>, ()> {
+    let mut data = types::encode_var_int(pos as i32)?;
+    data.append(&mut types::encode_var_int(total as i32)?);
+    Ok(data)
+}
+
+/// Render queue position message, replacing `{pos}` and `{total}` placeholders.
+fn position_message(template: &str, pos: usize, total: usize) -> String {
+    template
+        .replace("{pos}", &pos.to_string())
+        .replace("{total}", &total.to_string())
+}
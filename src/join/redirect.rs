@@ -0,0 +1,45 @@
+use crate::config::*;
+use crate::net;
+use crate::proto::action;
+use crate::proto::client::Client;
+use crate::server::{self, Server};
+use crate::stream::ClientStream;
+
+use super::MethodResult;
+
+/// Translation key recognized by clients as server address change.
+const REDIRECT_TRANSLATE_KEY: &str = "multiplayer.disconnect.server_address_change";
+
+/// Redirect the client.
+pub async fn occupy(
+    client: &Client,
+    config: &Config,
+    server: &Server,
+    mut inbound: ClientStream,
+) -> Result<MethodResult, ()> {
+    trace!(target: "lazymc", "Using redirect method to occupy joining client");
+
+    // Server must be sleeping or starting
+    match server.state() {
+        server::State::Stopped
+        | server::State::CrashLoopBackoff
+        | server::State::Starting
+        | server::State::Resuming => {}
+        server::State::Started | server::State::Stopping => {
+            return Ok(MethodResult::Continue(inbound))
+        }
+    }
+
+    debug!(target: "lazymc", "Redirecting client to {}", config.join.redirect.address);
+
+    let reason = serde_json::json!({
+        "translate": REDIRECT_TRANSLATE_KEY,
+        "with": [config.join.redirect.address.to_string()],
+    });
+    action::kick_json(client, &reason.to_string(), &mut inbound.split().1).await?;
+
+    // Gracefully close connection
+    net::close_tcp_stream(inbound).await.map_err(|_| ())?;
+
+    Ok(MethodResult::Consumed)
+}
@@ -1,4 +1,3 @@
-use std::io::ErrorKind;
 use std::ops::Deref;
 use std::sync::Arc;
 use std::time::Duration;
@@ -9,26 +8,34 @@ use minecraft_protocol::decoder::Decoder;
 use minecraft_protocol::version::v1_14_4::login::{
     LoginPluginRequest, LoginPluginResponse, LoginStart, LoginSuccess, SetCompression,
 };
-use tokio::io::AsyncWriteExt;
-use tokio::net::tcp::{ReadHalf, WriteHalf};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
 use tokio::net::TcpStream;
 use tokio::select;
 use tokio::time;
 
 use crate::config::*;
 use crate::forge;
+use crate::forwarding::{self, Forwarding};
 use crate::mc::uuid;
 use crate::net;
+use crate::packet_hook::Relay;
+use crate::probe;
 use crate::proto;
+use crate::proto::action;
 use crate::proto::client::{Client, ClientInfo, ClientState};
 use crate::proto::packets::play::join_game::JoinGameData;
 use crate::proto::{packet, packets};
 use crate::proxy;
 use crate::server::{Server, State};
+use crate::stats::ConnectionGuard;
+use crate::stream::ClientStream;
 
 /// Interval to send keep-alive packets at.
 pub const KEEP_ALIVE_INTERVAL: Duration = Duration::from_secs(10);
 
+/// Interval to send startup progress packets at.
+const PROGRESS_INTERVAL: Duration = Duration::from_secs(1);
+
 /// Timeout for creating new server connection for lobby client.
 const SERVER_CONNECT_TIMEOUT: Duration = Duration::from_secs(2 * 60);
 
@@ -53,7 +60,7 @@ const SERVER_WARMUP: Duration = Duration::from_secs(1);
 pub async fn serve(
     client: &Client,
     client_info: ClientInfo,
-    mut inbound: TcpStream,
+    mut inbound: ClientStream,
     config: Arc<Config>,
     server: Arc<Server>,
     queue: BytesMut,
@@ -98,10 +105,11 @@ pub async fn serve(
             debug!(target: "lazymc::lobby", "Login on lobby server (user: {})", login_start.name);
 
             // Replay Forge payload
-            if config.server.forge {
+            if server.forge_variant(&config).await == ForgeVariant::Fml2 {
                 forge::replay_login_payload(client, &mut inbound, server.clone(), &mut inbound_buf)
                     .await?;
-                let (_returned_reader, returned_writer) = inbound.split();
+                let (returned_reader, returned_writer) = inbound.split();
+                reader = returned_reader;
                 writer = returned_writer;
             }
 
@@ -113,7 +121,7 @@ pub async fn serve(
             }
 
             // Respond with login success, switch to play state
-            respond_login_success(client, &mut writer, &login_start).await?;
+            respond_login_success(client, &client_info, &mut writer, &login_start).await?;
             client.set_state(ClientState::Play);
 
             trace!(target: "lazymc::lobby", "Client login success, sending required play packets for lobby world");
@@ -121,13 +129,75 @@ pub async fn serve(
             // Send packets to client required to get into workable play state for lobby world
             send_lobby_play_packets(client, &client_info, &mut writer, &server).await?;
 
-            // Wait for server to come online
-            stage_wait(client, &client_info, &server, &config, &mut writer).await?;
+            // Show custom tab list header and footer
+            if config.join.lobby.has_tab_header_footer() {
+                packets::play::player_list::send_header_footer(
+                    client,
+                    &client_info,
+                    &mut writer,
+                    config.join.lobby.tab_header.as_deref().unwrap_or(""),
+                    config.join.lobby.tab_footer.as_deref().unwrap_or(""),
+                )
+                .await?;
+            }
+
+            // Show startup progress boss bar
+            if config.join.lobby.show_bossbar {
+                let progress = startup_progress(&server, &config).await;
+                packets::play::boss_bar::add(
+                    client,
+                    &client_info,
+                    &mut writer,
+                    &config.join.lobby.bossbar_title,
+                    progress,
+                )
+                .await?;
+            }
+
+            // Send lobby resource pack
+            if let Some(url) = config.join.lobby.resource_pack_url.as_deref() {
+                packets::play::resource_pack::send(
+                    client,
+                    &client_info,
+                    &mut writer,
+                    url,
+                    config
+                        .join
+                        .lobby
+                        .resource_pack_hash
+                        .as_deref()
+                        .unwrap_or(""),
+                    config.join.lobby.resource_pack_required,
+                )
+                .await?;
+            }
+
+            // Wait for server to come online, kick if required resource pack is declined
+            if !stage_wait(
+                client,
+                &client_info,
+                &server,
+                &config,
+                &mut reader,
+                &mut inbound_buf,
+                &mut writer,
+            )
+            .await?
+            {
+                info!(target: "lazymc::lobby", "Client declined required resource pack, disconnecting");
+                action::kick(
+                    client,
+                    &config.join.lobby.resource_pack_declined_message,
+                    &mut writer,
+                )
+                .await?;
+                break;
+            }
 
             // Start new connection to server
             let server_client_info = client_info.clone();
             let (server_client, mut outbound, mut server_buf) =
-                connect_to_server(&server_client_info, &inbound, &config).await?;
+                connect_to_server(&server_client_info, &inbound, &config, &server).await?;
             let (returned_reader, returned_writer) = inbound.split();
             reader = returned_reader;
             writer = returned_writer;
@@ -144,6 +214,23 @@ pub async fn serve(
             // Reset lobby title
             packets::play::title::send(client, &client_info, &mut writer, "").await?;
 
+            // Reset tab list header and footer, let real server take over
+            if config.join.lobby.has_tab_header_footer() && config.motd.from_server {
+                packets::play::player_list::send_header_footer(
+                    client,
+                    &client_info,
+                    &mut writer,
+                    "",
+                    "",
+                )
+                .await?;
+            }
+
+            // Remove startup progress boss bar
+            if config.join.lobby.show_bossbar {
+                packets::play::boss_bar::remove(client, &client_info, &mut writer).await?;
+            }
+
             // Play ready sound if configured
             play_lobby_ready_sound(client, &client_info, &mut writer, &config).await?;
 
@@ -164,7 +251,10 @@ pub async fn serve(
 
             // Client and server connection ready now, move client to proxy
             debug!(target: "lazymc::lobby", "Server connection ready, relaying lobby client to proxy");
-            route_proxy(inbound, outbound, server_buf);
+            let relay = Relay::new(ClientState::Play, client.compressed())
+                .with_client_info(&client_info)
+                .with_hooks(server.packet_hooks.clone());
+            route_proxy(inbound, outbound, config, &server, server_buf, relay);
 
             return Ok(());
         }
@@ -184,23 +274,30 @@ pub async fn serve(
 /// Respond to client with a set compression packet.
 async fn respond_set_compression(
     client: &Client,
-    writer: &mut WriteHalf<'_>,
+    writer: &mut (impl AsyncWrite + Unpin),
     threshold: i32,
 ) -> Result<(), ()> {
     packet::write_packet(SetCompression { threshold }, client, writer).await
 }
 
 /// Respond to client with login success packet
-// TODO: support online mode here
+///
+/// Uses the authenticated player profile in online mode.
 async fn respond_login_success(
     client: &Client,
-    writer: &mut WriteHalf<'_>,
+    client_info: &ClientInfo,
+    writer: &mut (impl AsyncWrite + Unpin),
     login_start: &LoginStart,
 ) -> Result<(), ()> {
     packet::write_packet(
         LoginSuccess {
-            uuid: uuid::offline_player_uuid(&login_start.name),
-            username: login_start.name.clone(),
+            uuid: client_info
+                .player_uuid()
+                .unwrap_or_else(|| uuid::offline_player_uuid(&login_start.name)),
+            username: client_info
+                .username
+                .clone()
+                .unwrap_or_else(|| login_start.name.clone()),
         },
         client,
         writer,
@@ -212,7 +309,7 @@ async fn respond_login_success(
 async fn play_lobby_ready_sound(
     client: &Client,
     client_info: &ClientInfo,
-    writer: &mut WriteHalf<'_>,
+    writer: &mut (impl AsyncWrite + Unpin),
     config: &Config,
 ) -> Result<(), ()> {
     if let Some(sound_name) = config.join.lobby.ready_sound.as_ref() {
@@ -234,7 +331,7 @@ async fn play_lobby_ready_sound(
 async fn send_lobby_play_packets(
     client: &Client,
     client_info: &ClientInfo,
-    writer: &mut WriteHalf<'_>,
+    writer: &mut (impl AsyncWrite + Unpin),
     server: &Server,
 ) -> Result<(), ()> {
     // See: https://wiki.vg/Protocol_FAQ#What.27s_the_normal_login_sequence_for_a_client.3F
@@ -254,27 +351,84 @@ async fn send_lobby_play_packets(
     Ok(())
 }
 
+/// Estimate server startup progress in range `0.0..=1.0`.
+///
+/// Based on time elapsed since the server started starting, relative to the start timeout.
+async fn startup_progress(server: &Server, config: &Config) -> f32 {
+    let timeout = startup_timeout(server, config);
+    if !server.state().is_starting() || timeout == 0 {
+        return 0.0;
+    }
+
+    let elapsed = server.state_elapsed().await.as_secs_f32();
+    (elapsed / timeout as f32).clamp(0.0, 1.0)
+}
+
+/// Estimate remaining server startup time in seconds.
+///
+/// Based on time elapsed since the server started starting, relative to the start timeout.
+async fn startup_remaining(server: &Server, config: &Config) -> u64 {
+    let timeout = startup_timeout(server, config) as u64;
+    if !server.state().is_starting() {
+        return timeout;
+    }
+
+    timeout.saturating_sub(server.state_elapsed().await.as_secs())
+}
+
+/// Timeout of the current server startup in seconds, the resume timeout if resuming.
+fn startup_timeout(server: &Server, config: &Config) -> u32 {
+    match server.state() {
+        State::Resuming => config.server.resume_timeout,
+        _ => config.server.start_timeout,
+    }
+}
+
 /// An infinite keep-alive loop.
 ///
 /// This will keep sending keep-alive and title packets to the client until it is dropped.
+/// Startup progress packets are sent in between if enabled.
 async fn keep_alive_loop(
     client: &Client,
     client_info: &ClientInfo,
-    writer: &mut WriteHalf<'_>,
+    writer: &mut (impl AsyncWrite + Unpin),
+    server: &Server,
     config: &Config,
 ) -> Result<(), ()> {
-    let mut interval = time::interval(KEEP_ALIVE_INTERVAL);
+    let mut keep_alive_interval = time::interval(KEEP_ALIVE_INTERVAL);
+    let mut progress_interval = time::interval(PROGRESS_INTERVAL);
 
     loop {
-        interval.tick().await;
+        select! {
+            _ = keep_alive_interval.tick() => {
+                trace!(target: "lazymc::lobby", "Sending keep-alive sequence to lobby client");
 
-        trace!(target: "lazymc::lobby", "Sending keep-alive sequence to lobby client");
+                // Send keep alive and title packets
+                packets::play::keep_alive::send(client, client_info, writer).await?;
+                packets::play::title::send(client, client_info, writer, &config.join.lobby.message).await?;
 
-        // Send keep alive and title packets
-        packets::play::keep_alive::send(client, client_info, writer).await?;
-        packets::play::title::send(client, client_info, writer, &config.join.lobby.message).await?;
+                // TODO: verify we receive correct keep alive response
+            }
+            _ = progress_interval.tick() => {
+                // Update startup progress boss bar
+                if config.join.lobby.show_bossbar {
+                    let progress = startup_progress(server, config).await;
+                    packets::play::boss_bar::update_progress(client, client_info, writer, progress)
+                        .await?;
+                }
 
-        // TODO: verify we receive correct keep alive response
+                // Update startup countdown action bar
+                if config.join.lobby.show_actionbar {
+                    let remaining = startup_remaining(server, config).await;
+                    let text = config
+                        .join
+                        .lobby
+                        .actionbar_message
+                        .replace("{remaining}", &remaining.to_string());
+                    packets::play::action_bar::send(client, client_info, writer, &text).await?;
+                }
+            }
+        }
     }
 }
 
@@ -283,16 +437,50 @@ async fn keep_alive_loop(
 /// In this stage we wait for the server to come online.
 ///
 /// During this stage we keep sending keep-alive and title packets to the client to keep it active.
+///
+/// Returns `false` if the client declined a required resource pack before the server came online.
 async fn stage_wait(
     client: &Client,
     client_info: &ClientInfo,
     server: &Server,
     config: &Config,
-    writer: &mut WriteHalf<'_>,
+    reader: &mut (impl AsyncRead + Unpin),
+    inbound_buf: &mut BytesMut,
+    writer: &mut (impl AsyncWrite + Unpin),
+) -> Result<bool, ()> {
+    let watch_resource_pack =
+        config.join.lobby.resource_pack_required && config.join.lobby.resource_pack_url.is_some();
+
+    let result = select! {
+        a = keep_alive_loop(client, client_info, writer, server, config) => a.map(|_| true),
+        b = wait_for_server(server, config) => b.map(|_| true),
+        c = wait_for_resource_pack_decline(client, reader, inbound_buf), if watch_resource_pack => c.map(|_| false),
+    };
+
+    // Kick with start timeout message if the server was killed while waiting
+    if result.is_err() && server.is_start_timed_out() {
+        action::kick(client, &config.join.kick.start_timeout, writer).await?;
+    }
+
+    result
+}
+
+/// Wait for client to decline the resource pack.
+///
+/// Other client packets are voided. Errors if the client disconnects.
+async fn wait_for_resource_pack_decline(
+    client: &Client,
+    reader: &mut (impl AsyncRead + Unpin),
+    inbound_buf: &mut BytesMut,
 ) -> Result<(), ()> {
-    select! {
-        a = keep_alive_loop(client, client_info, writer, config) => a,
-        b = wait_for_server(server, config) => b,
+    loop {
+        match packet::read_packet(client, inbound_buf, reader).await? {
+            Some((packet, _raw)) if packets::play::resource_pack::is_declined(&packet) => {
+                return Ok(())
+            }
+            Some(_) => continue,
+            None => return Err(()),
+        }
     }
 }
 
@@ -312,7 +500,7 @@ async fn wait_for_server(server: &Server, config: &Config) -> Result<(), ()> {
 
             match state.borrow().deref() {
                 // Still waiting on server start
-                State::Starting => {
+                State::Starting | State::Resuming => {
                     trace!(target: "lazymc::lobby", "Server not ready, holding client for longer");
                     continue;
                 }
@@ -323,7 +511,7 @@ async fn wait_for_server(server: &Server, config: &Config) -> Result<(), ()> {
                 }
 
                 // Server stopping, this shouldn't happen, kick
-                State::Stopping | State::Stopped => {
+                State::Stopping | State::Stopped | State::CrashLoopBackoff => {
                     break false;
                 }
             }
@@ -356,12 +544,13 @@ async fn wait_for_server(server: &Server, config: &Config) -> Result<(), ()> {
 /// This will initialize the connection to the play state. Client details are used.
 async fn connect_to_server(
     client_info: &ClientInfo,
-    inbound: &TcpStream,
+    inbound: &ClientStream,
     config: &Config,
+    server: &Server,
 ) -> Result<(Client, TcpStream, BytesMut), ()> {
     time::timeout(
         SERVER_CONNECT_TIMEOUT,
-        connect_to_server_no_timeout(client_info, inbound, config),
+        connect_to_server_no_timeout(client_info, inbound, config, server),
     )
     .await
     .map_err(|_| {
@@ -375,24 +564,28 @@ async fn connect_to_server(
 // TODO: clean this up
 async fn connect_to_server_no_timeout(
     client_info: &ClientInfo,
-    inbound: &TcpStream,
+    inbound: &ClientStream,
     config: &Config,
+    server: &Server,
 ) -> Result<(Client, TcpStream, BytesMut), ()> {
     // Open connection
     // TODO: on connect fail, ping server and redirect to serve_status if offline
     let mut outbound = TcpStream::connect(config.server.address)
         .await
         .map_err(|_| ())?;
+    net::configure_tcp_stream(&outbound, &config.advanced.tcp);
 
     // Add proxy header
-    if config.server.send_proxy_v2 {
+    if probe::send_proxy_v2(config, server) {
         trace!(target: "lazymc::lobby", "Sending client proxy header for server connection");
-        outbound
-            .write_all(&proxy::stream_proxy_header(inbound).map_err(|_| ())?)
-            .await
+        let header = proxy::stream_proxy_header(inbound, config.server.proxy_protocol_version)
             .map_err(|_| ())?;
+        outbound.write_all(&header).await.map_err(|_| ())?;
     }
 
+    // Client peer address, for player info forwarding
+    let peer = inbound.peer();
+
     // Construct temporary server client
     let tmp_client = match outbound.local_addr() {
         Ok(addr) => Client::new(addr),
@@ -408,12 +601,11 @@ async fn connect_to_server_no_timeout(
         ClientState::Login.to_id(),
         "Client handshake should have login as next state"
     );
-    packet::write_packet(
-        client_info.handshake.clone().unwrap(),
-        &tmp_client,
-        &mut writer,
-    )
-    .await?;
+    let mut handshake = client_info.handshake.clone().unwrap();
+    if let Some(forwarding) = Forwarding::new(config, peer.ip(), client_info) {
+        handshake = forwarding.handshake(handshake);
+    }
+    packet::write_packet(handshake, &tmp_client, &mut writer).await?;
 
     // Request login start
     packet::write_packet(
@@ -486,8 +678,20 @@ async fn connect_to_server_no_timeout(
                     dbg!(err);
                 })?;
 
+            // Respond with Velocity player info
+            if plugin_request.channel == forwarding::CHANNEL_VELOCITY {
+                if let Some(forwarding) = Forwarding::new(config, peer.ip(), client_info) {
+                    forwarding
+                        .respond_velocity(&tmp_client, plugin_request, &mut writer)
+                        .await?;
+                    continue;
+                }
+            }
+
             // Respond with Forge messages
-            if config.server.forge {
+            if server.forge_variant(config).await != ForgeVariant::None
+                && plugin_request.channel == forge::CHANNEL_LOGIN_WRAPPER
+            {
                 trace!(target: "lazymc::lobby", "Got login plugin request from server, responding with Forge reply");
 
                 // Respond to Forge login plugin request
@@ -630,32 +834,47 @@ async fn wait_for_server_join_game_no_timeout(
 /// Route our lobby client through the proxy to the real server, spawning a new task.
 ///
 /// `inbound_queue` is used for data already received from the server, that needs to be pushed to
-/// the client.
+/// the client. The relay tracks the protocol state of both connections.
 #[inline]
-pub fn route_proxy(inbound: TcpStream, outbound: TcpStream, inbound_queue: BytesMut) {
+pub fn route_proxy(
+    inbound: ClientStream,
+    outbound: TcpStream,
+    config: Arc<Config>,
+    server: &Server,
+    inbound_queue: BytesMut,
+    relay: Relay,
+) {
     // When server is online, proxy all
+    let connection = ConnectionGuard::new(&server.stats, Some(inbound.peer().ip()));
     let service = async move {
-        proxy::proxy_inbound_outbound_with_queue(inbound, outbound, &inbound_queue, &[])
-            .map(|r| {
-                if let Err(err) = r {
-                    warn!(target: "lazymc", "Failed to proxy: {}", err);
-                }
-            })
-            .await
+        proxy::proxy_inbound_outbound_with_queue(
+            inbound,
+            outbound,
+            &config,
+            &inbound_queue,
+            &[],
+            relay,
+        )
+        .map(|r| {
+            drop(connection);
+            if let Err(err) = r {
+                warn!(target: "lazymc", "Failed to proxy: {}", err);
+            }
+        })
+        .await
     };
 
     tokio::spawn(service);
 }
 
 /// Drain given reader until nothing is left voiding all data.
-async fn drain_stream(reader: &mut ReadHalf<'_>) -> Result<(), ()> {
+async fn drain_stream(reader: &mut (impl AsyncRead + Unpin)) -> Result<(), ()> {
     let mut drain_buf = [0; 8 * 1024];
     loop {
-        match reader.try_read(&mut drain_buf) {
-            Ok(0) => return Ok(()),
-            Err(err) if err.kind() == ErrorKind::WouldBlock => return Ok(()),
-            Ok(_) => continue,
-            Err(err) => {
+        match reader.read(&mut drain_buf).now_or_never() {
+            Some(Ok(0)) | None => return Ok(()),
+            Some(Ok(_)) => continue,
+            Some(Err(err)) => {
                 error!(target: "lazymc::lobby", "Failed to drain lobby client connection before relaying to real server. Maybe already disconnected? Error: {:?}", err);
                 return Ok(());
             }
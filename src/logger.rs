@@ -0,0 +1,242 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, OnceLock};
+
+use env_logger::filter::{Builder as FilterBuilder, Filter};
+use log::{Log, Metadata, Record};
+
+use crate::config::Log as LogConfig;
+
+/// Global logger instance.
+static LOGGER: OnceLock<Logger> = OnceLock::new();
+
+/// Log output formats.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum LogFormat {
+    /// Human readable, colored output.
+    Human,
+
+    /// Newline-delimited JSON objects.
+    Json,
+}
+
+impl std::str::FromStr for LogFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "human" => Ok(LogFormat::Human),
+            "json" => Ok(LogFormat::Json),
+            _ => Err(format!("Unknown log format: {}", s)),
+        }
+    }
+}
+
+/// Initialize logger with given format.
+///
+/// Log level filters are given as directives, such as `info,lazymc=debug`.
+pub fn init(format: LogFormat, filter: &str) {
+    let logger = LOGGER.get_or_init(|| Logger::new(format, filter));
+    log::set_max_level(logger.filter.filter());
+    log::set_logger(logger).expect("failed to initialize logger");
+}
+
+/// Configure log outputs from configuration.
+///
+/// Enables logging to file if configured. The path is resolved by the given function.
+pub fn configure(config: &LogConfig, resolve_path: impl FnOnce(&Path) -> PathBuf) {
+    let logger = match LOGGER.get() {
+        Some(logger) => logger,
+        None => return,
+    };
+
+    if let Some(path) = config.file.as_ref() {
+        let path = resolve_path(path);
+        match RotatingFile::open(path.clone(), config.max_size_mb, config.max_files) {
+            Ok(file) => {
+                logger.file.lock().unwrap().replace(file);
+            }
+            Err(err) => {
+                error!(target: "lazymc", "Failed to open log file at {}, not logging to file: {}", path.display(), err);
+            }
+        }
+    }
+
+    logger.console.store(config.console, Ordering::Relaxed);
+}
+
+/// Logger writing to console and optionally to a rotating log file.
+struct Logger {
+    /// Log format.
+    format: LogFormat,
+
+    /// Level filter.
+    filter: Filter,
+
+    /// Human readable console logger.
+    human: env_logger::Logger,
+
+    /// Whether to log to console.
+    console: AtomicBool,
+
+    /// Log file if enabled.
+    file: Mutex<Option<RotatingFile>>,
+}
+
+impl Logger {
+    /// Construct logger with filter directives.
+    fn new(format: LogFormat, filter: &str) -> Self {
+        Self {
+            format,
+            filter: FilterBuilder::new().parse(filter).build(),
+            human: pretty_env_logger::formatted_builder()
+                .parse_filters(filter)
+                .build(),
+            console: AtomicBool::new(true),
+            file: Mutex::new(None),
+        }
+    }
+}
+
+impl Log for Logger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        self.filter.enabled(metadata)
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.filter.matches(record) {
+            return;
+        }
+
+        // Log to console
+        if self.console.load(Ordering::Relaxed) {
+            match self.format {
+                LogFormat::Human => self.human.log(record),
+                LogFormat::Json => {
+                    let _ = writeln!(io::stderr().lock(), "{}", json_line(record));
+                }
+            }
+        }
+
+        // Log to file
+        if let Some(file) = self.file.lock().unwrap().as_mut() {
+            let line = match self.format {
+                LogFormat::Human => human_line(record),
+                LogFormat::Json => json_line(record),
+            };
+            if let Err(err) = file.write_line(&line) {
+                let _ = writeln!(io::stderr().lock(), "Failed to write to log file: {err}");
+            }
+        }
+    }
+
+    fn flush(&self) {
+        self.human.flush();
+        if let Some(file) = self.file.lock().unwrap().as_mut() {
+            let _ = file.file.flush();
+        }
+    }
+}
+
+/// Format record as JSON object.
+fn json_line(record: &Record) -> String {
+    serde_json::json!({
+        "timestamp": chrono::Utc::now().to_rfc3339(),
+        "level": record.level().as_str(),
+        "target": record.target(),
+        "message": record.args().to_string(),
+    })
+    .to_string()
+}
+
+/// Format record as plain human readable line.
+fn human_line(record: &Record) -> String {
+    format!(
+        "{} {:<5} {} > {}",
+        chrono::Local::now().format("%Y-%m-%d %H:%M:%S"),
+        record.level(),
+        record.target(),
+        record.args()
+    )
+}
+
+/// Log file, rotated when it exceeds its maximum size.
+struct RotatingFile {
+    /// Log file path.
+    path: PathBuf,
+
+    /// Maximum file size in bytes before rotating.
+    max_size: u64,
+
+    /// Maximum number of rotated files to keep.
+    max_files: usize,
+
+    /// Opened log file.
+    file: File,
+
+    /// Current log file size in bytes.
+    size: u64,
+}
+
+impl RotatingFile {
+    /// Open log file for appending.
+    fn open(path: PathBuf, max_size_mb: u64, max_files: usize) -> io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        let size = file.metadata()?.len();
+
+        Ok(Self {
+            path,
+            max_size: max_size_mb * 1024 * 1024,
+            max_files,
+            file,
+            size,
+        })
+    }
+
+    /// Write line to log file, rotating first if it would exceed the maximum size.
+    fn write_line(&mut self, line: &str) -> io::Result<()> {
+        let len = line.len() as u64 + 1;
+        if self.max_size > 0 && self.size > 0 && self.size + len > self.max_size {
+            self.rotate()?;
+        }
+
+        writeln!(self.file, "{line}")?;
+        self.size += len;
+
+        Ok(())
+    }
+
+    /// Rotate log files, `lazymc.log` becomes `lazymc.log.1`, `lazymc.log.1` becomes
+    /// `lazymc.log.2`, and so on. The oldest file is removed.
+    fn rotate(&mut self) -> io::Result<()> {
+        if self.max_files == 0 {
+            fs::remove_file(&self.path)?;
+        } else {
+            let _ = fs::remove_file(self.rotated_path(self.max_files));
+            for i in (1..self.max_files).rev() {
+                let from = self.rotated_path(i);
+                if from.is_file() {
+                    fs::rename(from, self.rotated_path(i + 1))?;
+                }
+            }
+            fs::rename(&self.path, self.rotated_path(1))?;
+        }
+
+        self.file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        self.size = 0;
+
+        Ok(())
+    }
+
+    /// Get path of rotated log file with given index.
+    fn rotated_path(&self, index: usize) -> PathBuf {
+        let mut path = self.path.clone().into_os_string();
+        path.push(format!(".{index}"));
+        path.into()
+    }
+}
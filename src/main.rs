@@ -7,29 +7,50 @@ extern crate derive_builder;
 #[macro_use]
 extern crate log;
 
+pub(crate) mod access;
 pub(crate) mod action;
+pub(crate) mod auth;
+pub(crate) mod balance;
 pub(crate) mod cli;
 pub(crate) mod config;
+pub(crate) mod control;
+pub(crate) mod filter;
 pub(crate) mod forge;
+pub(crate) mod forwarding;
+pub(crate) mod history;
+pub(crate) mod hooks;
 pub(crate) mod join;
 #[cfg(feature = "lobby")]
 pub(crate) mod lobby;
+pub(crate) mod logger;
 pub(crate) mod mc;
 pub(crate) mod monitor;
+pub(crate) mod motd;
 pub(crate) mod net;
+pub(crate) mod notify;
 pub(crate) mod os;
+pub(crate) mod packet_hook;
+pub(crate) mod pid_file;
 pub(crate) mod probe;
 pub(crate) mod proto;
 pub(crate) mod proxy;
 pub(crate) mod server;
 pub(crate) mod service;
+pub(crate) mod stats;
 pub(crate) mod status;
+pub(crate) mod stream;
+#[cfg(unix)]
+pub(crate) mod systemd;
+pub(crate) mod tls;
 pub(crate) mod types;
 pub(crate) mod util;
+pub(crate) mod vhost;
 
 use std::env;
 
-use clap::Command;
+use clap::ArgMatches;
+
+use crate::logger::LogFormat;
 
 // Compile time feature compatability check.
 #[cfg(all(windows, not(feature = "rcon")))]
@@ -40,16 +61,18 @@ const LOG_DEFAULT: &str = "info";
 
 /// Main entrypoint.
 fn main() -> Result<(), ()> {
+    // Build clap app, parse arguments
+    let matches = cli::app().get_matches();
+
     // Initialize logger
-    init_log();
+    init_log(&matches);
 
-    // Build clap app, invoke intended action
-    let app = cli::app();
-    invoke_action(app)
+    // Invoke intended action
+    invoke_action(matches)
 }
 
 /// Initialize logger.
-fn init_log() {
+fn init_log(matches: &ArgMatches) {
     // Load .env variables
     let _ = dotenv::dotenv();
 
@@ -59,12 +82,20 @@ fn init_log() {
     }
 
     // Initialize logger
-    pretty_env_logger::init();
+    let format = matches
+        .get_one::<String>("log-format")
+        .and_then(|f| f.parse().ok())
+        .unwrap_or(LogFormat::Human);
+    logger::init(format, &env::var("RUST_LOG").unwrap_or_default());
 }
 
 /// Invoke an action.
-fn invoke_action(app: Command) -> Result<(), ()> {
-    let matches = app.get_matches();
+fn invoke_action(matches: ArgMatches) -> Result<(), ()> {
+    // Dump effective config
+    if let Some(format) = matches.get_one::<String>("dump-config") {
+        action::dump_config::invoke(&matches, format);
+        return Ok(());
+    }
 
     // Config operations
     if let Some(matches) = matches.subcommand_matches("config") {
@@ -78,9 +109,68 @@ fn invoke_action(app: Command) -> Result<(), ()> {
             return Ok(());
         }
 
+        if let Some(matches) = matches.subcommand_matches("validate") {
+            action::config_validate::invoke(matches);
+            return Ok(());
+        }
+
+        if let Some(matches) = matches.subcommand_matches("migrate") {
+            action::config_migrate::invoke(matches);
+            return Ok(());
+        }
+
+        if matches.subcommand_matches("schema").is_some() {
+            action::config_schema::invoke();
+            return Ok(());
+        }
+
         unreachable!();
     }
 
+    // Show status of running instance
+    if let Some(matches) = matches.subcommand_matches("status") {
+        action::status::invoke(matches);
+        return Ok(());
+    }
+
+    // Wake or sleep server of running instance
+    if let Some(matches) = matches.subcommand_matches("wake") {
+        action::wake::invoke(matches);
+        return Ok(());
+    }
+    if let Some(matches) = matches.subcommand_matches("sleep") {
+        action::sleep::invoke(matches);
+        return Ok(());
+    }
+
+    // Manage banned IPs
+    if let Some(matches) = matches.subcommand_matches("ban") {
+        action::ban::invoke_ban(matches);
+        return Ok(());
+    }
+    if let Some(matches) = matches.subcommand_matches("unban") {
+        action::ban::invoke_unban(matches);
+        return Ok(());
+    }
+
+    // Show recorded stats
+    if let Some(matches) = matches.subcommand_matches("stats") {
+        action::stats::invoke(matches);
+        return Ok(());
+    }
+
+    // Control running instance
+    if let Some(matches) = matches.subcommand_matches("ctl") {
+        action::ctl::invoke(matches);
+        return Ok(());
+    }
+
+    // Reload running instance
+    if let Some(matches) = matches.subcommand_matches("reload") {
+        action::reload::invoke(matches);
+        return Ok(());
+    }
+
     // Start server
     action::start::invoke(&matches)
 }
@@ -3,9 +3,13 @@ use std::error::Error;
 use std::fs;
 use std::net::IpAddr;
 use std::path::Path;
+use std::time::{Duration, Instant, SystemTime};
 
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, Local, Utc};
 use serde::Deserialize;
+use serde_json::Value;
+
+use crate::util;
 
 /// File name.
 pub const FILE: &str = "banned-ips.json";
@@ -13,6 +17,15 @@ pub const FILE: &str = "banned-ips.json";
 /// The forever expiry literal.
 const EXPIRY_FOREVER: &str = "forever";
 
+/// Time format used in ban entries.
+const TIME_FORMAT: &str = "%Y-%m-%d %H:%M:%S %z";
+
+/// Source of bans added by lazymc.
+const BAN_SOURCE: &str = "lazymc";
+
+/// Minimum time between checking the banned IPs file for changes.
+const CACHE_TIME: Duration = Duration::from_secs(1);
+
 /// List of banned IPs.
 #[derive(Debug, Default)]
 pub struct BannedIps {
@@ -72,7 +85,7 @@ impl BannedIp {
         }
 
         // Parse expiry time, check if it has passed
-        let expiry = match DateTime::parse_from_str(expires, "%Y-%m-%d %H:%M:%S %z") {
+        let expiry = match DateTime::parse_from_str(expires, TIME_FORMAT) {
             Ok(expiry) => expiry,
             Err(err) => {
                 error!(target: "lazymc", "Failed to parse ban expiry '{}', assuming still banned: {}", expires, err);
@@ -97,3 +110,87 @@ pub fn load(path: &Path) -> Result<BannedIps, Box<dyn Error>> {
     let ips = ips.into_iter().map(|ip| (ip.ip, ip)).collect();
     Ok(BannedIps { ips })
 }
+
+/// Stat based cache of the banned IPs file.
+///
+/// Used to reload banned IPs once the file changed, checking it at most once every second.
+#[derive(Debug, Default)]
+pub struct BanFileCache {
+    /// Time the file was last checked.
+    checked: Option<Instant>,
+
+    /// Modification time of the file when last checked, `None` if it didn't exist.
+    modified: Option<SystemTime>,
+}
+
+impl BanFileCache {
+    /// Check whether the file changed since it was last checked.
+    ///
+    /// Always returns `false` if checked less than a second ago.
+    pub fn changed(&mut self, path: &Path) -> bool {
+        if self
+            .checked
+            .is_some_and(|checked| checked.elapsed() < CACHE_TIME)
+        {
+            return false;
+        }
+        let first = self.checked.replace(Instant::now()).is_none();
+
+        let modified = fs::metadata(path).and_then(|m| m.modified()).ok();
+        first || std::mem::replace(&mut self.modified, modified) != modified
+    }
+}
+
+/// Ban IP by adding it to the banned IPs file, replacing existing entries for it.
+///
+/// The file is created if it does not exist, and written atomically.
+pub fn ban(path: &Path, ip: IpAddr, reason: &str) -> Result<(), Box<dyn Error>> {
+    let mut entries = read_entries(path)?;
+    entries.retain(|entry| !entry_matches(entry, &ip));
+    entries.push(serde_json::json!({
+        "ip": ip.to_string(),
+        "created": Local::now().format(TIME_FORMAT).to_string(),
+        "source": BAN_SOURCE,
+        "expires": EXPIRY_FOREVER,
+        "reason": reason,
+    }));
+    write_entries(path, &entries)
+}
+
+/// Unban IP by removing it from the banned IPs file.
+///
+/// Returns the number of removed entries.
+pub fn unban(path: &Path, ip: IpAddr) -> Result<usize, Box<dyn Error>> {
+    let mut entries = read_entries(path)?;
+    let count = entries.len();
+    entries.retain(|entry| !entry_matches(entry, &ip));
+    let removed = count - entries.len();
+    if removed > 0 {
+        write_entries(path, &entries)?;
+    }
+    Ok(removed)
+}
+
+/// Read raw entries from banned IPs file, keeping unknown fields.
+///
+/// Returns no entries if the file does not exist.
+fn read_entries(path: &Path) -> Result<Vec<Value>, Box<dyn Error>> {
+    if !path.is_file() {
+        return Ok(vec![]);
+    }
+    Ok(serde_json::from_str(&fs::read_to_string(path)?)?)
+}
+
+/// Write raw entries to banned IPs file atomically.
+fn write_entries(path: &Path, entries: &[Value]) -> Result<(), Box<dyn Error>> {
+    util::fs::write_atomic(path, serde_json::to_string_pretty(entries)?)?;
+    Ok(())
+}
+
+/// Check whether raw entry is for the given IP.
+fn entry_matches(entry: &Value, ip: &IpAddr) -> bool {
+    entry["ip"]
+        .as_str()
+        .and_then(|entry| entry.parse::<IpAddr>().ok())
+        .is_some_and(|entry| entry == *ip)
+}
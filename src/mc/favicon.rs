@@ -5,6 +5,17 @@ use crate::proto::client::ClientInfo;
 /// Protocol version since when favicons are supported.
 const FAVICON_PROTOCOL_VERSION: u32 = 4;
 
+/// Required favicon width and height in pixels.
+const FAVICON_SIZE: u32 = 64;
+
+/// Maximum favicon file size in bytes.
+///
+/// The encoded favicon must fit in the status response, which is limited to 32767 characters.
+const FAVICON_MAX_BYTES: usize = 24 * 1024;
+
+/// PNG file signature.
+const PNG_SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+
 /// Get default server status favicon.
 pub fn default_favicon() -> String {
     encode_favicon(include_bytes!("../../res/unknown_server_optimized.png"))
@@ -30,3 +41,31 @@ pub fn supports_favicon(client_info: &ClientInfo) -> bool {
         .map(|p| p >= FAVICON_PROTOCOL_VERSION)
         .unwrap_or(true)
 }
+
+/// Validate favicon data.
+///
+/// Checks whether the data is a 64x64 PNG image small enough to fit in a status response.
+/// Returns a description of the problem if invalid.
+pub fn validate_favicon(data: &[u8]) -> Result<(), String> {
+    if data.len() > FAVICON_MAX_BYTES {
+        return Err(format!(
+            "file is {} bytes, must be at most {} bytes",
+            data.len(),
+            FAVICON_MAX_BYTES
+        ));
+    }
+
+    // PNG signature followed by IHDR chunk holding width and height
+    if data.len() < 24 || !data.starts_with(PNG_SIGNATURE) || &data[12..16] != b"IHDR" {
+        return Err("file is not a PNG image".into());
+    }
+    let width = u32::from_be_bytes(data[16..20].try_into().unwrap());
+    let height = u32::from_be_bytes(data[20..24].try_into().unwrap());
+    if width != FAVICON_SIZE || height != FAVICON_SIZE {
+        return Err(format!(
+            "image is {width}x{height}, must be {FAVICON_SIZE}x{FAVICON_SIZE}"
+        ));
+    }
+
+    Ok(())
+}
@@ -5,7 +5,6 @@ pub mod favicon;
 #[cfg(feature = "rcon")]
 pub mod rcon;
 pub mod server_properties;
-#[cfg(feature = "lobby")]
 pub mod uuid;
 pub mod whitelist;
 
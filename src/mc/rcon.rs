@@ -1,11 +1,21 @@
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+use std::sync::Arc;
 use std::time::Duration;
 
 use rust_rcon::{Connection, Error as RconError};
-use tokio::io::AsyncWriteExt;
+use tokio::io::{AsyncRead, AsyncWrite, AsyncWriteExt};
 use tokio::net::TcpStream;
+use tokio::sync::Mutex;
 use tokio::time;
+use tokio_rustls::rustls::{
+    Certificate, ClientConfig, OwnedTrustAnchor, RootCertStore, ServerName,
+};
+use tokio_rustls::TlsConnector;
 
-use crate::config::Config;
+use crate::config::{Config, ProxyVersion};
 use crate::proxy;
 
 /// Minecraft RCON quirk.
@@ -15,9 +25,17 @@ use crate::proxy;
 /// improve reliability.
 const QUIRK_RCON_GRACE_TIME: Duration = Duration::from_millis(200);
 
+/// RCON transport stream, plain TCP or TLS.
+trait RconStream: AsyncRead + AsyncWrite + Unpin + Send {}
+
+impl<T: AsyncRead + AsyncWrite + Unpin + Send> RconStream for T {}
+
 /// An RCON client.
 pub struct Rcon {
-    con: Connection<TcpStream>,
+    con: Connection<Box<dyn RconStream>>,
+
+    /// Whether this connection was taken from the pool and has not run a command since.
+    pooled: bool,
 }
 
 impl Rcon {
@@ -33,16 +51,35 @@ impl Rcon {
         // Add proxy header
         if config.rcon.send_proxy_v2 {
             trace!(target: "lazymc::rcon", "Sending local proxy header for RCON connection");
-            stream.write_all(&proxy::local_proxy_header()?).await?;
+            stream
+                .write_all(&proxy::local_proxy_header(ProxyVersion::V2)?)
+                .await?;
         }
 
+        // Wrap in TLS if enabled
+        let stream: Box<dyn RconStream> = if config.rcon.tls {
+            let server_name = match &config.rcon.tls_server_name {
+                Some(name) => ServerName::try_from(name.as_str())
+                    .map_err(|_| format!("invalid rcon.tls_server_name '{name}'"))?,
+                None => ServerName::IpAddress(stream.peer_addr()?.ip()),
+            };
+            let ca_cert = config
+                .rcon
+                .tls_ca_cert
+                .as_ref()
+                .map(|path| config.resolve_path(path));
+            Box::new(tls_connect(stream, server_name, ca_cert.as_deref()).await?)
+        } else {
+            Box::new(stream)
+        };
+
         // Start connection
         let con = Connection::builder()
             .enable_minecraft_quirks(true)
             .handshake(stream, pass)
             .await?;
 
-        Ok(Self { con })
+        Ok(Self { con, pooled: false })
     }
 
     /// Connect to a host from the given configuration.
@@ -71,3 +108,114 @@ impl Rcon {
         time::sleep(QUIRK_RCON_GRACE_TIME).await;
     }
 }
+
+/// Wrap stream in TLS, verifying the server certificate for the given name.
+///
+/// The certificate is verified against the given CA certificate if set, or the web PKI roots
+/// otherwise.
+async fn tls_connect(
+    stream: TcpStream,
+    server_name: ServerName,
+    ca_cert: Option<&Path>,
+) -> Result<tokio_rustls::client::TlsStream<TcpStream>, Box<dyn std::error::Error>> {
+    let mut roots = RootCertStore::empty();
+    match ca_cert {
+        Some(path) => {
+            let certs = rustls_pemfile::certs(&mut BufReader::new(File::open(path)?))?;
+            for cert in certs {
+                roots.add(&Certificate(cert))?;
+            }
+        }
+        None => roots.add_trust_anchors(webpki_roots::TLS_SERVER_ROOTS.iter().map(|ta| {
+            OwnedTrustAnchor::from_subject_spki_name_constraints(
+                ta.subject,
+                ta.spki,
+                ta.name_constraints,
+            )
+        })),
+    }
+
+    let tls_config = ClientConfig::builder()
+        .with_safe_defaults()
+        .with_root_certificates(roots)
+        .with_no_client_auth();
+
+    trace!(target: "lazymc::rcon", "Starting TLS for RCON connection");
+    Ok(TlsConnector::from(Arc::new(tls_config))
+        .connect(server_name, stream)
+        .await?)
+}
+
+/// A pool of idle RCON connections to reuse.
+#[derive(Default)]
+pub struct RconPool {
+    idle: Mutex<VecDeque<Rcon>>,
+}
+
+impl RconPool {
+    /// Get an idle connection from the pool, or connect a new one from the given configuration.
+    pub async fn get(&self, config: &Config) -> Result<Rcon, Box<dyn std::error::Error>> {
+        if let Some(mut rcon) = self.idle.lock().await.pop_front() {
+            trace!(target: "lazymc::rcon", "Reusing pooled RCON connection");
+            rcon.pooled = true;
+            return Ok(rcon);
+        }
+
+        Self::connect(config).await
+    }
+
+    /// Connect a new connection from the given configuration, with timeout.
+    async fn connect(config: &Config) -> Result<Rcon, Box<dyn std::error::Error>> {
+        let timeout = Duration::from_millis(config.rcon.connection_timeout_ms as u64);
+        time::timeout(timeout, Rcon::connect_config(config))
+            .await
+            .map_err(|_| "RCON connection timed out")?
+    }
+
+    /// Send command over the given connection.
+    ///
+    /// The server may have closed a pooled connection while it was idle. If the first command on
+    /// a pooled connection fails, it is retried once on a new connection, which replaces it.
+    pub async fn cmd(
+        &self,
+        config: &Config,
+        rcon: &mut Rcon,
+        cmd: &str,
+    ) -> Result<String, Box<dyn std::error::Error>> {
+        let result = match rcon.cmd(cmd).await {
+            Err(err) if rcon.pooled => {
+                debug!(target: "lazymc::rcon", "Pooled RCON connection failed, retrying with new connection: {}", err);
+                *rcon = Self::connect(config).await?;
+                rcon.cmd(cmd).await
+            }
+            result => result,
+        };
+        rcon.pooled = false;
+        Ok(result?)
+    }
+
+    /// Return connection to the pool, it is closed if the pool is full.
+    pub async fn put(&self, config: &Config, rcon: Rcon) {
+        let mut idle = self.idle.lock().await;
+        if idle.len() < config.rcon.pool_size as usize {
+            idle.push_back(rcon);
+            return;
+        }
+        drop(idle);
+
+        rcon.close().await;
+    }
+
+    /// Drop all pooled connections.
+    ///
+    /// Should be called when the server stops, connections are not usable afterwards.
+    pub async fn clear(&self) {
+        self.idle.lock().await.clear();
+    }
+}
+
+impl std::fmt::Debug for RconPool {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RconPool").finish_non_exhaustive()
+    }
+}
@@ -1,6 +1,9 @@
 use std::collections::HashMap;
 use std::fs;
 use std::path::Path;
+use std::time::SystemTime;
+
+use crate::util;
 
 /// File name.
 pub const FILE: &str = "server.properties";
@@ -73,8 +76,8 @@ pub fn rewrite_file<P: AsRef<Path>>(file: P, changes: HashMap<&str, String>) {
         }
     };
 
-    // Write changes
-    match fs::write(file, contents) {
+    // Write changes atomically, through temporary file in same directory
+    match util::fs::write_atomic(file.as_ref(), contents) {
         Ok(_) => {
             info!(target: "lazymc",
                 "Rewritten {} file with updated values",
@@ -179,3 +182,28 @@ pub fn read_property<P: AsRef<Path>>(file: P, property: &str) -> Option<String>
         .find(|(p, _)| p.trim().to_lowercase() == property.to_lowercase())
         .map(|(_, v)| v.trim().to_string())
 }
+
+/// Cached `max-players` property, read again once the file is modified.
+#[derive(Debug, Default)]
+pub struct MaxPlayersCache {
+    /// Modification time of the file when last read.
+    modified: Option<SystemTime>,
+
+    /// Property value when last read.
+    max_players: Option<u32>,
+}
+
+impl MaxPlayersCache {
+    /// Get `max-players` property from the given server.properties file.
+    ///
+    /// Returns `None` if the file does not exist or does not contain a valid value.
+    pub fn get(&mut self, file: &Path) -> Option<u32> {
+        let modified = fs::metadata(file).and_then(|m| m.modified()).ok()?;
+        if self.modified != Some(modified) {
+            self.modified = Some(modified);
+            self.max_players =
+                read_property(file, "max-players").and_then(|value| value.parse().ok());
+        }
+        self.max_players
+    }
+}
@@ -82,6 +82,15 @@ pub fn load_dir(path: &Path) -> Result<Whitelist, Box<dyn Error>> {
     Ok(Whitelist { whitelist, ops })
 }
 
+/// Check whether user is OP, by the OPs file in directory.
+///
+/// Returns `false` if the OPs file can't be loaded.
+pub fn is_op(path: &Path, username: &str) -> bool {
+    load_ops(&path.join(OPS_FILE))
+        .map(|ops| ops.iter().any(|op| op.eq_ignore_ascii_case(username)))
+        .unwrap_or(false)
+}
+
 /// Load whitelist from file.
 fn load_whitelist(path: &Path) -> Result<Vec<String>, Box<dyn Error>> {
     // Load file contents
@@ -9,19 +9,27 @@ use minecraft_protocol::version::v1_20_3::status::{
     PingRequest, PingResponse, ServerStatus, StatusRequest, StatusResponse,
 };
 use rand::Rng;
+use serde_json::Value;
 use tokio::io::AsyncWriteExt;
 use tokio::net::TcpStream;
 use tokio::time;
 
-use crate::config::Config;
+use crate::action::start::rewrite_server_properties;
+use crate::config::{Config, LiveConfig};
+use crate::forge;
+use crate::probe;
 use crate::proto::client::{Client, ClientState};
 use crate::proto::{packet, packets};
 use crate::proxy;
 use crate::server::{Server, State};
+use crate::types;
 
 /// Monitor ping inverval in seconds.
 const MONITOR_POLL_INTERVAL: Duration = Duration::from_secs(2);
 
+/// Monitor ping interval while a frozen server is resuming.
+const RESUMING_POLL_INTERVAL: Duration = Duration::from_millis(250);
+
 /// Status request timeout in seconds.
 const STATUS_TIMEOUT: u64 = 20;
 
@@ -29,21 +37,60 @@ const STATUS_TIMEOUT: u64 = 20;
 const PING_TIMEOUT: u64 = 10;
 
 /// Monitor server.
-pub async fn monitor_server(config: Arc<Config>, server: Arc<Server>) {
-    // Server address
-    let addr = config.server.address;
-
-    let mut poll_interval = time::interval(MONITOR_POLL_INTERVAL);
+pub async fn monitor_server(live_config: Arc<LiveConfig>, server: Arc<Server>) {
+    let mut delay = Duration::ZERO;
+    let mut starting_interval: Option<Duration> = None;
+    let mut orphan_warned = false;
 
     loop {
-        poll_interval.tick().await;
+        time::sleep(delay).await;
+
+        // Apply reloaded server configuration once the server process is gone
+        if server.state().is_stopped() && !server.has_process().await && live_config.apply_pending()
+        {
+            info!(target: "lazymc::config", "Applied reloaded server configuration");
+            rewrite_server_properties(&live_config.get());
+        }
+
+        // Use latest configuration and server address
+        let config = live_config.get();
+        let addr = config.server.address;
 
         // Poll server state and update internal status
         trace!(target: "lazymc::monitor", "Fetching status for {} ... ", addr);
         let status = poll_server(&config, &server, addr).await;
+
+        // Back off polling while starting server doesn't respond yet
+        starting_interval = match starting_interval {
+            _ if status.is_ok() || server.state() != State::Starting => None,
+            Some(interval) => Some(config.advanced.poll.next_interval(interval)),
+            None => Some(config.advanced.poll.initial_interval()),
+        };
+        delay = match server.state() {
+            // Resumed server was initialized before, it is ready quickly
+            State::Resuming => RESUMING_POLL_INTERVAL,
+            _ => starting_interval.unwrap_or(MONITOR_POLL_INTERVAL),
+        };
+
         match status {
+            // Got status, but port is held by an orphaned process, don't mark online
+            Ok(Some(_))
+                if config.server.orphan_port_check
+                    && server.state() != State::Started
+                    && !server.owns_port(addr.port()).await =>
+            {
+                if !orphan_warned {
+                    warn!(target: "lazymc::monitor", "Server port {} is held by a process lazymc didn't start, not marking server online", addr.port());
+                    orphan_warned = true;
+                }
+            }
+
             // Got status, update
-            Ok(Some(status)) => server.update_status(&config, Some(status)).await,
+            Ok(Some(status)) => {
+                orphan_warned = false;
+                server.update_status(&config, Some(status)).await;
+                forge::detect(&config, &server).await;
+            }
 
             // Error, reset status
             Err(_) => server.update_status(&config, None).await,
@@ -60,9 +107,21 @@ pub async fn monitor_server(config: Arc<Config>, server: Arc<Server>) {
             server.stop(&config).await;
         }
 
+        // Send stop signal if stop command timed out
+        #[cfg(unix)]
+        if server.should_terminate().await {
+            warn!(target: "lazymc::monitor", "Server didn't stop through stop command in time, sending stop signal");
+            if !server.terminate().await {
+                warn!(target: "lazymc", "Failed to send stop signal to server");
+            }
+        }
+
         // Check whether we should force kill server
         if server.should_kill().await {
             error!(target: "lazymc::monitor", "Force killing server, took too long to start or stop");
+            if server.state().is_starting() {
+                server.set_start_timed_out();
+            }
             if !server.force_kill().await {
                 warn!(target: "lazymc", "Failed to force kill server");
             }
@@ -80,71 +139,106 @@ pub async fn poll_server(
     addr: SocketAddr,
 ) -> Result<Option<ServerStatus>, ()> {
     // Fetch status
-    if let Ok(status) = fetch_status(config, addr).await {
+    let proxy_header = probe::send_proxy_v2(config, server);
+    if let Ok(status) = fetch_status(config, server, addr, proxy_header).await {
         return Ok(Some(status));
     }
 
     // Try ping fallback if server is currently started
     if server.state() == State::Started {
         debug!(target: "lazymc::monitor", "Failed to get status from started server, trying ping...");
-        do_ping(config, addr).await?;
+        do_ping(config, server, addr, proxy_header).await?;
     }
 
     Err(())
 }
 
 /// Attemp to fetch status from server.
-async fn fetch_status(config: &Config, addr: SocketAddr) -> Result<ServerStatus, ()> {
-    let mut stream = TcpStream::connect(addr).await.map_err(|_| ())?;
-
-    // Add proxy header
-    if config.server.send_proxy_v2 {
-        trace!(target: "lazymc::monitor", "Sending local proxy header for server connection");
-        stream
-            .write_all(&proxy::local_proxy_header().map_err(|_| ())?)
-            .await
-            .map_err(|_| ())?;
-    }
+///
+/// Sends a local proxy header first if `proxy_header` is set.
+pub async fn fetch_status(
+    config: &Config,
+    server: &Server,
+    addr: SocketAddr,
+    proxy_header: bool,
+) -> Result<ServerStatus, ()> {
+    let mut stream = connect(config, addr, proxy_header).await?;
 
     // Dummy client
     let client = Client::dummy();
 
-    send_handshake(&client, &mut stream, config, addr).await?;
+    send_handshake(&client, &mut stream, config, server, addr).await?;
     request_status(&client, &mut stream).await?;
     wait_for_status_timeout(&client, &mut stream).await
 }
 
-/// Attemp to ping server.
-async fn do_ping(config: &Config, addr: SocketAddr) -> Result<(), ()> {
-    let mut stream = TcpStream::connect(addr).await.map_err(|_| ())?;
+/// Attemp to fetch raw status JSON from server.
+///
+/// Includes fields not part of the regular server status, such as Forge data.
+pub async fn fetch_status_json(
+    config: &Config,
+    server: &Server,
+    addr: SocketAddr,
+    proxy_header: bool,
+) -> Result<Value, ()> {
+    let mut stream = connect(config, addr, proxy_header).await?;
 
-    // Add proxy header
-    if config.server.send_proxy_v2 {
-        trace!(target: "lazymc::monitor", "Sending local proxy header for server connection");
-        stream
-            .write_all(&proxy::local_proxy_header().map_err(|_| ())?)
-            .await
-            .map_err(|_| ())?;
-    }
+    // Dummy client
+    let client = Client::dummy();
+
+    send_handshake(&client, &mut stream, config, server, addr).await?;
+    request_status(&client, &mut stream).await?;
+    let status = wait_for_status_json(&client, &mut stream);
+    tokio::time::timeout(Duration::from_secs(STATUS_TIMEOUT), status)
+        .await
+        .map_err(|_| ())?
+}
+
+/// Attemp to ping server.
+async fn do_ping(
+    config: &Config,
+    server: &Server,
+    addr: SocketAddr,
+    proxy_header: bool,
+) -> Result<(), ()> {
+    let mut stream = connect(config, addr, proxy_header).await?;
 
     // Dummy client
     let client = Client::dummy();
 
-    send_handshake(&client, &mut stream, config, addr).await?;
+    send_handshake(&client, &mut stream, config, server, addr).await?;
     let token = send_ping(&client, &mut stream).await?;
     wait_for_ping_timeout(&client, &mut stream, token).await
 }
 
+/// Connect to server.
+///
+/// Sends a local proxy header first if `proxy_header` is set.
+async fn connect(config: &Config, addr: SocketAddr, proxy_header: bool) -> Result<TcpStream, ()> {
+    let mut stream = TcpStream::connect(addr).await.map_err(|_| ())?;
+
+    // Add proxy header
+    if proxy_header {
+        trace!(target: "lazymc::monitor", "Sending local proxy header for server connection");
+        let header =
+            proxy::local_proxy_header(config.server.proxy_protocol_version).map_err(|_| ())?;
+        stream.write_all(&header).await.map_err(|_| ())?;
+    }
+
+    Ok(stream)
+}
+
 /// Send handshake.
 async fn send_handshake(
     client: &Client,
     stream: &mut TcpStream,
     config: &Config,
+    server: &Server,
     addr: SocketAddr,
 ) -> Result<(), ()> {
     packet::write_packet(
         Handshake {
-            protocol_version: config.public.protocol as i32,
+            protocol_version: probe::protocol(config, server) as i32,
             server_addr: addr.ip().to_string(),
             server_port: addr.port(),
             next_state: ClientState::Status.to_id(),
@@ -192,6 +286,32 @@ async fn wait_for_status(client: &Client, stream: &mut TcpStream) -> Result<Serv
     Err(())
 }
 
+/// Wait for a status response, as raw JSON.
+async fn wait_for_status_json(client: &Client, stream: &mut TcpStream) -> Result<Value, ()> {
+    // Get stream reader, set up buffer
+    let (mut reader, mut _writer) = stream.split();
+    let mut buf = BytesMut::new();
+
+    loop {
+        // Read packet from stream
+        let (packet, _raw) = match packet::read_packet(client, &mut buf, &mut reader).await {
+            Ok(Some(packet)) => packet,
+            Ok(None) => break,
+            Err(_) => continue,
+        };
+
+        // Catch status response, a single JSON string
+        if packet.id == packets::status::CLIENT_STATUS {
+            let (read, len) = types::read_var_int(&packet.data)?;
+            let json = packet.data.get(read..read + len as usize).ok_or(())?;
+            return serde_json::from_slice(json).map_err(|_| ());
+        }
+    }
+
+    // Some error occurred
+    Err(())
+}
+
 /// Wait for a status response.
 async fn wait_for_status_timeout(
     client: &Client,
@@ -0,0 +1,111 @@
+use std::time::Duration;
+
+/// Values available to MOTD placeholders.
+#[derive(Debug, Clone, Default)]
+pub struct MotdContext {
+    /// Number of online players.
+    pub players_online: u32,
+
+    /// Maximum number of players.
+    pub players_max: u32,
+
+    /// Server version name.
+    pub server_version: String,
+
+    /// Time the server has been online for.
+    pub uptime: Duration,
+
+    /// Number of times the server was woken.
+    pub wake_count: u64,
+}
+
+/// Render MOTD template, replacing placeholders with values from the given context.
+///
+/// Supports `{players_online}`, `{players_max}`, `{server_version}`, `{uptime}` and
+/// `{wake_count}`. Templates without placeholders are returned as-is.
+pub fn render(template: &str, ctx: &MotdContext) -> String {
+    if !template.contains('{') {
+        return template.to_string();
+    }
+
+    template
+        .replace("{players_online}", &ctx.players_online.to_string())
+        .replace("{players_max}", &ctx.players_max.to_string())
+        .replace("{server_version}", &ctx.server_version)
+        .replace("{uptime}", &format_duration(ctx.uptime))
+        .replace("{wake_count}", &ctx.wake_count.to_string())
+}
+
+/// Format duration in human readable form, such as `1h 2m 3s`.
+pub fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    let (hours, minutes, seconds) = (secs / 3600, secs / 60 % 60, secs % 60);
+
+    if hours > 0 {
+        format!("{hours}h {minutes}m {seconds}s")
+    } else if minutes > 0 {
+        format!("{minutes}m {seconds}s")
+    } else {
+        format!("{seconds}s")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn context() -> MotdContext {
+        MotdContext {
+            players_online: 3,
+            players_max: 20,
+            server_version: "1.20.4".into(),
+            uptime: Duration::from_secs(3725),
+            wake_count: 7,
+        }
+    }
+
+    #[test]
+    fn render_players_online() {
+        assert_eq!(render("{players_online} online", &context()), "3 online");
+    }
+
+    #[test]
+    fn render_players_max() {
+        assert_eq!(render("max {players_max}", &context()), "max 20");
+    }
+
+    #[test]
+    fn render_server_version() {
+        assert_eq!(render("v{server_version}", &context()), "v1.20.4");
+    }
+
+    #[test]
+    fn render_uptime() {
+        assert_eq!(render("up {uptime}", &context()), "up 1h 2m 5s");
+    }
+
+    #[test]
+    fn render_wake_count() {
+        assert_eq!(
+            render("woken {wake_count} times", &context()),
+            "woken 7 times"
+        );
+    }
+
+    #[test]
+    fn render_unknown_placeholder() {
+        assert_eq!(
+            render("{players_online} {unknown}", &context()),
+            "3 {unknown}"
+        );
+        assert_eq!(render("no placeholders", &context()), "no placeholders");
+    }
+
+    #[test]
+    fn format_duration_units() {
+        assert_eq!(format_duration(Duration::from_secs(0)), "0s");
+        assert_eq!(format_duration(Duration::from_secs(59)), "59s");
+        assert_eq!(format_duration(Duration::from_secs(61)), "1m 1s");
+        assert_eq!(format_duration(Duration::from_secs(7200)), "2h 0m 0s");
+    }
+}
@@ -1,22 +1,167 @@
 use std::error::Error;
 use std::io;
-use tokio::io::AsyncWriteExt;
-use tokio::net::TcpStream;
+use std::net::{AddrParseError, IpAddr, SocketAddr};
+use std::time::Duration;
 
-/// Gracefully close given TCP stream.
+use ipnet::IpNet;
+use socket2::{Domain, Protocol, SockRef, Socket, TcpKeepalive, Type};
+use tokio::io::{AsyncWrite, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+use crate::config::TcpSettings;
+
+/// Maximum number of pending connections on listening socket.
+const LISTEN_BACKLOG: i32 = 1024;
+
+/// Bind TCP listener to given address, restricted to the given network interface if set.
+///
+/// Binding to an interface is only supported on Linux, a warning is shown and the interface is
+/// ignored on other platforms.
+///
+/// IPv6 listeners also accept IPv4 connections where supported, so `[::]` listens on all
+/// interfaces for both.
+pub fn bind_listener(addr: SocketAddr, interface: Option<&str>) -> io::Result<TcpListener> {
+    let socket = Socket::new(Domain::for_address(addr), Type::STREAM, Some(Protocol::TCP))?;
+    #[cfg(unix)]
+    socket.set_reuse_address(true)?;
+
+    if addr.is_ipv6() {
+        if let Err(err) = socket.set_only_v6(false) {
+            debug!(target: "lazymc", "Failed to make listener on {} dual-stack, only accepting IPv6: {}", addr, err);
+        }
+    }
+
+    if let Some(interface) = interface {
+        #[cfg(target_os = "linux")]
+        socket.bind_device(Some(interface.as_bytes()))?;
+        #[cfg(not(target_os = "linux"))]
+        warn!(target: "lazymc", "Binding to network interface '{}' is not supported on this platform, listening on all interfaces", interface);
+    }
+
+    socket.set_nonblocking(true)?;
+    socket.bind(&addr.into())?;
+    socket.listen(LISTEN_BACKLOG)?;
+    TcpListener::from_std(socket.into())
+}
+
+/// Parse network in CIDR notation, plain IP addresses are parsed as single address network.
+pub fn parse_ipnet(s: &str) -> Result<IpNet, AddrParseError> {
+    s.parse::<IpNet>()
+        .or_else(|_| s.parse::<IpAddr>().map(IpNet::from))
+}
+
+/// Map IPv4-mapped IPv6 addresses to IPv4.
+///
+/// Connections to dual-stack listeners report IPv4 peers as `::ffff:a.b.c.d`.
+pub fn canonical_ip(ip: IpAddr) -> IpAddr {
+    match ip {
+        IpAddr::V6(ip) => ip
+            .to_ipv4_mapped()
+            .map(IpAddr::V4)
+            .unwrap_or(IpAddr::V6(ip)),
+        ip => ip,
+    }
+}
+
+/// Map IPv4-mapped IPv6 socket addresses to IPv4.
+pub fn canonical_addr(addr: SocketAddr) -> SocketAddr {
+    SocketAddr::new(canonical_ip(addr.ip()), addr.port())
+}
+
+/// Apply TCP settings to given TCP stream.
+///
+/// Failing to apply a setting is logged and does not fail the connection.
+pub fn configure_tcp_stream(stream: &TcpStream, settings: &TcpSettings) {
+    let socket = SockRef::from(stream);
+    let mut result = socket.set_nodelay(settings.nodelay);
+    if let Some(secs) = settings.keepalive_secs {
+        let keepalive = TcpKeepalive::new().with_time(Duration::from_secs(secs as u64));
+        result = result.and(socket.set_tcp_keepalive(&keepalive));
+    }
+    if let Some(size) = settings.send_buffer_size {
+        result = result.and(socket.set_send_buffer_size(size as usize));
+    }
+    if let Some(size) = settings.recv_buffer_size {
+        result = result.and(socket.set_recv_buffer_size(size as usize));
+    }
+    if let Err(err) = result {
+        debug!(target: "lazymc", "Failed to apply TCP settings to connection: {}", err);
+    }
+}
+
+/// Gracefully close given TCP or client stream.
 ///
 /// Intended as helper to make code less messy. This also succeeds if already closed.
-pub async fn close_tcp_stream(mut stream: TcpStream) -> Result<(), Box<dyn Error>> {
+pub async fn close_tcp_stream(mut stream: impl AsyncWrite + Unpin) -> Result<(), Box<dyn Error>> {
     close_tcp_stream_ref(&mut stream).await
 }
 
-/// Gracefully close given TCP stream.
+/// Gracefully close given TCP or client stream.
 ///
 /// Intended as helper to make code less messy. This also succeeds if already closed.
-pub async fn close_tcp_stream_ref(stream: &mut TcpStream) -> Result<(), Box<dyn Error>> {
+pub async fn close_tcp_stream_ref(
+    stream: &mut (impl AsyncWrite + Unpin),
+) -> Result<(), Box<dyn Error>> {
     match stream.shutdown().await {
         Ok(_) => Ok(()),
         Err(err) if err.kind() == io::ErrorKind::NotConnected => Ok(()),
         Err(err) => Err(err.into()),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::net::{Ipv4Addr, Ipv6Addr};
+
+    use super::*;
+
+    #[test]
+    fn parse_ipnet_plain_ip() {
+        assert_eq!(
+            parse_ipnet("10.0.0.1").unwrap(),
+            "10.0.0.1/32".parse::<IpNet>().unwrap()
+        );
+        assert_eq!(
+            parse_ipnet("::1").unwrap(),
+            "::1/128".parse::<IpNet>().unwrap()
+        );
+        assert_eq!(
+            parse_ipnet("10.0.0.0/8").unwrap(),
+            "10.0.0.0/8".parse::<IpNet>().unwrap()
+        );
+        assert!(parse_ipnet("10.0.0.0/33").is_err());
+        assert!(parse_ipnet("example.com").is_err());
+    }
+
+    #[test]
+    fn canonical_ip_maps_ipv4() {
+        let mapped: IpAddr = "::ffff:1.2.3.4".parse().unwrap();
+        assert_eq!(canonical_ip(mapped), IpAddr::V4(Ipv4Addr::new(1, 2, 3, 4)));
+        assert_eq!(
+            canonical_addr("[::ffff:1.2.3.4]:25565".parse().unwrap()),
+            "1.2.3.4:25565".parse().unwrap()
+        );
+    }
+
+    #[test]
+    fn canonical_ip_keeps_ipv6() {
+        assert_eq!(
+            canonical_ip(IpAddr::V6(Ipv6Addr::LOCALHOST)),
+            IpAddr::V6(Ipv6Addr::LOCALHOST)
+        );
+        let addr: SocketAddr = "[::1]:25566".parse().unwrap();
+        assert_eq!(canonical_addr(addr), addr);
+    }
+
+    #[tokio::test]
+    #[ignore = "requires IPv6 loopback"]
+    async fn bind_listener_ipv6() {
+        let listener = bind_listener("[::1]:0".parse().unwrap(), None).unwrap();
+        let addr = listener.local_addr().unwrap();
+        assert!(addr.is_ipv6());
+
+        let stream = TcpStream::connect(addr).await.unwrap();
+        let (_, peer) = listener.accept().await.unwrap();
+        assert_eq!(canonical_addr(peer), stream.local_addr().unwrap());
+    }
+}
@@ -0,0 +1,119 @@
+use std::time::Duration;
+
+use reqwest::{Response, StatusCode};
+use serde_json::{json, Value};
+use tokio::time;
+
+use crate::config::{NotifyDiscord, ServerEvent};
+use crate::motd::format_duration;
+
+use super::EventContext;
+
+/// Maximum time to wait before retrying a rate limited request.
+const MAX_RETRY_AFTER: Duration = Duration::from_secs(60);
+
+/// Post Discord embed for event.
+///
+/// Retries once if rate limited.
+pub async fn send(discord: NotifyDiscord, ctx: EventContext) {
+    let body = message(&discord, &ctx);
+
+    for attempt in 0..2 {
+        let response = match super::client()
+            .post(&discord.webhook_url)
+            .json(&body)
+            .send()
+            .await
+        {
+            Ok(response) => response,
+            Err(err) => {
+                warn!(target: "lazymc::notify", "Failed to post Discord notification: {}", err);
+                return;
+            }
+        };
+
+        match response.status() {
+            status if status.is_success() => return,
+            StatusCode::TOO_MANY_REQUESTS if attempt == 0 => {
+                let retry_after = retry_after(response).await;
+                debug!(target: "lazymc::notify", "Discord rate limited notification, retrying in {}ms", retry_after.as_millis());
+                time::sleep(retry_after).await;
+            }
+            status => {
+                warn!(target: "lazymc::notify", "Discord responded to notification with {}", status);
+                return;
+            }
+        }
+    }
+}
+
+/// Build Discord webhook message with embed for event.
+fn message(discord: &NotifyDiscord, ctx: &EventContext) -> Value {
+    let (title, color) = match ctx.event {
+        ServerEvent::Sleeping => ("Server is sleeping", 0x95a5a6),
+        ServerEvent::Starting => ("Server is starting", 0xf1c40f),
+        ServerEvent::Online => ("Server is online", 0x2ecc71),
+        ServerEvent::Stopping => ("Server is stopping", 0x95a5a6),
+        ServerEvent::CrashLoop => ("Server crashed repeatedly", 0xe74c3c),
+    };
+
+    let mut fields = vec![json!({
+        "name": "Players",
+        "value": format!("{}/{}", ctx.players, ctx.players_max),
+        "inline": true,
+    })];
+    if let Some(uptime) = ctx.uptime {
+        fields.push(json!({
+            "name": "Uptime",
+            "value": format_duration(uptime),
+            "inline": true,
+        }));
+    }
+    if let (ServerEvent::Online, Some(start)) = (ctx.event, ctx.start_duration) {
+        fields.push(json!({
+            "name": "Time to start",
+            "value": format_duration(start),
+            "inline": true,
+        }));
+    }
+
+    let mut message = json!({
+        "embeds": [{
+            "title": title,
+            "color": color,
+            "fields": fields,
+            "timestamp": ctx.timestamp.to_rfc3339(),
+        }],
+    });
+    if let Some(username) = discord.username.as_ref() {
+        message["username"] = json!(username);
+    }
+    if let Some(avatar_url) = discord.avatar_url.as_ref() {
+        message["avatar_url"] = json!(avatar_url);
+    }
+    message
+}
+
+/// Get time to wait from rate limited response.
+///
+/// Uses the `Retry-After` header in seconds, or the `retry_after` body field.
+async fn retry_after(response: Response) -> Duration {
+    let header = response
+        .headers()
+        .get(reqwest::header::RETRY_AFTER)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.parse::<f64>().ok());
+    let secs = match header {
+        Some(secs) => Some(secs),
+        None => response
+            .json::<Value>()
+            .await
+            .ok()
+            .and_then(|body| body["retry_after"].as_f64()),
+    };
+
+    secs.filter(|s| s.is_finite() && *s >= 0.0)
+        .map(Duration::from_secs_f64)
+        .unwrap_or(Duration::from_secs(1))
+        .min(MAX_RETRY_AFTER)
+}
@@ -0,0 +1,103 @@
+pub mod discord;
+pub mod webhook;
+
+use std::sync::OnceLock;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+
+use crate::config::{Config, ServerEvent};
+use crate::server::{Server, State};
+
+/// Timeout for notification requests.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Shared HTTP client for notifications.
+static CLIENT: OnceLock<reqwest::Client> = OnceLock::new();
+
+/// Context of a server event, used to render notifications.
+#[derive(Debug, Clone)]
+pub struct EventContext {
+    /// The event.
+    pub event: ServerEvent,
+
+    /// Number of online players, as last known.
+    pub players: u32,
+
+    /// Maximum number of players, as last known.
+    pub players_max: u32,
+
+    /// Time the server has been online for, if online.
+    pub uptime: Option<Duration>,
+
+    /// Duration of the last server start, if known.
+    pub start_duration: Option<Duration>,
+
+    /// Time of the event.
+    pub timestamp: DateTime<Utc>,
+}
+
+impl EventContext {
+    /// Render template, replacing `{state}`, `{players}` and `{timestamp}` placeholders.
+    pub fn render(&self, template: &str) -> String {
+        template
+            .replace("{state}", self.event.name())
+            .replace("{players}", &self.players.to_string())
+            .replace("{timestamp}", &self.timestamp.to_rfc3339())
+    }
+}
+
+impl From<State> for ServerEvent {
+    fn from(state: State) -> Self {
+        match state {
+            State::Stopped => ServerEvent::Sleeping,
+            State::Starting | State::Resuming => ServerEvent::Starting,
+            State::Started => ServerEvent::Online,
+            State::Stopping => ServerEvent::Stopping,
+            State::CrashLoopBackoff => ServerEvent::CrashLoop,
+        }
+    }
+}
+
+/// Send notifications for server event to all configured endpoints.
+///
+/// Notifications are sent in the background, this does not block.
+pub async fn event(config: &Config, server: &Server, event: ServerEvent) {
+    let (players, players_max) = server
+        .status()
+        .await
+        .as_ref()
+        .map(|s| (s.players.online, s.players.max))
+        .unwrap_or((0, 0));
+    let ctx = EventContext {
+        event,
+        players,
+        players_max,
+        uptime: server.stats.uptime().await,
+        start_duration: server.stats.last_start_duration().await,
+        timestamp: Utc::now(),
+    };
+
+    for webhook in &config.notify.webhooks {
+        if !webhook.url.is_empty() && webhook.events.contains(&event) {
+            tokio::spawn(webhook::send(webhook.clone(), ctx.clone()));
+        }
+    }
+
+    if let Some(discord) = config.notify.discord.as_ref() {
+        if !discord.webhook_url.is_empty() && discord.events.contains(&event) {
+            tokio::spawn(discord::send(discord.clone(), ctx));
+        }
+    }
+}
+
+/// Get shared HTTP client.
+fn client() -> &'static reqwest::Client {
+    CLIENT.get_or_init(|| {
+        reqwest::Client::builder()
+            .user_agent(concat!("lazymc/", env!("CARGO_PKG_VERSION")))
+            .timeout(REQUEST_TIMEOUT)
+            .build()
+            .expect("failed to build HTTP client")
+    })
+}
@@ -0,0 +1,39 @@
+use reqwest::Method;
+
+use crate::config::Webhook;
+
+use super::EventContext;
+
+/// Call webhook for event.
+pub async fn send(webhook: Webhook, ctx: EventContext) {
+    let method = match Method::from_bytes(webhook.method.to_uppercase().as_bytes()) {
+        Ok(method) => method,
+        Err(_) => {
+            warn!(target: "lazymc::notify", "Invalid webhook HTTP method '{}', not calling {}", webhook.method, webhook.url);
+            return;
+        }
+    };
+
+    // Build request with templated or default JSON body
+    let mut request = super::client().request(method.clone(), &webhook.url);
+    if let Some(template) = webhook.body_template.as_ref() {
+        request = request.body(ctx.render(template));
+    } else if method != Method::GET {
+        request = request.json(&serde_json::json!({
+            "state": ctx.event.name(),
+            "players": ctx.players,
+            "timestamp": ctx.timestamp.to_rfc3339(),
+        }));
+    }
+
+    trace!(target: "lazymc::notify", "Calling webhook {} for {} event", webhook.url, ctx.event.name());
+    match request.send().await {
+        Ok(response) if response.status().is_success() => {}
+        Ok(response) => {
+            warn!(target: "lazymc::notify", "Webhook {} responded with {}", webhook.url, response.status());
+        }
+        Err(err) => {
+            warn!(target: "lazymc::notify", "Failed to call webhook {}: {}", webhook.url, err);
+        }
+    }
+}
@@ -0,0 +1,27 @@
+use nix::sys::resource::{getrlimit, setrlimit, Resource};
+
+use crate::config::ResourceLimits;
+
+/// Apply resource limits to the current process.
+///
+/// Limits are capped at the current hard limit, so they can also be applied without root.
+/// Called in the server child process before exec, so this must not allocate or log.
+pub fn apply(limits: &ResourceLimits) -> nix::Result<()> {
+    if let Some(limit) = limits.max_open_files {
+        set(Resource::RLIMIT_NOFILE, limit)?;
+    }
+    if let Some(limit) = limits.max_virtual_memory_bytes {
+        set(Resource::RLIMIT_AS, limit)?;
+    }
+    if let Some(limit) = limits.max_processes {
+        set(Resource::RLIMIT_NPROC, limit as u64)?;
+    }
+    Ok(())
+}
+
+/// Set soft and hard limit of resource.
+fn set(resource: Resource, limit: u64) -> nix::Result<()> {
+    let (_, hard) = getrlimit(resource)?;
+    let limit = limit.min(hard);
+    setrlimit(resource, limit, limit)
+}
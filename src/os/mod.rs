@@ -1,3 +1,11 @@
+#[cfg(unix)]
+pub mod limits;
+#[cfg(unix)]
+pub mod port;
+#[cfg(target_os = "linux")]
+pub mod splice;
+#[cfg(unix)]
+pub mod user;
 #[cfg(windows)]
 pub mod windows;
 
@@ -68,6 +76,31 @@ pub fn unfreeze(pid: u32) -> bool {
     );
 }
 
+/// Signal process to reload its configuration.
+/// Results in undefined behavior if PID is invaild.
+///
+/// # Panics
+/// Panics on platforms other than Unix.
+#[allow(unreachable_code, unused_variables)]
+pub fn reload(pid: u32) -> bool {
+    #[cfg(unix)]
+    return unix_signal(pid, Signal::SIGHUP);
+
+    unimplemented!("reloading lazymc configuration is not implemented on non-Unix platforms");
+}
+
+/// Check whether process with given PID is running.
+///
+/// # Panics
+/// Panics on platforms other than Unix.
+#[allow(unreachable_code, unused_variables)]
+pub fn is_running(pid: u32) -> bool {
+    #[cfg(unix)]
+    return signal::kill(Pid::from_raw(pid as i32), None).is_ok();
+
+    unimplemented!("checking whether process is running is not implemented on non-Unix platforms");
+}
+
 #[cfg(unix)]
 pub fn unix_signal(pid: u32, signal: Signal) -> bool {
     match signal::kill(Pid::from_raw(pid as i32), signal) {
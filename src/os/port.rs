@@ -0,0 +1,114 @@
+#[cfg(target_os = "linux")]
+use std::fs;
+use std::process::Command;
+
+/// TCP listen state in `/proc/net/tcp`.
+#[cfg(target_os = "linux")]
+const TCP_LISTEN: &str = "0A";
+
+/// Find PID of the process listening on the given TCP port.
+///
+/// Uses `/proc` on Linux, with `lsof` as fallback. Returns `None` if the owner can't be determined,
+/// for example if it belongs to another user.
+pub fn owner(port: u16) -> Option<u32> {
+    #[cfg(target_os = "linux")]
+    if let Some(pid) = proc_owner(port) {
+        return Some(pid);
+    }
+
+    lsof_owner(port)
+}
+
+/// Check whether process `pid` is process `ancestor` or one of its descendants.
+pub fn is_descendant(mut pid: u32, ancestor: u32) -> bool {
+    // Walk up parent processes until init
+    while pid > 1 {
+        if pid == ancestor {
+            return true;
+        }
+        pid = match parent(pid) {
+            Some(parent) => parent,
+            None => return false,
+        };
+    }
+    false
+}
+
+/// Find listening socket owner through `/proc`.
+#[cfg(target_os = "linux")]
+fn proc_owner(port: u16) -> Option<u32> {
+    // Find inodes of sockets listening on port
+    let inodes: Vec<String> = ["/proc/net/tcp", "/proc/net/tcp6"]
+        .iter()
+        .filter_map(|path| fs::read_to_string(path).ok())
+        .flat_map(|table| {
+            table
+                .lines()
+                .skip(1)
+                .filter_map(|line| {
+                    let fields: Vec<&str> = line.split_whitespace().collect();
+                    let local_port = fields.get(1)?.rsplit(':').next()?;
+                    let inode = fields.get(9)?;
+                    (u16::from_str_radix(local_port, 16).ok()? == port
+                        && *fields.get(3)? == TCP_LISTEN)
+                        .then(|| format!("socket:[{inode}]"))
+                })
+                .collect::<Vec<_>>()
+        })
+        .collect();
+    if inodes.is_empty() {
+        return None;
+    }
+
+    // Find process having one of these sockets open
+    fs::read_dir("/proc")
+        .ok()?
+        .filter_map(|entry| entry.ok()?.file_name().to_str()?.parse::<u32>().ok())
+        .find(|pid| {
+            fs::read_dir(format!("/proc/{pid}/fd"))
+                .map(|fds| {
+                    fds.filter_map(|fd| fs::read_link(fd.ok()?.path()).ok())
+                        .any(|link| {
+                            inodes
+                                .iter()
+                                .any(|inode| link.as_os_str() == inode.as_str())
+                        })
+                })
+                .unwrap_or(false)
+        })
+}
+
+/// Find listening socket owner through `lsof`.
+fn lsof_owner(port: u16) -> Option<u32> {
+    let output = Command::new("lsof")
+        .args(["-nP", &format!("-iTCP:{port}"), "-sTCP:LISTEN", "-t"])
+        .output()
+        .ok()?;
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .next()?
+        .trim()
+        .parse()
+        .ok()
+}
+
+/// Find parent PID of process.
+fn parent(pid: u32) -> Option<u32> {
+    // Command name in stat may contain spaces, parent PID follows state after it
+    #[cfg(target_os = "linux")]
+    if let Ok(stat) = fs::read_to_string(format!("/proc/{pid}/stat")) {
+        return stat
+            .rsplit(')')
+            .next()?
+            .split_whitespace()
+            .nth(1)?
+            .parse()
+            .ok();
+    }
+
+    let output = Command::new("ps")
+        .args(["-o", "ppid=", "-p", &pid.to_string()])
+        .output()
+        .ok()?;
+    String::from_utf8_lossy(&output.stdout).trim().parse().ok()
+}
@@ -0,0 +1,78 @@
+use std::io;
+use std::os::fd::{AsRawFd, FromRawFd, OwnedFd, RawFd};
+
+use tokio::io::Interest;
+use tokio::net::TcpStream;
+
+/// Maximum number of bytes to move per splice call, the default pipe capacity.
+const PIPE_SIZE: usize = 64 * 1024;
+
+/// Copy all data from one TCP stream to the other until EOF, through a kernel pipe.
+///
+/// Data is moved with `splice(2)` and is not copied through userspace. Returns an `EINVAL` error
+/// without moving any data if splicing these streams is not supported.
+pub async fn copy(from: &TcpStream, to: &TcpStream) -> io::Result<u64> {
+    let (pipe_read, pipe_write) = pipe()?;
+    let mut total = 0;
+
+    loop {
+        // Move available data from socket into pipe
+        let len = loop {
+            from.readable().await?;
+            match from.try_io(Interest::READABLE, || {
+                splice(from.as_raw_fd(), pipe_write.as_raw_fd(), PIPE_SIZE)
+            }) {
+                Ok(len) => break len,
+                Err(err) if err.kind() == io::ErrorKind::WouldBlock => continue,
+                Err(err) if total > 0 && err.raw_os_error() == Some(libc::EINVAL) => {
+                    return Err(io::Error::new(io::ErrorKind::Other, err));
+                }
+                Err(err) => return Err(err),
+            }
+        };
+        if len == 0 {
+            return Ok(total);
+        }
+
+        // Move all data from pipe into socket, pipe is empty afterwards
+        let mut remaining = len;
+        while remaining > 0 {
+            to.writable().await?;
+            match to.try_io(Interest::WRITABLE, || {
+                splice(pipe_read.as_raw_fd(), to.as_raw_fd(), remaining)
+            }) {
+                Ok(len) => remaining -= len,
+                Err(err) if err.kind() == io::ErrorKind::WouldBlock => continue,
+                Err(err) => return Err(err),
+            }
+        }
+        total += len as u64;
+    }
+}
+
+/// Create non-blocking pipe, returns read and write end.
+fn pipe() -> io::Result<(OwnedFd, OwnedFd)> {
+    let mut fds = [0; 2];
+    if unsafe { libc::pipe2(fds.as_mut_ptr(), libc::O_NONBLOCK | libc::O_CLOEXEC) } < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    unsafe { Ok((OwnedFd::from_raw_fd(fds[0]), OwnedFd::from_raw_fd(fds[1]))) }
+}
+
+/// Splice up to `len` bytes between file descriptors without blocking.
+fn splice(from: RawFd, to: RawFd, len: usize) -> io::Result<usize> {
+    let result = unsafe {
+        libc::splice(
+            from,
+            std::ptr::null_mut(),
+            to,
+            std::ptr::null_mut(),
+            len,
+            libc::SPLICE_F_MOVE | libc::SPLICE_F_NONBLOCK,
+        )
+    };
+    if result < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(result as usize)
+}
@@ -0,0 +1,182 @@
+use nix::unistd::{self, Group, User};
+
+use crate::config::Advanced;
+
+/// User and group IDs to run the server process as.
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq)]
+pub struct ServerIds {
+    /// User ID to switch to.
+    pub uid: Option<u32>,
+
+    /// Group ID to switch to.
+    pub gid: Option<u32>,
+}
+
+/// User and group lookup, system lookup is replaced in tests.
+trait Lookup {
+    /// Find user ID and primary group ID by user name.
+    fn user(&self, name: &str) -> Option<(u32, u32)>;
+
+    /// Find group ID by group name.
+    fn group(&self, name: &str) -> Option<u32>;
+
+    /// Effective user ID of lazymc.
+    fn current_uid(&self) -> u32;
+}
+
+/// Lookup in the system user database.
+struct SystemLookup;
+
+impl Lookup for SystemLookup {
+    fn user(&self, name: &str) -> Option<(u32, u32)> {
+        User::from_name(name)
+            .ok()
+            .flatten()
+            .map(|user| (user.uid.as_raw(), user.gid.as_raw()))
+    }
+
+    fn group(&self, name: &str) -> Option<u32> {
+        Group::from_name(name)
+            .ok()
+            .flatten()
+            .map(|group| group.gid.as_raw())
+    }
+
+    fn current_uid(&self) -> u32 {
+        unistd::geteuid().as_raw()
+    }
+}
+
+/// Resolve user and group IDs to run the server process as, from configured names.
+///
+/// Returns default IDs to not switch user if not configured, if a name is unknown or if lazymc is
+/// not allowed to switch.
+pub fn server_ids(config: &Advanced) -> ServerIds {
+    resolve(
+        &SystemLookup,
+        config.server_user.as_deref(),
+        config.server_group.as_deref(),
+    )
+    .unwrap_or_else(|err| {
+        warn!(target: "lazymc", "Not switching user for server process: {}", err);
+        ServerIds::default()
+    })
+}
+
+/// Resolve user and group IDs with the given lookup.
+fn resolve(
+    lookup: &impl Lookup,
+    user: Option<&str>,
+    group: Option<&str>,
+) -> Result<ServerIds, String> {
+    let mut ids = ServerIds::default();
+
+    // Group defaults to primary group of user
+    if let Some(user) = user {
+        let (uid, gid) = lookup
+            .user(user)
+            .ok_or_else(|| format!("user '{user}' not found"))?;
+        ids = ServerIds {
+            uid: Some(uid),
+            gid: Some(gid),
+        };
+    }
+    if let Some(group) = group {
+        let gid = lookup
+            .group(group)
+            .ok_or_else(|| format!("group '{group}' not found"))?;
+        ids.gid = Some(gid);
+    }
+
+    // Only root may switch to another user or group, nothing to switch if already that user
+    let current = lookup.current_uid();
+    if current != 0 {
+        if ids.uid.is_some_and(|uid| uid != current) || group.is_some() {
+            return Err("lazymc is not running as root".into());
+        }
+        return Ok(ServerIds::default());
+    }
+
+    Ok(ids)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Lookup with a fixed set of users and groups.
+    struct MockLookup {
+        current_uid: u32,
+    }
+
+    impl Lookup for MockLookup {
+        fn user(&self, name: &str) -> Option<(u32, u32)> {
+            match name {
+                "root" => Some((0, 0)),
+                "minecraft" => Some((1000, 1000)),
+                _ => None,
+            }
+        }
+
+        fn group(&self, name: &str) -> Option<u32> {
+            match name {
+                "games" => Some(60),
+                _ => None,
+            }
+        }
+
+        fn current_uid(&self) -> u32 {
+            self.current_uid
+        }
+    }
+
+    const ROOT: MockLookup = MockLookup { current_uid: 0 };
+
+    #[test]
+    fn resolve_none() {
+        assert_eq!(resolve(&ROOT, None, None), Ok(ServerIds::default()));
+    }
+
+    #[test]
+    fn resolve_user_primary_group() {
+        let ids = resolve(&ROOT, Some("minecraft"), None).unwrap();
+        assert_eq!(ids.uid, Some(1000));
+        assert_eq!(ids.gid, Some(1000));
+    }
+
+    #[test]
+    fn resolve_user_and_group() {
+        let ids = resolve(&ROOT, Some("minecraft"), Some("games")).unwrap();
+        assert_eq!(ids.uid, Some(1000));
+        assert_eq!(ids.gid, Some(60));
+    }
+
+    #[test]
+    fn resolve_group_only() {
+        let ids = resolve(&ROOT, None, Some("games")).unwrap();
+        assert_eq!(ids.uid, None);
+        assert_eq!(ids.gid, Some(60));
+    }
+
+    #[test]
+    fn resolve_unknown() {
+        assert!(resolve(&ROOT, Some("nobody"), None).is_err());
+        assert!(resolve(&ROOT, Some("minecraft"), Some("nogroup")).is_err());
+    }
+
+    #[test]
+    fn resolve_not_root() {
+        let lookup = MockLookup { current_uid: 1000 };
+        assert!(resolve(&lookup, Some("root"), None).is_err());
+        assert!(resolve(&lookup, None, Some("games")).is_err());
+    }
+
+    #[test]
+    fn resolve_not_root_same_user() {
+        let lookup = MockLookup { current_uid: 1000 };
+        assert_eq!(
+            resolve(&lookup, Some("minecraft"), None),
+            Ok(ServerIds::default())
+        );
+    }
+}
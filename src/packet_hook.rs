@@ -0,0 +1,723 @@
+use std::borrow::Cow;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+
+use bytes::BytesMut;
+use libloading::Library;
+use minecraft_protocol::decoder::Decoder;
+use minecraft_protocol::version::v1_14_4::handshake::Handshake;
+use minecraft_protocol::version::v1_14_4::login::SetCompression;
+use tokio::io::{self, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::select;
+use tokio::sync::Notify;
+
+use crate::config::Config;
+use crate::filter::{self, Verdict};
+use crate::hooks::{PendingPlayer, PlayerSession};
+use crate::proto::client::{Client, ClientInfo, ClientState};
+use crate::proto::packet::RawPacket;
+use crate::proto::packets;
+use crate::proto::packets::play::system_chat;
+use crate::proxy;
+use crate::types;
+
+/// Packet hook ABI version lazymc implements.
+const ABI_VERSION: u32 = 1;
+
+/// Symbol shared libraries export with the packet hook ABI version they implement.
+///
+/// Must be declared as `extern "C" fn lazymc_packet_hook_abi_version() -> u32`.
+const VERSION_SYMBOL: &[u8] = b"lazymc_packet_hook_abi_version";
+
+/// Symbol shared libraries export to inspect packets.
+///
+/// Must be declared as `extern "C" fn lazymc_packet_hook_inspect(direction: u8, packet_id: u8,
+/// payload: *const u8, len: usize, replacement: *mut HookBuffer) -> u8`, returning one of the
+/// `HOOK_*` codes.
+const INSPECT_SYMBOL: &[u8] = b"lazymc_packet_hook_inspect";
+
+/// Symbol shared libraries export to free replacement buffers they allocated.
+///
+/// Must be declared as `extern "C" fn lazymc_packet_hook_free(buffer: HookBuffer)`.
+const FREE_SYMBOL: &[u8] = b"lazymc_packet_hook_free";
+
+/// Inspect return code to relay packet unchanged.
+const HOOK_PASS_THROUGH: u8 = 0;
+
+/// Inspect return code to relay packet with the payload in the replacement buffer.
+const HOOK_REPLACE: u8 = 1;
+
+/// Inspect return code to drop packet.
+const HOOK_DROP: u8 = 2;
+
+/// Maximum length in bytes of a packet length prefix.
+const MAX_LEN_SIZE: usize = 5;
+
+/// Protocol version of Minecraft 1.20.2, adding the configuration state after login.
+const PROTOCOL_CONFIGURATION: u32 = 764;
+
+/// Packet hook ABI version function.
+type VersionFn = unsafe extern "C" fn() -> u32;
+
+/// Packet hook inspect function.
+type InspectFn = unsafe extern "C" fn(u8, u8, *const u8, usize, *mut HookBuffer) -> u8;
+
+/// Packet hook function to free replacement buffers.
+type FreeFn = unsafe extern "C" fn(HookBuffer);
+
+/// Buffer passed from packet hooks to lazymc, owned by the library that allocated it.
+#[repr(C)]
+pub struct HookBuffer {
+    /// Buffer data, null if not set.
+    pub data: *mut u8,
+
+    /// Buffer length in bytes.
+    pub len: usize,
+}
+
+/// Direction a packet flows in, passed to packet hooks as its discriminant.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[repr(u8)]
+pub enum Direction {
+    /// From client to server.
+    Serverbound = 0,
+
+    /// From server to client.
+    Clientbound = 1,
+}
+
+/// Result of inspecting a packet.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HookResult {
+    /// Relay packet unchanged.
+    PassThrough,
+
+    /// Relay packet with the given payload instead.
+    Replace(Vec<u8>),
+
+    /// Do not relay packet.
+    Drop,
+}
+
+/// Packet hook loaded from shared library.
+///
+/// Hooks only see packets after login, after built-in filters, the payload excludes the packet ID.
+/// Hooks are called from multiple threads at once.
+struct LoadedHook {
+    /// Inspect function.
+    inspect: InspectFn,
+
+    /// Function to free replacement buffers.
+    free: FreeFn,
+
+    /// Library the functions are loaded from, must outlive them.
+    _library: Library,
+}
+
+impl LoadedHook {
+    /// Inspect packet flowing in the given direction.
+    fn inspect(&self, direction: Direction, packet_id: u8, payload: &[u8]) -> HookResult {
+        let mut buffer = HookBuffer {
+            data: std::ptr::null_mut(),
+            len: 0,
+        };
+
+        // SAFETY: libraries implement the documented ABI, the payload outlives the call
+        let code = unsafe {
+            (self.inspect)(
+                direction as u8,
+                packet_id,
+                payload.as_ptr(),
+                payload.len(),
+                &mut buffer,
+            )
+        };
+
+        // Copy replacement, give buffer back to the library to free it
+        let replacement = (!buffer.data.is_null()).then(|| {
+            // SAFETY: the library set the buffer to valid data of the given length
+            let data = unsafe { std::slice::from_raw_parts(buffer.data, buffer.len) }.to_vec();
+            unsafe { (self.free)(buffer) };
+            data
+        });
+
+        match (code, replacement) {
+            (HOOK_PASS_THROUGH, _) => HookResult::PassThrough,
+            (HOOK_REPLACE, Some(data)) => HookResult::Replace(data),
+            (HOOK_DROP, _) => HookResult::Drop,
+            (code, _) => {
+                warn!(target: "lazymc", "Packet hook returned invalid code {}, relaying packet unchanged", code);
+                HookResult::PassThrough
+            }
+        }
+    }
+}
+
+/// Packet hooks of a server, in configured order.
+#[derive(Default)]
+pub struct PacketHooks {
+    /// Loaded hooks, set once loaded.
+    hooks: OnceLock<Vec<LoadedHook>>,
+}
+
+impl PacketHooks {
+    /// Load packet hooks from configured shared libraries.
+    ///
+    /// Does nothing if already loaded. Logs an error and skips libraries that fail to load.
+    pub fn load(&self, config: &Config) {
+        if config.advanced.packet_hooks.is_empty() || self.hooks.get().is_some() {
+            return;
+        }
+
+        let hooks = config
+        .advanced
+        .packet_hooks
+        .iter()
+        .map(|path| config.resolve_path(path))
+        .filter_map(|path| match load_hook(&path) {
+            Ok(hook) => {
+                info!(target: "lazymc", "Loaded packet hook from {}", path.display());
+                Some(hook)
+            }
+            Err(err) => {
+                error!(target: "lazymc", "Failed to load packet hook from {}, skipping: {}", path.display(), err);
+                None
+            }
+        })
+        .collect();
+        let _ = self.hooks.set(hooks);
+    }
+
+    /// Whether any packet hook is loaded, connections must then be relayed packet by packet.
+    pub fn is_enabled(&self) -> bool {
+        self.hooks.get().is_some_and(|hooks| !hooks.is_empty())
+    }
+
+    /// Run packet through all hooks.
+    ///
+    /// Every hook sees the payload replaced by earlier hooks. Stops at the first hook dropping it.
+    pub fn inspect(&self, direction: Direction, packet_id: u8, payload: &[u8]) -> HookResult {
+        let mut result = HookResult::PassThrough;
+        for loaded in self.hooks.get().into_iter().flatten() {
+            let current = match &result {
+                HookResult::Replace(data) => data.as_slice(),
+                _ => payload,
+            };
+            match loaded.inspect(direction, packet_id, current) {
+                HookResult::PassThrough => {}
+                HookResult::Drop => return HookResult::Drop,
+                replaced => result = replaced,
+            }
+        }
+        result
+    }
+}
+
+/// Load packet hook from shared library at path.
+///
+/// Fails if the library does not implement the packet hook ABI version of lazymc.
+fn load_hook(path: &std::path::Path) -> Result<LoadedHook, Box<dyn std::error::Error>> {
+    // SAFETY: libraries are trusted by configuration, the function signatures are documented
+    unsafe {
+        let library = Library::new(path)?;
+        let version = (*library.get::<VersionFn>(VERSION_SYMBOL)?)();
+        if version != ABI_VERSION {
+            return Err(format!(
+                "library implements packet hook ABI version {version}, lazymc requires {ABI_VERSION}"
+            )
+            .into());
+        }
+        Ok(LoadedHook {
+            inspect: *library.get::<InspectFn>(INSPECT_SYMBOL)?,
+            free: *library.get::<FreeFn>(FREE_SYMBOL)?,
+            _library: library,
+        })
+    }
+}
+
+/// Packet relay state shared by both directions of a connection.
+pub struct Relay {
+    /// Protocol state and compression of the connection.
+    client: Client,
+
+    /// Client protocol version, once known.
+    protocol: OnceLock<u32>,
+
+    /// Player username, once known.
+    username: OnceLock<String>,
+
+    /// Whether packets can't be inspected anymore, such as after encryption is enabled.
+    raw: AtomicBool,
+
+    /// Encoded packets to send to the client, such as filter feedback.
+    replies: Mutex<Vec<Vec<u8>>>,
+
+    /// Notified when replies are queued.
+    replies_queued: Notify,
+
+    /// Packet hooks of the server relayed to.
+    hooks: Arc<PacketHooks>,
+
+    /// Player to join once the server accepts the login.
+    pending_player: Mutex<Option<PendingPlayer>>,
+
+    /// Session of the joined player, ended when the relay is dropped.
+    player: OnceLock<PlayerSession>,
+}
+
+impl Relay {
+    /// Construct relay for connection in given state and compression threshold.
+    pub fn new(state: ClientState, compression: i32) -> Self {
+        let client = Client::dummy();
+        client.set_state(state);
+        client.set_compression(compression);
+        Self {
+            client,
+            protocol: OnceLock::new(),
+            username: OnceLock::new(),
+            raw: AtomicBool::new(false),
+            replies: Mutex::new(Vec::new()),
+            replies_queued: Notify::new(),
+            hooks: Default::default(),
+            pending_player: Mutex::new(None),
+            player: OnceLock::new(),
+        }
+    }
+
+    /// Run packets in play state through the given packet hooks.
+    pub fn with_hooks(mut self, hooks: Arc<PacketHooks>) -> Self {
+        self.hooks = hooks;
+        self
+    }
+
+    /// Join the given player once the server sends login success.
+    ///
+    /// Packets are inspected until then, to find out if the server accepts the player.
+    pub fn with_player(self, player: Option<PendingPlayer>) -> Self {
+        *self
+            .pending_player
+            .lock()
+            .unwrap_or_else(|err| err.into_inner()) = player;
+        self
+    }
+
+    /// Whether connections must be relayed packet by packet, for packet hooks or filters, or to
+    /// see a pending player join.
+    pub fn must_inspect(&self, config: &Config) -> bool {
+        self.hooks.is_enabled() || filter::is_enabled(config) || self.is_player_pending()
+    }
+
+    /// Whether a player is waiting for login success to join.
+    fn is_player_pending(&self) -> bool {
+        self.pending_player
+            .lock()
+            .unwrap_or_else(|err| err.into_inner())
+            .is_some()
+    }
+
+    /// Join the pending player, if any.
+    fn join_player(&self) {
+        let pending = self
+            .pending_player
+            .lock()
+            .unwrap_or_else(|err| err.into_inner())
+            .take();
+        if let Some(pending) = pending {
+            let _ = self.player.set(pending.join());
+        }
+    }
+
+    /// Set protocol version and username from client info, if the connection is past login.
+    pub fn with_client_info(self, client_info: &ClientInfo) -> Self {
+        if let Some(protocol) = client_info.protocol() {
+            let _ = self.protocol.set(protocol);
+        }
+        if let Some(username) = &client_info.username {
+            let _ = self.username.set(username.clone());
+        }
+        self
+    }
+
+    /// Track protocol state from packets sent to the server by other means, such as forwarding.
+    pub fn observe(&self, config: &Config, mut queue: &[u8]) {
+        while let Ok(Some(len)) = frame_len(queue) {
+            self.process(config, Direction::Serverbound, &queue[..len]);
+            queue = &queue[len..];
+        }
+    }
+
+    /// Whether the client enters the configuration state after login.
+    fn has_configuration(&self) -> bool {
+        self.protocol
+            .get()
+            .is_some_and(|protocol| *protocol >= PROTOCOL_CONFIGURATION)
+    }
+
+    /// Relay raw data from now on, without inspecting packets.
+    fn set_raw(&self) {
+        self.raw.store(true, Ordering::Relaxed);
+    }
+
+    /// Queue chat message to send to the client.
+    fn reply(&self, text: &str) {
+        let Some(protocol) = self.protocol.get() else {
+            return;
+        };
+        let Ok(frame) = system_chat::packet(*protocol, text)
+            .and_then(|packet| packet.encode_with_len(&self.client))
+        else {
+            return;
+        };
+        self.replies
+            .lock()
+            .unwrap_or_else(|err| err.into_inner())
+            .push(frame);
+        self.replies_queued.notify_one();
+    }
+
+    /// Take queued replies to send to the client.
+    fn take_replies(&self) -> Vec<Vec<u8>> {
+        std::mem::take(&mut *self.replies.lock().unwrap_or_else(|err| err.into_inner()))
+    }
+
+    /// Process a single length prefixed packet frame.
+    ///
+    /// Tracks protocol state and runs packets in play state through filters and hooks. Returns the
+    /// frame to relay, or `None` to drop it.
+    fn process<'a>(
+        &self,
+        config: &Config,
+        direction: Direction,
+        frame: &'a [u8],
+    ) -> Option<Cow<'a, [u8]>> {
+        let Ok(mut packet) = RawPacket::decode_with_len(&self.client, frame) else {
+            self.set_raw();
+            return Some(Cow::Borrowed(frame));
+        };
+
+        match (direction, self.client.state()) {
+            // Only login connections are inspected
+            (Direction::Serverbound, ClientState::Handshake) => {
+                let handshake = (packet.id == packets::handshake::SERVER_HANDSHAKE)
+                    .then(|| Handshake::decode(&mut packet.data.as_slice()).ok())
+                    .flatten();
+                match handshake {
+                    Some(handshake)
+                        if ClientState::from_id(handshake.next_state)
+                            == Some(ClientState::Login) =>
+                    {
+                        let _ = self.protocol.set(handshake.protocol_version as u32);
+                        self.client.set_state(ClientState::Login);
+                    }
+                    _ => self.set_raw(),
+                }
+            }
+            (Direction::Serverbound, ClientState::Login) => match packet.id {
+                packets::login::SERVER_LOGIN_START => {
+                    if let Ok((_, username)) = types::read_string(&packet.data) {
+                        let _ = self.username.set(username);
+                    }
+                }
+                packets::login::SERVER_LOGIN_ACKNOWLEDGED if self.has_configuration() => {
+                    self.client.set_state(ClientState::Configuration)
+                }
+                _ => {}
+            },
+            // Server sends play packets once client acknowledged finishing configuration
+            (Direction::Serverbound, ClientState::Configuration) => {
+                let protocol = self.protocol.get().copied().unwrap_or_default();
+                if packet.id == packets::configuration::finish_configuration(protocol) {
+                    self.client.set_state(ClientState::Play);
+                }
+            }
+            (Direction::Clientbound, ClientState::Login) => match packet.id {
+                packets::login::CLIENT_ENCRYPTION_REQUEST => {
+                    if filter::is_enabled(config) {
+                        debug!(target: "lazymc", "Server enabled encryption, chat and command filters can't inspect this connection");
+                    }
+                    self.set_raw();
+
+                    // Login success is encrypted, assume the server accepts the player
+                    self.join_player();
+                }
+                packets::login::CLIENT_SET_COMPRESSION => {
+                    match SetCompression::decode(&mut packet.data.as_slice()) {
+                        Ok(set_compression) => {
+                            self.client.set_compression(set_compression.threshold)
+                        }
+                        Err(_) => self.set_raw(),
+                    }
+                }
+                packets::login::CLIENT_LOGIN_SUCCESS => {
+                    self.join_player();
+
+                    // Configuration follows once client acknowledges login since 1.20.2
+                    if !self.has_configuration() {
+                        self.client.set_state(ClientState::Play);
+                    }
+
+                    // Only inspected to see the player join
+                    if !self.must_inspect(config) {
+                        self.set_raw();
+                    }
+                }
+                _ => {}
+            },
+            (_, ClientState::Play) => {
+                let mut replaced = false;
+                if direction == Direction::Serverbound {
+                    let username = self.username.get().map(|username| username.as_str());
+                    match filter::serverbound(
+                        config,
+                        self.protocol.get().copied(),
+                        username,
+                        &packet,
+                    ) {
+                        Verdict::Pass => {}
+                        Verdict::Replace(data) => {
+                            packet.data = data;
+                            replaced = true;
+                        }
+                        Verdict::Block(feedback) => {
+                            if let Some(feedback) = feedback {
+                                self.reply(&feedback);
+                            }
+                            return None;
+                        }
+                    }
+                }
+
+                match self.hooks.inspect(direction, packet.id, &packet.data) {
+                    HookResult::PassThrough => {}
+                    HookResult::Replace(data) => {
+                        packet.data = data;
+                        replaced = true;
+                    }
+                    HookResult::Drop => {
+                        trace!(target: "lazymc", "Packet hook dropped packet {:#04x}", packet.id);
+                        return None;
+                    }
+                }
+
+                if replaced {
+                    if let Ok(encoded) = packet.encode_with_len(&self.client) {
+                        return Some(Cow::Owned(encoded));
+                    }
+                }
+            }
+            _ => {}
+        }
+
+        Some(Cow::Borrowed(frame))
+    }
+}
+
+/// Forward packets from one stream to the other through the relay, then shut down writing.
+///
+/// Queued bytes are processed before data from the stream. Replies are sent to the client in
+/// between packets. Falls back to copying raw data once packets can't be inspected anymore.
+pub async fn forward(
+    relay: &Relay,
+    config: &Config,
+    direction: Direction,
+    from: &mut (impl AsyncRead + Unpin),
+    to: &mut (impl AsyncWrite + Unpin),
+    queue: &[u8],
+) -> io::Result<()> {
+    let (read_timeout, write_timeout) = (
+        config.advanced.proxy_read_timeout(),
+        config.advanced.proxy_write_timeout(),
+    );
+    let mut buf = BytesMut::from(queue);
+    loop {
+        if relay.raw.load(Ordering::Relaxed) {
+            to.write_all(&buf).await?;
+            proxy::copy(from, to, config).await?;
+            break;
+        }
+
+        // Relay complete frame from buffer, or read more data
+        let len = match frame_len(&buf) {
+            Ok(Some(len)) => len,
+            Ok(None) => {
+                // Send everything relayed so far before waiting for more
+                to.flush().await?;
+                select! {
+                    read = proxy::timeout(read_timeout, "read", from.read_buf(&mut buf)) => {
+                        if read? == 0 {
+                            to.write_all(&buf).await?;
+                            break;
+                        }
+                    }
+                    _ = relay.replies_queued.notified(), if direction == Direction::Clientbound => {
+                        for reply in relay.take_replies() {
+                            to.write_all(&reply).await?;
+                        }
+                    }
+                }
+                continue;
+            }
+            Err(_) => {
+                relay.set_raw();
+                continue;
+            }
+        };
+
+        let frame = buf.split_to(len);
+        if let Some(frame) = relay.process(config, direction, &frame) {
+            proxy::timeout(write_timeout, "write", to.write_all(&frame)).await?;
+        }
+    }
+
+    to.shutdown().await
+}
+
+/// Get length of the first frame in buffer including length prefix, `None` if incomplete.
+fn frame_len(buf: &[u8]) -> Result<Option<usize>, ()> {
+    match types::read_var_int(buf) {
+        Ok((read, len)) if len >= 0 => {
+            let len = read + len as usize;
+            Ok((buf.len() >= len).then_some(len))
+        }
+        Ok(_) => Err(()),
+        Err(_) if buf.len() < MAX_LEN_SIZE => Ok(None),
+        Err(_) => Err(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use minecraft_protocol::encoder::Encoder;
+
+    use super::*;
+
+    fn config() -> Config {
+        toml::from_str("[server]\ncommand = \"java -jar server.jar\"").unwrap()
+    }
+
+    /// Encode handshake frame with given next state.
+    fn handshake_frame(next_state: ClientState) -> Vec<u8> {
+        let mut data = Vec::new();
+        Handshake {
+            protocol_version: 765,
+            server_addr: "localhost".into(),
+            server_port: 25565,
+            next_state: next_state.to_id(),
+        }
+        .encode(&mut data)
+        .unwrap();
+        RawPacket::new(packets::handshake::SERVER_HANDSHAKE, data)
+            .encode_with_len(&Client::dummy())
+            .unwrap()
+    }
+
+    #[test]
+    fn frame_len_incomplete() {
+        assert_eq!(frame_len(&[]), Ok(None));
+        assert_eq!(frame_len(&[3, 0, 0]), Ok(None));
+        assert_eq!(frame_len(&[3, 0, 0, 0, 0]), Ok(Some(4)));
+        assert_eq!(frame_len(&[0xFF; 5]), Err(()));
+    }
+
+    #[test]
+    fn relay_tracks_handshake() {
+        let relay = Relay::new(ClientState::Handshake, -1);
+        let frame = handshake_frame(ClientState::Login);
+        assert!(relay
+            .process(&config(), Direction::Serverbound, &frame)
+            .is_some());
+        assert_eq!(relay.client.state(), ClientState::Login);
+        assert_eq!(relay.protocol.get(), Some(&765));
+        assert!(!relay.raw.load(Ordering::Relaxed));
+
+        let relay = Relay::new(ClientState::Handshake, -1);
+        relay.process(
+            &config(),
+            Direction::Serverbound,
+            &handshake_frame(ClientState::Status),
+        );
+        assert!(relay.raw.load(Ordering::Relaxed));
+    }
+
+    #[test]
+    fn relay_tracks_configuration() {
+        let frame = |id| {
+            RawPacket::new(id, Vec::new())
+                .encode_with_len(&Client::dummy())
+                .unwrap()
+        };
+        let relay = Relay::new(ClientState::Handshake, -1);
+        relay.process(
+            &config(),
+            Direction::Serverbound,
+            &handshake_frame(ClientState::Login),
+        );
+
+        // Login success is acknowledged before configuration, then play
+        relay.process(
+            &config(),
+            Direction::Clientbound,
+            &frame(packets::login::CLIENT_LOGIN_SUCCESS),
+        );
+        assert_eq!(relay.client.state(), ClientState::Login);
+        relay.process(
+            &config(),
+            Direction::Serverbound,
+            &frame(packets::login::SERVER_LOGIN_ACKNOWLEDGED),
+        );
+        assert_eq!(relay.client.state(), ClientState::Configuration);
+        relay.process(
+            &config(),
+            Direction::Serverbound,
+            &frame(packets::configuration::finish_configuration(765)),
+        );
+        assert_eq!(relay.client.state(), ClientState::Play);
+    }
+
+    #[test]
+    fn relay_joins_player_on_login_success() {
+        let config: Arc<Config> = Arc::new(
+            toml::from_str("[server]\ncommand = \"java -jar server.jar\"\n[stats]\nenabled = true")
+                .unwrap(),
+        );
+        let server = Arc::new(crate::server::Server::default());
+        let player = PendingPlayer::new(
+            config.clone(),
+            server.clone(),
+            &ClientInfo::default(),
+            "127.0.0.1".parse().unwrap(),
+        );
+        let relay = Relay::new(ClientState::Handshake, -1).with_player(player);
+        assert!(relay.must_inspect(&config));
+        relay.process(
+            &config,
+            Direction::Serverbound,
+            &handshake_frame(ClientState::Login),
+        );
+
+        // Server disconnecting the client does not join the player
+        let frame = |id| {
+            RawPacket::new(id, Vec::new())
+                .encode_with_len(&Client::dummy())
+                .unwrap()
+        };
+        relay.process(
+            &config,
+            Direction::Clientbound,
+            &frame(packets::login::CLIENT_DISCONNECT),
+        );
+        assert_eq!(server.players.load(Ordering::Relaxed), 0);
+
+        relay.process(
+            &config,
+            Direction::Clientbound,
+            &frame(packets::login::CLIENT_LOGIN_SUCCESS),
+        );
+        assert_eq!(server.players.load(Ordering::Relaxed), 1);
+        assert!(relay.raw.load(Ordering::Relaxed));
+
+        // Player leaves when the relay is dropped
+        drop(relay);
+        assert_eq!(server.players.load(Ordering::Relaxed), 0);
+    }
+}
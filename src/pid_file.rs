@@ -0,0 +1,56 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::Mutex;
+
+/// Path of the PID file created by this process, removed on exit.
+static PID_FILE: Mutex<Option<PathBuf>> = Mutex::new(None);
+
+/// PID file of this process.
+///
+/// The file is removed when dropped, on graceful quit and on panic.
+pub struct PidFile(());
+
+impl PidFile {
+    /// Write PID of this process to the given file.
+    pub fn create(path: PathBuf) -> io::Result<Self> {
+        fs::write(&path, format!("{}\n", process::id()))?;
+        PID_FILE.lock().unwrap().replace(path);
+
+        // Remove PID file on panic
+        let hook = std::panic::take_hook();
+        std::panic::set_hook(Box::new(move |info| {
+            remove();
+            hook(info);
+        }));
+
+        Ok(Self(()))
+    }
+}
+
+impl Drop for PidFile {
+    fn drop(&mut self) {
+        remove();
+    }
+}
+
+/// Remove PID file created by this process, if any.
+pub fn remove() {
+    // Don't panic in panic handler if lock is poisoned
+    let path = match PID_FILE.lock() {
+        Ok(mut path) => path.take(),
+        Err(_) => return,
+    };
+
+    if let Some(path) = path {
+        if let Err(err) = fs::remove_file(&path) {
+            warn!(target: "lazymc", "Failed to remove PID file at {}: {}", path.display(), err);
+        }
+    }
+}
+
+/// Read PID from the given PID file.
+pub fn read(path: &Path) -> Option<u32> {
+    fs::read_to_string(path).ok()?.trim().parse().ok()
+}
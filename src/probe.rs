@@ -1,4 +1,5 @@
 use std::ops::Deref;
+use std::sync::atomic::Ordering;
 use std::sync::Arc;
 use std::time::Duration;
 
@@ -11,8 +12,9 @@ use minecraft_protocol::version::v1_14_4::login::{
 use tokio::net::TcpStream;
 use tokio::time;
 
-use crate::config::Config;
+use crate::config::{Config, ForgeVariant};
 use crate::forge;
+use crate::monitor;
 use crate::net;
 use crate::proto::client::{Client, ClientInfo, ClientState};
 use crate::proto::packets::play::join_game::JoinGameData;
@@ -34,6 +36,37 @@ const PROBE_ONLINE_TIMEOUT: Duration = Duration::from_secs(10 * 60);
 /// This defines the maximum timeout for waiting on it.
 const PROBE_JOIN_GAME_TIMEOUT: Duration = Duration::from_secs(20);
 
+/// Whether to send proxy headers to the server.
+///
+/// Uses probed value if `server.probe_proxy_v2` is enabled and the server was probed, or the
+/// configured `server.send_proxy_v2` otherwise.
+pub fn send_proxy_v2(config: &Config, server: &Server) -> bool {
+    if !config.server.probe_proxy_v2 {
+        return config.server.send_proxy_v2;
+    }
+
+    match server.probed_proxy_header.load(Ordering::Relaxed) {
+        1 => false,
+        2 => true,
+        _ => config.server.send_proxy_v2,
+    }
+}
+
+/// Protocol version of the server.
+///
+/// Uses probed value if `public.protocol` is `0` for automatic detection, falling back to the
+/// default protocol if the server wasn't probed yet. Uses the configured value otherwise.
+pub fn protocol(config: &Config, server: &Server) -> u32 {
+    if config.public.protocol != 0 {
+        return config.public.protocol;
+    }
+
+    match server.probed_protocol.load(Ordering::Relaxed) {
+        0 => proto::PROTO_DEFAULT_PROTOCOL,
+        protocol => protocol,
+    }
+}
+
 /// Connect to the Minecraft server and probe useful details from it.
 pub async fn probe(config: Arc<Config>, server: Arc<Server>) -> Result<(), ()> {
     debug!(target: "lazymc::probe", "Starting server probe...");
@@ -49,8 +82,21 @@ pub async fn probe(config: Arc<Config>, server: Arc<Server>) -> Result<(), ()> {
         return Err(());
     }
 
+    // Probe proxy header support
+    if config.server.probe_proxy_v2 {
+        probe_proxy_header(&config, &server).await;
+    }
+
+    // Probe protocol version
+    if config.public.protocol == 0 {
+        probe_protocol(&config, &server).await;
+    }
+
     debug!(target: "lazymc::probe", "Connecting to server to probe details...");
 
+    // Detect Forge variant before connecting with it
+    forge::detect(&config, &server).await;
+
     // Connect to server, record Forge payload
     let forge_payload = connect_to_server(&config, &server).await?;
     *server.forge_payload.write().await = forge_payload;
@@ -58,6 +104,53 @@ pub async fn probe(config: Arc<Config>, server: Arc<Server>) -> Result<(), ()> {
     Ok(())
 }
 
+/// Probe whether the server accepts proxy headers, by requesting status with and without.
+async fn probe_proxy_header(config: &Config, server: &Server) {
+    let addr = config.server.address;
+    let with = monitor::fetch_status(config, server, addr, true)
+        .await
+        .is_ok();
+    let without = monitor::fetch_status(config, server, addr, false)
+        .await
+        .is_ok();
+
+    let probed = match (with, without) {
+        (true, false) => {
+            info!(target: "lazymc::probe", "Probed server requires proxy header, sending it");
+            2
+        }
+        (false, true) => {
+            info!(target: "lazymc::probe", "Probed server doesn't accept proxy header, not sending it");
+            1
+        }
+        (true, true) => {
+            debug!(target: "lazymc::probe", "Probed server accepts connections with and without proxy header, using configured value");
+            return;
+        }
+        (false, false) => {
+            warn!(target: "lazymc::probe", "Failed to probe proxy header support, server didn't respond");
+            return;
+        }
+    };
+    server.probed_proxy_header.store(probed, Ordering::Relaxed);
+}
+
+/// Probe protocol version of the server by requesting its status.
+async fn probe_protocol(config: &Config, server: &Server) {
+    let addr = config.server.address;
+    match monitor::fetch_status(config, server, addr, send_proxy_v2(config, server)).await {
+        Ok(status) => {
+            info!(target: "lazymc::probe", "Probed server protocol version {} ({})", status.version.protocol, status.version.name);
+            server
+                .probed_protocol
+                .store(status.version.protocol, Ordering::Relaxed);
+        }
+        Err(_) => {
+            warn!(target: "lazymc::probe", "Failed to probe protocol version, server didn't respond")
+        }
+    }
+}
+
 /// Wait for the server to come online.
 ///
 /// Returns `true` when it is online.
@@ -74,7 +167,7 @@ async fn wait_until_online<'a>(server: &Server) -> Result<bool, ()> {
 
             match state.borrow().deref() {
                 // Still waiting on server start
-                State::Starting => {
+                State::Starting | State::Resuming => {
                     continue;
                 }
 
@@ -90,7 +183,7 @@ async fn wait_until_online<'a>(server: &Server) -> Result<bool, ()> {
                 }
 
                 // Server stopped, this shouldn't happen, skip
-                State::Stopped => {
+                State::Stopped | State::CrashLoopBackoff => {
                     error!(target: "lazymc::probe", "Server stopped while trying to probe, skipping");
                     break false;
                 }
@@ -151,21 +244,22 @@ async fn connect_to_server_no_timeout(
 
     // Construct client info
     let mut tmp_client_info = ClientInfo::empty();
-    tmp_client_info.protocol.replace(config.public.protocol);
+    tmp_client_info.protocol.replace(protocol(config, server));
 
     let (mut reader, mut writer) = outbound.split();
 
     // Select server address to use, add magic if Forge
-    let server_addr = if config.server.forge {
-        format!("{}{}", config.server.address.ip(), forge::STATUS_MAGIC)
-    } else {
-        config.server.address.ip().to_string()
-    };
+    let forge_variant = server.forge_variant(config).await;
+    let server_addr = format!(
+        "{}{}",
+        config.server.address.ip(),
+        forge::status_magic(forge_variant)
+    );
 
     // Send handshake packet
     packet::write_packet(
         Handshake {
-            protocol_version: config.public.protocol as i32,
+            protocol_version: protocol(config, server) as i32,
             server_addr,
             server_port: config.server.address.port(),
             next_state: ClientState::Login.to_id(),
@@ -235,7 +329,9 @@ async fn connect_to_server_no_timeout(
             })?;
 
             // Handle plugin requests for Forge
-            if config.server.forge {
+            if forge_variant == ForgeVariant::Fml2
+                && plugin_request.channel == forge::CHANNEL_LOGIN_WRAPPER
+            {
                 // Record Forge login payload
                 forge_payload.push(raw);
 
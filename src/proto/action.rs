@@ -1,15 +1,21 @@
 use minecraft_protocol::data::chat::{Message, Payload};
 use minecraft_protocol::version::v1_14_4::game::GameDisconnect;
 use minecraft_protocol::version::v1_14_4::login::LoginDisconnect;
-use tokio::net::tcp::WriteHalf;
+use tokio::io::AsyncWrite;
 
 use crate::proto::client::{Client, ClientState};
-use crate::proto::packet;
+use crate::proto::packet::{self, RawPacket};
+use crate::proto::packets;
+use crate::types;
 
 /// Kick client with a message.
 ///
 /// Should close connection afterwards.
-pub async fn kick(client: &Client, msg: &str, writer: &mut WriteHalf<'_>) -> Result<(), ()> {
+pub async fn kick(
+    client: &Client,
+    msg: &str,
+    writer: &mut (impl AsyncWrite + Unpin),
+) -> Result<(), ()> {
     match client.state() {
         ClientState::Login => {
             packet::write_packet(
@@ -34,3 +40,26 @@ pub async fn kick(client: &Client, msg: &str, writer: &mut WriteHalf<'_>) -> Res
         _ => Err(()),
     }
 }
+
+/// Kick client with a raw JSON chat component as reason.
+///
+/// Only supported in login state. Should close connection afterwards.
+pub async fn kick_json(
+    client: &Client,
+    json: &str,
+    writer: &mut (impl AsyncWrite + Unpin),
+) -> Result<(), ()> {
+    if client.state() != ClientState::Login {
+        return Err(());
+    }
+
+    packet::write_raw_packet(
+        RawPacket::new(
+            packets::login::CLIENT_DISCONNECT,
+            types::encode_string(json)?,
+        ),
+        client,
+        writer,
+    )
+    .await
+}
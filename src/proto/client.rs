@@ -3,6 +3,10 @@ use std::sync::atomic::{AtomicI32, Ordering};
 use std::sync::Mutex;
 
 use minecraft_protocol::version::v1_14_4::handshake::Handshake;
+use uuid::Uuid;
+
+use crate::auth::ProfileProperty;
+use crate::mc::uuid::offline_player_uuid;
 
 /// Client state.
 ///
@@ -79,6 +83,9 @@ pub enum ClientState {
     /// State to login to server.
     Login,
 
+    /// State to configure client before playing, since Minecraft 1.20.2.
+    Configuration,
+
     /// State to play on the server.
     #[allow(unused)]
     Play,
@@ -101,7 +108,7 @@ impl ClientState {
             Self::Handshake => 0,
             Self::Status => 1,
             Self::Login => 2,
-            Self::Play => -1,
+            Self::Configuration | Self::Play => -1,
         }
     }
 }
@@ -123,6 +130,15 @@ pub struct ClientInfo {
 
     /// Client username.
     pub username: Option<String>,
+
+    /// Player UUID, only set once authenticated with the session server.
+    pub uuid: Option<Uuid>,
+
+    /// Client UUID, as sent in login start by recent clients. Not verified, must not be trusted.
+    pub login_uuid: Option<Uuid>,
+
+    /// Player profile properties, such as skin textures, if authenticated.
+    pub properties: Vec<ProfileProperty>,
 }
 
 impl ClientInfo {
@@ -135,4 +151,13 @@ impl ClientInfo {
         self.protocol
             .or_else(|| self.handshake.as_ref().map(|h| h.protocol_version as u32))
     }
+
+    /// Get player UUID.
+    ///
+    /// Uses authenticated UUID, falls back to offline player UUID for username. The UUID sent by
+    /// the client in login start is never used.
+    pub fn player_uuid(&self) -> Option<Uuid> {
+        self.uuid
+            .or_else(|| self.username.as_deref().map(offline_player_uuid))
+    }
 }
@@ -8,8 +8,7 @@ use flate2::Compression;
 use minecraft_protocol::encoder::Encoder;
 use minecraft_protocol::version::PacketId;
 use tokio::io;
-use tokio::io::{AsyncReadExt, AsyncWriteExt};
-use tokio::net::tcp::{ReadHalf, WriteHalf};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
 
 use crate::proto::client::Client;
 use crate::proto::BUF_SIZE;
@@ -161,7 +160,7 @@ impl RawPacket {
 pub async fn read_packet(
     client: &Client,
     buf: &mut BytesMut,
-    stream: &mut ReadHalf<'_>,
+    stream: &mut (impl AsyncRead + Unpin),
 ) -> Result<Option<(RawPacket, Vec<u8>)>, ()> {
     // Keep reading until we have at least 2 bytes
     while buf.len() < 2 {
@@ -219,17 +218,31 @@ pub async fn read_packet(
     Ok(Some((packet, raw.to_vec())))
 }
 
+/// Write raw packet to stream writer.
+pub async fn write_raw_packet(
+    packet: RawPacket,
+    client: &Client,
+    writer: &mut (impl AsyncWrite + Unpin),
+) -> Result<(), ()> {
+    let response = packet.encode_with_len(client)?;
+    writer.write_all(&response).await.map_err(|_| ())?;
+    writer.flush().await.map_err(|_| ())?;
+
+    Ok(())
+}
+
 /// Write packet to stream writer.
 pub async fn write_packet(
     packet: impl PacketId + Encoder + Debug,
     client: &Client,
-    writer: &mut WriteHalf<'_>,
+    writer: &mut (impl AsyncWrite + Unpin),
 ) -> Result<(), ()> {
     let mut data = Vec::new();
     packet.encode(&mut data).map_err(|_| ())?;
 
     let response = RawPacket::new(packet.packet_id(), data).encode_with_len(client)?;
     writer.write_all(&response).await.map_err(|_| ())?;
+    writer.flush().await.map_err(|_| ())?;
 
     Ok(())
 }
@@ -20,14 +20,27 @@ pub mod status {
 pub mod login {
     use minecraft_protocol::version::v1_14_4::login::*;
 
-    #[cfg(feature = "lobby")]
     pub const CLIENT_DISCONNECT: u8 = LoginDisconnect::PACKET_ID;
     pub const CLIENT_LOGIN_SUCCESS: u8 = LoginSuccess::PACKET_ID;
     pub const CLIENT_SET_COMPRESSION: u8 = SetCompression::PACKET_ID;
-    #[cfg(feature = "lobby")]
     pub const CLIENT_ENCRYPTION_REQUEST: u8 = EncryptionRequest::PACKET_ID;
     pub const CLIENT_LOGIN_PLUGIN_REQUEST: u8 = LoginPluginRequest::PACKET_ID;
     pub const SERVER_LOGIN_START: u8 = LoginStart::PACKET_ID;
-    #[cfg(feature = "lobby")]
+    pub const SERVER_ENCRYPTION_RESPONSE: u8 = EncryptionResponse::PACKET_ID;
     pub const SERVER_LOGIN_PLUGIN_RESPONSE: u8 = LoginPluginResponse::PACKET_ID;
+    pub const SERVER_LOGIN_ACKNOWLEDGED: u8 = 0x03;
+}
+
+pub mod configuration {
+    /// Protocol version of Minecraft 1.20.5.
+    const PROTOCOL_V1_20_5: u32 = 766;
+
+    /// Get finish configuration packet ID for protocol version, same for both directions.
+    pub fn finish_configuration(protocol: u32) -> u8 {
+        if protocol < PROTOCOL_V1_20_5 {
+            0x02
+        } else {
+            0x03
+        }
+    }
 }
@@ -0,0 +1,34 @@
+use minecraft_protocol::version::v1_17;
+use tokio::io::AsyncWrite;
+
+use crate::proto::client::{Client, ClientInfo};
+use crate::proto::packet::{self, RawPacket};
+use crate::types;
+
+/// Title packet ID for Minecraft 1.16.3 to 1.16.5, carries action bar action.
+const PACKET_ID_TITLE_V1_16_3: u8 = 0x4F;
+
+/// Title packet action to set action bar text for Minecraft 1.16.3 to 1.16.5.
+const TITLE_ACTION_SET_ACTION_BAR_V1_16_3: i32 = 2;
+
+/// Set action bar text packet ID for Minecraft 1.17 and 1.17.1.
+const PACKET_ID_ACTION_BAR_V1_17: u8 = 0x41;
+
+/// Send action bar text to client.
+pub async fn send(
+    client: &Client,
+    client_info: &ClientInfo,
+    writer: &mut (impl AsyncWrite + Unpin),
+    text: &str,
+) -> Result<(), ()> {
+    let packet = match client_info.protocol() {
+        Some(p) if p < v1_17::PROTOCOL => {
+            let mut data = types::encode_var_int(TITLE_ACTION_SET_ACTION_BAR_V1_16_3)?;
+            data.append(&mut super::encode_text(text)?);
+            RawPacket::new(PACKET_ID_TITLE_V1_16_3, data)
+        }
+        _ => RawPacket::new(PACKET_ID_ACTION_BAR_V1_17, super::encode_text(text)?),
+    };
+
+    packet::write_raw_packet(packet, client, writer).await
+}
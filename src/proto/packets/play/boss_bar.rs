@@ -0,0 +1,92 @@
+use minecraft_protocol::version::v1_17;
+use tokio::io::AsyncWrite;
+use uuid::Uuid;
+
+use crate::proto::client::{Client, ClientInfo};
+use crate::proto::packet::{self, RawPacket};
+use crate::types;
+
+/// Boss bar packet ID for Minecraft 1.16.3 to 1.16.5.
+const PACKET_ID_V1_16_3: u8 = 0x0C;
+
+/// Boss bar packet ID for Minecraft 1.17 and 1.17.1.
+const PACKET_ID_V1_17: u8 = 0x0D;
+
+/// Boss bar UUID used in lobby world.
+const BOSS_BAR_UUID: Uuid = Uuid::from_u128(0x6c61_7a79_6d63_4000_8000_0000_626f_7373);
+
+/// Boss bar action: add.
+const ACTION_ADD: i32 = 0;
+
+/// Boss bar action: remove.
+const ACTION_REMOVE: i32 = 1;
+
+/// Boss bar action: update health.
+const ACTION_UPDATE_HEALTH: i32 = 2;
+
+/// Boss bar color: green.
+const COLOR_GREEN: i32 = 3;
+
+/// Boss bar division: none.
+const DIVISION_NONE: i32 = 0;
+
+/// Show lobby boss bar with given title and progress.
+///
+/// Progress must be in range `0.0..=1.0`.
+pub async fn add(
+    client: &Client,
+    client_info: &ClientInfo,
+    writer: &mut (impl AsyncWrite + Unpin),
+    title: &str,
+    progress: f32,
+) -> Result<(), ()> {
+    let mut data = super::encode_text(title)?;
+    data.extend_from_slice(&progress.to_be_bytes());
+    data.append(&mut types::encode_var_int(COLOR_GREEN)?);
+    data.append(&mut types::encode_var_int(DIVISION_NONE)?);
+    data.push(0);
+
+    send(client, client_info, writer, ACTION_ADD, data).await
+}
+
+/// Update lobby boss bar progress.
+///
+/// Progress must be in range `0.0..=1.0`.
+pub async fn update_progress(
+    client: &Client,
+    client_info: &ClientInfo,
+    writer: &mut (impl AsyncWrite + Unpin),
+    progress: f32,
+) -> Result<(), ()> {
+    let data = progress.to_be_bytes().to_vec();
+    send(client, client_info, writer, ACTION_UPDATE_HEALTH, data).await
+}
+
+/// Remove lobby boss bar.
+pub async fn remove(
+    client: &Client,
+    client_info: &ClientInfo,
+    writer: &mut (impl AsyncWrite + Unpin),
+) -> Result<(), ()> {
+    send(client, client_info, writer, ACTION_REMOVE, vec![]).await
+}
+
+/// Send boss bar packet with given action and action data.
+async fn send(
+    client: &Client,
+    client_info: &ClientInfo,
+    writer: &mut (impl AsyncWrite + Unpin),
+    action: i32,
+    mut action_data: Vec<u8>,
+) -> Result<(), ()> {
+    let id = match client_info.protocol() {
+        Some(p) if p < v1_17::PROTOCOL => PACKET_ID_V1_16_3,
+        _ => PACKET_ID_V1_17,
+    };
+
+    let mut data = BOSS_BAR_UUID.as_bytes().to_vec();
+    data.append(&mut types::encode_var_int(action)?);
+    data.append(&mut action_data);
+
+    packet::write_raw_packet(RawPacket::new(id, data), client, writer).await
+}
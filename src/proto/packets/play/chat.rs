@@ -0,0 +1,56 @@
+use crate::proto::packet::RawPacket;
+use crate::types;
+
+/// Protocol version of Minecraft 1.19.
+const PROTOCOL_V1_19: u32 = 759;
+
+/// Protocol version of Minecraft 1.19.1.
+const PROTOCOL_V1_19_1: u32 = 760;
+
+/// Protocol version of Minecraft 1.20.5.
+const PROTOCOL_V1_20_5: u32 = 766;
+
+/// Chat packet sent by client.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Kind {
+    /// Chat message, commands start with `/` before Minecraft 1.19.
+    Message,
+
+    /// Command without leading `/`, Minecraft 1.19 and later.
+    Command,
+}
+
+/// Get kind of chat packet sent by client for the given protocol version, if it is one.
+///
+/// Supports Minecraft 1.14 and later.
+pub fn kind(protocol: u32, packet_id: u8) -> Option<Kind> {
+    let (message, commands) = match protocol {
+        p if p < PROTOCOL_V1_19 => (0x03, &[][..]),
+        p if p < PROTOCOL_V1_19_1 => (0x04, &[0x03][..]),
+        p if p < PROTOCOL_V1_20_5 => (0x05, &[0x04][..]),
+        _ => (0x06, &[0x04, 0x05][..]),
+    };
+
+    if packet_id == message {
+        Some(Kind::Message)
+    } else if commands.contains(&packet_id) {
+        Some(Kind::Command)
+    } else {
+        None
+    }
+}
+
+/// Read message or command text from chat packet sent by client.
+pub fn text(packet: &RawPacket) -> Result<String, ()> {
+    types::read_string(&packet.data).map(|(_, text)| text)
+}
+
+/// Replace message or command text in chat packet sent by client, keeping other fields.
+///
+/// Signed messages from Minecraft 1.19 and later keep their now invalid signature.
+pub fn replace_text(packet: &RawPacket, text: &str) -> Result<Vec<u8>, ()> {
+    let (read, _) = types::read_string(&packet.data)?;
+    let mut data = types::encode_string(text)?;
+    data.extend_from_slice(&packet.data[read..]);
+    Ok(data)
+}
@@ -3,7 +3,7 @@ use minecraft_protocol::error::DecodeError;
 use minecraft_protocol::version::{v1_16_3, v1_17};
 use nbt::CompoundTag;
 #[cfg(feature = "lobby")]
-use tokio::net::tcp::WriteHalf;
+use tokio::io::AsyncWrite;
 
 #[cfg(feature = "lobby")]
 use crate::mc::dimension;
@@ -102,7 +102,7 @@ pub fn is_packet(client_info: &ClientInfo, packet_id: u8) -> bool {
 pub async fn lobby_send(
     client: &Client,
     client_info: &ClientInfo,
-    writer: &mut WriteHalf<'_>,
+    writer: &mut (impl AsyncWrite + Unpin),
     server: &Server,
 ) -> Result<(), ()> {
     let status = server.status().await;
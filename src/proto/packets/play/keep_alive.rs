@@ -1,7 +1,7 @@
 use std::sync::atomic::{AtomicU64, Ordering};
 
 use minecraft_protocol::version::{v1_16_3, v1_17};
-use tokio::net::tcp::WriteHalf;
+use tokio::io::AsyncWrite;
 
 use crate::proto::client::{Client, ClientInfo};
 use crate::proto::packet;
@@ -15,7 +15,7 @@ static KEEP_ALIVE_ID: AtomicU64 = AtomicU64::new(0);
 pub async fn send(
     client: &Client,
     client_info: &ClientInfo,
-    writer: &mut WriteHalf<'_>,
+    writer: &mut (impl AsyncWrite + Unpin),
 ) -> Result<(), ()> {
     // Keep sending new IDs
     let id = KEEP_ALIVE_ID.fetch_add(1, Ordering::Relaxed);
@@ -1,15 +1,31 @@
+#[cfg(feature = "lobby")]
+pub mod action_bar;
+#[cfg(feature = "lobby")]
+pub mod boss_bar;
+pub mod chat;
 pub mod join_game;
 #[cfg(feature = "lobby")]
 pub mod keep_alive;
 #[cfg(feature = "lobby")]
+pub mod player_list;
+#[cfg(feature = "lobby")]
 pub mod player_pos;
 #[cfg(feature = "lobby")]
+pub mod resource_pack;
+#[cfg(feature = "lobby")]
 pub mod respawn;
 #[cfg(feature = "lobby")]
 pub mod server_brand;
 #[cfg(feature = "lobby")]
 pub mod sound;
+pub mod system_chat;
 #[cfg(feature = "lobby")]
 pub mod time_update;
 #[cfg(feature = "lobby")]
 pub mod title;
+
+/// Encode text as JSON chat component, prefixed with its var-int length.
+#[cfg(feature = "lobby")]
+fn encode_text(text: &str) -> Result<Vec<u8>, ()> {
+    crate::types::encode_string(&serde_json::json!({ "text": text }).to_string())
+}
@@ -0,0 +1,32 @@
+use minecraft_protocol::version::v1_17;
+use tokio::io::AsyncWrite;
+
+use crate::proto::client::{Client, ClientInfo};
+use crate::proto::packet::{self, RawPacket};
+
+/// Player list header and footer packet ID for Minecraft 1.16.3 to 1.16.5.
+const PACKET_ID_HEADER_FOOTER_V1_16_3: u8 = 0x53;
+
+/// Player list header and footer packet ID for Minecraft 1.17 and 1.17.1.
+const PACKET_ID_HEADER_FOOTER_V1_17: u8 = 0x5E;
+
+/// Send player list (tab list) header and footer to client.
+///
+/// Empty strings clear the header and footer.
+pub async fn send_header_footer(
+    client: &Client,
+    client_info: &ClientInfo,
+    writer: &mut (impl AsyncWrite + Unpin),
+    header: &str,
+    footer: &str,
+) -> Result<(), ()> {
+    let id = match client_info.protocol() {
+        Some(p) if p < v1_17::PROTOCOL => PACKET_ID_HEADER_FOOTER_V1_16_3,
+        _ => PACKET_ID_HEADER_FOOTER_V1_17,
+    };
+
+    let mut data = super::encode_text(header)?;
+    data.append(&mut super::encode_text(footer)?);
+
+    packet::write_raw_packet(RawPacket::new(id, data), client, writer).await
+}
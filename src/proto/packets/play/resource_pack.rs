@@ -0,0 +1,52 @@
+use minecraft_protocol::version::v1_17;
+use tokio::io::AsyncWrite;
+
+use crate::proto::client::{Client, ClientInfo};
+use crate::proto::packet::{self, RawPacket};
+use crate::types;
+
+/// Resource pack send packet ID for Minecraft 1.16.3 to 1.16.5.
+const PACKET_ID_RESOURCE_PACK_SEND_V1_16_3: u8 = 0x38;
+
+/// Resource pack send packet ID for Minecraft 1.17 and 1.17.1.
+const PACKET_ID_RESOURCE_PACK_SEND_V1_17: u8 = 0x3C;
+
+/// Resource pack status packet ID for Minecraft 1.16.3 to 1.17.1.
+const PACKET_ID_RESOURCE_PACK_STATUS: u8 = 0x21;
+
+/// Resource pack status result if the client declined the resource pack.
+const STATUS_DECLINED: i32 = 1;
+
+/// Ask client to load resource pack from the given URL.
+///
+/// The hash is the hex encoded SHA-1 of the resource pack, empty to not verify it.
+pub async fn send(
+    client: &Client,
+    client_info: &ClientInfo,
+    writer: &mut (impl AsyncWrite + Unpin),
+    url: &str,
+    hash: &str,
+    forced: bool,
+) -> Result<(), ()> {
+    let mut data = types::encode_string(url)?;
+    data.append(&mut types::encode_string(hash)?);
+
+    let packet = match client_info.protocol() {
+        Some(p) if p < v1_17::PROTOCOL => {
+            RawPacket::new(PACKET_ID_RESOURCE_PACK_SEND_V1_16_3, data)
+        }
+        _ => {
+            // Forced flag and no prompt message
+            data.extend_from_slice(&[forced as u8, 0]);
+            RawPacket::new(PACKET_ID_RESOURCE_PACK_SEND_V1_17, data)
+        }
+    };
+
+    packet::write_raw_packet(packet, client, writer).await
+}
+
+/// Check whether packet from client is a resource pack status that declines the resource pack.
+pub fn is_declined(packet: &RawPacket) -> bool {
+    packet.id == PACKET_ID_RESOURCE_PACK_STATUS
+        && types::read_var_int(&packet.data).is_ok_and(|(_, status)| status == STATUS_DECLINED)
+}
@@ -1,5 +1,5 @@
 use minecraft_protocol::version::{v1_16_3, v1_17};
-use tokio::net::tcp::WriteHalf;
+use tokio::io::AsyncWrite;
 
 use super::join_game::JoinGameData;
 use crate::mc::dimension;
@@ -12,7 +12,7 @@ use crate::proto::packet;
 pub async fn lobby_send(
     client: &Client,
     client_info: &ClientInfo,
-    writer: &mut WriteHalf<'_>,
+    writer: &mut (impl AsyncWrite + Unpin),
     data: JoinGameData,
 ) -> Result<(), ()> {
     match client_info.protocol() {
@@ -1,5 +1,5 @@
 use minecraft_protocol::version::{v1_16_3, v1_17};
-use tokio::net::tcp::WriteHalf;
+use tokio::io::AsyncWrite;
 
 use crate::proto::client::{Client, ClientInfo};
 use crate::proto::packet;
@@ -8,7 +8,7 @@ use crate::proto::packet;
 pub async fn send(
     client: &Client,
     client_info: &ClientInfo,
-    writer: &mut WriteHalf<'_>,
+    writer: &mut (impl AsyncWrite + Unpin),
     sound_name: &str,
 ) -> Result<(), ()> {
     match client_info.protocol() {
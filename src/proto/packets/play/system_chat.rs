@@ -0,0 +1,67 @@
+use crate::proto::packet::RawPacket;
+use crate::types;
+
+/// Protocol version of Minecraft 1.15.
+const PROTOCOL_V1_15: u32 = 573;
+
+/// Protocol version of Minecraft 1.16.
+const PROTOCOL_V1_16: u32 = 735;
+
+/// Protocol version of Minecraft 1.17.
+const PROTOCOL_V1_17: u32 = 755;
+
+/// Protocol version of Minecraft 1.19.
+const PROTOCOL_V1_19: u32 = 759;
+
+/// Protocol version of Minecraft 1.20.3, text components are sent as NBT from here.
+const PROTOCOL_V1_20_3: u32 = 765;
+
+/// Chat message position for system messages before Minecraft 1.19.
+const POSITION_SYSTEM: u8 = 1;
+
+/// NBT string tag type.
+const NBT_TAG_STRING: u8 = 0x08;
+
+/// Build system chat message packet for the given protocol version, Minecraft 1.14 and later.
+pub fn packet(protocol: u32, text: &str) -> Result<RawPacket, ()> {
+    let id = match protocol {
+        p if p < PROTOCOL_V1_15 => 0x0E,
+        p if p < PROTOCOL_V1_16 => 0x0F,
+        p if p < PROTOCOL_V1_17 => 0x0E,
+        p if p < PROTOCOL_V1_19 => 0x0F,
+        759 => 0x5F,
+        760 => 0x62,
+        761 => 0x60,
+        762 | 763 => 0x64,
+        764 => 0x67,
+        765 => 0x69,
+        _ => 0x6C,
+    };
+
+    let mut data = if protocol < PROTOCOL_V1_20_3 {
+        types::encode_string(&serde_json::json!({ "text": text }).to_string())?
+    } else {
+        encode_nbt_string(text)?
+    };
+    match protocol {
+        p if p < PROTOCOL_V1_16 => data.push(POSITION_SYSTEM),
+        p if p < PROTOCOL_V1_19 => {
+            data.push(POSITION_SYSTEM);
+            data.extend_from_slice(&[0; 16]);
+        }
+        PROTOCOL_V1_19 => data.append(&mut types::encode_var_int(POSITION_SYSTEM as i32)?),
+        // Not an overlay
+        _ => data.push(0),
+    }
+
+    Ok(RawPacket::new(id, data))
+}
+
+/// Encode text as nameless NBT string tag, a plain text component.
+fn encode_nbt_string(text: &str) -> Result<Vec<u8>, ()> {
+    let len = u16::try_from(text.len()).map_err(|_| ())?;
+    let mut data = vec![NBT_TAG_STRING];
+    data.extend_from_slice(&len.to_be_bytes());
+    data.extend_from_slice(text.as_bytes());
+    Ok(data)
+}
@@ -1,6 +1,6 @@
 use minecraft_protocol::data::chat::{Message, Payload};
 use minecraft_protocol::version::{v1_16_3, v1_17};
-use tokio::net::tcp::WriteHalf;
+use tokio::io::AsyncWrite;
 
 #[cfg(feature = "lobby")]
 use crate::lobby::KEEP_ALIVE_INTERVAL;
@@ -21,7 +21,7 @@ const DISPLAY_TIME: i32 = 10 * mc::TICKS_PER_SECOND as i32 * 2;
 pub async fn send(
     client: &Client,
     client_info: &ClientInfo,
-    writer: &mut WriteHalf<'_>,
+    writer: &mut (impl AsyncWrite + Unpin),
     text: &str,
 ) -> Result<(), ()> {
     // Grab title and subtitle bits
@@ -36,7 +36,7 @@ pub async fn send(
 
 async fn send_v1_16_3(
     client: &Client,
-    writer: &mut WriteHalf<'_>,
+    writer: &mut (impl AsyncWrite + Unpin),
     title: &str,
     subtitle: &str,
 ) -> Result<(), ()> {
@@ -92,7 +92,7 @@ async fn send_v1_16_3(
 
 async fn send_v1_17(
     client: &Client,
-    writer: &mut WriteHalf<'_>,
+    writer: &mut (impl AsyncWrite + Unpin),
     title: &str,
     subtitle: &str,
 ) -> Result<(), ()> {
@@ -1,87 +1,199 @@
 use std::error::Error;
+use std::future::Future;
 use std::net::SocketAddr;
+use std::time::Duration;
 
 use bytes::BytesMut;
 use proxy_protocol::version2::{ProxyAddresses, ProxyCommand, ProxyTransportProtocol};
 use proxy_protocol::EncodeError;
 use tokio::io;
-use tokio::io::AsyncWriteExt;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
 use tokio::net::TcpStream;
+use tokio::time;
+#[cfg(target_os = "linux")]
+use {socket2::SockRef, std::net::Shutdown};
 
+use crate::config::{Config, ProxyVersion};
+use crate::forwarding::Forwarding;
 use crate::net;
+#[cfg(target_os = "linux")]
+use crate::os;
+use crate::packet_hook::{self, Direction, Relay};
+use crate::stream::ClientStream;
+
+/// Buffer size for copying between streams when splicing is not supported.
+const COPY_BUFFER_SIZE: usize = 8 * 1024;
 
 /// Proxy the inbound stream to a target address.
+///
+/// The relay tracks the protocol state and runs packet hooks of the target server.
 pub async fn proxy(
-    inbound: TcpStream,
+    inbound: ClientStream,
     proxy_header: ProxyHeader,
     addr_target: SocketAddr,
+    config: &Config,
+    relay: Relay,
 ) -> Result<(), Box<dyn Error>> {
-    proxy_with_queue(inbound, proxy_header, addr_target, &[]).await
+    proxy_with_queue(inbound, proxy_header, None, addr_target, config, &[], relay).await
 }
 
 /// Proxy the inbound stream to a target address.
 ///
-/// Send the queue to the target server before proxying.
+/// Send the queue to the target server before proxying. Player info is forwarded if given.
 pub async fn proxy_with_queue(
-    inbound: TcpStream,
+    inbound: ClientStream,
     proxy_header: ProxyHeader,
+    forwarding: Option<Forwarding>,
     addr_target: SocketAddr,
+    config: &Config,
     queue: &[u8],
+    relay: Relay,
 ) -> Result<(), Box<dyn Error>> {
-    // Set up connection to server
     // TODO: on connect fail, ping server and redirect to serve_status if offline
-    let mut outbound = TcpStream::connect(addr_target).await?;
+    let outbound = connect(addr_target, config).await?;
+    proxy_outbound_with_queue(
+        inbound,
+        outbound,
+        proxy_header,
+        forwarding,
+        config,
+        queue,
+        relay,
+    )
+    .await
+}
 
+/// Set up connection to target server.
+pub async fn connect(addr_target: SocketAddr, config: &Config) -> io::Result<TcpStream> {
+    let outbound = TcpStream::connect(addr_target).await?;
+    net::configure_tcp_stream(&outbound, &config.advanced.tcp);
+    Ok(outbound)
+}
+
+/// Proxy the inbound stream to a connected target server.
+///
+/// Send the queue to the target server before proxying. Player info is forwarded if given.
+pub async fn proxy_outbound_with_queue(
+    inbound: ClientStream,
+    mut outbound: TcpStream,
+    proxy_header: ProxyHeader,
+    forwarding: Option<Forwarding>,
+    config: &Config,
+    queue: &[u8],
+    relay: Relay,
+) -> Result<(), Box<dyn Error>> {
     // Add proxy header
     match proxy_header {
         ProxyHeader::None => {}
-        ProxyHeader::Local => {
-            let header = local_proxy_header()?;
+        ProxyHeader::Local(version) => {
+            let header = local_proxy_header(version)?;
             outbound.write_all(&header).await?;
         }
-        ProxyHeader::Proxy => {
-            let header = stream_proxy_header(&inbound)?;
+        ProxyHeader::Proxy(version) => {
+            let header = stream_proxy_header(&inbound, version)?;
             outbound.write_all(&header).await?;
         }
     }
 
+    // Forward player info, relay server response to client
+    if let Some(forwarding) = forwarding {
+        let inbound_queue = forwarding
+            .forward(&mut outbound, queue)
+            .await
+            .map_err(|_| "failed to forward player info")?;
+        relay.observe(config, queue);
+        return proxy_inbound_outbound_with_queue(
+            inbound,
+            outbound,
+            config,
+            &inbound_queue,
+            &[],
+            relay,
+        )
+        .await;
+    }
+
     // Start proxy on both streams
-    proxy_inbound_outbound_with_queue(inbound, outbound, &[], queue).await
+    proxy_inbound_outbound_with_queue(inbound, outbound, config, &[], queue, relay).await
 }
 
 /// Proxy the inbound stream to a target address.
 ///
-/// Send the queue to the target server before proxying.
+/// Send the queue to the target server before proxying. The relay tracks the protocol state the
+/// connection is in, to inspect packets if packet hooks or filters are enabled.
 // TODO: find better name for this
 pub async fn proxy_inbound_outbound_with_queue(
-    mut inbound: TcpStream,
+    mut inbound: ClientStream,
     mut outbound: TcpStream,
+    config: &Config,
     inbound_queue: &[u8],
     outbound_queue: &[u8],
+    relay: Relay,
 ) -> Result<(), Box<dyn Error>> {
-    let (mut ri, mut wi) = inbound.split();
-    let (mut ro, mut wo) = outbound.split();
+    // Relay packet by packet for packet hooks and filters to inspect them
+    if relay.must_inspect(config) {
+        let (mut ri, mut wi) = inbound.split();
+        let (mut ro, mut wo) = outbound.split();
+        tokio::try_join!(
+            packet_hook::forward(
+                &relay,
+                config,
+                Direction::Serverbound,
+                &mut ri,
+                &mut wo,
+                outbound_queue
+            ),
+            packet_hook::forward(
+                &relay,
+                config,
+                Direction::Clientbound,
+                &mut ro,
+                &mut wi,
+                inbound_queue
+            ),
+        )?;
+        net::close_tcp_stream(inbound).await?;
+        return Ok(());
+    }
 
-    // Forward queued bytes to client once writable
+    // Forward queued bytes to client
     if !inbound_queue.is_empty() {
-        wi.writable().await?;
         trace!(target: "lazymc", "Relaying {} queued bytes to client", inbound_queue.len());
-        wi.write_all(inbound_queue).await?;
+        inbound.write_all(inbound_queue).await?;
+        inbound.flush().await?;
     }
 
     // Forward queued bytes to server once writable
     if !outbound_queue.is_empty() {
-        wo.writable().await?;
+        outbound.writable().await?;
         trace!(target: "lazymc", "Relaying {} queued bytes to server", outbound_queue.len());
-        wo.write_all(outbound_queue).await?;
+        outbound.write_all(outbound_queue).await?;
+    }
+
+    // Splice between streams in kernel if enabled, cannot time out stalled connections or splice
+    // TLS or encrypted client streams
+    let timeouts = config.advanced.proxy_read_timeout_secs.is_some()
+        || config.advanced.proxy_write_timeout_secs.is_some();
+    #[cfg(target_os = "linux")]
+    if config.advanced.splice_forwarding && !timeouts && inbound.is_plain() {
+        tokio::try_join!(
+            forward_splice(inbound.tcp(), &outbound),
+            forward_splice(&outbound, inbound.tcp()),
+        )?;
+        net::close_tcp_stream(inbound).await?;
+        return Ok(());
     }
+    #[cfg(not(target_os = "linux"))]
+    let _ = timeouts;
 
+    let (mut ri, mut wi) = inbound.split();
+    let (mut ro, mut wo) = outbound.split();
     let client_to_server = async {
-        io::copy(&mut ri, &mut wo).await?;
+        copy(&mut ri, &mut wo, config).await?;
         wo.shutdown().await
     };
     let server_to_client = async {
-        io::copy(&mut ro, &mut wi).await?;
+        copy(&mut ro, &mut wi, config).await?;
         wi.shutdown().await
     };
 
@@ -93,6 +205,101 @@ pub async fn proxy_inbound_outbound_with_queue(
     Ok(())
 }
 
+/// Copy all data from reader to writer.
+///
+/// Fails with a timed out error if reading or writing stalls longer than the configured proxy
+/// timeouts.
+pub async fn copy<R, W>(from: &mut R, to: &mut W, config: &Config) -> io::Result<u64>
+where
+    R: AsyncRead + Unpin + ?Sized,
+    W: AsyncWrite + Unpin + ?Sized,
+{
+    let (read_timeout, write_timeout) = (
+        config.advanced.proxy_read_timeout(),
+        config.advanced.proxy_write_timeout(),
+    );
+    if read_timeout.is_none() && write_timeout.is_none() {
+        return io::copy(from, to).await;
+    }
+
+    let mut buf = vec![0; COPY_BUFFER_SIZE];
+    let mut total = 0;
+    loop {
+        let len = timeout(read_timeout, "read", from.read(&mut buf)).await?;
+        if len == 0 {
+            return Ok(total);
+        }
+        timeout(write_timeout, "write", to.write_all(&buf[..len])).await?;
+        timeout(write_timeout, "write", to.flush()).await?;
+        total += len as u64;
+    }
+}
+
+/// Run IO operation on proxied connection with optional timeout.
+///
+/// Fails with a timed out error describing the stalled operation if the timeout is reached.
+pub async fn timeout<T>(
+    duration: Option<Duration>,
+    operation: &str,
+    future: impl Future<Output = io::Result<T>>,
+) -> io::Result<T> {
+    let Some(duration) = duration else {
+        return future.await;
+    };
+    match time::timeout(duration, future).await {
+        Ok(result) => result,
+        Err(_) => Err(io::Error::new(
+            io::ErrorKind::TimedOut,
+            format!(
+                "connection stalled, could not {} data for {} seconds",
+                operation,
+                duration.as_secs()
+            ),
+        )),
+    }
+}
+
+/// Forward all data from one stream to the other using splice, then shut down writing.
+///
+/// Falls back to copying through userspace if splicing is not supported.
+#[cfg(target_os = "linux")]
+async fn forward_splice(from: &TcpStream, to: &TcpStream) -> io::Result<()> {
+    match os::splice::copy(from, to).await {
+        Ok(_) => {}
+        Err(err) if err.raw_os_error() == Some(libc::EINVAL) => {
+            debug!(target: "lazymc", "Splice not supported for connection, falling back to copying");
+            forward_copy(from, to).await?;
+        }
+        Err(err) => return Err(err),
+    }
+    SockRef::from(to).shutdown(Shutdown::Write)
+}
+
+/// Forward all data from one stream to the other by copying through userspace.
+#[cfg(target_os = "linux")]
+async fn forward_copy(from: &TcpStream, to: &TcpStream) -> io::Result<()> {
+    let mut buf = vec![0; COPY_BUFFER_SIZE];
+    loop {
+        from.readable().await?;
+        let len = match from.try_read(&mut buf) {
+            Ok(0) => return Ok(()),
+            Ok(len) => len,
+            Err(err) if err.kind() == io::ErrorKind::WouldBlock => continue,
+            Err(err) => return Err(err),
+        };
+
+        let mut written = 0;
+        while written < len {
+            to.writable().await?;
+            match to.try_write(&buf[written..len]) {
+                Ok(n) => written += n,
+                Err(err) if err.kind() == io::ErrorKind::WouldBlock => continue,
+                Err(err) => return Err(err),
+            }
+        }
+    }
+}
+
 /// Proxy header.
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub enum ProxyHeader {
@@ -101,10 +308,10 @@ pub enum ProxyHeader {
 
     /// Header for locally initiated connection.
     #[allow(unused)]
-    Local,
+    Local(ProxyVersion),
 
     /// Header for proxied connection.
-    Proxy,
+    Proxy(ProxyVersion),
 }
 
 impl ProxyHeader {
@@ -123,7 +330,11 @@ impl ProxyHeader {
 /// Get the proxy header for a locally initiated connection.
 ///
 /// This header may be sent over the outbound stream to signal client information.
-pub fn local_proxy_header() -> Result<BytesMut, EncodeError> {
+pub fn local_proxy_header(version: ProxyVersion) -> Result<BytesMut, EncodeError> {
+    if version == ProxyVersion::V1 {
+        return Ok(BytesMut::from(&b"PROXY UNKNOWN\r\n"[..]));
+    }
+
     // Build proxy header
     let header = proxy_protocol::ProxyHeader::Version2 {
         command: ProxyCommand::Local,
@@ -137,15 +348,29 @@ pub fn local_proxy_header() -> Result<BytesMut, EncodeError> {
 /// Get the proxy header for the given inbound stream.
 ///
 /// This header may be sent over the outbound stream to signal client information.
-pub fn stream_proxy_header(inbound: &TcpStream) -> Result<BytesMut, EncodeError> {
+pub fn stream_proxy_header(
+    inbound: &ClientStream,
+    version: ProxyVersion,
+) -> Result<BytesMut, EncodeError> {
     // Get peer and local address
-    let peer = inbound
-        .peer_addr()
-        .expect("Peer address not known for TCP stream");
+    let peer = inbound.peer();
     let local = inbound
         .local_addr()
         .expect("Local address not known for TCP stream");
 
+    proxy_header(peer, local, version)
+}
+
+/// Get the proxy header for a connection from the given peer to the given local address.
+fn proxy_header(
+    peer: SocketAddr,
+    local: SocketAddr,
+    version: ProxyVersion,
+) -> Result<BytesMut, EncodeError> {
+    if version == ProxyVersion::V1 {
+        return Ok(proxy_header_v1(peer, local));
+    }
+
     // Build proxy header
     let header = proxy_protocol::ProxyHeader::Version2 {
         command: ProxyCommand::Proxy,
@@ -165,3 +390,231 @@ pub fn stream_proxy_header(inbound: &TcpStream) -> Result<BytesMut, EncodeError>
 
     proxy_protocol::encode(header)
 }
+
+/// Get the text format version 1 proxy header for the given addresses.
+fn proxy_header_v1(source: SocketAddr, destination: SocketAddr) -> BytesMut {
+    let header = match (source, destination) {
+        (SocketAddr::V4(source), SocketAddr::V4(destination)) => format!(
+            "PROXY TCP4 {} {} {} {}\r\n",
+            source.ip(),
+            destination.ip(),
+            source.port(),
+            destination.port(),
+        ),
+        (source, destination) => {
+            let ipv6 = |addr: SocketAddr| match addr {
+                SocketAddr::V4(addr) => addr.ip().to_ipv6_mapped(),
+                SocketAddr::V6(addr) => *addr.ip(),
+            };
+            format!(
+                "PROXY TCP6 {} {} {} {}\r\n",
+                ipv6(source),
+                ipv6(destination),
+                source.port(),
+                destination.port(),
+            )
+        }
+    };
+
+    BytesMut::from(header.as_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Version 2 header signature.
+    const V2_SIGNATURE: [u8; 12] = [
+        0x0D, 0x0A, 0x0D, 0x0A, 0x00, 0x0D, 0x0A, 0x51, 0x55, 0x49, 0x54, 0x0A,
+    ];
+
+    #[test]
+    fn proxy_header_v1_ipv4() {
+        let header = proxy_header(
+            "192.0.2.1:56324".parse().unwrap(),
+            "198.51.100.1:25565".parse().unwrap(),
+            ProxyVersion::V1,
+        )
+        .unwrap();
+        assert_eq!(
+            &header[..],
+            b"PROXY TCP4 192.0.2.1 198.51.100.1 56324 25565\r\n"
+        );
+    }
+
+    #[test]
+    fn proxy_header_v1_ipv6() {
+        let header = proxy_header(
+            "[2001:db8::1]:56324".parse().unwrap(),
+            "[2001:db8::2]:25565".parse().unwrap(),
+            ProxyVersion::V1,
+        )
+        .unwrap();
+        assert_eq!(
+            &header[..],
+            b"PROXY TCP6 2001:db8::1 2001:db8::2 56324 25565\r\n"
+        );
+    }
+
+    #[test]
+    fn proxy_header_v2_ipv4() {
+        let header = proxy_header(
+            "192.0.2.1:56324".parse().unwrap(),
+            "198.51.100.1:25565".parse().unwrap(),
+            ProxyVersion::V2,
+        )
+        .unwrap();
+
+        let mut expected = V2_SIGNATURE.to_vec();
+        expected.extend([0x21, 0x11, 0x00, 0x0C]);
+        expected.extend([192, 0, 2, 1, 198, 51, 100, 1]);
+        expected.extend(56324u16.to_be_bytes());
+        expected.extend(25565u16.to_be_bytes());
+        assert_eq!(&header[..], &expected[..]);
+    }
+
+    #[test]
+    fn proxy_header_v2_ipv6() {
+        let source: std::net::Ipv6Addr = "2001:db8::1".parse().unwrap();
+        let destination: std::net::Ipv6Addr = "2001:db8::2".parse().unwrap();
+        let header = proxy_header(
+            "[2001:db8::1]:56324".parse().unwrap(),
+            "[2001:db8::2]:25565".parse().unwrap(),
+            ProxyVersion::V2,
+        )
+        .unwrap();
+
+        let mut expected = V2_SIGNATURE.to_vec();
+        expected.extend([0x21, 0x21, 0x00, 0x24]);
+        expected.extend(source.octets());
+        expected.extend(destination.octets());
+        expected.extend(56324u16.to_be_bytes());
+        expected.extend(25565u16.to_be_bytes());
+        assert_eq!(&header[..], &expected[..]);
+    }
+
+    #[tokio::test]
+    #[ignore = "requires IPv6 loopback"]
+    async fn proxy_round_trip_ipv6() {
+        use crate::proto::client::ClientState;
+
+        let config: Config =
+            toml::from_str("[server]\ncommand = \"java -jar server.jar\"").unwrap();
+        let public = net::bind_listener("[::1]:0".parse().unwrap(), None).unwrap();
+        let backend = net::bind_listener("[::1]:0".parse().unwrap(), None).unwrap();
+        let backend_addr = backend.local_addr().unwrap();
+
+        let mut client = TcpStream::connect(public.local_addr().unwrap())
+            .await
+            .unwrap();
+        let (inbound, _) = public.accept().await.unwrap();
+        let inbound = ClientStream::new(inbound).unwrap();
+        let header = format!(
+            "PROXY TCP6 ::1 ::1 {} {}\r\n",
+            client.local_addr().unwrap().port(),
+            public.local_addr().unwrap().port()
+        );
+
+        let relay = Relay::new(ClientState::Handshake, -1);
+        let proxy = proxy(
+            inbound,
+            ProxyHeader::Proxy(ProxyVersion::V1),
+            backend_addr,
+            &config,
+            relay,
+        );
+        let peers = async {
+            client.write_all(b"ping").await.unwrap();
+            client.shutdown().await.unwrap();
+
+            // Backend sees the client IPv6 address in the proxy header
+            let (mut server, _) = backend.accept().await.unwrap();
+            let mut received = Vec::new();
+            server.read_to_end(&mut received).await.unwrap();
+            assert_eq!(received, format!("{header}ping").into_bytes());
+            server.write_all(b"pong").await.unwrap();
+            drop(server);
+
+            let mut response = Vec::new();
+            client.read_to_end(&mut response).await.unwrap();
+            assert_eq!(response, b"pong");
+        };
+
+        let (result, _) = tokio::join!(proxy, peers);
+        result.unwrap();
+    }
+
+    #[tokio::test]
+    async fn copy_read_timeout() {
+        let mut config: Config =
+            toml::from_str("[server]\ncommand = \"java -jar server.jar\"").unwrap();
+        config.advanced.proxy_read_timeout_secs = Some(1);
+
+        // Peer keeps connection open without sending data
+        let (mut from, _peer) = io::duplex(64);
+        let err = copy(&mut from, &mut io::sink(), &config).await.unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::TimedOut);
+    }
+
+    /// Forward `size` bytes between loopback connections, returns time taken.
+    #[cfg(target_os = "linux")]
+    async fn forward_throughput(size: usize, splice: bool) -> std::time::Duration {
+        use tokio::net::TcpListener;
+
+        // Source writes to proxied stream, which is forwarded to sink
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let mut source = TcpStream::connect(addr).await.unwrap();
+        let (mut from, _) = listener.accept().await.unwrap();
+        let mut to = TcpStream::connect(addr).await.unwrap();
+        let (mut sink, _) = listener.accept().await.unwrap();
+
+        let start = std::time::Instant::now();
+        let writer = tokio::spawn(async move {
+            let buf = vec![0; 64 * 1024];
+            for _ in 0..size / buf.len() {
+                source.write_all(&buf).await.unwrap();
+            }
+            source.shutdown().await.unwrap();
+        });
+        let reader = tokio::spawn(async move {
+            let mut buf = vec![0; 64 * 1024];
+            let mut total = 0;
+            loop {
+                match sink.read(&mut buf).await.unwrap() {
+                    0 => return total,
+                    len => total += len,
+                }
+            }
+        });
+
+        if splice {
+            forward_splice(&from, &to).await.unwrap();
+        } else {
+            let (mut ri, _) = from.split();
+            let (_, mut wo) = to.split();
+            io::copy(&mut ri, &mut wo).await.unwrap();
+            wo.shutdown().await.unwrap();
+        }
+        writer.await.unwrap();
+        assert_eq!(reader.await.unwrap(), size);
+        start.elapsed()
+    }
+
+    /// Benchmark splice forwarding against copying with Tokio.
+    ///
+    /// Run with `cargo test --release bench_forward -- --ignored --nocapture`.
+    #[cfg(target_os = "linux")]
+    #[tokio::test(flavor = "multi_thread")]
+    #[ignore]
+    async fn bench_forward_throughput() {
+        const SIZE: usize = 1 << 30;
+        for (name, splice) in [("tokio copy", false), ("splice", true)] {
+            let elapsed = forward_throughput(SIZE, splice).await;
+            println!(
+                "{name}: {:.0} MB/s",
+                SIZE as f64 / elapsed.as_secs_f64() / 1_000_000.0
+            );
+        }
+    }
+}
@@ -1,22 +1,44 @@
-use std::net::IpAddr;
-use std::sync::atomic::{AtomicU8, Ordering};
+use std::net::{IpAddr, SocketAddr};
+use std::process::Stdio;
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU8, Ordering};
 use std::sync::Arc;
 use std::time::{Duration, Instant};
 
+use chrono::{DateTime, Utc};
 use futures::FutureExt;
+use log::Level;
 use minecraft_protocol::version::v1_20_3::status::ServerStatus;
-use tokio::process::Command;
+use regex::Regex;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWriteExt, BufReader};
+use tokio::process::{ChildStdin, Command};
 use tokio::sync::watch;
-#[cfg(feature = "rcon")]
-use tokio::sync::Semaphore;
 use tokio::sync::{Mutex, RwLock, RwLockReadGuard};
+#[cfg(feature = "rcon")]
+use tokio::sync::{Notify, Semaphore};
 use tokio::time;
 
-use crate::config::{Config, Server as ConfigServer};
-use crate::mc::ban::{BannedIp, BannedIps};
+use crate::action::start::rewrite_server_properties;
+use crate::balance::Balancer;
+#[cfg(feature = "rcon")]
+use crate::config::LiveConfig;
+use crate::config::{
+    Config, ForgeVariant, FreezeFailAction, Server as ConfigServer, TimeoutAction,
+};
+use crate::history::History;
+use crate::hooks::{self, Hook};
+use crate::join::queue::QueueState;
+use crate::mc::ban::{self, BanFileCache, BannedIp, BannedIps};
+#[cfg(feature = "rcon")]
+use crate::mc::rcon::RconPool;
+use crate::mc::server_properties::{self, MaxPlayersCache};
 use crate::mc::whitelist::Whitelist;
+use crate::motd;
+use crate::notify;
 use crate::os;
+use crate::packet_hook::PacketHooks;
 use crate::proto::packets::play::join_game::JoinGameData;
+use crate::stats::ServerStats;
+use crate::status::CachedStatus;
 
 /// Server cooldown after the process quit.
 /// Used to give it some more time to quit forgotten threads, such as for RCON.
@@ -29,12 +51,37 @@ const SERVER_QUIT_COOLDOWN: Duration = Duration::from_millis(2500);
 #[cfg(feature = "rcon")]
 const RCON_COOLDOWN: Duration = Duration::from_secs(15);
 
+/// Interval to retry connecting to RCON at, to run commands after the server started.
+#[cfg(feature = "rcon")]
+const RCON_COMMANDS_RETRY_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Number of attempts to connect to RCON, to run commands after the server started.
+#[cfg(feature = "rcon")]
+const RCON_COMMANDS_ATTEMPTS: u32 = 12;
+
 /// Exit codes that are allowed.
 ///
 /// - 143: https://github.com/timvisee/lazymc/issues/26#issuecomment-1435670029
 /// - 130: https://unix.stackexchange.com/q/386836/61092
 const ALLOWED_EXIT_CODES: [i32; 2] = [130, 143];
 
+/// Environment variables always passed to the server process when using an environment
+/// passthrough list. Matched case-insensitively for Windows.
+const ENV_SAFE: [&str; 12] = [
+    "PATH",
+    "HOME",
+    "USER",
+    "LANG",
+    "LC_ALL",
+    "TZ",
+    "TERM",
+    "TMPDIR",
+    "TEMP",
+    "TMP",
+    "SYSTEMROOT",
+    "COMSPEC",
+];
+
 /// Shared server state.
 #[derive(Debug)]
 pub struct Server {
@@ -49,16 +96,32 @@ pub struct Server {
     /// State watch receiver, subscribe to state changes.
     state_watch_receiver: watch::Receiver<State>,
 
+    /// Time of last state change.
+    state_changed_at: RwLock<Instant>,
+
     /// Server process PID.
     ///
     /// Set if a server process is running.
     pid: Mutex<Option<u32>>,
 
+    /// PID of the last server process lazymc started.
+    ///
+    /// Remains set after the process quit.
+    last_pid: Mutex<Option<u32>>,
+
+    /// Server process stdin.
+    ///
+    /// Set if a server process is running and a stop command is sent through stdin.
+    stdin: Mutex<Option<ChildStdin>>,
+
     /// Last known server status.
     ///
     /// Will remain set once known, not cleared if server goes offline.
     status: RwLock<Option<ServerStatus>>,
 
+    /// Status response cached for status pings.
+    pub status_cache: RwLock<Option<CachedStatus>>,
+
     /// Last active time.
     ///
     /// The last time there was activity on the server. Also set at the moment the server comes
@@ -68,17 +131,57 @@ pub struct Server {
     /// Force server to stay online until.
     keep_online_until: RwLock<Option<Instant>>,
 
+    /// Lock held while starting, to run the pre start hook once.
+    start_lock: Mutex<()>,
+
+    /// Whether a scheduled sleep is pending, sleeps once no players are online.
+    scheduled_sleep: AtomicBool,
+
+    /// Peak number of online players since the server came online.
+    peak_players: AtomicU32,
+
+    /// Whether the server is started by the wake schedule.
+    ///
+    /// Idle time then counts from the end of the minimum online time.
+    scheduled_wake: AtomicBool,
+
+    /// Whether lockout is enabled by the lockout schedule, overriding the configured state.
+    scheduled_lockout: AtomicBool,
+
+    /// Number of consecutive crash restarts.
+    crash_restarts: AtomicU32,
+
+    /// Whether the last start was force killed for reaching the start timeout.
+    start_timed_out: AtomicBool,
+
+    /// Whether freezing the server process failed, to stop it instead.
+    freeze_unsupported: AtomicBool,
+
+    /// Whether the server is being started once more after reaching the start timeout.
+    start_timeout_retried: AtomicBool,
+
     /// Time to force kill the server process at.
     ///
     /// Used as starting/stopping timeout.
     kill_at: RwLock<Option<Instant>>,
 
+    /// Time to send the server process a stop signal at.
+    ///
+    /// Used as timeout after sending the stop command.
+    terminate_at: RwLock<Option<Instant>>,
+
     /// List of banned IPs.
     banned_ips: RwLock<BannedIps>,
 
+    /// Cache to reload banned IPs file once changed.
+    ban_file_cache: std::sync::Mutex<BanFileCache>,
+
     /// Whitelist if enabled.
     whitelist: RwLock<Option<Whitelist>>,
 
+    /// Cache of maximum players from server.properties.
+    max_players_cache: std::sync::Mutex<MaxPlayersCache>,
+
     /// Lock for exclusive RCON operations.
     #[cfg(feature = "rcon")]
     rcon_lock: Semaphore,
@@ -87,6 +190,22 @@ pub struct Server {
     #[cfg(feature = "rcon")]
     rcon_last_stop: Mutex<Option<Instant>>,
 
+    /// Pool of idle RCON connections.
+    #[cfg(feature = "rcon")]
+    rcon_pool: RconPool,
+
+    /// Notified each time the server is started, to run RCON start commands.
+    #[cfg(feature = "rcon")]
+    rcon_started: Notify,
+
+    /// Probed protocol version of server, `0` if unknown.
+    pub probed_protocol: AtomicU32,
+
+    /// Probed proxy header support of server.
+    ///
+    /// `0` if unknown, `1` if the server doesn't accept proxy headers, `2` if it requires them.
+    pub probed_proxy_header: AtomicU8,
+
     /// Probed join game data.
     pub probed_join_game: RwLock<Option<JoinGameData>>,
 
@@ -94,6 +213,29 @@ pub struct Server {
     ///
     /// Sent to clients when they connect to lobby. Recorded from server by probe.
     pub forge_payload: RwLock<Vec<Vec<u8>>>,
+
+    /// Forge variant detected from server status.
+    ///
+    /// Only used if Forge variant is set to automatic detection.
+    pub forge_detected: RwLock<Option<ForgeVariant>>,
+
+    /// Queue of clients held by the queue join method.
+    pub join_queue: Arc<Mutex<QueueState>>,
+
+    /// Server statistics.
+    pub stats: ServerStats,
+
+    /// Number of players currently connected through lazymc, tracked for player hooks.
+    pub players: AtomicU32,
+
+    /// History database recording sessions and server events.
+    pub history: History,
+
+    /// Packet hooks run on connections relayed to the server.
+    pub packet_hooks: Arc<PacketHooks>,
+
+    /// Balancer over configured backends.
+    pub balancer: Balancer,
 }
 
 impl Server {
@@ -102,6 +244,11 @@ impl Server {
         State::from_u8(self.state.load(Ordering::Relaxed))
     }
 
+    /// Get time elapsed since the last state change.
+    pub async fn state_elapsed(&self) -> Duration {
+        self.state_changed_at.read().await.elapsed()
+    }
+
     /// Get state receiver to subscribe on server state changes.
     pub fn state_receiver(&self) -> watch::Receiver<State> {
         self.state_watch_receiver.clone()
@@ -144,6 +291,7 @@ impl Server {
         trace!("Change server state from {:?} to {:?}", old, new);
 
         // Broadcast change
+        *self.state_changed_at.write().await = Instant::now();
         let _ = self.state_watch_sender.send(new);
 
         // Update kill at time for starting/stopping state
@@ -151,11 +299,66 @@ impl Server {
             State::Starting if config.server.start_timeout > 0 => {
                 Some(Instant::now() + Duration::from_secs(config.server.start_timeout as u64))
             }
+            State::Resuming if config.server.resume_timeout > 0 => {
+                Some(Instant::now() + Duration::from_secs(config.server.resume_timeout as u64))
+            }
             State::Stopping if config.server.stop_timeout > 0 => {
                 Some(Instant::now() + Duration::from_secs(config.server.stop_timeout as u64))
             }
             _ => None,
         };
+        self.terminate_at.write().await.take();
+
+        // Update statistics, the server was idle since it was last active
+        let idle = match new {
+            State::Stopped => self.last_active.read().await.map(|at| at.elapsed()),
+            _ => None,
+        };
+        let mut online_for = None;
+        match new {
+            State::Starting | State::Resuming => self.stats.record_wake().await,
+            State::Started => self.stats.record_online().await,
+            State::Stopped => online_for = self.stats.record_offline(idle).await,
+            State::Stopping | State::CrashLoopBackoff => {}
+        }
+        if config.stats.enabled {
+            self.history.record_event(new.name());
+        }
+
+        // Scheduled sleep is done or cancelled once the server goes down or starts again
+        if new != State::Started {
+            self.scheduled_sleep.store(false, Ordering::Relaxed);
+        }
+
+        // Start timeout is for the last start only, retry is done once the server comes online
+        match new {
+            State::Starting | State::Resuming => {
+                self.start_timed_out.store(false, Ordering::Relaxed)
+            }
+            State::Started => self.start_timeout_retried.store(false, Ordering::Relaxed),
+            _ => {}
+        }
+
+        // Pooled RCON connections are closed by the stopped server
+        #[cfg(feature = "rcon")]
+        if new == State::Stopped {
+            self.rcon_pool.clear().await;
+        }
+
+        // Run RCON start commands, no matter how the server was detected to be online
+        #[cfg(feature = "rcon")]
+        if new == State::Started {
+            self.rcon_started.notify_one();
+        }
+
+        // Notify systemd of new state
+        #[cfg(unix)]
+        if config.advanced.systemd_notify() {
+            crate::systemd::notify(&format!("STATUS=Server {}", new.name()));
+        }
+
+        // Send notifications
+        notify::event(config, self, new.into()).await;
 
         // Online/offline messages
         match new {
@@ -164,11 +367,36 @@ impl Server {
             _ => {}
         }
 
+        // Report wake cycle times, to help tuning the sleep after time
+        if let Some(online_for) = online_for {
+            info!(
+                target: "lazymc::monitor",
+                "Server was online for {}, of which {} without players before sleeping",
+                motd::format_duration(online_for),
+                motd::format_duration(idle.unwrap_or_default()),
+            );
+        }
+
         // If Starting -> Started, update active time and keep it online for configured time
-        if old == State::Starting && new == State::Started {
+        if old.is_starting() && new == State::Started {
+            self.peak_players.store(0, Ordering::Relaxed);
             self.update_last_active().await;
             self.keep_online_for(Some(config.time.min_online_time))
                 .await;
+
+            // Scheduled wake without players, start idle time after minimum online time
+            if self.scheduled_wake.swap(false, Ordering::Relaxed) {
+                self.last_active.write().await.replace(
+                    Instant::now() + Duration::from_secs(config.time.min_online_time as u64),
+                );
+            }
+        }
+
+        // Run post start/stop hooks in the background, state changes must not wait on them
+        match new {
+            State::Started => hooks::spawn(config, self, Hook::PostStart).await,
+            State::Stopped => hooks::spawn(config, self, Hook::PostStop).await,
+            _ => {}
         }
 
         true
@@ -181,7 +409,10 @@ impl Server {
     pub async fn update_status(&self, config: &Config, status: Option<ServerStatus>) {
         // Update state based on curren
         match (self.state(), &status) {
-            (State::Stopped | State::Starting, Some(_)) => {
+            (
+                State::Stopped | State::CrashLoopBackoff | State::Starting | State::Resuming,
+                Some(_),
+            ) => {
                 self.update_state(State::Started, config).await;
             }
             (State::Started, None) => {
@@ -192,8 +423,11 @@ impl Server {
 
         // Update last status if known
         if let Some(status) = status {
-            // Update last active time if there are online players
-            if status.players.online > 0 {
+            self.peak_players
+                .fetch_max(status.players.online, Ordering::Relaxed);
+
+            // Update last active time if enough players are online
+            if status.players.online >= config.time.min_players() {
                 self.update_last_active().await;
             }
 
@@ -203,20 +437,50 @@ impl Server {
 
     /// Try to start the server.
     ///
-    /// Does nothing if currently not in stopped state.
-    pub async fn start(config: Arc<Config>, server: Arc<Server>, username: Option<String>) -> bool {
-        // Must set state from stopped to starting
-        if !server
-            .update_state_from(Some(State::Stopped), State::Starting, &config)
-            .await
+    /// Does nothing if currently not in stopped or crash loop backoff state.
+    pub async fn start(config: Arc<Config>, server: Arc<Server>, wake: Option<WakeEvent>) -> bool {
+        // A stopped server still having a process is frozen
+        let frozen =
+            cfg!(unix) && config.server.freeze_process && server.pid.lock().await.is_some();
+
+        // Run pre start hook once, abort start if it fails
         {
-            return false;
+            let _start_lock = server.start_lock.lock().await;
+            let stopped = server.state();
+            if !stopped.is_stopped() {
+                return false;
+            }
+            if !hooks::run(&config, &server, Hook::PreStart).await {
+                error!(target: "lazymc", "Not starting server, pre start hook failed");
+                return false;
+            }
+
+            // Must set state from stopped to starting, or resuming if the process is frozen
+            let starting = if frozen {
+                State::Resuming
+            } else {
+                State::Starting
+            };
+            if !server
+                .update_state_from(Some(stopped), starting, &config)
+                .await
+            {
+                return false;
+            }
         }
 
-        // Log starting message
-        match username {
-            Some(username) => info!(target: "lazymc", "Starting server for '{}'...", username),
-            None => info!(target: "lazymc", "Starting server..."),
+        // Log starting message, player wake resets crash restarts
+        let action = if frozen { "Resuming" } else { "Starting" };
+        match wake {
+            Some(wake) => {
+                info!(target: "lazymc", "{} server for '{}'...", action, wake.triggered_by_username.as_deref().unwrap_or("?"));
+                wake.log();
+                if config.stats.enabled {
+                    server.history.record_wake(&wake);
+                }
+                server.reset_crash_restarts();
+            }
+            None => info!(target: "lazymc", "{} server...", action),
         }
 
         // Unfreeze server if it is frozen
@@ -242,15 +506,41 @@ impl Server {
     /// This will attempt to stop the server with all available methods.
     #[allow(unused_variables)]
     pub async fn stop(&self, config: &Config) -> bool {
+        // Run pre stop hook, only if a stop begins and not if stopped or stopping already
+        if !self.state().is_stopped() && self.state() != State::Stopping {
+            hooks::run(config, self, Hook::PreStop).await;
+        }
+
+        // Run RCON stop commands
+        #[cfg(feature = "rcon")]
+        if self.state() == State::Started && config.rcon.enabled {
+            run_rcon_commands(config, self, &config.rcon.on_stop_commands, 1).await;
+        }
+
         // Try to freeze through signal
         #[cfg(unix)]
-        if config.server.freeze_process && freeze_server_signal(config, self).await {
+        if config.server.freeze_process
+            && !self.freeze_unsupported.load(Ordering::Relaxed)
+            && freeze_server_signal(config, self).await
+        {
             return true;
         }
+        #[cfg(not(unix))]
+        if config.server.freeze_process && !self.freeze_unsupported.swap(true, Ordering::Relaxed) {
+            warn!(target: "lazymc", "Freezing server process is not supported on this platform, stopping it instead");
+        }
 
         // Try to stop through RCON if started
         #[cfg(feature = "rcon")]
-        if self.state() == State::Started && stop_server_rcon(config, self).await {
+        if self.state() == State::Started
+            && ConfigServer::stop_via_rcon(config)
+            && stop_server_rcon(config, self).await
+        {
+            return true;
+        }
+
+        // Try to stop through stop command on stdin
+        if stop_server_stdin(config, self).await {
             return true;
         }
 
@@ -264,6 +554,41 @@ impl Server {
         false
     }
 
+    /// Check whether a server process is currently running.
+    ///
+    /// This includes a frozen server process.
+    pub async fn has_process(&self) -> bool {
+        self.pid.lock().await.is_some()
+    }
+
+    /// Check whether the server port is held by the server process lazymc started.
+    ///
+    /// A port held after the server process quit belongs to an orphaned process. Always true if
+    /// lazymc never started a server process, or if the port owner can't be determined.
+    #[allow(unused_variables)]
+    pub async fn owns_port(&self, port: u16) -> bool {
+        if self.last_pid.lock().await.is_none() {
+            return true;
+        }
+
+        #[cfg(unix)]
+        {
+            let pid = *self.pid.lock().await;
+            let owner = tokio::task::spawn_blocking(move || os::port::owner(port))
+                .await
+                .ok()
+                .flatten();
+            return match (owner, pid) {
+                (None, _) => true,
+                (Some(owner), Some(pid)) => os::port::is_descendant(owner, pid),
+                (Some(_), None) => false,
+            };
+        }
+
+        #[cfg(not(unix))]
+        true
+    }
+
     /// Force kill running server.
     ///
     /// This requires the server PID to be known.
@@ -283,15 +608,35 @@ impl Server {
             return false;
         }
 
-        // Never sleep if players are online
         let players_online = self
             .status
             .read()
             .await
             .as_ref()
-            .map(|status| status.players.online > 0)
-            .unwrap_or(false);
-        if players_online {
+            .map(|status| status.players.online)
+            .unwrap_or(0);
+
+        // Give server time to finish autosaving after the last player left before freezing it
+        if cfg!(unix) && config.server.freeze_process && players_online == 0 {
+            let grace = Duration::from_secs(config.server.freeze_grace_secs as u64);
+            let in_grace = self
+                .last_active
+                .read()
+                .await
+                .is_some_and(|last_active| last_active.elapsed() < grace);
+            if in_grace {
+                trace!(target: "lazymc", "Not sleeping because of freeze grace period");
+                return false;
+            }
+        }
+
+        // Sleep now if scheduled and nobody is online
+        if players_online == 0 && self.scheduled_sleep.load(Ordering::Relaxed) {
+            return true;
+        }
+
+        // Never sleep if enough players are online
+        if players_online >= config.time.min_players() {
             trace!(target: "lazymc", "Not sleeping because players are online");
             return false;
         }
@@ -308,14 +653,58 @@ impl Server {
             return false;
         }
 
-        // Last active time must have passed sleep threshold
+        // Last active time must have passed sleep threshold, based on peak players
         if let Some(last_idle) = self.last_active.read().await.as_ref() {
-            return last_idle.elapsed() >= Duration::from_secs(config.time.sleep_after as u64);
+            let sleep_after = config
+                .time
+                .sleep_after_for(self.peak_players.load(Ordering::Relaxed));
+            return last_idle.elapsed() >= Duration::from_secs(sleep_after as u64);
         }
 
         false
     }
 
+    /// Mark whether the next start is triggered by the wake schedule.
+    pub fn set_scheduled_wake(&self, scheduled: bool) {
+        self.scheduled_wake.store(scheduled, Ordering::Relaxed);
+    }
+
+    /// Enable lockout by schedule, or revert to the configured lockout state.
+    pub fn set_scheduled_lockout(&self, enabled: bool) {
+        self.scheduled_lockout.store(enabled, Ordering::Relaxed);
+    }
+
+    /// Whether lockout is currently enabled, either in config or by schedule.
+    pub fn is_locked_out(&self, config: &Config) -> bool {
+        config.lockout.enabled || self.scheduled_lockout.load(Ordering::Relaxed)
+    }
+
+    /// Whether the last start was force killed for reaching the start timeout.
+    pub fn is_start_timed_out(&self) -> bool {
+        self.start_timed_out.load(Ordering::Relaxed)
+    }
+
+    /// Mark the current start as force killed for reaching the start timeout.
+    pub fn set_start_timed_out(&self) {
+        self.start_timed_out.store(true, Ordering::Relaxed);
+    }
+
+    /// Reset consecutive crash restarts.
+    fn reset_crash_restarts(&self) {
+        self.crash_restarts.store(0, Ordering::Relaxed);
+    }
+
+    /// Schedule the server to sleep once no players are online.
+    ///
+    /// Does nothing if the server is not online.
+    pub fn schedule_sleep(&self) -> bool {
+        if self.state() != State::Started {
+            return false;
+        }
+        self.scheduled_sleep.store(true, Ordering::Relaxed);
+        true
+    }
+
     /// Decide whether to force kill the server process.
     pub async fn should_kill(&self) -> bool {
         self.kill_at
@@ -325,6 +714,49 @@ impl Server {
             .unwrap_or(false)
     }
 
+    /// Decide whether to send the server process a stop signal, after the stop command timed out.
+    pub async fn should_terminate(&self) -> bool {
+        self.terminate_at
+            .read()
+            .await
+            .map(|t| t <= Instant::now())
+            .unwrap_or(false)
+    }
+
+    /// Send the server process a stop signal, after the stop command timed out.
+    ///
+    /// Only available on Unix.
+    #[cfg(unix)]
+    pub async fn terminate(&self) -> bool {
+        self.terminate_at.write().await.take();
+        match *self.pid.lock().await {
+            Some(pid) => os::kill_gracefully(pid),
+            None => false,
+        }
+    }
+
+    /// Send stop signal after stop timeout if the stop command doesn't stop the server.
+    ///
+    /// Postpones force killing so the stop signal has a full stop timeout as well.
+    async fn terminate_after_timeout(&self, config: &Config) {
+        if config.server.stop_command.is_none() || config.server.stop_timeout == 0 {
+            return;
+        }
+
+        #[cfg(unix)]
+        {
+            let timeout = Duration::from_secs(config.server.stop_timeout as u64);
+            self.terminate_at
+                .write()
+                .await
+                .replace(Instant::now() + timeout);
+            self.kill_at
+                .write()
+                .await
+                .replace(Instant::now() + timeout * 2);
+        }
+    }
+
     /// Read last known server status.
     pub async fn status(&self) -> RwLockReadGuard<'_, Option<ServerStatus>> {
         self.status.read().await
@@ -355,14 +787,6 @@ impl Server {
         self.banned_ips.read().await.get(ip)
     }
 
-    /// Check whether the given IP is banned.
-    ///
-    /// This uses the latest known `banned-ips.json` contents if known.
-    /// If this feature is disabled, this will always return false.
-    pub fn is_banned_ip_blocking(&self, ip: &IpAddr) -> bool {
-        futures::executor::block_on(async { self.is_banned_ip(ip).await })
-    }
-
     /// Check whether the given username is whitelisted.
     ///
     /// Returns `true` if no whitelist is currently used.
@@ -375,6 +799,46 @@ impl Server {
             .unwrap_or(true)
     }
 
+    /// Get the Forge variant of the server.
+    ///
+    /// Uses the detected variant with automatic detection, not Forge if not detected yet.
+    pub async fn forge_variant(&self, config: &Config) -> ForgeVariant {
+        match config.server.forge_variant() {
+            ForgeVariant::Auto => self
+                .forge_detected
+                .read()
+                .await
+                .unwrap_or(ForgeVariant::None),
+            variant => variant,
+        }
+    }
+
+    /// Load banned IPs from the server directory if the file changed since it was last checked.
+    ///
+    /// Blocking, the file is checked at most once per second. Returns `None` if unchanged, or if
+    /// banned IPs are not used.
+    pub fn load_changed_banned_ips(&self, config: &Config) -> Option<BannedIps> {
+        if !config.server.block_banned_ips && !config.server.drop_banned_ips {
+            return None;
+        }
+        let path = ConfigServer::server_directory(config)?.join(ban::FILE);
+        if !self.ban_file_cache.lock().unwrap().changed(&path) {
+            return None;
+        }
+
+        trace!(target: "lazymc", "Banned IPs file changed, reloading...");
+        if !path.is_file() {
+            return Some(BannedIps::default());
+        }
+        match ban::load(&path) {
+            Ok(ips) => Some(ips),
+            Err(err) => {
+                debug!(target: "lazymc", "Failed load banned IPs from {}, ignoring: {}", ban::FILE, err);
+                None
+            }
+        }
+    }
+
     /// Update the list of banned IPs.
     pub async fn set_banned_ips(&self, ips: BannedIps) {
         *self.banned_ips.write().await = ips;
@@ -385,6 +849,12 @@ impl Server {
         futures::executor::block_on(async { self.set_banned_ips(ips).await })
     }
 
+    /// Get maximum number of players from server.properties, if it exists.
+    pub fn properties_max_players(&self, config: &Config) -> Option<u32> {
+        let path = ConfigServer::server_directory(config)?.join(server_properties::FILE);
+        self.max_players_cache.lock().unwrap().get(&path)
+    }
+
     /// Update the whitelist.
     pub async fn set_whitelist(&self, whitelist: Option<Whitelist>) {
         *self.whitelist.write().await = whitelist;
@@ -404,21 +874,85 @@ impl Default for Server {
             state: AtomicU8::new(State::Stopped.to_u8()),
             state_watch_sender,
             state_watch_receiver,
+            state_changed_at: RwLock::new(Instant::now()),
             pid: Default::default(),
+            last_pid: Default::default(),
+            stdin: Default::default(),
             status: Default::default(),
+            status_cache: Default::default(),
             last_active: Default::default(),
             keep_online_until: Default::default(),
+            start_lock: Default::default(),
+            scheduled_sleep: Default::default(),
+            scheduled_wake: Default::default(),
+            scheduled_lockout: Default::default(),
+            peak_players: Default::default(),
+            crash_restarts: Default::default(),
+            start_timed_out: Default::default(),
+            freeze_unsupported: Default::default(),
+            start_timeout_retried: Default::default(),
             kill_at: Default::default(),
+            terminate_at: Default::default(),
             banned_ips: Default::default(),
+            ban_file_cache: Default::default(),
             whitelist: Default::default(),
+            max_players_cache: Default::default(),
             #[cfg(feature = "rcon")]
             rcon_lock: Semaphore::new(1),
             #[cfg(feature = "rcon")]
             rcon_last_stop: Default::default(),
+            #[cfg(feature = "rcon")]
+            rcon_pool: Default::default(),
+            #[cfg(feature = "rcon")]
+            rcon_started: Notify::new(),
+            probed_protocol: Default::default(),
+            probed_proxy_header: Default::default(),
             probed_join_game: Default::default(),
             forge_payload: Default::default(),
+            forge_detected: Default::default(),
+            join_queue: Default::default(),
+            stats: Default::default(),
+            players: Default::default(),
+            history: Default::default(),
+            packet_hooks: Default::default(),
+            balancer: Default::default(),
+        }
+    }
+}
+
+/// Player wake of a sleeping server.
+#[derive(Debug, Clone)]
+pub struct WakeEvent {
+    /// Username of the player that triggered the wake, if known.
+    pub triggered_by_username: Option<String>,
+
+    /// Address of the client that triggered the wake.
+    pub triggered_by_ip: SocketAddr,
+
+    /// Time of the wake.
+    pub triggered_at: DateTime<Utc>,
+}
+
+impl WakeEvent {
+    /// Wake triggered now by the given client.
+    pub fn new(username: Option<String>, peer: SocketAddr) -> Self {
+        Self {
+            triggered_by_username: username,
+            triggered_by_ip: peer,
+            triggered_at: Utc::now(),
         }
     }
+
+    /// Log who woke the server.
+    fn log(&self) {
+        info!(
+            target: "lazymc::wake",
+            "Server woken by '{}' from {} at {}",
+            self.triggered_by_username.as_deref().unwrap_or("?"),
+            self.triggered_by_ip.ip(),
+            self.triggered_at.to_rfc3339(),
+        );
+    }
 }
 
 /// Server state.
@@ -435,6 +969,12 @@ pub enum State {
 
     /// Server is stopping.
     Stopping,
+
+    /// Frozen server process is resuming.
+    Resuming,
+
+    /// Server is stopped after crashing repeatedly, not restarted until woken by a player.
+    CrashLoopBackoff,
 }
 
 impl State {
@@ -445,6 +985,8 @@ impl State {
             1 => Self::Starting,
             2 => Self::Started,
             3 => Self::Stopping,
+            4 => Self::Resuming,
+            5 => Self::CrashLoopBackoff,
             _ => panic!("invalid State u8"),
         }
     }
@@ -456,8 +998,32 @@ impl State {
             Self::Starting => 1,
             Self::Started => 2,
             Self::Stopping => 3,
+            Self::Resuming => 4,
+            Self::CrashLoopBackoff => 5,
         }
     }
+
+    /// Human readable state name.
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Stopped => "sleeping",
+            Self::Starting => "starting",
+            Self::Started => "online",
+            Self::Stopping => "stopping",
+            Self::Resuming => "resuming",
+            Self::CrashLoopBackoff => "crash_loop_backoff",
+        }
+    }
+
+    /// Whether the server is stopped, including after crashing repeatedly.
+    pub fn is_stopped(self) -> bool {
+        matches!(self, Self::Stopped | Self::CrashLoopBackoff)
+    }
+
+    /// Whether the server is starting or resuming, and is not online yet.
+    pub fn is_starting(self) -> bool {
+        matches!(self, Self::Starting | Self::Resuming)
+    }
 }
 
 /// Invoke server command, store PID and wait for it to quit.
@@ -465,17 +1031,60 @@ pub async fn invoke_server_cmd(
     config: Arc<Config>,
     state: Arc<Server>,
 ) -> Result<(), Box<dyn std::error::Error>> {
+    // Rewrite server.properties with latest configuration before each start
+    rewrite_server_properties(&config);
+
     // Configure command
-    let args = shlex::split(&config.server.command).expect("invalid server command");
+    let args = ConfigServer::command_args(&config).expect("invalid server command");
     let mut cmd = Command::new(&args[0]);
     cmd.args(args.iter().skip(1));
     cmd.kill_on_drop(true);
+    if ConfigServer::stop_via_stdin(&config) {
+        cmd.stdin(Stdio::piped());
+    }
+    let ready_pattern = config.server.ready_pattern();
+    if config.server.log_stdout || ready_pattern.is_some() {
+        cmd.stdout(Stdio::piped());
+    }
+    if config.server.log_stderr {
+        cmd.stderr(Stdio::piped());
+    }
 
     // Set working directory
     if let Some(ref dir) = ConfigServer::server_directory(&config) {
         cmd.current_dir(dir);
     }
 
+    // Configure environment, only pass listed variables if configured
+    if !config.server.env_passthrough.is_empty() {
+        cmd.env_clear();
+        cmd.envs(std::env::vars_os().filter(|(key, _)| {
+            key.to_str().is_some_and(|key| {
+                ENV_SAFE.iter().any(|safe| safe.eq_ignore_ascii_case(key))
+                    || config.server.env_passthrough.iter().any(|k| k == key)
+            })
+        }));
+    }
+    cmd.envs(&config.server.env_set);
+
+    // Drop to configured user and group
+    #[cfg(unix)]
+    {
+        let ids = os::user::server_ids(&config.advanced);
+        if let Some(gid) = ids.gid {
+            cmd.gid(gid);
+        }
+        if let Some(uid) = ids.uid {
+            cmd.uid(uid);
+        }
+
+        // Apply resource limits in child before exec
+        let limits = config.advanced.resource_limits.clone();
+        unsafe {
+            cmd.pre_exec(move || os::limits::apply(&limits).map_err(std::io::Error::from));
+        }
+    }
+
     // Spawn process
     let mut child = match cmd.spawn() {
         Ok(child) => child,
@@ -486,11 +1095,30 @@ pub async fn invoke_server_cmd(
     };
 
     // Remember PID
-    state
-        .pid
-        .lock()
-        .await
-        .replace(child.id().expect("unknown server PID"));
+    let pid = child.id().expect("unknown server PID");
+    state.pid.lock().await.replace(pid);
+    state.last_pid.lock().await.replace(pid);
+    *state.stdin.lock().await = child.stdin.take();
+
+    // Handle server output
+    if let Some(stdout) = child.stdout.take() {
+        tokio::spawn(handle_output(
+            stdout,
+            config.server.log_stdout.then_some(Level::Info),
+            ready_pattern,
+            config.clone(),
+            state.clone(),
+        ));
+    }
+    if let Some(stderr) = child.stderr.take() {
+        tokio::spawn(handle_output(
+            stderr,
+            Some(Level::Warn),
+            None,
+            config.clone(),
+            state.clone(),
+        ));
+    }
 
     // Wait for process to exit, handle status
     let crashed = match child.wait().await {
@@ -509,7 +1137,10 @@ pub async fn invoke_server_cmd(
         }
         Ok(status) => {
             warn!(target: "lazymc", "Server process stopped with error code ({})", status);
-            state.state() == State::Started
+            matches!(
+                state.state(),
+                State::Starting | State::Resuming | State::Started
+            )
         }
         Err(err) => {
             error!(target: "lazymc", "Failed to wait for server process to quit: {}", err);
@@ -520,6 +1151,7 @@ pub async fn invoke_server_cmd(
 
     // Forget server PID
     state.pid.lock().await.take();
+    state.stdin.lock().await.take();
 
     // Give server a little more time to quit forgotten threads
     time::sleep(SERVER_QUIT_COOLDOWN).await;
@@ -527,20 +1159,164 @@ pub async fn invoke_server_cmd(
     // Set server state to stopped
     state.update_state(State::Stopped, &config).await;
 
-    // Restart on crash
+    // Handle reaching start timeout with configured action, kill restarts as crash
+    if state.is_start_timed_out() {
+        match config.server.start_timeout_action {
+            TimeoutAction::Kill => {}
+            TimeoutAction::KillAndRestart
+                if !state.start_timeout_retried.swap(true, Ordering::Relaxed) =>
+            {
+                warn!(target: "lazymc", "Server took too long to start, trying to start it once more...");
+                Server::start(config, state, None).await;
+                return Ok(());
+            }
+            TimeoutAction::KillAndRestart | TimeoutAction::KillAndSleep => {
+                warn!(target: "lazymc", "Server took too long to start, sleeping until woken again");
+                state.start_timeout_retried.store(false, Ordering::Relaxed);
+                return Ok(());
+            }
+        }
+    }
+
+    // Restart on crash, with backoff and limited number of attempts
     if crashed && config.server.wake_on_crash {
-        warn!(target: "lazymc", "Server crashed, restarting...");
+        let attempt = state.crash_restarts.fetch_add(1, Ordering::Relaxed);
+        if let Some(max) = config.server.max_crash_restarts {
+            if attempt >= max {
+                error!(target: "lazymc", "Server crashed {} times in a row, not restarting until woken by a player", attempt + 1);
+                state
+                    .update_state_from(Some(State::Stopped), State::CrashLoopBackoff, &config)
+                    .await;
+                return Ok(());
+            }
+        }
+
+        let backoff = config.server.restart_backoff(attempt);
+        warn!(target: "lazymc", "Server crashed, restarting in {}s...", backoff.as_secs());
+        time::sleep(backoff).await;
         Server::start(config, state, None).await;
     }
 
     Ok(())
 }
 
+/// Handle lines of server process output.
+///
+/// Logs lines at the given level if set. Marks the server as online once a line matches the
+/// ready pattern while starting.
+async fn handle_output(
+    output: impl AsyncRead + Unpin,
+    level: Option<Level>,
+    mut ready_pattern: Option<Regex>,
+    config: Arc<Config>,
+    server: Arc<Server>,
+) {
+    let filter = config.server.log_filter();
+    let mut lines = BufReader::new(output).lines();
+    loop {
+        match lines.next_line().await {
+            Ok(Some(line)) => {
+                if ready_pattern
+                    .as_ref()
+                    .map(|p| p.is_match(&line))
+                    .unwrap_or(false)
+                {
+                    ready_pattern = None;
+                    if server
+                        .update_state_from(Some(State::Starting), State::Started, &config)
+                        .await
+                    {
+                        debug!(target: "lazymc", "Server reported it is ready in its output");
+                    }
+                }
+
+                let level = match level {
+                    Some(level) => level,
+                    None => continue,
+                };
+                if filter.as_ref().map(|f| f.is_match(&line)).unwrap_or(false) {
+                    continue;
+                }
+                log!(target: "lazymc::server", level, "[{}] {}", config.server.log_prefix, line);
+            }
+            Ok(None) => break,
+            Err(err) => {
+                debug!(target: "lazymc", "Failed to read server process output: {}", err);
+                break;
+            }
+        }
+    }
+}
+
+/// Run RCON commands each time the server has started.
+///
+/// Retries until RCON becomes available, as it may come up some time after the server is online.
+#[cfg(feature = "rcon")]
+pub async fn run_rcon_start_commands(live_config: Arc<LiveConfig>, server: Arc<Server>) {
+    loop {
+        server.rcon_started.notified().await;
+
+        let config = live_config.get();
+        if !config.rcon.enabled || config.rcon.on_start_commands.is_empty() {
+            continue;
+        }
+
+        run_rcon_commands(
+            &config,
+            &server,
+            &config.rcon.on_start_commands,
+            RCON_COMMANDS_ATTEMPTS,
+        )
+        .await;
+    }
+}
+
+/// Run RCON commands in sequence, logging responses.
+///
+/// Connecting to RCON is attempted the given number of times while the server is started.
+#[cfg(feature = "rcon")]
+async fn run_rcon_commands(config: &Config, server: &Server, commands: &[String], attempts: u32) {
+    if commands.is_empty() {
+        return;
+    }
+
+    // Grab RCON lock, connect with retries
+    let rcon_lock = server.rcon_lock.acquire().await.unwrap();
+    let mut attempt = 0;
+    let mut rcon = loop {
+        attempt += 1;
+        match server.rcon_pool.get(config).await {
+            Ok(rcon) => break rcon,
+            Err(err) if attempt >= attempts || server.state() != State::Started => {
+                error!(target: "lazymc::rcon", "Failed to connect to RCON to run commands: {}", err);
+                return;
+            }
+            Err(err) => {
+                debug!(target: "lazymc::rcon", "RCON not available yet, retrying: {}", err);
+                time::sleep(RCON_COMMANDS_RETRY_INTERVAL).await;
+            }
+        }
+    };
+
+    for command in commands {
+        match server.rcon_pool.cmd(config, &mut rcon, command).await {
+            Ok(response) => {
+                info!(target: "lazymc::rcon", "Ran RCON command '{}': {}", command, response.trim())
+            }
+            Err(err) => {
+                error!(target: "lazymc::rcon", "Failed to run RCON command '{}': {}", command, err);
+                return;
+            }
+        }
+    }
+
+    server.rcon_pool.put(config, rcon).await;
+    drop(rcon_lock);
+}
+
 /// Stop server through RCON.
 #[cfg(feature = "rcon")]
 async fn stop_server_rcon(config: &Config, server: &Server) -> bool {
-    use crate::mc::rcon::Rcon;
-
     // RCON must be enabled
     if !config.rcon.enabled {
         trace!(target: "lazymc", "Not using RCON to stop server, disabled in config");
@@ -562,8 +1338,8 @@ async fn stop_server_rcon(config: &Config, server: &Server) -> bool {
         return false;
     }
 
-    // Create RCON client
-    let mut rcon = match Rcon::connect_config(config).await {
+    // Get pooled RCON client
+    let mut rcon = match server.rcon_pool.get(config).await {
         Ok(rcon) => rcon,
         Err(err) => {
             error!(target: "lazymc", "Failed to RCON server to sleep: {}", err);
@@ -572,7 +1348,8 @@ async fn stop_server_rcon(config: &Config, server: &Server) -> bool {
     };
 
     // Invoke stop
-    if let Err(err) = rcon.cmd("stop").await {
+    let stop_command = config.server.stop_command.as_deref().unwrap_or("stop");
+    if let Err(err) = server.rcon_pool.cmd(config, &mut rcon, stop_command).await {
         error!(target: "lazymc", "Failed to invoke stop through RCON: {}", err);
         return false;
     }
@@ -580,15 +1357,57 @@ async fn stop_server_rcon(config: &Config, server: &Server) -> bool {
     // Set server to stopping state, update last RCON time
     server.rcon_last_stop.lock().await.replace(Instant::now());
     server.update_state(State::Stopping, config).await;
+    server.terminate_after_timeout(config).await;
 
-    // Gracefully close connection
-    rcon.close().await;
+    // Return connection to pool
+    server.rcon_pool.put(config, rcon).await;
 
     drop(rcon_lock);
 
     true
 }
 
+/// Stop server by sending the stop command to its stdin.
+async fn stop_server_stdin(config: &Config, server: &Server) -> bool {
+    // Stop command must be sent through stdin
+    let stop_command = match config.server.stop_command.as_ref() {
+        Some(cmd) if ConfigServer::stop_via_stdin(config) => cmd,
+        _ => return false,
+    };
+
+    let mut stdin = server.stdin.lock().await;
+    let stdin = match stdin.as_mut() {
+        Some(stdin) => stdin,
+        None => {
+            debug!(target: "lazymc", "Could not send stop command to server process, stdin unavailable");
+            return false;
+        }
+    };
+
+    debug!(target: "lazymc", "Sending stop command to server process: {}", stop_command);
+    let result = async {
+        stdin
+            .write_all(format!("{stop_command}\n").as_bytes())
+            .await?;
+        stdin.flush().await
+    }
+    .await;
+    if let Err(err) = result {
+        error!(target: "lazymc", "Failed to send stop command to server process: {}", err);
+        return false;
+    }
+
+    server
+        .update_state_from(Some(State::Starting), State::Stopping, config)
+        .await;
+    server
+        .update_state_from(Some(State::Started), State::Stopping, config)
+        .await;
+    server.terminate_after_timeout(config).await;
+
+    true
+}
+
 /// Stop server by sending SIGTERM signal.
 ///
 /// Only available on Unix.
@@ -633,12 +1452,26 @@ async fn freeze_server_signal(config: &Config, server: &Server) -> bool {
     };
 
     if !os::freeze(pid) {
-        error!(target: "lazymc", "Failed to send freeze signal to server process.");
+        match config.server.freeze_fallback {
+            FreezeFailAction::Error => {
+                error!(target: "lazymc", "Failed to send freeze signal to server process, keeping it running");
+                server.update_last_active().await;
+                return true;
+            }
+            FreezeFailAction::FallbackToStop => {
+                warn!(target: "lazymc", "Freezing server process is unsupported, stopping it instead from now on");
+                server.freeze_unsupported.store(true, Ordering::Relaxed);
+                return false;
+            }
+        }
     }
 
     server
         .update_state_from(Some(State::Starting), State::Stopped, config)
         .await;
+    server
+        .update_state_from(Some(State::Resuming), State::Stopped, config)
+        .await;
     server
         .update_state_from(Some(State::Started), State::Stopped, config)
         .await;
@@ -665,11 +1498,54 @@ async fn unfreeze_server_signal(config: &Config, server: &Server) -> bool {
     }
 
     server
-        .update_state_from(Some(State::Stopping), State::Starting, config)
+        .update_state_from(Some(State::Stopping), State::Resuming, config)
         .await;
     server
-        .update_state_from(Some(State::Stopped), State::Starting, config)
+        .update_state_from(Some(State::Stopped), State::Resuming, config)
         .await;
 
     true
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> Config {
+        toml::from_str("[server]\ncommand = \"java -jar server.jar\"").unwrap()
+    }
+
+    #[test]
+    fn state_crash_loop_backoff() {
+        let state = State::CrashLoopBackoff;
+        assert_eq!(State::from_u8(state.to_u8()), state);
+        assert_eq!(state.name(), "crash_loop_backoff");
+        assert!(state.is_stopped());
+        assert!(!state.is_starting());
+        assert!(!State::Stopping.is_stopped());
+    }
+
+    #[cfg(feature = "rcon")]
+    #[tokio::test]
+    async fn ready_output_runs_rcon_start_commands_once() {
+        let server = Arc::new(Server::default());
+        server
+            .state
+            .store(State::Starting.to_u8(), Ordering::Relaxed);
+
+        let output: &[u8] = b"Loading\nDone (1.0s)! For help, type \"help\"\nDone (2.0s)!\n";
+        let pattern = Regex::new(r"Done \(").unwrap();
+        handle_output(
+            output,
+            None,
+            Some(pattern),
+            Arc::new(config()),
+            server.clone(),
+        )
+        .await;
+
+        assert_eq!(server.state(), State::Started);
+        assert!(server.rcon_started.notified().now_or_never().is_some());
+        assert!(server.rcon_started.notified().now_or_never().is_none());
+    }
+}
@@ -0,0 +1,107 @@
+use std::sync::Arc;
+
+use serde_json::{json, Value};
+use subtle::ConstantTimeEq;
+use tokio::net::TcpStream;
+
+use crate::config::LiveConfig;
+use crate::control;
+use crate::net;
+use crate::server::Server;
+use crate::service;
+use crate::util::http::{self, Request};
+
+/// JSON content type.
+const CONTENT_TYPE: &str = "application/json";
+
+/// HTTP management API task.
+pub async fn service(config: Arc<LiveConfig>, server: Arc<Server>) {
+    let address = config.get().api.address;
+    let listener = match net::bind_listener(address, None) {
+        Ok(listener) => listener,
+        Err(err) => {
+            error!(target: "lazymc::api", "Failed to start API server on {}: {}", address, err);
+            return;
+        }
+    };
+
+    info!(target: "lazymc::api", "Serving management API on http://{}", address);
+
+    if config.get().api.token.is_none() {
+        warn!(target: "lazymc::api", "No API token configured, anybody able to connect can control the server");
+    }
+
+    while let Ok((stream, _)) = listener.accept().await {
+        tokio::spawn(serve(stream, config.clone(), server.clone()));
+    }
+}
+
+/// Serve API request on given stream.
+async fn serve(mut stream: TcpStream, config: Arc<LiveConfig>, server: Arc<Server>) {
+    let request = match http::read_request(&mut stream).await {
+        Some(request) => request,
+        None => return,
+    };
+
+    let (status, body) = if authorized(&request, &config) {
+        handle(&request, &config, &server).await
+    } else {
+        (401, json!({ "error": "unauthorized" }))
+    };
+
+    if let Err(err) =
+        http::write_response(&mut stream, status, CONTENT_TYPE, &body.to_string()).await
+    {
+        debug!(target: "lazymc::api", "Failed to respond to API request: {}", err);
+    }
+}
+
+/// Check whether request is authorized with the configured bearer token.
+///
+/// Tokens are compared in constant time to not leak how much of a guess is correct.
+fn authorized(request: &Request, config: &LiveConfig) -> bool {
+    let config = config.get();
+    let token = match config.api.token.as_ref() {
+        Some(token) => token,
+        None => return true,
+    };
+
+    request
+        .header("authorization")
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .map(|value| bool::from(value.trim().as_bytes().ct_eq(token.as_bytes())))
+        .unwrap_or(false)
+}
+
+/// Handle API request, returns HTTP status code and JSON body.
+async fn handle(request: &Request, config: &LiveConfig, server: &Arc<Server>) -> (u16, Value) {
+    match (request.method.as_str(), request.path.as_str()) {
+        ("GET", "/status") => (200, control::status(server).await),
+        ("POST", "/wake") => {
+            let waking = control::wake(config.get(), server.clone()).await;
+            result(waking, "server is not sleeping")
+        }
+        ("POST", "/sleep") => {
+            let sleeping = control::sleep(&config.get(), server).await;
+            result(sleeping, "server is not online")
+        }
+        ("GET", "/config") => (200, control::config(&config.get())),
+        ("POST", "/reload") => {
+            let reloaded = service::signal::reload(config, server).await;
+            result(reloaded, "failed to reload config")
+        }
+        (_, "/status" | "/wake" | "/sleep" | "/config" | "/reload") => {
+            (405, json!({ "error": "method not allowed" }))
+        }
+        _ => (404, json!({ "error": "not found" })),
+    }
+}
+
+/// Build response for action result.
+fn result(ok: bool, error: &str) -> (u16, Value) {
+    if ok {
+        (202, json!({ "ok": true }))
+    } else {
+        (409, json!({ "ok": false, "error": error }))
+    }
+}
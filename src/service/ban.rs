@@ -0,0 +1,28 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use tokio::time;
+
+use crate::config::LiveConfig;
+use crate::server::Server;
+
+/// Interval to check the banned IPs file for changes at.
+const REFRESH_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Banned IPs refresh service.
+///
+/// Reloads `banned-ips.json` when it changes, so bans made through the `ban` and `unban`
+/// subcommands take effect without a restart.
+pub async fn service(live_config: Arc<LiveConfig>, server: Arc<Server>) {
+    let mut interval = time::interval(REFRESH_INTERVAL);
+
+    loop {
+        interval.tick().await;
+
+        let (config, load_server) = (live_config.get(), server.clone());
+        let ips = tokio::task::spawn_blocking(move || load_server.load_changed_banned_ips(&config));
+        if let Ok(Some(ips)) = ips.await {
+            server.set_banned_ips(ips).await;
+        }
+    }
+}
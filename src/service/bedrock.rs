@@ -0,0 +1,106 @@
+use std::sync::Arc;
+
+use rand::Rng;
+use tokio::net::UdpSocket;
+
+use crate::config::LiveConfig;
+use crate::server::Server;
+use crate::status;
+
+/// RakNet unconnected ping packet ID.
+const UNCONNECTED_PING: u8 = 0x01;
+
+/// RakNet unconnected ping packet ID, only answered if open connections are available.
+const UNCONNECTED_PING_OPEN_CONNECTIONS: u8 = 0x02;
+
+/// RakNet unconnected pong packet ID.
+const UNCONNECTED_PONG: u8 = 0x1C;
+
+/// RakNet offline message magic.
+const MAGIC: [u8; 16] = [
+    0x00, 0xFF, 0xFF, 0x00, 0xFE, 0xFE, 0xFE, 0xFE, 0xFD, 0xFD, 0xFD, 0xFD, 0x12, 0x34, 0x56, 0x78,
+];
+
+/// Bedrock protocol version to report, Bedrock clients do not require it to match.
+const BEDROCK_PROTOCOL: u32 = 671;
+
+/// Bedrock version name to report.
+const BEDROCK_VERSION: &str = "1.20.80";
+
+/// Maximum datagram size to receive.
+const MAX_DATAGRAM_SIZE: usize = 1500;
+
+/// Bedrock status service.
+///
+/// Responds to Bedrock Edition status pings with the server state MOTD.
+pub async fn service(live_config: Arc<LiveConfig>, server: Arc<Server>) {
+    let addr = live_config.get().public.bedrock_address;
+    let socket = match UdpSocket::bind(addr).await {
+        Ok(socket) => socket,
+        Err(err) => {
+            error!(target: "lazymc::bedrock", "Failed to bind Bedrock status socket on {}: {}", addr, err);
+            return;
+        }
+    };
+
+    info!(target: "lazymc::bedrock", "Responding to Bedrock status pings on {}", addr);
+
+    // Random server GUID, stable while running
+    let guid: u64 = rand::thread_rng().gen();
+
+    let mut buf = [0; MAX_DATAGRAM_SIZE];
+    loop {
+        let (len, peer) = match socket.recv_from(&mut buf).await {
+            Ok(result) => result,
+            Err(err) => {
+                debug!(target: "lazymc::bedrock", "Failed to receive Bedrock datagram: {}", err);
+                continue;
+            }
+        };
+
+        // Unconnected ping: ID, time, magic, client GUID
+        let ping = &buf[..len];
+        if len < 33
+            || !matches!(
+                ping[0],
+                UNCONNECTED_PING | UNCONNECTED_PING_OPEN_CONNECTIONS
+            )
+            || ping[9..25] != MAGIC
+        {
+            trace!(target: "lazymc::bedrock", "Ignoring unknown Bedrock datagram from {}", peer);
+            continue;
+        }
+
+        let config = live_config.get();
+        let status = server.status().await;
+        let motd = status::state_motd(&config, &server, status.as_ref()).await;
+        let players_max = status::max_players(&config, &server, status.as_ref());
+        drop(status);
+        let pong = pong(&ping[1..9], guid, &motd, players_max, addr.port());
+
+        if let Err(err) = socket.send_to(&pong, peer).await {
+            debug!(target: "lazymc::bedrock", "Failed to send Bedrock status pong to {}: {}", peer, err);
+        }
+    }
+}
+
+/// Build unconnected pong packet, echoing the ping time.
+fn pong(time: &[u8], guid: u64, motd: &str, players_max: u32, port: u16) -> Vec<u8> {
+    // Bedrock shows two MOTD lines, separators are not allowed
+    let mut lines = motd.lines().map(|line| line.replace(';', ""));
+    let line1 = lines.next().unwrap_or_default();
+    let line2 = lines.next().unwrap_or_default();
+
+    let status = format!(
+        "MCPE;{line1};{BEDROCK_PROTOCOL};{BEDROCK_VERSION};0;{players_max};{guid};{line2};Survival;1;{port};{port};"
+    );
+
+    let mut pong = Vec::with_capacity(35 + status.len());
+    pong.push(UNCONNECTED_PONG);
+    pong.extend_from_slice(time);
+    pong.extend_from_slice(&guid.to_be_bytes());
+    pong.extend_from_slice(&MAGIC);
+    pong.extend_from_slice(&(status.len() as u16).to_be_bytes());
+    pong.extend_from_slice(status.as_bytes());
+    pong
+}
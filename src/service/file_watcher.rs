@@ -128,19 +128,22 @@ fn reload_bans(config: &Config, server: &Server, path: &Path) {
         return;
     }
 
-    // Load and update banned IPs
-    match ban::load(path) {
-        Ok(ips) => server.set_banned_ips_blocking(ips),
+    // Load banned IPs
+    let ips = match ban::load(path) {
+        Ok(ips) => ips,
         Err(err) => {
             debug!(target: "lazymc", "Failed load banned IPs from {}, ignoring: {}", ban::FILE, err);
+            return;
         }
-    }
+    };
 
     // Show warning if 127.0.0.1 is banned
-    if server.is_banned_ip_blocking(&("127.0.0.1".parse().unwrap())) {
+    if ips.is_banned(&("127.0.0.1".parse().unwrap())) {
         warn!(target: "lazymc", "Local address 127.0.0.1 IP banned, probably not what you want");
         warn!(target: "lazymc", "Use '/pardon-ip 127.0.0.1' on the server to unban");
     }
+
+    server.set_banned_ips_blocking(ips);
 }
 
 /// Reload whitelisted users.
@@ -0,0 +1,97 @@
+use std::ops::Deref;
+use std::process::Stdio;
+use std::sync::Arc;
+use std::time::Duration;
+
+use tokio::time;
+
+use crate::config::{Config, LiveConfig, Server as ConfigServer};
+use crate::server::{Server, State};
+use crate::util::shell;
+
+/// Interval to recheck whether the health check is configured.
+const DISABLED_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Server health check service.
+///
+/// Periodically runs the configured health check command while the server is online, and
+/// restarts the server after too many consecutive failures.
+pub async fn service(live_config: Arc<LiveConfig>, server: Arc<Server>) {
+    let mut failures = 0;
+
+    loop {
+        let config = live_config.get();
+        let command = match config.server.health_check_command.as_ref() {
+            Some(command) => command,
+            None => {
+                time::sleep(DISABLED_INTERVAL).await;
+                continue;
+            }
+        };
+        let interval = Duration::from_secs(config.server.health_check_interval_secs.max(1) as u64);
+        time::sleep(interval).await;
+
+        // Only check online server
+        if server.state() != State::Started {
+            failures = 0;
+            continue;
+        }
+
+        if check(&config, command, interval).await {
+            failures = 0;
+            continue;
+        }
+
+        failures += 1;
+        warn!(target: "lazymc::health", "Server health check failed ({}/{})", failures, config.server.health_check_failures_before_restart);
+        if failures < config.server.health_check_failures_before_restart {
+            continue;
+        }
+
+        failures = 0;
+        error!(target: "lazymc::health", "Server is unhealthy, restarting...");
+        restart(config, server.clone()).await;
+    }
+}
+
+/// Run health check command, returns `true` if healthy.
+async fn check(config: &Config, command: &str, timeout: Duration) -> bool {
+    let mut cmd = shell::command(command);
+    cmd.stdin(Stdio::null()).kill_on_drop(true);
+    if let Some(ref dir) = ConfigServer::server_directory(config) {
+        cmd.current_dir(dir);
+    }
+
+    match time::timeout(timeout, cmd.status()).await {
+        Ok(Ok(status)) => {
+            trace!(target: "lazymc::health", "Health check exited with {}", status);
+            status.success()
+        }
+        Ok(Err(err)) => {
+            error!(target: "lazymc::health", "Failed to run health check command: {}", err);
+            false
+        }
+        Err(_) => {
+            warn!(target: "lazymc::health", "Health check command timed out");
+            false
+        }
+    }
+}
+
+/// Stop the server and start it again.
+async fn restart(config: Arc<Config>, server: Arc<Server>) {
+    // Frozen process would stay unhealthy, force kill it instead
+    if config.server.freeze_process || !server.stop(&config).await {
+        server.force_kill().await;
+    }
+
+    // Wait for server to stop, then start it again
+    let mut state = server.state_receiver();
+    while !state.borrow().deref().is_stopped() {
+        if state.changed().await.is_err() {
+            return;
+        }
+    }
+
+    Server::start(config, server, None).await;
+}
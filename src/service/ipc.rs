@@ -0,0 +1,80 @@
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use serde::Deserialize;
+use serde_json::{json, Value};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{UnixListener, UnixStream};
+
+use crate::config::LiveConfig;
+use crate::control;
+use crate::server::Server;
+use crate::service;
+
+/// IPC request.
+#[derive(Debug, Deserialize)]
+struct Request {
+    /// Command to invoke.
+    cmd: String,
+}
+
+/// IPC socket task.
+///
+/// Serves line-delimited JSON commands on a Unix socket.
+pub async fn service(path: PathBuf, config: Arc<LiveConfig>, server: Arc<Server>) {
+    // Remove stale socket from previous run
+    if path.exists() {
+        let _ = std::fs::remove_file(&path);
+    }
+
+    let listener = match UnixListener::bind(&path) {
+        Ok(listener) => listener,
+        Err(err) => {
+            error!(target: "lazymc::ipc", "Failed to create IPC socket at {}: {}", path.display(), err);
+            return;
+        }
+    };
+
+    debug!(target: "lazymc::ipc", "Listening for IPC commands on {}", path.display());
+
+    while let Ok((stream, _)) = listener.accept().await {
+        tokio::spawn(serve(stream, config.clone(), server.clone()));
+    }
+}
+
+/// Serve IPC commands on given stream until closed.
+async fn serve(stream: UnixStream, config: Arc<LiveConfig>, server: Arc<Server>) {
+    let (reader, mut writer) = stream.into_split();
+    let mut lines = BufReader::new(reader).lines();
+
+    while let Ok(Some(line)) = lines.next_line().await {
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let response = match serde_json::from_str::<Request>(&line) {
+            Ok(request) => handle(&request.cmd, &config, &server).await,
+            Err(err) => json!({ "error": format!("invalid request: {err}") }),
+        };
+
+        if writer
+            .write_all(format!("{response}\n").as_bytes())
+            .await
+            .is_err()
+        {
+            break;
+        }
+    }
+}
+
+/// Handle IPC command.
+async fn handle(cmd: &str, config: &LiveConfig, server: &Arc<Server>) -> Value {
+    match cmd {
+        "status" => control::status(server).await,
+        "wake" => json!({ "ok": control::wake(config.get(), server.clone()).await }),
+        "sleep" => json!({ "ok": control::sleep(&config.get(), server).await }),
+        "kill" => json!({ "ok": control::kill(server).await }),
+        "reload" => json!({ "ok": service::signal::reload(config, server).await }),
+        _ => json!({ "error": format!("unknown command: {cmd}") }),
+    }
+}
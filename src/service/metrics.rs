@@ -0,0 +1,132 @@
+use std::fmt::Write;
+use std::sync::Arc;
+
+use tokio::net::TcpStream;
+
+use crate::config::Config;
+use crate::net;
+use crate::server::Server;
+use crate::util::http;
+
+/// Prometheus text format content type.
+const CONTENT_TYPE: &str = "text/plain; version=0.0.4";
+
+/// Metrics server task.
+///
+/// Serves Prometheus metrics over HTTP.
+pub async fn service(config: Arc<Config>, server: Arc<Server>) {
+    let listener = match net::bind_listener(config.metrics.address, None) {
+        Ok(listener) => listener,
+        Err(err) => {
+            error!(target: "lazymc::metrics", "Failed to start metrics server on {}: {}", config.metrics.address, err);
+            return;
+        }
+    };
+
+    info!(target: "lazymc::metrics", "Serving metrics on http://{}{}", config.metrics.address, config.metrics.path);
+
+    while let Ok((stream, _)) = listener.accept().await {
+        tokio::spawn(serve(stream, config.clone(), server.clone()));
+    }
+}
+
+/// Serve metrics request on given stream.
+async fn serve(mut stream: TcpStream, config: Arc<Config>, server: Arc<Server>) {
+    let request = match http::read_request(&mut stream).await {
+        Some(request) => request,
+        None => return,
+    };
+
+    let result = if request.method == "GET" && request.path == config.metrics.path {
+        http::write_response(&mut stream, 200, CONTENT_TYPE, &render(&server).await).await
+    } else {
+        http::write_response(&mut stream, 404, CONTENT_TYPE, "not found\n").await
+    };
+
+    if let Err(err) = result {
+        debug!(target: "lazymc::metrics", "Failed to respond to metrics request: {}", err);
+    }
+}
+
+/// Render metrics in Prometheus text format.
+async fn render(server: &Server) -> String {
+    let stats = &server.stats;
+    let state = server.state().to_u8();
+    let start_duration = stats
+        .last_start_duration()
+        .await
+        .map(|d| d.as_secs_f64())
+        .unwrap_or(0.0);
+    let online_duration = stats
+        .last_online_duration()
+        .await
+        .map(|d| d.as_secs_f64())
+        .unwrap_or(0.0);
+    let idle_before_sleep = stats.actual_idle_before_sleep_secs().await.unwrap_or(0);
+
+    let mut out = String::new();
+    metric(
+        &mut out,
+        "wake_total",
+        "counter",
+        "Number of times the server was woken.",
+        stats.wake_count(),
+    );
+    metric(
+        &mut out,
+        "sleep_total",
+        "counter",
+        "Number of times the server went to sleep.",
+        stats.sleep_count(),
+    );
+    metric(
+        &mut out,
+        "active_connections",
+        "gauge",
+        "Number of connections currently handled.",
+        stats.active_connections(),
+    );
+    metric(
+        &mut out,
+        "server_start_duration_seconds",
+        "gauge",
+        "Duration of the last server start.",
+        start_duration,
+    );
+    metric(
+        &mut out,
+        "server_online_duration_seconds",
+        "gauge",
+        "Duration the server was online for during the last wake cycle.",
+        online_duration,
+    );
+    metric(
+        &mut out,
+        "server_idle_before_sleep_seconds",
+        "gauge",
+        "Time the server ran without players before it last went to sleep.",
+        idle_before_sleep,
+    );
+    metric(
+        &mut out,
+        "server_state",
+        "gauge",
+        "Server state: 0 sleeping, 1 starting, 2 online, 3 stopping, 4 resuming, 5 crash loop backoff.",
+        state,
+    );
+    metric(
+        &mut out,
+        "join_method_kicks_total",
+        "counter",
+        "Number of clients kicked by the kick join method.",
+        stats.kick_count(),
+    );
+    out
+}
+
+/// Write single metric with help and type to output.
+fn metric(out: &mut String, name: &str, kind: &str, help: &str, value: impl std::fmt::Display) {
+    let _ = writeln!(out, "# HELP {name} {help}");
+    let _ = writeln!(out, "# TYPE {name} {kind}");
+    let _ = writeln!(out, "{name} {value}");
+}
@@ -1,5 +1,15 @@
+pub mod api;
+pub mod ban;
+pub mod bedrock;
 pub mod file_watcher;
+pub mod health;
+#[cfg(unix)]
+pub mod ipc;
+pub mod metrics;
 pub mod monitor;
 pub mod probe;
+pub mod query;
+pub mod rate_limit;
+pub mod schedule;
 pub mod server;
 pub mod signal;
@@ -1,10 +1,16 @@
 use std::sync::Arc;
 
-use crate::config::Config;
+use crate::config::LiveConfig;
 use crate::monitor;
 use crate::server::Server;
 
 /// Server monitor task.
-pub async fn service(config: Arc<Config>, state: Arc<Server>) {
+pub async fn service(config: Arc<LiveConfig>, state: Arc<Server>) {
+    #[cfg(feature = "rcon")]
+    tokio::spawn(crate::server::run_rcon_start_commands(
+        config.clone(),
+        state.clone(),
+    ));
+
     monitor::monitor_server(config, state).await
 }
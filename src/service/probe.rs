@@ -1,11 +1,18 @@
 use std::sync::Arc;
 
-use crate::config::{Config, Method};
+use crate::config::{Config, ForgeVariant, Method};
 use crate::probe;
 use crate::server::Server;
 
 /// Probe server.
 pub async fn service(config: Arc<Config>, state: Arc<Server>) {
+    // Lobby cannot perform legacy Forge handshake, which happens in play state
+    if config.server.forge_variant() == ForgeVariant::Fml1
+        && config.join.methods.contains(&Method::Lobby)
+    {
+        warn!(target: "lazymc::probe", "Lobby does not support the legacy FML1 Forge handshake, Forge clients may fail to join");
+    }
+
     // Only probe if enabled or if we must
     if !config.server.probe_on_start && !must_probe(&config) {
         return;
@@ -22,8 +29,20 @@ pub async fn service(config: Arc<Config>, state: Arc<Server>) {
 
 /// Check whether we must probe.
 fn must_probe(config: &Config) -> bool {
+    // Must probe to detect proxy header support
+    if config.server.probe_proxy_v2 {
+        return true;
+    }
+
+    // Must probe to detect protocol version
+    if config.public.protocol == 0 {
+        return true;
+    }
+
     // Must probe with lobby and Forge
-    if config.server.forge && config.join.methods.contains(&Method::Lobby) {
+    if config.server.forge_variant() != ForgeVariant::None
+        && config.join.methods.contains(&Method::Lobby)
+    {
         warn!(target: "lazymc::probe", "Starting server to probe for Forge lobby...");
         warn!(target: "lazymc::probe", "Set 'server.probe_on_start = true' to remove this warning");
         return true;
@@ -0,0 +1,232 @@
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use rand::Rng;
+use tokio::net::UdpSocket;
+
+use crate::config::{Config, LiveConfig};
+use crate::server::{self, Server};
+use crate::status;
+
+/// Query packet magic.
+const MAGIC: [u8; 2] = [0xFE, 0xFD];
+
+/// Handshake packet type.
+const TYPE_HANDSHAKE: u8 = 0x09;
+
+/// Stat packet type.
+const TYPE_STAT: u8 = 0x00;
+
+/// Challenge token lifetime.
+const TOKEN_LIFETIME: Duration = Duration::from_secs(30);
+
+/// Full stat key-value section padding.
+const PADDING_KV: &[u8] = b"splitnum\0\x80\0";
+
+/// Full stat player section padding.
+const PADDING_PLAYERS: &[u8] = b"\x01player_\0\0";
+
+/// Maximum datagram size to receive.
+const MAX_DATAGRAM_SIZE: usize = 1500;
+
+/// Query service.
+///
+/// Responds to Minecraft Query protocol requests with the current server state.
+pub async fn service(live_config: Arc<LiveConfig>, server: Arc<Server>) {
+    let config = live_config.get();
+    let addr = SocketAddr::new(config.public.address.ip(), config.query.port);
+    let socket = match UdpSocket::bind(addr).await {
+        Ok(socket) => socket,
+        Err(err) => {
+            error!(target: "lazymc::query", "Failed to bind query socket on {}: {}", addr, err);
+            return;
+        }
+    };
+
+    info!(target: "lazymc::query", "Responding to queries on {}", addr);
+
+    // Challenge tokens handed out to clients
+    let mut tokens: HashMap<SocketAddr, (i32, Instant)> = HashMap::new();
+
+    let mut buf = [0; MAX_DATAGRAM_SIZE];
+    loop {
+        let (len, peer) = match socket.recv_from(&mut buf).await {
+            Ok(result) => result,
+            Err(err) => {
+                debug!(target: "lazymc::query", "Failed to receive query datagram: {}", err);
+                continue;
+            }
+        };
+
+        // Request: magic, type, session ID, payload
+        let request = &buf[..len];
+        if len < 7 || request[..2] != MAGIC {
+            continue;
+        }
+        let session = &request[3..7];
+        let payload = &request[7..];
+
+        let response = match request[2] {
+            TYPE_HANDSHAKE => {
+                tokens.retain(|_, (_, issued)| issued.elapsed() < TOKEN_LIFETIME);
+                let token: i32 = rand::thread_rng().gen_range(0..i32::MAX);
+                tokens.insert(peer, (token, Instant::now()));
+
+                let mut response = header(TYPE_HANDSHAKE, session);
+                response.extend(token.to_string().as_bytes());
+                response.push(0);
+                response
+            }
+            TYPE_STAT if payload.len() >= 4 => {
+                let token = i32::from_be_bytes(payload[..4].try_into().unwrap());
+                let valid = tokens.get(&peer).is_some_and(|(expected, issued)| {
+                    *expected == token && issued.elapsed() < TOKEN_LIFETIME
+                });
+                if !valid {
+                    trace!(target: "lazymc::query", "Ignoring query from {} with invalid challenge token", peer);
+                    continue;
+                }
+
+                // Full stat requests are padded to 8 bytes
+                let stat = Stat::new(&live_config.get(), &server).await;
+                if payload.len() >= 8 {
+                    stat.full(session)
+                } else {
+                    stat.basic(session)
+                }
+            }
+            _ => continue,
+        };
+
+        if let Err(err) = socket.send_to(&response, peer).await {
+            debug!(target: "lazymc::query", "Failed to send query response to {}: {}", peer, err);
+        }
+    }
+}
+
+/// Response header: type and session ID.
+fn header(kind: u8, session: &[u8]) -> Vec<u8> {
+    let mut header = vec![kind];
+    header.extend_from_slice(session);
+    header
+}
+
+/// Server stat to respond with.
+struct Stat {
+    motd: String,
+    state: &'static str,
+    version: String,
+    players: Vec<String>,
+    players_online: u32,
+    players_max: u32,
+    port: u16,
+    ip: String,
+}
+
+impl Stat {
+    /// Collect current server stat.
+    async fn new(config: &Config, server: &Server) -> Self {
+        let state = server.state();
+        let status = server.status().await;
+
+        // Only report players from a started server
+        let started = state == server::State::Started && status.is_some();
+        let motd = match status.as_ref() {
+            Some(status) if started => status.description.clone(),
+            _ => status::state_motd(config, server, status.as_ref()).await,
+        };
+        let (players_online, players) = match status.as_ref() {
+            Some(status) if started => (
+                status.players.online,
+                status
+                    .players
+                    .sample
+                    .iter()
+                    .map(|player| player.name.clone())
+                    .collect(),
+            ),
+            _ => (0, vec![]),
+        };
+
+        Self {
+            // Query does not support formatting or multiple lines
+            motd: motd.lines().next().unwrap_or_default().to_string(),
+            state: state.name(),
+            version: status
+                .as_ref()
+                .map(|status| status.version.name.clone())
+                .unwrap_or_else(|| config.public.version.clone()),
+            players,
+            players_online,
+            players_max: status::max_players(config, server, status.as_ref()),
+            port: config.public.address.port(),
+            ip: config.public.address.ip().to_string(),
+        }
+    }
+
+    /// Basic stat response.
+    fn basic(&self, session: &[u8]) -> Vec<u8> {
+        let (online, max) = (
+            self.players_online.to_string(),
+            self.players_max.to_string(),
+        );
+
+        let mut response = header(TYPE_STAT, session);
+        for value in [
+            self.motd.as_str(),
+            "SMP",
+            "world",
+            online.as_str(),
+            max.as_str(),
+        ] {
+            push_str(&mut response, value);
+        }
+        response.extend_from_slice(&self.port.to_le_bytes());
+        push_str(&mut response, &self.ip);
+        response
+    }
+
+    /// Full stat response, includes lazymc server state.
+    fn full(&self, session: &[u8]) -> Vec<u8> {
+        let (online, max) = (
+            self.players_online.to_string(),
+            self.players_max.to_string(),
+        );
+        let port = self.port.to_string();
+
+        let mut response = header(TYPE_STAT, session);
+        response.extend_from_slice(PADDING_KV);
+        for (key, value) in [
+            ("hostname", self.motd.as_str()),
+            ("gametype", "SMP"),
+            ("game_id", "MINECRAFT"),
+            ("version", self.version.as_str()),
+            ("plugins", ""),
+            ("map", "world"),
+            ("numplayers", online.as_str()),
+            ("maxplayers", max.as_str()),
+            ("hostport", port.as_str()),
+            ("hostip", self.ip.as_str()),
+            ("state", self.state),
+        ] {
+            push_str(&mut response, key);
+            push_str(&mut response, value);
+        }
+        response.push(0);
+
+        response.extend_from_slice(PADDING_PLAYERS);
+        for player in &self.players {
+            push_str(&mut response, player);
+        }
+        response.push(0);
+        response
+    }
+}
+
+/// Push null-terminated string.
+fn push_str(buf: &mut Vec<u8>, value: &str) {
+    buf.extend_from_slice(value.as_bytes());
+    buf.push(0);
+}
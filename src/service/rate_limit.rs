@@ -0,0 +1,19 @@
+use std::time::Duration;
+
+use tokio::time;
+
+use crate::access;
+
+/// Interval to clean up idle rate limit buckets at.
+const CLEANUP_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Rate limit cleanup service.
+///
+/// Periodically removes rate limit state of idle networks.
+pub async fn service() {
+    let mut interval = time::interval(CLEANUP_INTERVAL);
+    loop {
+        interval.tick().await;
+        access::rate_limit_cleanup();
+    }
+}
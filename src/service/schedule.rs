@@ -0,0 +1,106 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::Local;
+use tokio::time;
+
+use crate::config::LiveConfig;
+use crate::server::Server;
+
+/// Maximum time to wait before reevaluating the schedule, to pick up reloaded configuration.
+const RECHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Scheduled event.
+#[derive(Debug, Copy, Clone)]
+enum Event {
+    Sleep,
+    Wake,
+    LockoutEnable,
+    LockoutDisable,
+}
+
+/// Schedule service.
+///
+/// Puts the server to sleep, wakes it and toggles lockout at configured times.
+pub async fn service(live_config: Arc<LiveConfig>, server: Arc<Server>) {
+    // Events before startup are missed, apply lockout state of the current window
+    let lockout = live_config
+        .get()
+        .lockout
+        .schedule
+        .as_ref()
+        .and_then(|s| s.is_enabled_at(&Local::now()));
+    if lockout == Some(true) {
+        info!(target: "lazymc", "Enabling lockout, started within scheduled lockout window");
+        server.set_scheduled_lockout(true);
+    }
+
+    loop {
+        let config = live_config.get();
+        let now = Local::now();
+
+        // Find first upcoming scheduled event
+        let sleep = config
+            .time
+            .schedule
+            .sleep()
+            .and_then(|s| s.after(&now).next())
+            .map(|at| (at, Event::Sleep));
+        let wake = config
+            .time
+            .wake_schedule()
+            .and_then(|s| s.after(&now).next())
+            .map(|at| (at, Event::Wake));
+        let lockout = config.lockout.schedule.as_ref();
+        let lockout_enable = lockout
+            .and_then(|s| s.enabled())
+            .and_then(|s| s.after(&now).next())
+            .map(|at| (at, Event::LockoutEnable));
+        let lockout_disable = lockout
+            .and_then(|s| s.disabled())
+            .and_then(|s| s.after(&now).next())
+            .map(|at| (at, Event::LockoutDisable));
+        let next = [wake, sleep, lockout_enable, lockout_disable]
+            .into_iter()
+            .flatten()
+            .min_by_key(|(at, _)| *at);
+
+        // Wait for event, recheck periodically
+        let (at, event) = match next {
+            Some(next) => next,
+            None => {
+                time::sleep(RECHECK_INTERVAL).await;
+                continue;
+            }
+        };
+        let wait = (at - now).to_std().unwrap_or_default();
+        if wait > RECHECK_INTERVAL {
+            time::sleep(RECHECK_INTERVAL).await;
+            continue;
+        }
+        time::sleep(wait).await;
+
+        match event {
+            Event::Wake => {
+                info!(target: "lazymc", "Waking server on schedule");
+                server.set_scheduled_wake(true);
+                if !Server::start(live_config.get(), server.clone(), None).await {
+                    server.set_scheduled_wake(false);
+                }
+            }
+            Event::Sleep => {
+                if server.schedule_sleep() {
+                    info!(target: "lazymc", "Scheduled sleep, server sleeps once no players are online");
+                }
+            }
+            Event::LockoutEnable => {
+                info!(target: "lazymc", "Enabling lockout on schedule");
+                server.set_scheduled_lockout(true);
+            }
+            Event::LockoutDisable => {
+                info!(target: "lazymc", "Disabling scheduled lockout");
+                server.set_scheduled_lockout(false);
+            }
+        }
+    }
+}
@@ -4,34 +4,124 @@ use std::sync::Arc;
 use bytes::BytesMut;
 use futures::FutureExt;
 use tokio::net::{TcpListener, TcpStream};
+use tokio_rustls::TlsAcceptor;
 
-use crate::config::Config;
-use crate::proto::client::Client;
+use crate::access::{self, RateLimited};
+use crate::auth;
+use crate::balance::BackendGuard;
+use crate::config::{Config, DenyAction, ForwardingMode, LiveConfig};
+use crate::filter;
+use crate::forwarding::Forwarding;
+use crate::hooks::PendingPlayer;
+use crate::net;
+use crate::packet_hook::Relay;
+use crate::probe;
+use crate::proto::action;
+use crate::proto::client::{Client, ClientInfo, ClientState};
 use crate::proxy::{self, ProxyHeader};
 use crate::server::{self, Server};
 use crate::service;
+use crate::stats::ConnectionGuard;
 use crate::status;
+use crate::stream::ClientStream;
+use crate::tls;
 use crate::util::error::{quit_error, ErrorHints};
+use crate::vhost::{self, VirtualHost, VirtualHosts};
+
+/// Server instance, loaded from a single config file.
+#[derive(Clone)]
+pub struct Instance {
+    /// Instance configuration.
+    pub config: Arc<LiveConfig>,
+
+    /// Instance server state.
+    pub server: Arc<Server>,
+
+    /// Virtual hosts of instance, each with its own server.
+    pub vhosts: Arc<VirtualHosts>,
+}
 
 /// Start lazymc.
 ///
-/// Main entrypoint to start all server/status/proxy logic.
+/// Main entrypoint to start all server/status/proxy logic, for each given config.
 ///
-/// Spawns a tokio runtime to complete all work on.
+/// Spawns a tokio runtime to complete all work on, shared by all server instances.
 #[tokio::main(flavor = "multi_thread")]
-pub async fn service(config: Arc<Config>) -> Result<(), ()> {
-    // Load server state
-    let server = Arc::new(Server::default());
+pub async fn service(configs: Vec<Arc<LiveConfig>>) -> Result<(), ()> {
+    // Load server state for each config
+    let instances: Vec<Instance> = configs
+        .into_iter()
+        .map(|config| Instance {
+            vhosts: Arc::new(VirtualHosts::new(&config.get())),
+            server: Arc::new(Server::default()),
+            config,
+        })
+        .collect();
+
+    // Spawn process wide services: signal handler, rate limit cleanup
+    tokio::spawn(service::signal::service(instances.clone()));
+    tokio::spawn(service::rate_limit::service());
+
+    // Bind listeners of all instances before starting any
+    let mut bound = Vec::with_capacity(instances.len());
+    for instance in &instances {
+        bound.push(bind(&instance.config.get()).await?);
+    }
+    let tasks: Vec<_> = instances
+        .iter()
+        .cloned()
+        .zip(bound)
+        .map(|(instance, (listener, tls))| tokio::spawn(run(instance, listener, tls)))
+        .collect();
+
+    // Notify systemd we're ready
+    #[cfg(unix)]
+    if instances[0].config.get().advanced.systemd_notify() {
+        let state = instances[0].server.state();
+        crate::systemd::notify(&format!("READY=1\nSTATUS=Server {}", state.name()));
+        tokio::spawn(crate::systemd::watchdog());
+    }
 
+    for task in tasks {
+        let _ = task.await;
+    }
+
+    Ok(())
+}
+
+/// Bind public listener and load TLS for config, quits on error.
+async fn bind(config: &Config) -> Result<(TcpListener, Option<TlsAcceptor>), ()> {
     // Listen for new connections
-    let listener = TcpListener::bind(config.public.address)
-        .await
-        .map_err(|err| {
-            quit_error(
-                anyhow!(err).context("Failed to start proxy server"),
-                ErrorHints::default(),
-            );
-        })?;
+    let listener = net::bind_listener(
+        config.public.address,
+        config.advanced.bind_interface.as_deref(),
+    )
+    .map_err(|err| {
+        quit_error(
+            anyhow!(err).context("Failed to start proxy server"),
+            ErrorHints::default(),
+        );
+    })?;
+
+    // Load TLS for client connections
+    let tls = tls::acceptor(config).await.map_err(|err| {
+        quit_error(
+            anyhow!(err).context("Failed to set up TLS"),
+            ErrorHints::default(),
+        );
+    })?;
+
+    Ok((listener, tls))
+}
+
+/// Run server instance, accepting connections on given listener.
+async fn run(instance: Instance, listener: TcpListener, tls: Option<TlsAcceptor>) {
+    let Instance {
+        config: live_config,
+        server,
+        vhosts,
+    } = instance;
+    let config = live_config.get();
 
     info!(
         target: "lazymc",
@@ -46,65 +136,368 @@ pub async fn service(config: Arc<Config>) -> Result<(), ()> {
         );
     }
 
-    // Spawn services: monitor, signal handler
-    tokio::spawn(service::monitor::service(config.clone(), server.clone()));
-    tokio::spawn(service::signal::service(config.clone(), server.clone()));
+    if config.join.forwarding == ForwardingMode::Legacy {
+        warn!(
+            target: "lazymc",
+            "BungeeCord forwarding is enabled, this requires 'bungeecord: true' in the server's spigot.yml",
+        );
+    }
+
+    if filter::is_enabled(&config) && config.public.online_mode && !auth::must_authenticate(&config)
+    {
+        warn!(
+            target: "lazymc",
+            "Chat or command filter is enabled, but lazymc does not authenticate players, filters do nothing if the server is in online mode",
+        );
+    }
+
+    if !config.player_routes.is_empty() && !auth::must_authenticate(&config) {
+        warn!(
+            target: "lazymc",
+            "Player routes are ignored, lazymc does not authenticate players so their username or UUID can't be trusted",
+        );
+    }
+
+    if !config.public.online_mode {
+        warn!(
+            target: "lazymc",
+            "Online mode is disabled, players are NOT authenticated and anyone can join with any username",
+        );
+    }
+
+    // Virtual hosts, each with its own server
+    for vhost in vhosts.iter() {
+        info!(
+            target: "lazymc",
+            "Proxying virtual host {} to server {}",
+            vhost.hostname, vhost.config.get().server.address,
+        );
+    }
+
+    // Spawn services of the server and each virtual host server
+    spawn_server_services(&live_config, &server);
+    for vhost in vhosts.iter() {
+        spawn_server_services(&vhost.config, &vhost.server);
+    }
 
     // Initiate server start
     if config.server.wake_on_start {
         Server::start(config.clone(), server.clone(), None).await;
+        for vhost in vhosts.iter() {
+            Server::start(vhost.config.get(), vhost.server.clone(), None).await;
+        }
+    }
+
+    // Spawn IPC socket service
+    #[cfg(unix)]
+    if let Some(path) = config.advanced.ipc_socket.as_ref() {
+        tokio::spawn(service::ipc::service(
+            config.resolve_path(path),
+            live_config.clone(),
+            server.clone(),
+        ));
+    }
+
+    // Spawn management API service
+    if config.api.enabled {
+        tokio::spawn(service::api::service(live_config.clone(), server.clone()));
+    }
+
+    // Spawn Bedrock status service
+    if config.public.bedrock {
+        tokio::spawn(service::bedrock::service(
+            live_config.clone(),
+            server.clone(),
+        ));
+    }
+
+    // Spawn query service
+    if config.query.enabled {
+        tokio::spawn(service::query::service(live_config.clone(), server.clone()));
+    }
+
+    // Spawn metrics service
+    if config.metrics.enabled {
+        tokio::spawn(service::metrics::service(config.clone(), server.clone()));
     }
 
-    // Spawn additional services: probe and ban manager
+    // Route all incomming connections, always use latest config
+    while let Ok((inbound, _)) = listener.accept().await {
+        match &tls {
+            Some(acceptor) => {
+                tokio::spawn(route_tls(
+                    inbound,
+                    acceptor.clone(),
+                    live_config.clone(),
+                    server.clone(),
+                    vhosts.clone(),
+                ));
+            }
+            None => match ClientStream::new(inbound) {
+                Ok(inbound) => dispatch(inbound, &live_config, &server, &vhosts),
+                Err(err) => {
+                    warn!(target: "lazymc", "Connection from unknown peer address, disconnecting: {}", err);
+                }
+            },
+        }
+    }
+}
+
+/// Open history and packet hooks of a server, spawn its services.
+///
+/// Services: monitor, schedule, health check, banned IPs refresh, probe and file watcher.
+fn spawn_server_services(live_config: &Arc<LiveConfig>, server: &Arc<Server>) {
+    let config = live_config.get();
+    server.history.open(&config);
+    server.packet_hooks.load(&config);
+
+    tokio::spawn(service::monitor::service(
+        live_config.clone(),
+        server.clone(),
+    ));
+    tokio::spawn(service::schedule::service(
+        live_config.clone(),
+        server.clone(),
+    ));
+    tokio::spawn(service::health::service(
+        live_config.clone(),
+        server.clone(),
+    ));
+    tokio::spawn(service::ban::service(live_config.clone(), server.clone()));
     tokio::spawn(service::probe::service(config.clone(), server.clone()));
     tokio::task::spawn_blocking({
-        let (config, server) = (config.clone(), server.clone());
+        let server = server.clone();
         || service::file_watcher::service(config, server)
     });
+}
 
-    // Route all incomming connections
-    while let Ok((inbound, _)) = listener.accept().await {
-        route(inbound, config.clone(), server.clone());
+/// Dispatch inbound client stream to the default server or virtual hosts.
+fn dispatch(
+    inbound: ClientStream,
+    live_config: &Arc<LiveConfig>,
+    server: &Arc<Server>,
+    vhosts: &Arc<VirtualHosts>,
+) {
+    if vhosts.is_empty() {
+        tokio::spawn(route(inbound, live_config.get(), server.clone()));
+    } else {
+        tokio::spawn(route_vhost(
+            inbound,
+            live_config.clone(),
+            server.clone(),
+            vhosts.clone(),
+        ));
     }
-
-    Ok(())
 }
 
-/// Route inbound TCP stream to correct service, spawning a new task.
-#[inline]
-fn route(inbound: TcpStream, config: Arc<Config>, server: Arc<Server>) {
-    // Get user peer address
+/// Accept TLS on inbound TCP stream, then route the decrypted stream.
+///
+/// The peer is checked before the handshake, so rate limited, banned or denied clients can't make
+/// lazymc do the handshake work.
+async fn route_tls(
+    inbound: TcpStream,
+    acceptor: TlsAcceptor,
+    live_config: Arc<LiveConfig>,
+    server: Arc<Server>,
+    vhosts: Arc<VirtualHosts>,
+) {
+    let config = live_config.get();
+    net::configure_tcp_stream(&inbound, &config.advanced.tcp);
+
     let peer = match inbound.peer_addr() {
-        Ok(peer) => peer,
+        Ok(peer) => net::canonical_addr(peer),
         Err(err) => {
             warn!(target: "lazymc", "Connection from unknown peer address, disconnecting: {}", err);
             return;
         }
     };
+    if !rate_limit(&config, peer) {
+        return;
+    }
+    let admission = admit(&config, &server, peer).await;
+    if admission == Admission::Drop {
+        return;
+    }
+
+    let Ok(mut inbound) = tls::accept(&acceptor, inbound).await else {
+        return;
+    };
+
+    // Check peer again against the virtual host it connects to
+    match find_vhost(&mut inbound, &vhosts).await {
+        Some(vhost) => {
+            let (config, server) = (vhost.config.get(), vhost.server.clone());
+            let admission = admit(&config, &server, peer).await;
+            serve(inbound, config, server, admission);
+        }
+        None => serve(inbound, config, server, admission),
+    }
+}
 
+/// Route inbound client stream to the virtual host matching the handshake hostname.
+///
+/// Uses the default server if no virtual host matches.
+async fn route_vhost(
+    mut inbound: ClientStream,
+    live_config: Arc<LiveConfig>,
+    server: Arc<Server>,
+    vhosts: Arc<VirtualHosts>,
+) {
+    match find_vhost(&mut inbound, &vhosts).await {
+        Some(vhost) => route(inbound, vhost.config.get(), vhost.server.clone()).await,
+        None => route(inbound, live_config.get(), server).await,
+    }
+}
+
+/// Find the virtual host matching the handshake hostname of the client stream.
+///
+/// Returns `None` if there are no virtual hosts or none matches.
+async fn find_vhost<'a>(
+    inbound: &mut ClientStream,
+    vhosts: &'a VirtualHosts,
+) -> Option<&'a VirtualHost> {
+    if vhosts.is_empty() {
+        return None;
+    }
+
+    let hostname = vhost::peek_hostname(inbound).await?;
+    let vhost = vhosts.find(&hostname)?;
+    debug!(target: "lazymc", "Routing connection to virtual host {}", vhost.hostname);
+    Some(vhost)
+}
+
+/// Route inbound client stream to correct service.
+async fn route(inbound: ClientStream, config: Arc<Config>, server: Arc<Server>) {
+    net::configure_tcp_stream(inbound.tcp(), &config.advanced.tcp);
+    let peer = inbound.peer();
+    if !rate_limit(&config, peer) {
+        return;
+    }
+    let admission = admit(&config, &server, peer).await;
+    serve(inbound, config, server, admission);
+}
+
+/// How to handle a new connection, based on its peer address.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Admission {
+    /// Drop the connection.
+    Drop,
+
+    /// Forward denied connection to the configured forward address.
+    Forward,
+
+    /// Kick because the connection limit is reached.
+    Limited,
+
+    /// Serve connection, through the proxy or status server.
+    Serve { banned: bool, denied: bool },
+}
+
+/// Check whether connection from peer exceeds the connection rate limit, drop it if so.
+fn rate_limit(config: &Config, peer: SocketAddr) -> bool {
+    let allowed = access::rate_limit(
+        &config.access.rate_limit,
+        peer.ip(),
+        RateLimited::Connection,
+    );
+    if !allowed {
+        debug!(target: "lazymc", "Connection from {} exceeds rate limit, dropping", peer.ip());
+    }
+    allowed
+}
+
+/// Check ban state, access lists and connection limit of peer.
+async fn admit(config: &Config, server: &Server, peer: SocketAddr) -> Admission {
     // Check ban state, just drop connection if enabled
-    let banned = server.is_banned_ip_blocking(&peer.ip());
+    let banned = server.is_banned_ip(&peer.ip()).await;
     if banned && config.server.drop_banned_ips {
         info!(target: "lazymc", "Connection from banned IP {}, dropping", peer.ip());
-        return;
+        return Admission::Drop;
+    }
+
+    // Check access lists and GeoIP, drop or forward denied connections if configured
+    let (denied, country) = access::check(config, peer.ip());
+    let country = country.map(|c| format!(" ({c})")).unwrap_or_default();
+    if config.access.geoip.enabled {
+        debug!(target: "lazymc", "Connection from {}{}", peer.ip(), country);
     }
+    if denied {
+        match config.access.denylist_action {
+            DenyAction::Drop => {
+                info!(target: "lazymc", "Connection from denied IP {}{}, dropping", peer.ip(), country);
+                return Admission::Drop;
+            }
+            DenyAction::Forward => {
+                info!(target: "lazymc", "Connection from denied IP {}{}, forwarding", peer.ip(), country);
+                return Admission::Forward;
+            }
+            DenyAction::Kick => {}
+        }
+    }
+
+    // Disconnect with message if connection limit is reached
+    if config
+        .advanced
+        .is_connection_limited(&server.stats, &peer.ip())
+    {
+        info!(target: "lazymc", "Connection from {} exceeds connection limit, disconnecting", peer.ip());
+        return Admission::Limited;
+    }
+
+    Admission::Serve { banned, denied }
+}
+
+/// Serve inbound client stream as admitted, spawning a new task.
+fn serve(inbound: ClientStream, config: Arc<Config>, server: Arc<Server>, admission: Admission) {
+    let peer = inbound.peer();
+    let (banned, denied) = match admission {
+        Admission::Drop => return,
+        Admission::Forward => {
+            route_proxy_address_queue(
+                inbound,
+                ProxyHeader::Proxy(config.join.forward.proxy_protocol_version)
+                    .not_none(config.join.forward.send_proxy_v2),
+                None,
+                config.join.forward.address,
+                config.clone(),
+                &server,
+                BytesMut::new(),
+            );
+            return;
+        }
+        Admission::Limited => {
+            let service = status::serve_limited(Client::new(peer), inbound, config).map(|r| {
+                if let Err(err) = r {
+                    warn!(target: "lazymc", "Failed to serve connection limit kick: {:?}", err);
+                }
+            });
+            tokio::spawn(service);
+            return;
+        }
+        Admission::Serve { banned, denied } => (banned, denied),
+    };
 
     // Route connection through proper channel
-    let should_proxy =
-        !banned && server.state() == server::State::Started && !config.lockout.enabled;
+    let should_proxy = !banned
+        && !denied
+        && server.state() == server::State::Started
+        && !(server.is_locked_out(&config) && !config.lockout.allows_ip(&peer.ip()))
+        && !config.needs_login_to_proxy();
     if should_proxy {
-        route_proxy(inbound, config)
+        route_proxy(inbound, config, &server)
     } else {
         route_status(inbound, config, server, peer)
     }
 }
 
-/// Route inbound TCP stream to status server, spawning a new task.
+/// Route inbound client stream to status server, spawning a new task.
 #[inline]
-fn route_status(inbound: TcpStream, config: Arc<Config>, server: Arc<Server>, peer: SocketAddr) {
+fn route_status(inbound: ClientStream, config: Arc<Config>, server: Arc<Server>, peer: SocketAddr) {
     // When server is not online, spawn a status server
     let client = Client::new(peer);
+    let connection = ConnectionGuard::new(&server.stats, Some(peer.ip()));
     let service = status::serve(client, inbound, config, server).map(|r| {
+        drop(connection);
         if let Err(err) = r {
             warn!(target: "lazymc", "Failed to serve status: {:?}", err);
         }
@@ -113,53 +506,166 @@ fn route_status(inbound: TcpStream, config: Arc<Config>, server: Arc<Server>, pe
     tokio::spawn(service);
 }
 
-/// Route inbound TCP stream to proxy, spawning a new task.
+/// Route inbound client stream to proxy, spawning a new task.
 #[inline]
-fn route_proxy(inbound: TcpStream, config: Arc<Config>) {
-    // When server is online, proxy all
-    let service = proxy::proxy(
-        inbound,
-        ProxyHeader::Proxy.not_none(config.server.send_proxy_v2),
-        config.server.address,
-    )
-    .map(|r| {
-        if let Err(err) = r {
-            warn!(target: "lazymc", "Failed to proxy: {}", err);
-        }
-    });
+fn route_proxy(inbound: ClientStream, config: Arc<Config>, server: &Server) {
+    // When server is online, proxy all to selected backend
+    let (addr, backend) = server.balancer.select(&config);
+    let connection = ConnectionGuard::new(&server.stats, Some(inbound.peer().ip()));
+    let proxy_header = ProxyHeader::Proxy(config.server.proxy_protocol_version)
+        .not_none(probe::send_proxy_v2(&config, server));
+    let relay = Relay::new(ClientState::Handshake, -1).with_hooks(server.packet_hooks.clone());
+    let service = async move {
+        proxy::proxy(inbound, proxy_header, addr, &config, relay)
+            .map(|r| {
+                drop(backend);
+                drop(connection);
+                if let Err(err) = r {
+                    warn!(target: "lazymc", "Failed to proxy: {}", err);
+                }
+            })
+            .await
+    };
 
     tokio::spawn(service);
 }
 
-/// Route inbound TCP stream to proxy with queued data, spawning a new task.
+/// Route inbound client stream to proxy with queued data, spawning a new task.
+///
+/// If a refused message is given, the client is kicked with it if the server can't be connected
+/// to. Disconnects sent by the server itself are relayed as-is.
 #[inline]
-pub fn route_proxy_queue(inbound: TcpStream, config: Arc<Config>, queue: BytesMut) {
-    route_proxy_address_queue(
+pub fn route_proxy_queue(
+    inbound: ClientStream,
+    config: Arc<Config>,
+    server: Arc<Server>,
+    client_info: &ClientInfo,
+    queue: BytesMut,
+    refused_message: Option<String>,
+) {
+    let peer = inbound.peer();
+    let forwarding = Forwarding::new(&config, peer.ip(), client_info);
+
+    // Relay player to dedicated server, otherwise select backend
+    let username = client_info.username.as_deref();
+    let (addr, backend) = match config.player_route(username, client_info.player_uuid()) {
+        Some(addr) => {
+            debug!(target: "lazymc", "Routing player {} to dedicated server {}", username.unwrap_or("?"), addr);
+            (addr, BackendGuard::none())
+        }
+        None => server.balancer.select(&config),
+    };
+
+    // Run player hooks and record session once the server accepts the login
+    let player = PendingPlayer::new(config.clone(), server.clone(), client_info, peer.ip());
+
+    spawn_proxy(
         inbound,
-        ProxyHeader::Proxy.not_none(config.server.send_proxy_v2),
-        config.server.address,
+        ProxyHeader::Proxy(config.server.proxy_protocol_version)
+            .not_none(probe::send_proxy_v2(&config, &server)),
+        forwarding,
+        addr,
+        config,
+        &server,
         queue,
+        backend,
+        player,
+        refused_message,
     );
 }
 
-/// Route inbound TCP stream to proxy with given address and queued data, spawning a new task.
+/// Route inbound client stream to proxy with given address and queued data, spawning a new task.
 #[inline]
 pub fn route_proxy_address_queue(
-    inbound: TcpStream,
+    inbound: ClientStream,
+    proxy_header: ProxyHeader,
+    forwarding: Option<Forwarding>,
+    addr: SocketAddr,
+    config: Arc<Config>,
+    server: &Server,
+    queue: BytesMut,
+) {
+    spawn_proxy(
+        inbound,
+        proxy_header,
+        forwarding,
+        addr,
+        config,
+        server,
+        queue,
+        BackendGuard::none(),
+        None,
+        None,
+    );
+}
+
+/// Spawn proxy task with queued data, holding the backend guard until done.
+///
+/// The player, if given, joins once the server accepts the login.
+///
+/// Kicks the client with the refused message if given and the server can't be connected to.
+#[allow(clippy::too_many_arguments)]
+fn spawn_proxy(
+    inbound: ClientStream,
     proxy_header: ProxyHeader,
+    forwarding: Option<Forwarding>,
     addr: SocketAddr,
+    config: Arc<Config>,
+    server: &Server,
     queue: BytesMut,
+    backend: BackendGuard,
+    player: Option<PendingPlayer>,
+    refused_message: Option<String>,
 ) {
-    // When server is online, proxy all
+    let connection = ConnectionGuard::new(&server.stats, Some(inbound.peer().ip()));
+    let relay = Relay::new(ClientState::Handshake, -1)
+        .with_hooks(server.packet_hooks.clone())
+        .with_player(player);
     let service = async move {
-        proxy::proxy_with_queue(inbound, proxy_header, addr, &queue)
-            .map(|r| {
-                if let Err(err) = r {
-                    warn!(target: "lazymc", "Failed to proxy: {}", err);
+        let result = match proxy::connect(addr, &config).await {
+            Ok(outbound) => {
+                proxy::proxy_outbound_with_queue(
+                    inbound,
+                    outbound,
+                    proxy_header,
+                    forwarding,
+                    &config,
+                    &queue,
+                    relay,
+                )
+                .await
+            }
+            Err(err) => {
+                if let Some(msg) = refused_message {
+                    kick_refused(inbound, &msg).await;
                 }
-            })
-            .await
+                Err(err.into())
+            }
+        };
+
+        drop(backend);
+        drop(connection);
+        if let Err(err) = result {
+            warn!(target: "lazymc", "Failed to proxy: {}", err);
+        }
     };
 
     tokio::spawn(service);
 }
+
+/// Kick client still logging in because the server refused the connection.
+///
+/// The server never sent a disconnect reason, the given message is used instead.
+async fn kick_refused(mut inbound: ClientStream, msg: &str) {
+    // Held clients are in login state without compression
+    let client = Client::dummy();
+    client.set_state(ClientState::Login);
+
+    debug!(target: "lazymc", "Server refused connection, kicking client");
+    if action::kick(&client, msg, &mut inbound.split().1)
+        .await
+        .is_ok()
+    {
+        let _ = net::close_tcp_stream(inbound).await;
+    }
+}
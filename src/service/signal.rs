@@ -1,22 +1,61 @@
 use std::sync::Arc;
 
-use crate::config::Config;
-use crate::server::{self, Server};
+use crate::action::start::rewrite_server_properties;
+use crate::config::{Config, ConfigError, LiveConfig};
+use crate::pid_file;
+use crate::server::Server;
+use crate::service::server::Instance;
 use crate::util::error;
 
-/// Signal handler task.
-pub async fn service(config: Arc<Config>, server: Arc<Server>) {
+/// Signal handler task, for all server instances.
+pub async fn service(instances: Vec<Instance>) {
+    // Reload config of each instance and its virtual hosts on SIGHUP
+    #[cfg(unix)]
+    for instance in &instances {
+        tokio::spawn(reload_service(
+            instance.config.clone(),
+            instance.server.clone(),
+            None,
+        ));
+        for vhost in instance.vhosts.iter() {
+            tokio::spawn(reload_service(
+                vhost.config.clone(),
+                vhost.server.clone(),
+                Some(vhost.hostname.clone()),
+            ));
+        }
+    }
+
     loop {
         // Wait for SIGTERM/SIGINT signal
         tokio::signal::ctrl_c().await.unwrap();
 
-        // Quit if stopped
-        if server.state() == server::State::Stopped {
+        // Servers of all instances and their virtual hosts
+        let servers: Vec<_> = instances
+            .iter()
+            .flat_map(|instance| {
+                [(&instance.config, &instance.server)].into_iter().chain(
+                    instance
+                        .vhosts
+                        .iter()
+                        .map(|vhost| (&vhost.config, &vhost.server)),
+                )
+            })
+            .collect();
+
+        // Quit if all stopped
+        if servers
+            .iter()
+            .all(|(_, server)| server.state().is_stopped())
+        {
             quit();
         }
 
-        // Try to stop server
-        let stopping = server.stop(&config).await;
+        // Try to stop servers
+        let mut stopping = false;
+        for (config, server) in servers {
+            stopping |= server.stop(&config.get()).await;
+        }
 
         // If not stopping, maybe due to failure, just quit
         if !stopping {
@@ -25,8 +64,133 @@ pub async fn service(config: Arc<Config>, server: Arc<Server>) {
     }
 }
 
+/// Signal handler task to reload config on SIGHUP.
+///
+/// Reloads the configuration of the given virtual host if set.
+#[cfg(unix)]
+async fn reload_service(config: Arc<LiveConfig>, server: Arc<Server>, vhost: Option<String>) {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    let mut hangup = match signal(SignalKind::hangup()) {
+        Ok(hangup) => hangup,
+        Err(err) => {
+            error!(target: "lazymc", "Failed to listen for SIGHUP signal, config reloading is unavailable: {}", err);
+            return;
+        }
+    };
+
+    while hangup.recv().await.is_some() {
+        info!(target: "lazymc::config", "Received SIGHUP, reloading config...");
+        reload_host(&config, &server, vhost.as_deref()).await;
+    }
+}
+
+/// Reload configuration and make it active.
+///
+/// Only changed parts are applied. Server configuration is deferred until the server is stopped
+/// if it is currently running. Keeps the current configuration if the new configuration fails to
+/// load.
+///
+/// Returns `true` if the configuration was reloaded.
+pub async fn reload(config: &LiveConfig, server: &Server) -> bool {
+    reload_host(config, server, None).await
+}
+
+/// Reload configuration of the given virtual host, or of the server if not set.
+///
+/// See [`reload`].
+async fn reload_host(config: &LiveConfig, server: &Server, vhost: Option<&str>) -> bool {
+    let current = config.get();
+
+    // Load new config, keep current one on failure
+    let mut new = match current.reload() {
+        Ok(new) => new,
+        Err(err) => {
+            error!(target: "lazymc::config", "Failed to reload config, keeping current config: {}", err);
+            return false;
+        }
+    };
+
+    // Virtual hosts are reloaded from the base config they are defined in
+    if let Some(hostname) = vhost {
+        new = match new
+            .vhosts
+            .iter()
+            .find(|v| v.hostname.eq_ignore_ascii_case(hostname))
+        {
+            Some(vhost) => new.vhost(vhost),
+            None => {
+                warn!(target: "lazymc::config", "Removed vhost '{}' requires restarting lazymc, keeping current config", hostname);
+                return false;
+            }
+        };
+    } else if hostnames(&new) != hostnames(&current) {
+        warn!(target: "lazymc::config", "Added or removed vhosts require restarting lazymc");
+    }
+
+    // Public address and RCON can't be changed at runtime, listener is bound and password is set
+    if new.public.address != current.public.address {
+        warn!(target: "lazymc::config", "Changed public.address requires restarting lazymc, ignoring");
+        new.public.address = current.public.address;
+    }
+    let mut rcon = new.rcon.clone();
+    if rcon.randomize_password && current.rcon.randomize_password {
+        rcon.password = current.rcon.password.clone();
+    }
+    if rcon != current.rcon {
+        warn!(target: "lazymc::config", "Changed rcon configuration requires restarting lazymc, ignoring");
+    }
+    new.rcon = current.rcon.clone();
+
+    // Don't proxy to ourselves
+    if let Some(err) = new.validate().into_iter().find(ConfigError::is_fatal) {
+        error!(target: "lazymc::config", "Invalid reloaded config, keeping current config: {}", err);
+        return false;
+    }
+
+    let diff = Config::diff(&current, &new);
+    if diff.is_empty() {
+        // Still replace, drops server configuration pending from an earlier reload
+        config.replace(new, false);
+        info!(target: "lazymc::config", "Config reloaded, nothing changed");
+        return true;
+    }
+    debug!(target: "lazymc::config", "Changed config fields: {}", diff.changed.join(", "));
+
+    // Server configuration is applied on next start if server is running
+    let running = !server.state().is_stopped() || server.has_process().await;
+    let defer_server = running && diff.contains("server");
+    if defer_server {
+        if diff.contains("server.command") {
+            info!(target: "lazymc::config", "Changed server.command will be used on next server start");
+        }
+        info!(target: "lazymc::config", "Server configuration changes will be applied on next server start");
+    }
+
+    // Properties file is built from these sections
+    let rewrite = !defer_server
+        && ["server", "join", "advanced"]
+            .iter()
+            .any(|section| diff.contains(section));
+
+    // Other sections are read from live config when used, swapping is enough to apply them
+    config.replace(new, defer_server);
+    if rewrite {
+        rewrite_server_properties(&config.get());
+    }
+    info!(target: "lazymc::config", "Config reloaded, changed: {}", diff.sections().join(", "));
+
+    true
+}
+
+/// Hostnames of configured virtual hosts.
+fn hostnames(config: &Config) -> Vec<&str> {
+    config.vhosts.iter().map(|v| v.hostname.as_str()).collect()
+}
+
 /// Gracefully quit.
 fn quit() -> ! {
     // TODO: gracefully quit self
+    pid_file::remove();
     error::quit();
 }
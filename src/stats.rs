@@ -0,0 +1,184 @@
+use std::net::IpAddr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime};
+
+use dashmap::DashMap;
+use tokio::sync::RwLock;
+
+/// Shared server statistics.
+///
+/// Collected over the lifetime of lazymc, used for MOTD placeholders and metrics.
+#[derive(Debug, Default)]
+pub struct ServerStats {
+    /// Number of times the server was woken.
+    wake_count: AtomicU64,
+
+    /// Number of times the server went to sleep.
+    sleep_count: AtomicU64,
+
+    /// Number of clients kicked by the kick join method.
+    kick_count: AtomicU64,
+
+    /// Time the server started starting, if starting.
+    starting_since: RwLock<Option<Instant>>,
+
+    /// Time the server came online, if online.
+    online_since: RwLock<Option<Instant>>,
+
+    /// Duration of the last server start, from starting until online.
+    last_start_duration: RwLock<Option<Duration>>,
+
+    /// Time the server was last woken.
+    last_wake: RwLock<Option<SystemTime>>,
+
+    /// Time the server last went to sleep.
+    last_sleep: RwLock<Option<SystemTime>>,
+
+    /// Duration the server was online for during the last wake cycle.
+    last_online_duration: RwLock<Option<Duration>>,
+
+    /// Seconds the server ran without players before it last went to sleep.
+    actual_idle_before_sleep_secs: RwLock<Option<u64>>,
+
+    /// Connections currently handled by lazymc.
+    connections: Arc<Connections>,
+}
+
+impl ServerStats {
+    /// Record server being woken.
+    pub async fn record_wake(&self) {
+        self.wake_count.fetch_add(1, Ordering::Relaxed);
+        self.starting_since.write().await.replace(Instant::now());
+        self.last_wake.write().await.replace(SystemTime::now());
+    }
+
+    /// Record server coming online.
+    pub async fn record_online(&self) {
+        if let Some(since) = self.starting_since.write().await.take() {
+            self.last_start_duration
+                .write()
+                .await
+                .replace(since.elapsed());
+        }
+        self.online_since.write().await.replace(Instant::now());
+    }
+
+    /// Record server going offline, after being idle for the given time.
+    ///
+    /// Returns how long the server was online for, `None` if it never came online.
+    pub async fn record_offline(&self, idle: Option<Duration>) -> Option<Duration> {
+        self.sleep_count.fetch_add(1, Ordering::Relaxed);
+        self.starting_since.write().await.take();
+        self.last_sleep.write().await.replace(SystemTime::now());
+
+        // Only update wake cycle times if the server came online
+        let online = self.online_since.write().await.take()?.elapsed();
+        self.last_online_duration.write().await.replace(online);
+        *self.actual_idle_before_sleep_secs.write().await = idle.map(|idle| idle.as_secs());
+        Some(online)
+    }
+
+    /// Record client being kicked by the kick join method.
+    pub fn record_kick(&self) {
+        self.kick_count.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Number of times the server was woken.
+    pub fn wake_count(&self) -> u64 {
+        self.wake_count.load(Ordering::Relaxed)
+    }
+
+    /// Number of times the server went to sleep.
+    pub fn sleep_count(&self) -> u64 {
+        self.sleep_count.load(Ordering::Relaxed)
+    }
+
+    /// Number of clients kicked by the kick join method.
+    pub fn kick_count(&self) -> u64 {
+        self.kick_count.load(Ordering::Relaxed)
+    }
+
+    /// Time the server has been online for, `None` if offline.
+    pub async fn uptime(&self) -> Option<Duration> {
+        self.online_since.read().await.map(|since| since.elapsed())
+    }
+
+    /// Duration of the last server start, `None` if never started.
+    pub async fn last_start_duration(&self) -> Option<Duration> {
+        *self.last_start_duration.read().await
+    }
+
+    /// Time the server was last woken, `None` if never woken.
+    pub async fn last_wake(&self) -> Option<SystemTime> {
+        *self.last_wake.read().await
+    }
+
+    /// Time the server last went to sleep, `None` if it never slept.
+    pub async fn last_sleep(&self) -> Option<SystemTime> {
+        *self.last_sleep.read().await
+    }
+
+    /// Duration the server was online for during the last wake cycle, `None` if never online.
+    pub async fn last_online_duration(&self) -> Option<Duration> {
+        *self.last_online_duration.read().await
+    }
+
+    /// Seconds the server ran without players before it last went to sleep, `None` if unknown.
+    pub async fn actual_idle_before_sleep_secs(&self) -> Option<u64> {
+        *self.actual_idle_before_sleep_secs.read().await
+    }
+
+    /// Number of connections currently handled by lazymc.
+    pub fn active_connections(&self) -> u64 {
+        self.connections.active.load(Ordering::Relaxed)
+    }
+
+    /// Number of connections currently handled by lazymc for the given IP.
+    pub fn ip_connections(&self, ip: &IpAddr) -> u32 {
+        self.connections.ips.get(ip).map(|c| *c).unwrap_or(0)
+    }
+}
+
+/// Connections currently handled by lazymc.
+#[derive(Debug, Default)]
+struct Connections {
+    /// Number of connections.
+    active: AtomicU64,
+
+    /// Number of connections per IP.
+    ips: DashMap<IpAddr, u32>,
+}
+
+/// Guard tracking an active connection, for as long as it lives.
+pub struct ConnectionGuard {
+    /// Connections of the server the connection is tracked for.
+    connections: Arc<Connections>,
+
+    /// Peer IP if known.
+    ip: Option<IpAddr>,
+}
+
+impl ConnectionGuard {
+    /// Start tracking an active connection from the given peer IP in server statistics.
+    pub fn new(stats: &ServerStats, ip: Option<IpAddr>) -> Self {
+        let connections = stats.connections.clone();
+        connections.active.fetch_add(1, Ordering::Relaxed);
+        if let Some(ip) = ip {
+            *connections.ips.entry(ip).or_insert(0) += 1;
+        }
+        Self { connections, ip }
+    }
+}
+
+impl Drop for ConnectionGuard {
+    fn drop(&mut self) {
+        self.connections.active.fetch_sub(1, Ordering::Relaxed);
+        if let Some(ip) = self.ip {
+            self.connections.ips.remove_if_mut(&ip, |_, count| {
+                *count = count.saturating_sub(1);
+                *count == 0
+            });
+        }
+    }
+}
@@ -1,24 +1,33 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 use bytes::BytesMut;
 use minecraft_protocol::data::server_status::{OnlinePlayers, ServerVersion};
 use minecraft_protocol::decoder::Decoder;
-use minecraft_protocol::encoder::Encoder;
 use minecraft_protocol::version::v1_14_4::handshake::Handshake;
 use minecraft_protocol::version::v1_14_4::login::LoginStart;
-use minecraft_protocol::version::v1_20_3::status::{ServerStatus, StatusResponse};
+use minecraft_protocol::version::v1_20_3::status::ServerStatus;
+use serde_json::{json, Value};
 use tokio::fs;
 use tokio::io::AsyncWriteExt;
-use tokio::net::TcpStream;
+use uuid::Uuid;
 
-use crate::config::{Config, Server as ConfigServer};
+use crate::access::{self, RateLimited};
+use crate::auth;
+use crate::config::{Config, MotdFormat, Server as ConfigServer};
 use crate::join;
 use crate::mc::favicon;
+use crate::motd::{self, MotdContext};
+use crate::probe;
 use crate::proto::action;
 use crate::proto::client::{Client, ClientInfo, ClientState};
 use crate::proto::packet::{self, RawPacket};
 use crate::proto::packets;
-use crate::server::{self, Server};
+use crate::server::{self, Server, WakeEvent};
+use crate::service;
+use crate::stream::ClientStream;
+use crate::types;
 
 /// The ban message prefix.
 const BAN_MESSAGE_PREFIX: &str = "Your IP address is banned from this server.\nReason: ";
@@ -26,17 +35,23 @@ const BAN_MESSAGE_PREFIX: &str = "Your IP address is banned from this server.\nR
 /// Default ban reason if unknown.
 const DEFAULT_BAN_REASON: &str = "Banned by an operator.";
 
+/// The failed authentication kick message.
+const AUTH_FAILED_MESSAGE: &str = "Failed to verify username!";
+
 /// The not-whitelisted kick message.
 const WHITELIST_MESSAGE: &str = "You are not white-listed on this server!";
 
 /// Server icon file path.
 const SERVER_ICON_FILE: &str = "server-icon.png";
 
+/// Number of status pings responded to with a sleeping MOTD, used to cycle messages.
+static SLEEPING_MOTD_PINGS: AtomicUsize = AtomicUsize::new(0);
+
 /// Proxy the given inbound stream to a target address.
 // TODO: do not drop error here, return Box<dyn Error>
 pub async fn serve(
     client: Client,
-    mut inbound: TcpStream,
+    mut inbound: ClientStream,
     config: Arc<Config>,
     server: Arc<Server>,
 ) -> Result<(), ()> {
@@ -102,14 +117,20 @@ pub async fn serve(
 
         // Hijack server status packet
         if client_state == ClientState::Status && packet.id == packets::status::SERVER_STATUS {
-            let server_status = server_status(&client_info, &config, &server).await;
-            let packet = StatusResponse { server_status };
+            // Drop connection if rate limited
+            let ip = client.peer.ip();
+            if !access::rate_limit(&config.access.rate_limit, ip, RateLimited::StatusPing) {
+                debug!(target: "lazymc", "Status ping from {} exceeds rate limit, dropping", ip);
+                break;
+            }
 
-            let mut data = Vec::new();
-            packet.encode(&mut data).map_err(|_| ())?;
+            let server_status = cached_server_status(&client_info, &config, &server).await?;
+            let data = types::encode_string(&server_status)?;
 
-            let response = RawPacket::new(0, data).encode_with_len(&client)?;
+            let response =
+                RawPacket::new(packets::status::CLIENT_STATUS, data).encode_with_len(&client)?;
             writer.write_all(&response).await.map_err(|_| ())?;
+            writer.flush().await.map_err(|_| ())?;
 
             continue;
         }
@@ -117,6 +138,7 @@ pub async fn serve(
         // Hijack ping packet
         if client_state == ClientState::Status && packet.id == packets::status::SERVER_PING {
             writer.write_all(&raw).await.map_err(|_| ())?;
+            writer.flush().await.map_err(|_| ())?;
             continue;
         }
 
@@ -128,9 +150,24 @@ pub async fn serve(
                 .ok()
                 .map(|p| p.name);
             client_info.username = username.clone();
+            client_info.login_uuid = client_info
+                .protocol()
+                .and_then(|protocol| login_start_uuid(protocol, &packet.data));
+
+            // Kick if client is denied access
+            if let (true, country) = access::check(&config, client.peer.ip()) {
+                let country = country.map(|c| format!(" ({c})")).unwrap_or_default();
+                info!(target: "lazymc", "Login from denied IP {}{}, disconnecting", client.peer.ip(), country);
+                action::kick(&client, &config.access.denylist_message, &mut writer).await?;
+                break;
+            }
 
             // Kick if lockout is enabled
-            if config.lockout.enabled {
+            if server.is_locked_out(&config)
+                && !config
+                    .lockout
+                    .allows(&client.peer.ip(), username.as_deref())
+            {
                 match username {
                     Some(username) => {
                         info!(target: "lazymc", "Kicked '{}' because lockout is enabled", username)
@@ -141,6 +178,32 @@ pub async fn serve(
                 break;
             }
 
+            // Authenticate player with session server, enables encryption
+            let authenticate = auth::must_authenticate(&config);
+            if authenticate {
+                let result =
+                    auth::authenticate(&config, &client, &mut client_info, &mut inbound, &mut buf)
+                        .await;
+                writer = inbound.split().1;
+                if result.is_err() {
+                    action::kick(&client, AUTH_FAILED_MESSAGE, &mut writer).await?;
+                    return Ok(());
+                }
+            }
+            let username = client_info.username.clone();
+
+            // Check lockout again with the verified username, the client may claim any name
+            if authenticate
+                && server.is_locked_out(&config)
+                && !config
+                    .lockout
+                    .allows(&client.peer.ip(), username.as_deref())
+            {
+                info!(target: "lazymc", "Kicked '{}' because lockout is enabled", username.as_deref().unwrap_or("unknown"));
+                action::kick(&client, &config.lockout.message, &mut writer).await?;
+                break;
+            }
+
             // Kick if client is banned
             if let Some(ban) = server.ban_entry(&client.peer.ip()).await {
                 if ban.is_banned() {
@@ -167,12 +230,33 @@ pub async fn serve(
             }
 
             // Start server if not starting yet
-            Server::start(config.clone(), server.clone(), username).await;
+            Server::start(
+                config.clone(),
+                server.clone(),
+                Some(WakeEvent::new(username, client.peer)),
+            )
+            .await;
 
             // Remember inbound packets
             inbound_history.extend(&raw);
             inbound_history.extend(&buf);
 
+            // Proxy to started server directly, forwarding player info, routing player or
+            // bypassing lockout
+            if server.state() == server::State::Started
+                && (config.needs_login_to_proxy() || server.is_locked_out(&config))
+            {
+                service::server::route_proxy_queue(
+                    inbound,
+                    config,
+                    server,
+                    &client_info,
+                    inbound_history,
+                    None,
+                );
+                return Ok(());
+            }
+
             // Build inbound packet queue with everything from login start (including this)
             let mut login_queue = BytesMut::with_capacity(raw.len() + buf.len());
             login_queue.extend(&raw);
@@ -204,45 +288,161 @@ pub async fn serve(
     Ok(())
 }
 
-/// Build server status object to respond to client with.
-async fn server_status(client_info: &ClientInfo, config: &Config, server: &Server) -> ServerStatus {
+/// Get player UUID from login start packet data, sent by 1.19.3+ clients.
+fn login_start_uuid(protocol: u32, data: &[u8]) -> Option<Uuid> {
+    // Skip username
+    let (read, len) = types::read_var_int(data).ok()?;
+    let mut data = data.get(read + len as usize..)?;
+
+    match protocol {
+        // 1.20.2+: UUID
+        764.. => {}
+        // 1.19.3 to 1.20.1: optional UUID
+        761..=763 => {
+            let (has_uuid, rest) = data.split_first()?;
+            if *has_uuid == 0 {
+                return None;
+            }
+            data = rest;
+        }
+        _ => return None,
+    }
+
+    Uuid::from_slice(data.get(..16)?).ok()
+}
+
+/// Serve client exceeding the connection limit.
+///
+/// Kicks the client with the connection limit message on login, drops status requests.
+pub async fn serve_limited(
+    client: Client,
+    mut inbound: ClientStream,
+    config: Arc<Config>,
+) -> Result<(), ()> {
+    let (mut reader, mut writer) = inbound.split();
+    let mut buf = BytesMut::new();
+
+    loop {
+        let (packet, _) = match packet::read_packet(&client, &mut buf, &mut reader).await {
+            Ok(Some(packet)) => packet,
+            Ok(None) | Err(_) => break,
+        };
+
+        match client.state() {
+            // Switch to requested state
+            ClientState::Handshake if packet.id == packets::handshake::SERVER_HANDSHAKE => {
+                let handshake = match Handshake::decode(&mut packet.data.as_slice()) {
+                    Ok(handshake) => handshake,
+                    Err(_) => break,
+                };
+                match ClientState::from_id(handshake.next_state) {
+                    Some(state @ ClientState::Login) => client.set_state(state),
+                    _ => break,
+                }
+            }
+
+            // Kick on login
+            ClientState::Login if packet.id == packets::login::SERVER_LOGIN_START => {
+                action::kick(
+                    &client,
+                    &config.advanced.connection_limit_message,
+                    &mut writer,
+                )
+                .await?;
+                break;
+            }
+
+            _ => break,
+        }
+    }
+
+    Ok(())
+}
+
+/// Status response cached for a short while.
+pub struct CachedStatus {
+    /// Time the status was built at.
+    at: Instant,
+
+    /// Whether the status was built for a client supporting favicons.
+    favicon: bool,
+
+    /// Status response JSON.
+    json: String,
+}
+
+/// Build server status JSON to respond to client with, cached for the configured interval.
+async fn cached_server_status(
+    client_info: &ClientInfo,
+    config: &Config,
+    server: &Server,
+) -> Result<String, ()> {
+    let cache_for = Duration::from_millis(config.advanced.status_cache_ms);
+    if cache_for.is_zero() {
+        return Ok(server_status(client_info, config, server)
+            .await?
+            .to_string());
+    }
+
+    // Use cached status if still fresh
+    let favicon = favicon::supports_favicon(client_info);
+    if let Some(cached) = server.status_cache.read().await.as_ref() {
+        if cached.favicon == favicon && cached.at.elapsed() < cache_for {
+            return Ok(cached.json.clone());
+        }
+    }
+
+    let json = server_status(client_info, config, server)
+        .await?
+        .to_string();
+    server.status_cache.write().await.replace(CachedStatus {
+        at: Instant::now(),
+        favicon,
+        json: json.clone(),
+    });
+    Ok(json)
+}
+
+/// Build server status JSON object to respond to client with.
+async fn server_status(
+    client_info: &ClientInfo,
+    config: &Config,
+    server: &Server,
+) -> Result<Value, ()> {
     let status = server.status().await;
     let server_state = server.state();
 
     // Respond with real server status if started
     if server_state == server::State::Started && status.is_some() {
-        return status.as_ref().unwrap().clone();
+        return to_json(status.as_ref().unwrap());
     }
 
-    // Select version and player max from last known server status
-    let (version, max) = match status.as_ref() {
-        Some(status) => (status.version.clone(), status.players.max),
-        None => (
-            ServerVersion {
-                name: config.public.version.clone(),
-                protocol: config.public.protocol,
-            },
-            0,
-        ),
+    // Select version from last known server status
+    let version = match status.as_ref() {
+        Some(status) => status.version.clone(),
+        None => ServerVersion {
+            name: config.public.version.clone(),
+            protocol: probe::protocol(config, server),
+        },
     };
 
+    let max = max_players(config, server, status.as_ref());
+
     // Select description, use server MOTD if enabled, or use configured
+    let from_server = config.motd.from_server && status.is_some();
     let description = {
-        if config.motd.from_server && status.is_some() {
+        if from_server {
             status.as_ref().unwrap().description.clone()
         } else {
-            match server_state {
-                server::State::Stopped | server::State::Started => config.motd.sleeping.clone(),
-                server::State::Starting => config.motd.starting.clone(),
-                server::State::Stopping => config.motd.stopping.clone(),
-            }
+            state_motd(config, server, status.as_ref()).await
         }
     };
 
-    // Extract favicon from real server status, load from disk, or use default
+    // Use configured state favicon, extract from real server status, load from disk, or use default
     let mut favicon = None;
     if favicon::supports_favicon(client_info) {
-        if config.motd.from_server && status.is_some() {
+        favicon = state_favicon(config, server_state).await;
+        if favicon.is_none() && config.motd.from_server && status.is_some() {
             favicon = status.as_ref().unwrap().favicon.clone()
         }
         if favicon.is_none() {
@@ -250,8 +450,21 @@ async fn server_status(client_info: &ClientInfo, config: &Config, server: &Serve
         }
     }
 
+    // Configured JSON MOTD is used as raw text component
+    let raw_description = if !from_server && config.motd.format == MotdFormat::Json {
+        match serde_json::from_str::<Value>(&description) {
+            Ok(value) => Some(value),
+            Err(err) => {
+                warn!(target: "lazymc::status", "Configured MOTD is not valid JSON, using it as text: {err}");
+                None
+            }
+        }
+    } else {
+        None
+    };
+
     // Build status resposne
-    ServerStatus {
+    let mut status = to_json(&ServerStatus {
         version,
         description,
         players: OnlinePlayers {
@@ -260,6 +473,95 @@ async fn server_status(client_info: &ClientInfo, config: &Config, server: &Serve
             sample: vec![],
         },
         favicon,
+    })?;
+    if let Some(raw_description) = raw_description {
+        status["description"] = raw_description;
+    }
+
+    // List fake players while sleeping, to appear active in server lists
+    if server_state.is_stopped() && !config.motd.fake_players.is_empty() {
+        status["players"]["sample"] = config
+            .motd
+            .fake_players
+            .iter()
+            .map(|player| json!({ "name": player.name, "id": player.uuid.to_string() }))
+            .collect();
+    }
+
+    Ok(status)
+}
+
+/// Render configured MOTD for the current server state.
+///
+/// Takes the last known server status, to fill in placeholders.
+pub async fn state_motd(config: &Config, server: &Server, status: Option<&ServerStatus>) -> String {
+    let template = match server.state() {
+        server::State::CrashLoopBackoff => config.motd.crash_loop.clone(),
+        server::State::Stopped if server.is_start_timed_out() => config.motd.start_timeout.clone(),
+        server::State::Stopped | server::State::Started => sleeping_motd(config),
+        server::State::Starting => config.motd.starting.clone(),
+        server::State::Resuming => config.motd.resuming.clone(),
+        server::State::Stopping => config.motd.stopping.clone(),
+    };
+    let ctx = MotdContext {
+        players_online: status.map(|s| s.players.online).unwrap_or(0),
+        players_max: max_players(config, server, status),
+        server_version: status
+            .map(|s| s.version.name.clone())
+            .unwrap_or_else(|| config.public.version.clone()),
+        uptime: server.stats.uptime().await.unwrap_or_default(),
+        wake_count: server.stats.wake_count(),
+    };
+    motd::render(&template, &ctx)
+}
+
+/// Select player max from server.properties, last known server status, or use configured.
+pub fn max_players(config: &Config, server: &Server, status: Option<&ServerStatus>) -> u32 {
+    server
+        .properties_max_players(config)
+        .or_else(|| status.map(|status| status.players.max))
+        .unwrap_or(config.public.max_players)
+}
+
+/// Serialize server status to JSON object.
+fn to_json(status: &ServerStatus) -> Result<Value, ()> {
+    serde_json::to_value(status).map_err(|err| {
+        error!(target: "lazymc::status", "Failed to serialize server status: {err}");
+    })
+}
+
+/// Select sleeping MOTD, cycling through configured messages on each status ping.
+fn sleeping_motd(config: &Config) -> String {
+    let messages = &config.motd.sleeping_messages;
+    if messages.len() <= 1 {
+        return messages.first().cloned().unwrap_or_default();
+    }
+
+    let ping = SLEEPING_MOTD_PINGS.fetch_add(1, Ordering::Relaxed);
+    let index = (ping / config.motd.cycle_interval.max(1) as usize) % messages.len();
+    messages[index].clone()
+}
+
+/// Get configured status favicon for the given server state.
+///
+/// Returns `None` if not configured or if it failed to load.
+async fn state_favicon(config: &Config, state: server::State) -> Option<String> {
+    let path = match state {
+        server::State::Stopped | server::State::CrashLoopBackoff | server::State::Started => {
+            &config.motd.favicon_sleeping
+        }
+        server::State::Starting | server::State::Resuming => &config.motd.favicon_starting,
+        server::State::Stopping => &config.motd.favicon_stopping,
+    }
+    .as_ref()?;
+
+    let path = config.resolve_path(path);
+    match fs::read(&path).await {
+        Ok(data) => Some(favicon::encode_favicon(&data)),
+        Err(err) => {
+            error!(target: "lazymc::status", "Failed to read favicon from {}, falling back: {err}", path.display());
+            None
+        }
     }
 }
 
@@ -287,3 +589,82 @@ async fn server_favicon(config: &Config) -> String {
 
     favicon::encode_favicon(&data)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    #[ignore = "requires IPv6 loopback"]
+    async fn serve_status_ipv6() {
+        use minecraft_protocol::encoder::Encoder;
+        use tokio::net::TcpStream;
+
+        use crate::net;
+
+        let config: Arc<Config> = Arc::new(
+            toml::from_str(
+                "[server]\ncommand = \"java -jar server.jar\"\ndirectory = \"lazymc-test-missing\"",
+            )
+            .unwrap(),
+        );
+        let listener = net::bind_listener("[::1]:0".parse().unwrap(), None).unwrap();
+        let mut stream = TcpStream::connect(listener.local_addr().unwrap())
+            .await
+            .unwrap();
+        let (inbound, peer) = listener.accept().await.unwrap();
+        let inbound = ClientStream::new(inbound).unwrap();
+        let version = config.public.version.clone();
+        tokio::spawn(serve(
+            Client::new(peer),
+            inbound,
+            config,
+            Arc::new(Server::default()),
+        ));
+
+        // Handshake into status state and request status
+        let client = Client::dummy();
+        let mut data = Vec::new();
+        Handshake {
+            protocol_version: 765,
+            server_addr: "::1".into(),
+            server_port: 25565,
+            next_state: ClientState::Status.to_id(),
+        }
+        .encode(&mut data)
+        .unwrap();
+        let mut request = RawPacket::new(packets::handshake::SERVER_HANDSHAKE, data)
+            .encode_with_len(&client)
+            .unwrap();
+        request.extend(
+            RawPacket::new(packets::status::SERVER_STATUS, Vec::new())
+                .encode_with_len(&client)
+                .unwrap(),
+        );
+        stream.write_all(&request).await.unwrap();
+
+        let mut buf = BytesMut::new();
+        let (packet, _) = packet::read_packet(&client, &mut buf, &mut stream)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(packet.id, packets::status::CLIENT_STATUS);
+        let (_, status) = types::read_string(&packet.data).unwrap();
+        let status: Value = serde_json::from_str(&status).unwrap();
+        assert_eq!(status["version"]["name"], version.as_str());
+    }
+
+    #[tokio::test]
+    async fn state_motd_players_max_unprobed() {
+        // Server directory without server.properties, server never probed
+        let mut config: Config = toml::from_str(
+            "[server]\ncommand = \"java -jar server.jar\"\ndirectory = \"lazymc-test-missing\"",
+        )
+        .unwrap();
+        config.motd.sleeping_messages = vec!["{players_online}/{players_max}".into()];
+        config.public.max_players = 42;
+
+        let server = Server::default();
+        assert_eq!(state_motd(&config, &server, None).await, "0/42");
+    }
+}
@@ -0,0 +1,340 @@
+use std::io;
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::task::{ready, Context, Poll};
+
+use aes::cipher::{BlockDecryptMut, BlockEncryptMut, KeyIvInit};
+use aes::Aes128;
+use futures::FutureExt;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, ReadBuf};
+use tokio::net::TcpStream;
+use tokio_rustls::server::TlsStream;
+
+use crate::net;
+
+/// Client to cipher.
+type Encryptor = cfb8::Encryptor<Aes128>;
+
+/// Cipher to client.
+type Decryptor = cfb8::Decryptor<Aes128>;
+
+/// Read half of a split client stream.
+pub type ReadHalf<'a> = tokio::io::ReadHalf<&'a mut ClientStream>;
+
+/// Write half of a split client stream.
+pub type WriteHalf<'a> = tokio::io::WriteHalf<&'a mut ClientStream>;
+
+/// Client connection stream.
+///
+/// Wraps the accepted TCP stream, with TLS and Minecraft protocol encryption layered on top if
+/// enabled. Reading and writing always uses plain data.
+pub struct ClientStream {
+    /// Underlying transport.
+    transport: Transport,
+
+    /// Protocol encryption, once enabled.
+    cipher: Option<Box<Cipher>>,
+
+    /// Client address.
+    peer: SocketAddr,
+
+    /// Data peeked at but not read yet.
+    peeked: Vec<u8>,
+}
+
+impl ClientStream {
+    /// Construct client stream for accepted TCP stream.
+    pub fn new(stream: TcpStream) -> io::Result<Self> {
+        let peer = net::canonical_addr(stream.peer_addr()?);
+        Ok(Self::with_transport(Transport::Tcp(stream), peer))
+    }
+
+    /// Construct client stream for TLS stream, accepted from given client address.
+    pub fn tls(stream: TlsStream<TcpStream>, peer: SocketAddr) -> Self {
+        Self::with_transport(Transport::Tls(Box::new(stream)), peer)
+    }
+
+    fn with_transport(transport: Transport, peer: SocketAddr) -> Self {
+        Self {
+            transport,
+            cipher: None,
+            peer,
+            peeked: Vec::new(),
+        }
+    }
+
+    /// Client address.
+    pub fn peer(&self) -> SocketAddr {
+        self.peer
+    }
+
+    /// Local address the client connected to.
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.tcp().local_addr().map(net::canonical_addr)
+    }
+
+    /// Underlying TCP stream.
+    ///
+    /// Must not be read from or written to directly, unless the stream [`is_plain`](Self::is_plain).
+    pub fn tcp(&self) -> &TcpStream {
+        match &self.transport {
+            Transport::Tcp(stream) => stream,
+            Transport::Tls(stream) => stream.get_ref().0,
+        }
+    }
+
+    /// Whether data is sent as-is over the underlying TCP stream, without TLS or encryption.
+    pub fn is_plain(&self) -> bool {
+        matches!(self.transport, Transport::Tcp(_))
+            && self.cipher.is_none()
+            && self.peeked.is_empty()
+    }
+
+    /// Split into borrowed read and write half.
+    pub fn split(&mut self) -> (ReadHalf<'_>, WriteHalf<'_>) {
+        tokio::io::split(self)
+    }
+
+    /// Enable protocol encryption with the given shared secret.
+    ///
+    /// Data already received from the client is decrypted in place.
+    pub fn encrypt(&mut self, secret: &[u8], received: &mut [u8]) -> Result<(), ()> {
+        let mut cipher = Cipher {
+            encryptor: Encryptor::new_from_slices(secret, secret).map_err(|_| ())?,
+            decryptor: Decryptor::new_from_slices(secret, secret).map_err(|_| ())?,
+        };
+        cipher.decrypt(received);
+        cipher.decrypt(&mut self.peeked);
+        self.cipher = Some(Box::new(cipher));
+        Ok(())
+    }
+
+    /// Peek at received data without consuming it.
+    ///
+    /// Waits for data if nothing is received yet, returns the number of bytes peeked.
+    pub async fn peek(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if let Transport::Tcp(stream) = &self.transport {
+            if self.cipher.is_none() && self.peeked.is_empty() {
+                return stream.peek(buf).await;
+            }
+        }
+
+        // Wait for data if nothing peeked yet, otherwise take what is available right now
+        let was_empty = self.peeked.is_empty();
+        while self.peeked.len() < buf.len() {
+            let mut tmp = vec![0; buf.len() - self.peeked.len()];
+            let read = match self.read_transport(&mut tmp).now_or_never() {
+                Some(read) => read?,
+                None if was_empty && self.peeked.is_empty() => {
+                    self.read_transport(&mut tmp).await?
+                }
+                None => break,
+            };
+            if read == 0 {
+                break;
+            }
+            self.peeked.extend_from_slice(&tmp[..read]);
+        }
+
+        let len = self.peeked.len().min(buf.len());
+        buf[..len].copy_from_slice(&self.peeked[..len]);
+        Ok(len)
+    }
+
+    /// Read decrypted data from the transport, bypassing peeked data.
+    async fn read_transport(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.transport.read(buf).await?;
+        if let Some(cipher) = self.cipher.as_mut() {
+            cipher.decrypt(&mut buf[..read]);
+        }
+        Ok(read)
+    }
+}
+
+impl AsyncRead for ClientStream {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+
+        // Return peeked data first
+        if !this.peeked.is_empty() {
+            let len = this.peeked.len().min(buf.remaining());
+            buf.put_slice(&this.peeked[..len]);
+            this.peeked.drain(..len);
+            return Poll::Ready(Ok(()));
+        }
+
+        let filled = buf.filled().len();
+        ready!(Pin::new(&mut this.transport).poll_read(cx, buf))?;
+        if let Some(cipher) = this.cipher.as_mut() {
+            cipher.decrypt(&mut buf.filled_mut()[filled..]);
+        }
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl AsyncWrite for ClientStream {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        let Some(cipher) = this.cipher.as_mut() else {
+            return Pin::new(&mut this.transport).poll_write(cx, buf);
+        };
+
+        // Encrypt with copy of the cipher, only advance it by what is actually written
+        let mut encryptor = cipher.encryptor.clone();
+        let mut data = buf.to_vec();
+        for byte in data.chunks_mut(1) {
+            encryptor.encrypt_block_mut(byte.into());
+        }
+        let written = ready!(Pin::new(&mut this.transport).poll_write(cx, &data))?;
+        if written == data.len() {
+            cipher.encryptor = encryptor;
+        } else {
+            let mut data = buf[..written].to_vec();
+            for byte in data.chunks_mut(1) {
+                cipher.encryptor.encrypt_block_mut(byte.into());
+            }
+        }
+        Poll::Ready(Ok(written))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().transport).poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().transport).poll_shutdown(cx)
+    }
+}
+
+/// Client stream transport.
+enum Transport {
+    /// Plain TCP.
+    Tcp(TcpStream),
+
+    /// TLS over TCP.
+    Tls(Box<TlsStream<TcpStream>>),
+}
+
+impl AsyncRead for Transport {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        match self.get_mut() {
+            Transport::Tcp(stream) => Pin::new(stream).poll_read(cx, buf),
+            Transport::Tls(stream) => Pin::new(stream.as_mut()).poll_read(cx, buf),
+        }
+    }
+}
+
+impl AsyncWrite for Transport {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        match self.get_mut() {
+            Transport::Tcp(stream) => Pin::new(stream).poll_write(cx, buf),
+            Transport::Tls(stream) => Pin::new(stream.as_mut()).poll_write(cx, buf),
+        }
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        match self.get_mut() {
+            Transport::Tcp(stream) => Pin::new(stream).poll_flush(cx),
+            Transport::Tls(stream) => Pin::new(stream.as_mut()).poll_flush(cx),
+        }
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        match self.get_mut() {
+            Transport::Tcp(stream) => Pin::new(stream).poll_shutdown(cx),
+            Transport::Tls(stream) => Pin::new(stream.as_mut()).poll_shutdown(cx),
+        }
+    }
+}
+
+/// Minecraft protocol encryption, AES/CFB8 with the shared secret as key and IV.
+struct Cipher {
+    encryptor: Encryptor,
+    decryptor: Decryptor,
+}
+
+impl Cipher {
+    /// Decrypt data received from the client in place.
+    fn decrypt(&mut self, data: &mut [u8]) {
+        for byte in data.chunks_mut(1) {
+            self.decryptor.decrypt_block_mut(byte.into());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::io::AsyncWriteExt;
+    use tokio::net::TcpListener;
+
+    use super::*;
+
+    /// Connected client stream, and the remote end.
+    async fn pair() -> (ClientStream, TcpStream) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let remote = TcpStream::connect(listener.local_addr().unwrap())
+            .await
+            .unwrap();
+        let (stream, _) = listener.accept().await.unwrap();
+        (ClientStream::new(stream).unwrap(), remote)
+    }
+
+    #[tokio::test]
+    async fn encrypted_round_trip() {
+        let secret = [7; 16];
+        let (mut stream, mut remote) = pair().await;
+        stream.encrypt(&secret, &mut []).unwrap();
+
+        // Remote end encrypts and decrypts the same way as the client
+        let mut encryptor = Encryptor::new_from_slices(&secret, &secret).unwrap();
+        let mut decryptor = Decryptor::new_from_slices(&secret, &secret).unwrap();
+
+        let mut data = b"hello".to_vec();
+        for byte in data.chunks_mut(1) {
+            encryptor.encrypt_block_mut(byte.into());
+        }
+        remote.write_all(&data).await.unwrap();
+        let mut buf = [0; 5];
+        stream.read_exact(&mut buf).await.unwrap();
+        assert_eq!(&buf, b"hello");
+
+        stream.write_all(b"world").await.unwrap();
+        remote.read_exact(&mut buf).await.unwrap();
+        for byte in buf.chunks_mut(1) {
+            decryptor.decrypt_block_mut(byte.into());
+        }
+        assert_eq!(&buf, b"world");
+    }
+
+    #[tokio::test]
+    async fn peek_does_not_consume() {
+        let (mut stream, mut remote) = pair().await;
+        stream.encrypt(&[1; 16], &mut []).unwrap();
+        remote.write_all(&[1, 2, 3]).await.unwrap();
+
+        let mut peeked = [0; 3];
+        let mut len = 0;
+        while len < 3 {
+            len = stream.peek(&mut peeked).await.unwrap();
+        }
+        let mut read = [0; 3];
+        stream.read_exact(&mut read).await.unwrap();
+        assert_eq!(peeked, read);
+    }
+}
@@ -0,0 +1,75 @@
+//! Systemd service notifications, see `sd_notify(3)`.
+
+use std::env;
+use std::os::unix::net::UnixDatagram;
+use std::time::Duration;
+
+use tokio::time;
+
+/// Send notification message to systemd.
+///
+/// Does nothing if `NOTIFY_SOCKET` is not set. Returns `true` if the message was sent.
+pub fn notify(msg: &str) -> bool {
+    let path = match env::var_os("NOTIFY_SOCKET") {
+        Some(path) => path,
+        None => return false,
+    };
+
+    let result = UnixDatagram::unbound().and_then(|socket| {
+        // Socket may be in abstract namespace, prefixed with '@'
+        #[cfg(target_os = "linux")]
+        if let Some(name) = path.to_str().and_then(|p| p.strip_prefix('@')) {
+            use std::os::linux::net::SocketAddrExt;
+            let addr = std::os::unix::net::SocketAddr::from_abstract_name(name)?;
+            return socket.send_to_addr(msg.as_bytes(), &addr);
+        }
+
+        socket.send_to(msg.as_bytes(), &path)
+    });
+
+    match result {
+        Ok(_) => true,
+        Err(err) => {
+            warn!(target: "lazymc::systemd", "Failed to send systemd notification: {}", err);
+            false
+        }
+    }
+}
+
+/// Get systemd watchdog interval to send keep-alive notifications at.
+///
+/// Returns `None` if the watchdog is not enabled.
+fn watchdog_interval() -> Option<Duration> {
+    let usec: u64 = env::var("WATCHDOG_USEC").ok()?.parse().ok()?;
+
+    // Watchdog must be for this process if PID is given
+    if let Some(pid) = env::var("WATCHDOG_PID")
+        .ok()
+        .and_then(|p| p.parse::<u32>().ok())
+    {
+        if pid != std::process::id() {
+            return None;
+        }
+    }
+
+    // Notify at half the timeout
+    Some(Duration::from_micros(usec / 2))
+}
+
+/// Systemd watchdog task.
+///
+/// Periodically sends keep-alive notifications if the watchdog is enabled.
+pub async fn watchdog() {
+    let interval = match watchdog_interval() {
+        Some(interval) => interval,
+        None => return,
+    };
+
+    debug!(target: "lazymc::systemd", "Sending systemd watchdog notifications every {}ms", interval.as_millis());
+
+    let mut interval = time::interval(interval);
+    loop {
+        interval.tick().await;
+        notify("WATCHDOG=1");
+    }
+}
@@ -0,0 +1,275 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, SystemTime};
+
+use instant_acme::{
+    Account, AccountCredentials, AuthorizationStatus, ChallengeType, Identifier, NewAccount,
+    NewOrder, OrderStatus,
+};
+use rcgen::{CertificateParams, DistinguishedName};
+use tokio::net::TcpListener;
+use tokio::time;
+use tokio_rustls::rustls::server::{ClientHello, ResolvesServerCert};
+use tokio_rustls::rustls::sign::{self, CertifiedKey};
+use tokio_rustls::TlsAcceptor;
+
+use crate::config::{Config, TlsAcme};
+use crate::net;
+use crate::util::{self, http};
+
+/// Cached ACME account credentials file.
+const ACCOUNT_FILE: &str = "account.json";
+
+/// Cached certificate chain file.
+const CERT_FILE: &str = "cert.pem";
+
+/// Cached private key file.
+const KEY_FILE: &str = "key.pem";
+
+/// Renew certificates older than this, Let's Encrypt certificates are valid for 90 days.
+const RENEW_AFTER: Duration = Duration::from_secs(60 * 24 * 60 * 60);
+
+/// Interval to check whether the certificate must be renewed.
+const RENEW_CHECK_INTERVAL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Interval to poll ACME order state at.
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Maximum number of times to poll ACME order state.
+const POLL_ATTEMPTS: u32 = 30;
+
+/// Path HTTP-01 challenges are requested at.
+const CHALLENGE_PATH: &str = "/.well-known/acme-challenge/";
+
+/// Challenge response content type.
+const CONTENT_TYPE: &str = "text/plain";
+
+/// Certificate resolver, always serving the latest obtained certificate.
+struct Resolver {
+    key: RwLock<Arc<CertifiedKey>>,
+}
+
+impl ResolvesServerCert for Resolver {
+    fn resolve(&self, _client_hello: ClientHello) -> Option<Arc<CertifiedKey>> {
+        Some(
+            self.key
+                .read()
+                .unwrap_or_else(|err| err.into_inner())
+                .clone(),
+        )
+    }
+}
+
+/// Load TLS acceptor with ACME certificate.
+///
+/// Serves the cached certificate if it exists, renewing it in the background if old. Obtains one
+/// first otherwise. Spawns a task to renew it.
+pub async fn acceptor(config: &Config) -> Result<TlsAcceptor, Box<dyn Error + Send + Sync>> {
+    let acme = config.tls.acme.clone();
+    let cache_dir = config.resolve_path(&acme.cache_dir);
+    fs::create_dir_all(&cache_dir)
+        .map_err(|err| format!("failed to create {}: {}", cache_dir.display(), err))?;
+
+    let key = match certified_key(&cache_dir) {
+        Ok(key) => key,
+        Err(_) => obtain(&acme, &cache_dir).await?,
+    };
+
+    let resolver = Arc::new(Resolver {
+        key: RwLock::new(key),
+    });
+    tokio::spawn(renew(acme, cache_dir, resolver.clone()));
+
+    let tls_config = super::builder(config)?.with_cert_resolver(resolver);
+    Ok(TlsAcceptor::from(Arc::new(tls_config)))
+}
+
+/// Renew certificate when it gets old, forever.
+///
+/// Checks right away, the cached certificate may already be old at startup.
+async fn renew(acme: TlsAcme, cache_dir: PathBuf, resolver: Arc<Resolver>) {
+    loop {
+        if must_renew(&cache_dir) {
+            match obtain(&acme, &cache_dir).await {
+                Ok(key) => {
+                    *resolver.key.write().unwrap_or_else(|err| err.into_inner()) = key;
+                    info!(target: "lazymc::tls", "Renewed TLS certificate for {}", acme.domain);
+                }
+                Err(err) => {
+                    error!(target: "lazymc::tls", "Failed to renew TLS certificate, retrying later: {}", err)
+                }
+            }
+        }
+
+        time::sleep(RENEW_CHECK_INTERVAL).await;
+    }
+}
+
+/// Check whether cached certificate is missing or old enough to renew.
+fn must_renew(cache_dir: &Path) -> bool {
+    fs::metadata(cache_dir.join(CERT_FILE))
+        .and_then(|meta| meta.modified())
+        .ok()
+        .and_then(|modified| SystemTime::now().duration_since(modified).ok())
+        .map_or(true, |age| age >= RENEW_AFTER)
+}
+
+/// Load cached certificate and key.
+fn certified_key(cache_dir: &Path) -> Result<Arc<CertifiedKey>, Box<dyn Error + Send + Sync>> {
+    let certs = super::certs(&cache_dir.join(CERT_FILE))?;
+    let key = sign::any_supported_type(&super::private_key(&cache_dir.join(KEY_FILE))?)?;
+    Ok(Arc::new(CertifiedKey::new(certs, key)))
+}
+
+/// Obtain certificate through ACME HTTP-01 challenge, stores it in the cache directory.
+async fn obtain(
+    acme: &TlsAcme,
+    cache_dir: &Path,
+) -> Result<Arc<CertifiedKey>, Box<dyn Error + Send + Sync>> {
+    info!(target: "lazymc::tls", "Obtaining TLS certificate for {} through ACME", acme.domain);
+
+    let account = account(acme, cache_dir).await?;
+    let mut order = account
+        .new_order(&NewOrder {
+            identifiers: &[Identifier::Dns(acme.domain.clone())],
+        })
+        .await?;
+
+    // Collect HTTP-01 challenges for pending authorizations
+    let mut responses = HashMap::new();
+    let mut challenge_urls = Vec::new();
+    for authz in order.authorizations().await? {
+        if !matches!(authz.status, AuthorizationStatus::Pending) {
+            continue;
+        }
+        let challenge = authz
+            .challenges
+            .iter()
+            .find(|challenge| challenge.r#type == ChallengeType::Http01)
+            .ok_or("ACME server did not offer HTTP-01 challenge")?;
+        responses.insert(
+            challenge.token.clone(),
+            order.key_authorization(challenge).as_str().to_string(),
+        );
+        challenge_urls.push(challenge.url.clone());
+    }
+
+    // Answer challenges while the order is validated
+    let listener = net::bind_listener(acme.http_address, None).map_err(|err| {
+        format!(
+            "failed to listen for ACME challenges on {}: {}",
+            acme.http_address, err
+        )
+    })?;
+    let challenges = tokio::spawn(serve_challenges(listener, Arc::new(responses)));
+    let chain = async {
+        for url in &challenge_urls {
+            order.set_challenge_ready(url).await?;
+        }
+        wait_ready(&mut order).await?;
+
+        // Finalize with new key, fetch issued certificate chain
+        let mut params = CertificateParams::new(vec![acme.domain.clone()]);
+        params.distinguished_name = DistinguishedName::new();
+        let cert = rcgen::Certificate::from_params(params)?;
+        order.finalize(&cert.serialize_request_der()?).await?;
+        for _ in 0..POLL_ATTEMPTS {
+            if let Some(chain) = order.certificate().await? {
+                return Ok((chain, cert.serialize_private_key_pem()));
+            }
+            time::sleep(POLL_INTERVAL).await;
+        }
+        Err::<_, Box<dyn Error + Send + Sync>>("timed out waiting for ACME certificate".into())
+    }
+    .await;
+    challenges.abort();
+    let (chain, key) = chain?;
+
+    // Write key first, a new certificate must never be paired with the old key
+    util::fs::write_atomic_private(&cache_dir.join(KEY_FILE), key)?;
+    util::fs::write_atomic(&cache_dir.join(CERT_FILE), chain)?;
+    info!(target: "lazymc::tls", "Obtained TLS certificate for {}", acme.domain);
+
+    certified_key(cache_dir)
+}
+
+/// Wait for ACME order to become ready after challenges are validated.
+async fn wait_ready(order: &mut instant_acme::Order) -> Result<(), Box<dyn Error + Send + Sync>> {
+    for _ in 0..POLL_ATTEMPTS {
+        time::sleep(POLL_INTERVAL).await;
+        match &order.refresh().await?.status {
+            OrderStatus::Pending => continue,
+            OrderStatus::Ready => return Ok(()),
+            status => return Err(format!("ACME order failed with status {status:?}").into()),
+        }
+    }
+    Err("timed out waiting for ACME challenge validation".into())
+}
+
+/// Load cached ACME account, or create a new one.
+async fn account(
+    acme: &TlsAcme,
+    cache_dir: &Path,
+) -> Result<Account, Box<dyn Error + Send + Sync>> {
+    let path = cache_dir.join(ACCOUNT_FILE);
+    if let Ok(data) = fs::read_to_string(&path) {
+        let credentials: AccountCredentials = serde_json::from_str(&data)?;
+        return Ok(Account::from_credentials(credentials)?);
+    }
+
+    // Creating an account agrees to the terms of service, which the user must do
+    if !acme.accept_terms {
+        return Err(
+            "refusing to create ACME account, set tls.acme.accept_terms to agree to the terms of service"
+                .into(),
+        );
+    }
+
+    let contact = format!("mailto:{}", acme.email);
+    let contacts: Vec<&str> = if acme.email.is_empty() {
+        vec![]
+    } else {
+        vec![&contact]
+    };
+    let (account, credentials) = Account::create(
+        &NewAccount {
+            contact: &contacts,
+            terms_of_service_agreed: true,
+            only_return_existing: false,
+        },
+        &acme.directory_url,
+        None,
+    )
+    .await?;
+    util::fs::write_atomic_private(&path, serde_json::to_string(&credentials)?)?;
+
+    Ok(account)
+}
+
+/// Serve HTTP-01 challenge responses by token.
+async fn serve_challenges(listener: TcpListener, responses: Arc<HashMap<String, String>>) {
+    while let Ok((mut stream, _)) = listener.accept().await {
+        let responses = responses.clone();
+        tokio::spawn(async move {
+            let Some(request) = http::read_request(&mut stream).await else {
+                return;
+            };
+            let response = request
+                .path
+                .strip_prefix(CHALLENGE_PATH)
+                .and_then(|token| responses.get(token));
+            let result = match response {
+                Some(response) => {
+                    http::write_response(&mut stream, 200, CONTENT_TYPE, response).await
+                }
+                None => http::write_response(&mut stream, 404, CONTENT_TYPE, "not found\n").await,
+            };
+            if let Err(err) = result {
+                debug!(target: "lazymc::tls", "Failed to respond to ACME challenge request: {}", err);
+            }
+        });
+    }
+}
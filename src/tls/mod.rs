@@ -0,0 +1,108 @@
+use std::error::Error;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+
+mod acme;
+
+use rustls_pemfile::Item;
+use tokio::net::TcpStream;
+use tokio::time;
+use tokio_rustls::rustls::server::{AllowAnyAuthenticatedClient, WantsServerCert};
+use tokio_rustls::rustls::{Certificate, ConfigBuilder, PrivateKey, RootCertStore, ServerConfig};
+use tokio_rustls::TlsAcceptor;
+
+use crate::config::Config;
+use crate::net;
+use crate::stream::ClientStream;
+
+/// Time to wait for clients to complete the TLS handshake.
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Load TLS acceptor for client connections, `None` if TLS is disabled.
+///
+/// Obtains a certificate through ACME first if enabled and none is cached.
+pub async fn acceptor(
+    config: &Config,
+) -> Result<Option<TlsAcceptor>, Box<dyn Error + Send + Sync>> {
+    let tls = &config.tls;
+    if !tls.enabled {
+        return Ok(None);
+    }
+    if tls.acme.enabled {
+        return acme::acceptor(config).await.map(Some);
+    }
+
+    let certs = certs(&config.resolve_path(&tls.cert))?;
+    let key = private_key(&config.resolve_path(&tls.key))?;
+    let tls_config = builder(config)?.with_single_cert(certs, key)?;
+
+    Ok(Some(TlsAcceptor::from(Arc::new(tls_config))))
+}
+
+/// TLS server config builder, requiring client certificates if `tls.client_ca` is set.
+fn builder(
+    config: &Config,
+) -> Result<ConfigBuilder<ServerConfig, WantsServerCert>, Box<dyn Error + Send + Sync>> {
+    let builder = ServerConfig::builder().with_safe_defaults();
+    Ok(match &config.tls.client_ca {
+        Some(path) => {
+            let mut roots = RootCertStore::empty();
+            for cert in rustls_pemfile::certs(&mut open(&config.resolve_path(path))?)? {
+                roots.add(&Certificate(cert))?;
+            }
+            builder.with_client_cert_verifier(AllowAnyAuthenticatedClient::new(roots).boxed())
+        }
+        None => builder.with_no_client_auth(),
+    })
+}
+
+/// Open PEM file for reading.
+fn open(path: &Path) -> Result<BufReader<File>, Box<dyn Error + Send + Sync>> {
+    File::open(path)
+        .map(BufReader::new)
+        .map_err(|err| format!("failed to open {}: {}", path.display(), err).into())
+}
+
+/// Read certificate chain from PEM file.
+fn certs(path: &Path) -> Result<Vec<Certificate>, Box<dyn Error + Send + Sync>> {
+    Ok(rustls_pemfile::certs(&mut open(path)?)?
+        .into_iter()
+        .map(Certificate)
+        .collect())
+}
+
+/// Read first private key from PEM file.
+fn private_key(path: &Path) -> Result<PrivateKey, Box<dyn Error + Send + Sync>> {
+    for item in rustls_pemfile::read_all(&mut open(path)?)? {
+        match item {
+            Item::RSAKey(key) | Item::PKCS8Key(key) | Item::ECKey(key) => {
+                return Ok(PrivateKey(key))
+            }
+            _ => {}
+        }
+    }
+    Err(format!("no private key found in {}", path.display()).into())
+}
+
+/// Accept TLS connection on the client stream.
+///
+/// Returns the client stream, reading and writing decrypted data.
+pub async fn accept(acceptor: &TlsAcceptor, inbound: TcpStream) -> Result<ClientStream, ()> {
+    let peer = net::canonical_addr(inbound.peer_addr().map_err(|_| ())?);
+    let stream = match time::timeout(HANDSHAKE_TIMEOUT, acceptor.accept(inbound)).await {
+        Ok(Ok(stream)) => stream,
+        Ok(Err(err)) => {
+            debug!(target: "lazymc::tls", "TLS handshake with {} failed: {}", peer, err);
+            return Err(());
+        }
+        Err(_) => {
+            debug!(target: "lazymc::tls", "TLS handshake with {} timed out", peer);
+            return Err(());
+        }
+    };
+
+    Ok(ClientStream::tls(stream, peer))
+}
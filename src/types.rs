@@ -27,3 +27,23 @@ pub fn encode_var_int(i: i32) -> Result<Vec<u8>, ()> {
     minecraft_protocol::encoder::var_int::encode(&i, &mut buf).map_err(|_| ())?;
     Ok(buf)
 }
+
+/// Encode string, prefixed with its var-int length.
+pub fn encode_string(s: &str) -> Result<Vec<u8>, ()> {
+    let mut buf = encode_var_int(s.len() as i32)?;
+    buf.extend_from_slice(s.as_bytes());
+    Ok(buf)
+}
+
+/// Read string prefixed with its var-int length.
+///
+/// Returns the number of bytes read and the string.
+pub fn read_string(buf: &[u8]) -> Result<(usize, String), ()> {
+    let (read, len) = read_var_int(buf)?;
+    let end = read
+        .checked_add(usize::try_from(len).map_err(|_| ())?)
+        .ok_or(())?;
+    let data = buf.get(read..end).ok_or(())?;
+    let s = String::from_utf8(data.to_vec()).map_err(|_| ())?;
+    Ok((end, s))
+}
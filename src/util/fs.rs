@@ -0,0 +1,46 @@
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::Path;
+
+/// Write file contents atomically.
+///
+/// Contents are written to a temporary file in the same directory first, which is then renamed
+/// to the target file.
+pub fn write_atomic(file: &Path, contents: impl AsRef<[u8]>) -> io::Result<()> {
+    write_atomic_with(file, contents.as_ref(), OpenOptions::new())
+}
+
+/// Write file contents atomically, only readable by the current user.
+///
+/// The file is created with `0600` permissions on Unix, see [`write_atomic`].
+pub fn write_atomic_private(file: &Path, contents: impl AsRef<[u8]>) -> io::Result<()> {
+    let mut options = OpenOptions::new();
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    write_atomic_with(file, contents.as_ref(), options)
+}
+
+/// Write file contents atomically, opening the temporary file with the given options.
+fn write_atomic_with(file: &Path, contents: &[u8], mut options: OpenOptions) -> io::Result<()> {
+    let name = file
+        .file_name()
+        .map(|name| name.to_string_lossy())
+        .unwrap_or_default();
+    let tmp = file.with_file_name(format!(".{name}.lazymc.tmp"));
+
+    // Remove leftover temporary file, its permissions would be kept otherwise
+    let _ = fs::remove_file(&tmp);
+    let result = options
+        .write(true)
+        .create_new(true)
+        .open(&tmp)
+        .and_then(|mut f| f.write_all(contents))
+        .and_then(|_| fs::rename(&tmp, file));
+    if result.is_err() {
+        let _ = fs::remove_file(&tmp);
+    }
+    result
+}
@@ -0,0 +1,103 @@
+use std::collections::HashMap;
+
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+
+/// Maximum size of HTTP request head in bytes.
+const MAX_HEAD_SIZE: usize = 8 * 1024;
+
+/// Minimal HTTP request.
+///
+/// Only the request line and headers are parsed, request bodies are not supported.
+#[derive(Debug)]
+pub struct Request {
+    /// Request method, such as `GET`.
+    pub method: String,
+
+    /// Request path, without query string.
+    pub path: String,
+
+    /// Request headers, with lowercase names.
+    pub headers: HashMap<String, String>,
+}
+
+impl Request {
+    /// Get header value by lowercase name.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.get(name).map(|v| v.as_str())
+    }
+}
+
+/// Read HTTP request head from stream.
+///
+/// Returns `None` if the connection closed or the request is malformed.
+pub async fn read_request(stream: &mut TcpStream) -> Option<Request> {
+    // Read until end of head
+    let mut buf = Vec::with_capacity(1024);
+    let head_len = loop {
+        if let Some(pos) = buf.windows(4).position(|w| w == b"\r\n\r\n") {
+            break pos;
+        }
+        if buf.len() >= MAX_HEAD_SIZE {
+            return None;
+        }
+        match stream.read_buf(&mut buf).await {
+            Ok(0) | Err(_) => return None,
+            Ok(_) => {}
+        }
+    };
+    let head = std::str::from_utf8(&buf[..head_len]).ok()?;
+    let mut lines = head.split("\r\n");
+
+    // Parse request line
+    let mut request_line = lines.next()?.split_whitespace();
+    let method = request_line.next()?.to_uppercase();
+    let target = request_line.next()?;
+    let path = target.split('?').next().unwrap_or(target).to_string();
+
+    // Parse headers
+    let headers = lines
+        .filter_map(|line| line.split_once(':'))
+        .map(|(name, value)| (name.trim().to_lowercase(), value.trim().to_string()))
+        .collect();
+
+    Some(Request {
+        method,
+        path,
+        headers,
+    })
+}
+
+/// Write HTTP response to stream and close it.
+pub async fn write_response(
+    stream: &mut TcpStream,
+    status: u16,
+    content_type: &str,
+    body: &str,
+) -> std::io::Result<()> {
+    let response = format!(
+        "HTTP/1.1 {} {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        reason(status),
+        content_type,
+        body.len(),
+        body,
+    );
+    stream.write_all(response.as_bytes()).await?;
+    stream.shutdown().await
+}
+
+/// Get reason phrase for HTTP status code.
+fn reason(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        202 => "Accepted",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        409 => "Conflict",
+        500 => "Internal Server Error",
+        _ => "Unknown",
+    }
+}
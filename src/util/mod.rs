@@ -1,6 +1,9 @@
 pub mod cli;
 pub mod error;
+pub mod fs;
+pub mod http;
 pub mod serde;
+pub mod shell;
 pub mod style;
 
 use std::env;
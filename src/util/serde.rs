@@ -1,7 +1,11 @@
 use std::net::{SocketAddr, ToSocketAddrs};
 
+use ipnet::IpNet;
 use serde::de::{Error, Unexpected};
-use serde::{Deserialize, Deserializer};
+use serde::{Deserialize, Deserializer, Serializer};
+use uuid::Uuid;
+
+use crate::net;
 
 /// Deserialize a `Vec` into a `HashMap` by key.
 pub fn to_socket_addrs<'de, D>(d: D) -> Result<SocketAddr, D::Error>
@@ -28,3 +32,100 @@ where
         Error::invalid_value(Unexpected::Str(&addr), &"IP or resolvable host and port")
     })
 }
+
+/// Deserialize a single string or a list of strings into a `Vec`.
+pub fn string_or_vec<'de, D>(d: D) -> Result<Vec<String>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum StringOrVec {
+        String(String),
+        Vec(Vec<String>),
+    }
+
+    Ok(match StringOrVec::deserialize(d)? {
+        StringOrVec::String(s) => vec![s],
+        StringOrVec::Vec(v) => v,
+    })
+}
+
+/// Deserialize a protocol version number, or `"auto"` into `0`.
+pub fn protocol_or_auto<'de, D>(d: D) -> Result<u32, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum ProtocolOrAuto {
+        Protocol(u32),
+        String(String),
+    }
+
+    match ProtocolOrAuto::deserialize(d)? {
+        ProtocolOrAuto::Protocol(protocol) => Ok(protocol),
+        ProtocolOrAuto::String(s) if s.eq_ignore_ascii_case("auto") => Ok(0),
+        ProtocolOrAuto::String(s) => Err(Error::invalid_value(
+            Unexpected::Str(&s),
+            &"protocol version number or \"auto\"",
+        )),
+    }
+}
+
+/// Deserialize a list of networks, plain IP addresses are single address networks.
+pub fn to_ipnets<'de, D>(d: D) -> Result<Vec<IpNet>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Vec::<String>::deserialize(d)?
+        .iter()
+        .map(|s| {
+            net::parse_ipnet(s)
+                .map_err(|_| Error::invalid_value(Unexpected::Str(s), &"IP address or network"))
+        })
+        .collect()
+}
+
+/// Deserialize a UUID string, with or without hyphens.
+pub fn to_uuid<'de, D>(d: D) -> Result<Uuid, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let uuid = String::deserialize(d)?;
+    Uuid::parse_str(&uuid).map_err(|_| Error::invalid_value(Unexpected::Str(&uuid), &"UUID"))
+}
+
+/// Serialize a UUID as hyphenated string.
+pub fn from_uuid<S>(uuid: &Uuid, s: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    s.collect_str(&uuid.hyphenated())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::Ipv6Addr;
+
+    use serde::de::value::{Error, StrDeserializer};
+
+    use super::*;
+
+    fn socket_addr(addr: &str) -> Result<SocketAddr, Error> {
+        to_socket_addrs(StrDeserializer::<Error>::new(addr))
+    }
+
+    #[test]
+    fn socket_addrs_ipv6() {
+        assert_eq!(
+            socket_addr("[::]:25565").unwrap(),
+            SocketAddr::new(Ipv6Addr::UNSPECIFIED.into(), 25565)
+        );
+        assert_eq!(
+            socket_addr("[::1]:25566").unwrap(),
+            SocketAddr::new(Ipv6Addr::LOCALHOST.into(), 25566)
+        );
+        assert!(socket_addr("::1").is_err());
+    }
+}
@@ -0,0 +1,18 @@
+use tokio::process::Command;
+
+/// Build command to run the given shell command line with the platform shell.
+pub fn command(cmd: &str) -> Command {
+    #[cfg(windows)]
+    {
+        let mut command = Command::new("cmd");
+        command.args(["/C", cmd]);
+        command
+    }
+
+    #[cfg(not(windows))]
+    {
+        let mut command = Command::new("sh");
+        command.args(["-c", cmd]);
+        command
+    }
+}
@@ -0,0 +1,125 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use minecraft_protocol::decoder::Decoder;
+use minecraft_protocol::version::v1_14_4::handshake::Handshake;
+use tokio::time;
+
+use crate::config::{Config, LiveConfig};
+use crate::proto::client::Client;
+use crate::proto::packet::RawPacket;
+use crate::proto::packets;
+use crate::server::Server;
+use crate::stream::ClientStream;
+use crate::types;
+
+/// Timeout for receiving the client handshake to route on.
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Interval to peek for more handshake data at.
+const PEEK_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Maximum handshake packet size.
+const MAX_HANDSHAKE_SIZE: usize = 1024;
+
+/// Virtual host, with independent configuration and server state.
+pub struct VirtualHost {
+    /// Hostname clients connect with.
+    pub hostname: String,
+
+    /// Virtual host configuration.
+    pub config: Arc<LiveConfig>,
+
+    /// Virtual host server state.
+    pub server: Arc<Server>,
+}
+
+/// Configured virtual hosts.
+#[derive(Default)]
+pub struct VirtualHosts {
+    hosts: Vec<VirtualHost>,
+}
+
+impl VirtualHosts {
+    /// Set up virtual hosts from configuration.
+    pub fn new(config: &Config) -> Self {
+        let hosts = config
+            .vhosts
+            .iter()
+            .map(|vhost| VirtualHost {
+                hostname: vhost.hostname.clone(),
+                config: Arc::new(LiveConfig::new(config.vhost(vhost))),
+                server: Arc::new(Server::default()),
+            })
+            .collect();
+        Self { hosts }
+    }
+
+    /// Whether any virtual host is configured.
+    pub fn is_empty(&self) -> bool {
+        self.hosts.is_empty()
+    }
+
+    /// Iterate over all virtual hosts.
+    pub fn iter(&self) -> impl Iterator<Item = &VirtualHost> {
+        self.hosts.iter()
+    }
+
+    /// Find virtual host for given handshake hostname.
+    pub fn find(&self, hostname: &str) -> Option<&VirtualHost> {
+        self.hosts
+            .iter()
+            .find(|vhost| vhost.hostname.eq_ignore_ascii_case(hostname))
+    }
+}
+
+/// Peek the handshake hostname the client connected with, without consuming the stream.
+///
+/// Returns `None` if the client did not send a valid handshake in time, such as for legacy pings.
+pub async fn peek_hostname(inbound: &mut ClientStream) -> Option<String> {
+    time::timeout(HANDSHAKE_TIMEOUT, peek_hostname_no_timeout(inbound))
+        .await
+        .ok()
+        .flatten()
+}
+
+/// Peek the handshake hostname the client connected with, with no timeout.
+async fn peek_hostname_no_timeout(inbound: &mut ClientStream) -> Option<String> {
+    let mut buf = [0; MAX_HANDSHAKE_SIZE];
+    loop {
+        let read = inbound.peek(&mut buf).await.ok()?;
+        if read == 0 {
+            return None;
+        }
+
+        // Wait for more data if packet length is unknown or packet is incomplete
+        let packet = &buf[..read];
+        let (consumed, len) = match types::read_var_int(packet) {
+            Ok(result) => result,
+            Err(_) if read < 5 => {
+                time::sleep(PEEK_INTERVAL).await;
+                continue;
+            }
+            Err(_) => return None,
+        };
+        let end = consumed + len as usize;
+        if end > MAX_HANDSHAKE_SIZE {
+            return None;
+        }
+        if read < end {
+            time::sleep(PEEK_INTERVAL).await;
+            continue;
+        }
+
+        // Handshake is never compressed
+        let packet = RawPacket::decode_with_len(&Client::dummy(), &packet[..end]).ok()?;
+        if packet.id != packets::handshake::SERVER_HANDSHAKE {
+            return None;
+        }
+        let handshake = Handshake::decode(&mut packet.data.as_slice()).ok()?;
+
+        // Strip markers, such as Forge, and trailing dot of fully qualified names
+        let hostname = handshake.server_addr.split('\0').next().unwrap_or_default();
+        return Some(hostname.trim_end_matches('.').to_string());
+    }
+}